@@ -1,26 +1,38 @@
 use anyhow::anyhow;
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{info, warn};
 
 use pixelcore_claw::{
-    ClawClient,
-    types::{LlmRequest, ApiMessage, ApiContent, ContentBlock},
+    ClawClient, LlmClient, ToolContent,
+    types::{LlmRequest, LlmResponse, ApiMessage, ApiContent, ContentBlock},
 };
-use pixelcore_runtime::{Agent, AgentId, AgentConfig, AgentState, RuntimeError, Message};
-use pixelcore_skills::{Skill, SkillInput, SkillRegistry};
-use pixelcore_storage::Storage;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 
-const MAX_TOOL_ROUNDS: usize = 10;
+use crate::context::{self, ContextPolicy, ContextStrategy, ContextUsage, ContextWindow};
+use crate::structured::{self, OutputSchema};
+use crate::tools::{Approval, ApprovalRequest, ToolApprover, ToolPolicy};
+use pixelcore_runtime::{Agent, AgentId, AgentConfig, AgentState, RuntimeError, Message, StreamingSender};
+use pixelcore_skills::{PermissionAuditSink, PermissionManager, Skill, SkillError, SkillInput, SkillRegistry};
+use pixelcore_storage::Storage;
 
 pub struct ClaudeAgent {
     config: AgentConfig,
     state: AgentState,
-    client: ClawClient,
+    client: Box<dyn LlmClient>,
     /// Full conversation history with blocks preserved for tool-use round-trips.
     history: Vec<ApiMessage>,
     skills: SkillRegistry,
     storage: Option<Storage>,
+    /// Keeps `history` within `config.context_budget`.
+    context: ContextWindow,
+    tool_policy: ToolPolicy,
+    approver: Option<Arc<dyn ToolApprover>>,
+    /// Skills that need the approver's consent before each call.
+    gated_skills: HashSet<String>,
 }
 
 impl ClaudeAgent {
@@ -34,17 +46,78 @@ impl ClaudeAgent {
         Self::with_client(config, ClawClient::new(api_key))
     }
 
-    pub fn with_client(config: AgentConfig, client: ClawClient) -> Self {
+    /// Use any completion backend, e.g. a `ClawRouter` so that `config.model`
+    /// can name a logical model such as `fast` or `smart`.
+    pub fn with_client(config: AgentConfig, client: impl LlmClient + 'static) -> Self {
+        let skills = SkillRegistry::new().with_agent(config.id);
+        let mut context = ContextWindow::new(ContextPolicy::default());
+        context.set_budget(config.context_budget);
         Self {
             config,
             state: AgentState::Idle,
-            client,
+            client: Box::new(client),
             history: Vec::new(),
-            skills: SkillRegistry::new(),
+            skills,
             storage: None,
+            context,
+            tool_policy: ToolPolicy::default(),
+            approver: None,
+            gated_skills: HashSet::new(),
         }
     }
 
+    /// Concurrency, timeout and round limits for tool calls.
+    pub fn with_tool_policy(mut self, policy: ToolPolicy) -> Self {
+        self.tool_policy = policy;
+        self
+    }
+
+    /// Ask `approver` before every call to one of `skills`. Denied calls are
+    /// reported to the model as failed tool results.
+    pub fn with_approval_hook(
+        mut self,
+        approver: Arc<dyn ToolApprover>,
+        skills: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.approver = Some(approver);
+        self.gated_skills = skills.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// How history is compacted once it exceeds `AgentConfig::context_budget`.
+    /// Defaults to `ContextPolicy::sliding_window()`.
+    pub fn with_context_policy(mut self, policy: ContextPolicy) -> Self {
+        self.context.policy = policy;
+        self
+    }
+
+    /// Token accounting: reported usage, the current estimate and compactions so far.
+    pub fn context_usage(&self) -> &ContextUsage {
+        self.context.usage()
+    }
+
+    /// Rolling summary of compacted turns, if the policy summarizes.
+    pub fn summary(&self) -> Option<&str> {
+        self.context.summary.as_deref()
+    }
+
+    pub fn history(&self) -> &[ApiMessage] {
+        &self.history
+    }
+
+    /// Grant what the agent's skills may access. Without this, skills that need
+    /// any permission are denied.
+    pub fn with_permissions(mut self, permissions: PermissionManager) -> Self {
+        self.skills.set_permissions(permissions);
+        self
+    }
+
+    /// Record every skill permission decision on `sink`.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn PermissionAuditSink>) -> Self {
+        self.skills.add_audit_sink(sink);
+        self
+    }
+
     /// Attach a storage backend. History will be auto-saved after each turn
     /// and can be restored via `load_history`.
     pub fn with_storage(mut self, storage: Storage) -> Self {
@@ -59,12 +132,17 @@ impl ClaudeAgent {
 
     pub fn reset(&mut self) {
         self.history.clear();
+        self.context.reset();
     }
 
     fn history_key(&self) -> String {
         format!("agent:{}:history", self.config.id)
     }
 
+    fn summary_key(&self) -> String {
+        format!("agent:{}:summary", self.config.id)
+    }
+
     /// Persist current history to storage.
     pub fn save_history(&self) -> Result<(), RuntimeError> {
         let Some(storage) = &self.storage else { return Ok(()); };
@@ -72,6 +150,11 @@ impl ClaudeAgent {
             .map_err(|e| RuntimeError::Other(anyhow!(e)))?;
         storage.set(self.history_key(), value)
             .map_err(|e| RuntimeError::Other(anyhow!(e)))?;
+        let result = match &self.context.summary {
+            Some(summary) => storage.set(self.summary_key(), serde_json::json!(summary)),
+            None => storage.delete(&self.summary_key()).map(|_| ()),
+        };
+        result.map_err(|e| RuntimeError::Other(anyhow!(e)))?;
         Ok(())
     }
 
@@ -87,19 +170,133 @@ impl ClaudeAgent {
             Err(pixelcore_storage::StorageError::NotFound(_)) => {}
             Err(e) => return Err(RuntimeError::Other(anyhow!(e))),
         }
+        match storage.get(&self.summary_key()) {
+            Ok(value) => self.context.set_summary(value.as_str().map(str::to_string)),
+            Err(pixelcore_storage::StorageError::NotFound(_)) => self.context.set_summary(None),
+            Err(e) => return Err(RuntimeError::Other(anyhow!(e))),
+        }
         Ok(())
     }
 
+    fn system_prompt(&self) -> String {
+        match &self.context.summary {
+            Some(summary) => format!(
+                "{}\n\n<conversation_summary>\n{}\n</conversation_summary>",
+                self.config.system_prompt, summary
+            ),
+            None => self.config.system_prompt.clone(),
+        }
+    }
+
     fn build_request(&self) -> LlmRequest {
         let tools = self.skills.as_tools();
         LlmRequest {
             model: self.config.model.clone(),
             max_tokens: self.config.max_tokens,
             messages: self.history.clone(),
-            system: Some(self.config.system_prompt.clone()),
+            system: Some(self.system_prompt()),
             tools: if tools.is_empty() { None } else { Some(tools) },
             temperature: Some(self.config.temperature),
+            stream: false,
+            tool_choice: None,
+            output_format: None,
+        }
+    }
+
+    /// Heuristic size of everything sent besides history: system prompt (with
+    /// summary) and tool definitions.
+    fn overhead_tokens(&self, request: &LlmRequest) -> usize {
+        let tools = request.tools.as_ref()
+            .map(|t| serde_json::to_string(t).unwrap_or_default())
+            .unwrap_or_default();
+        context::text_tokens(request.system.as_deref().unwrap_or_default()) + context::text_tokens(&tools)
+    }
+
+    /// Compact history if the next request would exceed the context budget.
+    async fn fit_context(&mut self) {
+        let Some(budget) = self.config.context_budget else { return };
+        let overhead = self.overhead_tokens(&self.build_request());
+        let estimate = self.context.estimate(&self.history, overhead);
+        if estimate <= budget {
+            return;
+        }
+
+        let summarize = self.context.policy.strategy == ContextStrategy::Summarize;
+        let mut target = (budget as f32 * self.context.policy.target_ratio) as u32;
+        if summarize {
+            // Leave room for the summary that replaces the removed turns.
+            target = target.saturating_sub(self.context.policy.summary_max_tokens);
+        }
+        let cut = self.context.compaction_point(&self.history, overhead, target);
+        if cut == 0 {
+            warn!(agent = %self.config.name, estimate, budget, "current turn alone exceeds the context budget");
+            return;
+        }
+
+        let removed: Vec<ApiMessage> = self.history.drain(..cut).collect();
+        let turns = context::count_turns(&removed);
+        let mut summarized = false;
+        if summarize {
+            match self.summarize(&removed).await {
+                Ok(summary) => {
+                    self.context.set_summary(Some(summary));
+                    summarized = true;
+                }
+                Err(e) => {
+                    // Keep the model aware that earlier context is missing.
+                    warn!(agent = %self.config.name, error = %e, "summary failed, replacing turns with a truncation marker");
+                    let marker = format!("[{turns} earlier turns were removed without a summary]");
+                    let summary = match self.context.summary.take() {
+                        Some(previous) => format!("{previous}\n{marker}"),
+                        None => marker,
+                    };
+                    self.context.set_summary(Some(summary));
+                }
+            }
+        }
+        self.context.compacted(turns, summarized);
+        info!(agent = %self.config.name, turns, summarized, estimate, budget, "history compacted");
+    }
+
+    /// Merge `removed` into the rolling summary.
+    async fn summarize(&self, removed: &[ApiMessage]) -> Result<String, pixelcore_claw::ClawError> {
+        let mut prompt = String::new();
+        if let Some(summary) = &self.context.summary {
+            prompt.push_str(&format!("Previous summary:\n{summary}\n\n"));
         }
+        prompt.push_str(&format!("New transcript:\n{}", context::render_transcript(removed)));
+
+        let policy = &self.context.policy;
+        let request = LlmRequest {
+            model: policy.summary_model.clone().unwrap_or_else(|| self.config.model.clone()),
+            max_tokens: policy.summary_max_tokens,
+            messages: vec![ApiMessage { role: "user".to_string(), content: ApiContent::Text(prompt) }],
+            system: Some(context::SUMMARY_SYSTEM_PROMPT.to_string()),
+            tools: None,
+            temperature: Some(0.0),
+            stream: false,
+            tool_choice: None,
+            output_format: None,
+        };
+        let response = self.client.complete(request).await?;
+        Ok(Self::extract_text(&response.content))
+    }
+
+    /// Like `process`, but streams the reply through `sender` as it is generated.
+    ///
+    /// Text arrives as `Text` chunks and each tool call as a `Status` chunk.
+    /// A `Done` chunk is sent when the turn finishes, or an `Error` chunk if it fails.
+    pub async fn process_stream(
+        &mut self,
+        message: Message,
+        sender: &StreamingSender,
+    ) -> Result<Message, RuntimeError> {
+        let result = self.run_turn(message, Some(sender)).await;
+        match &result {
+            Ok(_) => { let _ = sender.send_done(); }
+            Err(e) => { let _ = sender.send_error(e.to_string()); }
+        }
+        result
     }
 
     fn extract_text(blocks: &[ContentBlock]) -> String {
@@ -137,12 +334,17 @@ impl Agent for ClaudeAgent {
     }
 
     async fn process(&mut self, message: Message) -> Result<Message, RuntimeError> {
-        if self.state != AgentState::Running {
-            return Err(RuntimeError::Other(anyhow!(
-                "agent '{}' is not running (state: {})",
-                self.config.name, self.state
-            )));
-        }
+        self.run_turn(message, None).await
+    }
+}
+
+impl ClaudeAgent {
+    async fn run_turn(
+        &mut self,
+        message: Message,
+        sender: Option<&StreamingSender>,
+    ) -> Result<Message, RuntimeError> {
+        self.ensure_running()?;
 
         // Append user message.
         self.history.push(ApiMessage {
@@ -150,66 +352,577 @@ impl Agent for ClaudeAgent {
             content: ApiContent::Text(message.content),
         });
 
-        for _ in 0..MAX_TOOL_ROUNDS {
+        let mut round = 0;
+        loop {
+            self.fit_context().await;
             let request = self.build_request();
-            let response = self.client.complete(request).await.map_err(|e| {
-                warn!(agent = %self.config.name, error = %e, "API call failed");
-                self.state = AgentState::Error(e.to_string());
-                RuntimeError::Other(anyhow!(e.to_string()))
-            })?;
-
-            info!(
-                agent = %self.config.name,
-                input_tokens = response.usage.input_tokens,
-                output_tokens = response.usage.output_tokens,
-                stop_reason = ?response.stop_reason,
-                "turn complete"
-            );
+            let response = self.send(request, sender).await?;
 
             let tool_uses = Self::extract_tool_uses(&response.content);
             let stop_reason = response.stop_reason.as_deref().unwrap_or("");
+            let done = tool_uses.is_empty() || stop_reason == "end_turn" || stop_reason == "stop";
+            let out_of_rounds = !done && round >= self.tool_policy.max_rounds;
+
+            let mut content = response.content;
+            if out_of_rounds {
+                // Keep the text and drop the unanswered calls so that history
+                // stays valid for the next turn.
+                warn!(agent = %self.config.name, rounds = round, "tool round limit reached, ending turn");
+                content.retain(|b| !matches!(b, ContentBlock::ToolUse { .. }));
+                if Self::extract_text(&content).is_empty() {
+                    content.push(ContentBlock::Text { text: format!("(stopped after {round} tool rounds)") });
+                }
+            }
+            let text = Self::extract_text(&content);
 
             // Append assistant turn with full blocks.
             self.history.push(ApiMessage {
                 role: "assistant".to_string(),
-                content: ApiContent::Blocks(response.content.clone()),
+                content: ApiContent::Blocks(content),
             });
 
-            if tool_uses.is_empty() || stop_reason == "end_turn" || stop_reason == "stop" {
-                let text = Self::extract_text(&response.content);
+            if done || out_of_rounds {
                 if let Err(e) = self.save_history() {
                     warn!(agent = %self.config.name, error = %e, "failed to save history");
                 }
                 return Ok(Message::assistant(text));
             }
 
-            // Execute each tool call and collect results.
-            let mut result_blocks: Vec<ContentBlock> = Vec::new();
-            for (tool_use_id, skill_name, input) in &tool_uses {
-                let result = match self.skills.get(skill_name) {
-                    Ok(skill) => {
-                        let skill_input = SkillInput { name: skill_name.clone(), args: input.clone() };
-                        match skill.execute(skill_input).await {
-                            Ok(out) => out.result.to_string(),
-                            Err(e) => format!("error: {e}"),
-                        }
-                    }
-                    Err(_) => format!("unknown skill: {skill_name}"),
-                };
-                info!(agent = %self.config.name, skill = %skill_name, "skill executed");
-                result_blocks.push(ContentBlock::ToolResult {
-                    tool_use_id: tool_use_id.clone(),
-                    content: result,
-                });
-            }
+            let result_blocks = self.run_tools(tool_uses, sender).await;
 
             // Append tool results as a user turn.
             self.history.push(ApiMessage {
                 role: "user".to_string(),
                 content: ApiContent::Blocks(result_blocks),
             });
+            round += 1;
         }
+    }
+
+    fn ensure_running(&self) -> Result<(), RuntimeError> {
+        if self.state != AgentState::Running {
+            return Err(RuntimeError::Other(anyhow!(
+                "agent '{}' is not running (state: {})",
+                self.config.name, self.state
+            )));
+        }
+        Ok(())
+    }
+
+    /// Send one request, streaming through `sender` if given, and record its usage.
+    async fn send(
+        &mut self,
+        request: LlmRequest,
+        sender: Option<&StreamingSender>,
+    ) -> Result<LlmResponse, RuntimeError> {
+        let sent = request.messages.len();
+        let overhead = self.overhead_tokens(&request);
+        let response = match sender {
+            Some(sender) => match self.client.complete_stream(request).await {
+                Ok(stream) => stream.forward_to(sender).await,
+                Err(e) => Err(e),
+            },
+            None => self.client.complete(request).await,
+        };
+        let response = response.map_err(|e| {
+            warn!(agent = %self.config.name, error = %e, "API call failed");
+            // Transient failures (rate limits, overload) leave the agent usable.
+            if !e.is_retryable() {
+                self.state = AgentState::Error(e.to_string());
+            }
+            RuntimeError::Other(anyhow!(e.to_string()))
+        })?;
+
+        self.context.record(&self.history, sent, overhead, &response.usage);
+        info!(
+            agent = %self.config.name,
+            input_tokens = response.usage.input_tokens,
+            output_tokens = response.usage.output_tokens,
+            stop_reason = ?response.stop_reason,
+            "turn complete"
+        );
+        Ok(response)
+    }
+
+    /// Ask for a reply that matches `schema` and return it as JSON.
+    ///
+    /// Skills are not offered for this turn. Anthropic is forced to answer
+    /// through a tool whose input schema is `schema`; OpenAI-compatible
+    /// backends get it as `response_format`. Replies that fail validation are
+    /// sent back with the errors, up to `schema.max_attempts` calls in all.
+    /// History keeps only the message and the final, valid reply.
+    pub async fn process_json(&mut self, message: Message, schema: &OutputSchema) -> Result<serde_json::Value, RuntimeError> {
+        self.ensure_running()?;
+        let validator = schema.validator().map_err(|e| RuntimeError::Other(anyhow!(e)))?;
+        let format = schema.output_format();
+
+        self.history.push(ApiMessage {
+            role: "user".to_string(),
+            content: ApiContent::Text(message.content),
+        });
+        self.fit_context().await;
+
+        // Failed attempts and their feedback, sent after history but never stored.
+        let mut retries: Vec<ApiMessage> = Vec::new();
+        let mut errors = Vec::new();
+        for attempt in 1..=schema.max_attempts {
+            let mut request = self.build_request();
+            request.tools = None;
+            request.messages.extend(retries.iter().cloned());
+            request.output_format = Some(format.clone());
+            let response = match self.send(request, None).await {
+                Ok(response) => response,
+                Err(e) => {
+                    self.history.pop();
+                    return Err(e);
+                }
+            };
+
+            // Anthropic answers with the forced tool call, other backends with text.
+            let tool_call = response.content.iter().find_map(|b| match b {
+                ContentBlock::ToolUse { id, name, input } if *name == format.name => Some((id.clone(), input.clone())),
+                _ => None,
+            });
+            let parsed = match &tool_call {
+                Some((_, input)) => Ok(input.clone()),
+                None => structured::parse_json_text(&Self::extract_text(&response.content)),
+            };
+            errors = match parsed {
+                Ok(value) => {
+                    let value = schema.unwrap_value(value);
+                    let errors = structured::validation_errors(&validator, &value);
+                    if errors.is_empty() {
+                        self.history.push(ApiMessage {
+                            role: "assistant".to_string(),
+                            content: ApiContent::Blocks(vec![ContentBlock::text(value.to_string())]),
+                        });
+                        if let Err(e) = self.save_history() {
+                            warn!(agent = %self.config.name, error = %e, "failed to save history");
+                        }
+                        return Ok(value);
+                    }
+                    errors
+                }
+                Err(error) => vec![error],
+            };
+            warn!(agent = %self.config.name, attempt, errors = ?errors, "structured reply failed validation");
+
+            let feedback = structured::retry_prompt(&errors);
+            retries.push(ApiMessage { role: "assistant".to_string(), content: ApiContent::Blocks(response.content) });
+            retries.push(ApiMessage {
+                role: "user".to_string(),
+                content: match tool_call {
+                    Some((tool_use_id, _)) => ApiContent::Blocks(vec![ContentBlock::ToolResult {
+                        tool_use_id,
+                        content: feedback.into(),
+                        is_error: true,
+                    }]),
+                    None => ApiContent::Text(feedback),
+                },
+            });
+        }
+
+        // Leave history as it was so the next turn starts cleanly.
+        self.history.pop();
+        Err(RuntimeError::Other(anyhow!(
+            "reply did not match schema '{}' after {} attempts: {}",
+            schema.name, schema.max_attempts, errors.join("; ")
+        )))
+    }
+
+    /// `process_json` with the schema derived from `T`, deserialized into `T`.
+    pub async fn process_structured<T>(&mut self, message: Message) -> Result<T, RuntimeError>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        let value = self.process_json(message, &OutputSchema::of::<T>()).await?;
+        serde_json::from_value(value).map_err(|e| RuntimeError::Other(anyhow!(e)))
+    }
+
+    /// Run one assistant turn's tool calls. Gated calls are approved one at a
+    /// time first; approved calls then run concurrently. Results keep the
+    /// order of the calls.
+    async fn run_tools(
+        &self,
+        tool_uses: Vec<(String, String, serde_json::Value)>,
+        sender: Option<&StreamingSender>,
+    ) -> Vec<ContentBlock> {
+        let mut calls = Vec::with_capacity(tool_uses.len());
+        for (tool_use_id, skill_name, input) in tool_uses {
+            let denied = match &self.approver {
+                Some(approver) if self.gated_skills.contains(&skill_name) => {
+                    let request = ApprovalRequest {
+                        agent_id: self.config.id,
+                        agent_name: self.config.name.clone(),
+                        tool_use_id: tool_use_id.clone(),
+                        skill: skill_name.clone(),
+                        input: input.clone(),
+                    };
+                    match approver.approve(&request).await {
+                        Approval::Approve => None,
+                        Approval::Deny(reason) => {
+                            info!(agent = %self.config.name, skill = %skill_name, %reason, "tool call denied");
+                            Some(reason)
+                        }
+                    }
+                }
+                _ => None,
+            };
+            calls.push((tool_use_id, skill_name, input, denied));
+        }
+
+        stream::iter(calls)
+            .map(|(tool_use_id, skill_name, input, denied)| async move {
+                let (content, is_error) = match denied {
+                    Some(reason) => (format!("denied: {reason}").into(), true),
+                    None => self.run_tool(&skill_name, input).await,
+                };
+                if let Some(sender) = sender {
+                    let _ = sender.send_status(format!("tool_result: {skill_name}"));
+                }
+                ContentBlock::ToolResult { tool_use_id, content, is_error }
+            })
+            .buffered(self.tool_policy.max_concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Execute one skill under the policy's timeout. Returns the result and
+    /// whether it is an error.
+    async fn run_tool(&self, skill_name: &str, args: serde_json::Value) -> (ApiContent, bool) {
+        let input = SkillInput { name: skill_name.to_string(), args };
+        let timeout = self.tool_policy.timeout;
+        let (content, is_error) = match tokio::time::timeout(timeout, self.skills.execute(input)).await {
+            Ok(Ok(out)) if out.success => (Self::tool_content(out.result), false),
+            Ok(Ok(out)) => (out.error.unwrap_or_else(|| out.result.to_string()).into(), true),
+            Ok(Err(SkillError::NotFound(_))) => (format!("unknown skill: {skill_name}").into(), true),
+            Ok(Err(e)) => (e.to_string().into(), true),
+            Err(_) => (format!("timed out after {}s", timeout.as_secs_f32()).into(), true),
+        };
+        info!(agent = %self.config.name, skill = %skill_name, is_error, "skill executed");
+        (content, is_error)
+    }
+
+    /// A skill result as tool-result content.
+    ///
+    /// Results shaped like `{"text": .., "content": [MCP tool content]}` (what
+    /// `McpSkill` returns for images) become text, image and document blocks so
+    /// the model can see them; anything else is sent as JSON text.
+    fn tool_content(result: serde_json::Value) -> ApiContent {
+        let media = result.get("content")
+            .and_then(|c| serde_json::from_value::<Vec<ToolContent>>(c.clone()).ok())
+            .filter(|items| items.iter().any(|c| c.as_text().is_none()));
+        let Some(items) = media else { return ApiContent::Text(result.to_string()) };
+
+        let mut blocks = Vec::new();
+        match result.get("text") {
+            Some(serde_json::Value::String(text)) => blocks.push(ContentBlock::text(text.clone())),
+            Some(serde_json::Value::Null) | None => {}
+            Some(text) => blocks.push(ContentBlock::text(text.to_string())),
+        }
+        blocks.extend(items.iter().filter(|c| c.as_text().is_none()).filter_map(ContentBlock::from_mcp));
+        ApiContent::Blocks(blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixelcore_claw::types::{LlmResponse, Usage};
+    use pixelcore_claw::{ClawError, LlmStream};
+    use pixelcore_skills::SkillOutput;
+    use serde_json::json;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Replies "ok" (or a summary to summary requests) and reports roughly
+    /// chars / 4 input tokens, like a real tokenizer would. Scripted tool
+    /// calls are sent first, one reply per entry.
+    #[derive(Clone, Default)]
+    struct MockClient {
+        requests: Arc<Mutex<Vec<LlmRequest>>>,
+        script: Arc<Mutex<VecDeque<Vec<ContentBlock>>>>,
+        fail_summaries: bool,
+    }
+
+    impl MockClient {
+        fn scripted(replies: impl IntoIterator<Item = Vec<ContentBlock>>) -> Self {
+            Self { script: Arc::new(Mutex::new(replies.into_iter().collect())), ..Self::default() }
+        }
+    }
+
+    fn tool_use(id: &str, name: &str, input: serde_json::Value) -> ContentBlock {
+        ContentBlock::ToolUse { id: id.to_string(), name: name.to_string(), input }
+    }
+
+    /// Sleeps for `ms`, then echoes it; fails when `fail` is set.
+    struct SleepSkill;
+
+    #[async_trait]
+    impl Skill for SleepSkill {
+        fn name(&self) -> &str { "sleep" }
+        fn description(&self) -> &str { "Sleep for a while" }
+        fn input_schema(&self) -> serde_json::Value { json!({"type": "object"}) }
+        async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
+            let ms = input.args["ms"].as_u64().unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            if input.args["fail"].as_bool().unwrap_or(false) {
+                return Ok(SkillOutput::err("boom"));
+            }
+            Ok(SkillOutput::ok(json!(ms)))
+        }
+    }
+
+    fn tool_results(agent: &ClaudeAgent, index: usize) -> Vec<(String, String, bool)> {
+        let ApiContent::Blocks(blocks) = &agent.history()[index].content else { panic!("expected blocks") };
+        blocks.iter().filter_map(|b| match b {
+            ContentBlock::ToolResult { tool_use_id, content, is_error } => {
+                Some((tool_use_id.clone(), content.to_text(), *is_error))
+            }
+            _ => None,
+        }).collect()
+    }
+
+    #[async_trait]
+    impl LlmClient for MockClient {
+        async fn complete(&self, request: LlmRequest) -> Result<LlmResponse, ClawError> {
+            let chars = serde_json::to_string(&request.messages).unwrap().len()
+                + request.system.as_deref().unwrap_or_default().len();
+            let text = if request.system.as_deref() == Some(context::SUMMARY_SYSTEM_PROMPT) {
+                if self.fail_summaries {
+                    return Err(ClawError::NoProvider("mock".to_string()));
+                }
+                "the user asked about numbers".to_string()
+            } else {
+                "ok".to_string()
+            };
+            self.requests.lock().unwrap().push(request);
+            let (stop_reason, content) = match self.script.lock().unwrap().pop_front() {
+                Some(blocks) => ("tool_use", blocks),
+                None => ("end_turn", vec![ContentBlock::Text { text }]),
+            };
+            Ok(LlmResponse {
+                id: "msg".to_string(),
+                model: "mock".to_string(),
+                stop_reason: Some(stop_reason.to_string()),
+                content,
+                usage: Usage { input_tokens: (chars / 4) as u32, output_tokens: 1 },
+            })
+        }
+
+        async fn complete_stream(&self, _request: LlmRequest) -> Result<LlmStream, ClawError> {
+            Err(ClawError::NoProvider("mock".to_string()))
+        }
+    }
+
+    async fn chat(agent: &mut ClaudeAgent, turns: usize) {
+        for i in 0..turns {
+            let text = format!("question {i}: {}", "lorem ipsum ".repeat(20));
+            agent.process(Message::user(text)).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_keeps_history_within_budget() {
+        let client = MockClient::default();
+        let config = AgentConfig::new("bot", "be brief").with_context_budget(300);
+        let mut agent = ClaudeAgent::with_client(config, client.clone());
+        agent.start().await.unwrap();
+
+        chat(&mut agent, 10).await;
+
+        let usage = agent.context_usage().clone();
+        assert!(usage.compacted_turns > 0);
+        assert_eq!(usage.summarized_turns, 0);
+        assert!(usage.last_input_tokens <= 300, "sent {} tokens", usage.last_input_tokens);
+        assert!(usage.total_input_tokens > u64::from(usage.last_input_tokens));
+        assert!(agent.summary().is_none());
+        // History always starts on a user turn.
+        assert!(matches!(agent.history()[0].content, ApiContent::Text(_)));
+        assert!(agent.history().len() < 20);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_folds_old_turns_into_system_prompt() {
+        let client = MockClient::default();
+        let config = AgentConfig::new("bot", "be brief").with_context_budget(400);
+        let mut agent = ClaudeAgent::with_client(config, client.clone())
+            .with_context_policy(ContextPolicy::summarize().with_summary_max_tokens(50));
+        agent.start().await.unwrap();
+
+        chat(&mut agent, 10).await;
+
+        assert!(agent.context_usage().summarized_turns > 0);
+        assert_eq!(agent.summary(), Some("the user asked about numbers"));
+        let requests = client.requests.lock().unwrap();
+        let summary_requests = requests
+            .iter()
+            .filter(|r| r.system.as_deref() == Some(context::SUMMARY_SYSTEM_PROMPT))
+            .count();
+        assert!(summary_requests > 0);
+        let last = requests.last().unwrap();
+        assert!(last.system.as_deref().unwrap().contains("<conversation_summary>"));
+        assert_eq!(last.max_tokens, 8192);
+    }
+
+    #[tokio::test]
+    async fn test_failed_summary_leaves_truncation_marker() {
+        let client = MockClient { fail_summaries: true, ..MockClient::default() };
+        let config = AgentConfig::new("bot", "be brief").with_context_budget(400);
+        let mut agent = ClaudeAgent::with_client(config, client.clone())
+            .with_context_policy(ContextPolicy::summarize().with_summary_max_tokens(50));
+        agent.start().await.unwrap();
+
+        chat(&mut agent, 10).await;
+
+        let usage = agent.context_usage();
+        assert!(usage.compacted_turns > 0);
+        assert_eq!(usage.summarized_turns, 0);
+        assert!(agent.summary().unwrap().contains("earlier turns were removed without a summary"));
+        let requests = client.requests.lock().unwrap();
+        assert!(requests.last().unwrap().system.as_deref().unwrap().contains("<conversation_summary>"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_run_concurrently_with_error_flags() {
+        let client = MockClient::scripted([vec![
+            tool_use("a", "sleep", json!({"ms": 200})),
+            tool_use("b", "sleep", json!({"ms": 200, "fail": true})),
+            tool_use("c", "sleep", json!({"ms": 200})),
+            tool_use("d", "missing", json!({})),
+            tool_use("e", "sleep", json!({"ms": 5000})),
+        ]]);
+        let policy = ToolPolicy::default().with_timeout(Duration::from_millis(400));
+        let mut agent = ClaudeAgent::with_client(AgentConfig::new("bot", "be brief"), client)
+            .with_tool_policy(policy);
+        agent.register_skill(Arc::new(SleepSkill));
+        agent.start().await.unwrap();
+
+        let started = Instant::now();
+        let reply = agent.process(Message::user("go")).await.unwrap();
+        assert_eq!(reply.content, "ok");
+        assert!(started.elapsed() < Duration::from_millis(900), "took {:?}", started.elapsed());
+
+        let results = tool_results(&agent, 2);
+        let ids: Vec<&str> = results.iter().map(|r| r.0.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c", "d", "e"]);
+        assert_eq!(results[0].1, "200");
+        assert!(!results[0].2);
+        assert_eq!((results[1].1.as_str(), results[1].2), ("boom", true));
+        assert!(results[3].2 && results[3].1.contains("unknown skill"));
+        assert!(results[4].2 && results[4].1.contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_round_limit_ends_turn_gracefully() {
+        let call = || vec![ContentBlock::Text { text: "working".to_string() }, tool_use("t", "sleep", json!({}))];
+        let client = MockClient::scripted([call(), call(), call(), call()]);
+        let mut agent = ClaudeAgent::with_client(AgentConfig::new("bot", "be brief"), client.clone())
+            .with_tool_policy(ToolPolicy::default().with_max_rounds(2));
+        agent.register_skill(Arc::new(SleepSkill));
+        agent.start().await.unwrap();
+
+        let reply = agent.process(Message::user("go")).await.unwrap();
+        assert_eq!(reply.content, "working");
+        assert_eq!(client.requests.lock().unwrap().len(), 3);
+        // The unanswered call was dropped, so the next turn is valid.
+        let ApiContent::Blocks(last) = &agent.history().last().unwrap().content else { panic!() };
+        assert!(last.iter().all(|b| !matches!(b, ContentBlock::ToolUse { .. })));
+        assert_eq!(agent.process(Message::user("again")).await.unwrap().content, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_approval_hook_gates_sensitive_skills() {
+        let client = MockClient::scripted([vec![
+            tool_use("a", "sleep", json!({"ms": 1})),
+            tool_use("b", "echo", json!({"message": "hi"})),
+        ]]);
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let seen = asked.clone();
+        let approver = move |request: &ApprovalRequest| {
+            seen.lock().unwrap().push(request.skill.clone());
+            Approval::Deny("not now".to_string())
+        };
+        let mut agent = ClaudeAgent::with_client(AgentConfig::new("bot", "be brief"), client)
+            .with_approval_hook(Arc::new(approver), ["sleep"]);
+        agent.register_skill(Arc::new(SleepSkill));
+        agent.register_skill(Arc::new(pixelcore_skills::EchoSkill));
+        agent.start().await.unwrap();
+
+        agent.process(Message::user("go")).await.unwrap();
+        assert_eq!(*asked.lock().unwrap(), ["sleep"]);
+        let results = tool_results(&agent, 2);
+        assert_eq!(results[0], ("a".to_string(), "denied: not now".to_string(), true));
+        assert!(!results[1].2);
+    }
+
+    #[test]
+    fn test_image_results_become_image_blocks() {
+        let result = json!({
+            "text": "rendered page 1",
+            "content": [{"type": "image", "data": "AAAA", "mimeType": "image/png"}]
+        });
+        let ApiContent::Blocks(blocks) = ClaudeAgent::tool_content(result) else { panic!("expected blocks") };
+        assert!(matches!(&blocks[0], ContentBlock::Text { text } if text == "rendered page 1"));
+        assert!(matches!(&blocks[1], ContentBlock::Image { .. }));
+
+        let plain = ClaudeAgent::tool_content(json!({"content": "just a field"}));
+        assert!(matches!(plain, ApiContent::Text(_)));
+    }
+
+    #[derive(Debug, serde::Deserialize, JsonSchema)]
+    struct Weather {
+        city: String,
+        temp_c: f64,
+    }
+
+    #[tokio::test]
+    async fn test_structured_reply_is_validated_and_retried() {
+        let client = MockClient::scripted([
+            vec![tool_use("t1", "Weather", json!({"city": "Oslo"}))],
+            vec![tool_use("t2", "Weather", json!({"city": "Oslo", "temp_c": -3.5}))],
+        ]);
+        let mut agent = ClaudeAgent::with_client(AgentConfig::new("bot", "be brief"), client.clone());
+        agent.register_skill(Arc::new(SleepSkill));
+        agent.start().await.unwrap();
+
+        let weather: Weather = agent.process_structured(Message::user("Weather in Oslo?")).await.unwrap();
+        assert_eq!((weather.city.as_str(), weather.temp_c), ("Oslo", -3.5));
+
+        let requests = client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].output_format.as_ref().unwrap().name, "Weather");
+        assert!(requests[0].tools.is_none());
+        // The retry carries the validation error as a failed tool result.
+        let ApiContent::Blocks(feedback) = &requests[1].messages.last().unwrap().content else { panic!() };
+        assert!(matches!(&feedback[0], ContentBlock::ToolResult { tool_use_id, is_error: true, content }
+            if tool_use_id == "t1" && content.to_text().contains("temp_c")));
+        // Only the question and the valid answer are kept.
+        assert_eq!(agent.history().len(), 2);
+        assert_eq!(agent.history()[1].content.to_text(), json!({"city": "Oslo", "temp_c": -3.5}).to_string());
+    }
+
+    #[tokio::test]
+    async fn test_structured_text_replies_and_giving_up() {
+        let text = |t: &str| vec![ContentBlock::text(t)];
+        let client = MockClient::scripted([
+            text("```json\n{\"value\": [\"Oslo\", \"Bergen\"]}\n```"),
+            text("Sure! Oslo."),
+            text("[1, 2]"),
+        ]);
+        let mut agent = ClaudeAgent::with_client(AgentConfig::new("bot", "be brief"), client.clone());
+        agent.start().await.unwrap();
+        let schema = OutputSchema::new("cities", json!({"type": "array", "items": {"type": "string"}}))
+            .with_max_attempts(2);
+
+        let cities = agent.process_json(Message::user("Two cities"), &schema).await.unwrap();
+        assert_eq!(cities, json!(["Oslo", "Bergen"]));
+        let format = client.requests.lock().unwrap()[0].output_format.clone().unwrap();
+        assert_eq!(format.schema["properties"]["value"]["type"], "array");
 
-        Err(RuntimeError::Other(anyhow!("exceeded max tool rounds ({})", MAX_TOOL_ROUNDS)))
+        let err = agent.process_json(Message::user("Two more"), &schema).await.unwrap_err();
+        assert!(err.to_string().contains("after 2 attempts"), "{err}");
+        assert_eq!(agent.history().len(), 2);
     }
 }
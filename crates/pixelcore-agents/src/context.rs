@@ -0,0 +1,382 @@
+//! Keeping `ClaudeAgent` history inside the model's context window.
+//!
+//! History is compacted a whole turn at a time. A turn starts with a user
+//! message other than tool results and runs through every tool_use /
+//! tool_result exchange that follows, so compaction never separates a tool
+//! call from its result.
+
+use serde::{Deserialize, Serialize};
+
+use pixelcore_claw::types::{ApiContent, ApiMessage, ContentBlock, Usage};
+
+/// Rough characters-per-token ratio used before the API has reported usage.
+const CHARS_PER_TOKEN: usize = 4;
+/// Per-message framing overhead (role, separators).
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Rough cost of an image or document; reported usage corrects it.
+const MEDIA_TOKENS: usize = 1600;
+
+/// What happens to turns that no longer fit the budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Drop the oldest turns.
+    SlidingWindow,
+    /// Fold the oldest turns into a rolling LLM-written summary that is sent
+    /// with the system prompt.
+    Summarize,
+}
+
+/// How `ClaudeAgent` keeps its history within `AgentConfig::context_budget`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextPolicy {
+    pub strategy: ContextStrategy,
+    /// Once over budget, compact down to this fraction of it so that the next
+    /// few turns fit without compacting again.
+    pub target_ratio: f32,
+    /// Output limit for summary requests; also reserved in the budget for the summary.
+    pub summary_max_tokens: u32,
+    /// Model for summary requests. Defaults to the agent's model.
+    pub summary_model: Option<String>,
+}
+
+impl ContextPolicy {
+    pub fn sliding_window() -> Self {
+        Self {
+            strategy: ContextStrategy::SlidingWindow,
+            target_ratio: 0.75,
+            summary_max_tokens: 1024,
+            summary_model: None,
+        }
+    }
+
+    pub fn summarize() -> Self {
+        Self { strategy: ContextStrategy::Summarize, ..Self::sliding_window() }
+    }
+
+    pub fn with_target_ratio(mut self, ratio: f32) -> Self {
+        self.target_ratio = ratio.clamp(0.1, 1.0);
+        self
+    }
+
+    pub fn with_summary_model(mut self, model: impl Into<String>) -> Self {
+        self.summary_model = Some(model.into());
+        self
+    }
+
+    pub fn with_summary_max_tokens(mut self, tokens: u32) -> Self {
+        self.summary_max_tokens = tokens;
+        self
+    }
+}
+
+impl Default for ContextPolicy {
+    fn default() -> Self {
+        Self::sliding_window()
+    }
+}
+
+/// Token accounting for one agent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextUsage {
+    pub budget: Option<u32>,
+    /// Estimated input tokens of the next request
+    pub estimated_tokens: u32,
+    /// Input tokens the API reported for the last request
+    pub last_input_tokens: u32,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    /// Turns removed from history so far
+    pub compacted_turns: usize,
+    /// How many of those are covered by the summary
+    pub summarized_turns: usize,
+}
+
+/// Usage reported for the last request, and what that request contained
+#[derive(Debug, Clone, Copy)]
+struct Calibration {
+    input_tokens: u32,
+    output_tokens: u32,
+    /// Messages sent in the request; the reply was appended after them.
+    messages: usize,
+}
+
+/// Per-agent context state: policy, rolling summary and usage-driven estimates
+#[derive(Debug, Clone)]
+pub(crate) struct ContextWindow {
+    pub(crate) policy: ContextPolicy,
+    pub(crate) summary: Option<String>,
+    calibration: Option<Calibration>,
+    /// Reported / estimated tokens, learned from the last request
+    ratio: f64,
+    usage: ContextUsage,
+}
+
+impl ContextWindow {
+    pub(crate) fn new(policy: ContextPolicy) -> Self {
+        Self { policy, summary: None, calibration: None, ratio: 1.0, usage: ContextUsage::default() }
+    }
+
+    pub(crate) fn usage(&self) -> &ContextUsage {
+        &self.usage
+    }
+
+    /// Learn from the usage the API reported for a request of `messages`
+    /// history messages plus `overhead` (heuristic) tokens of system prompt and tools.
+    pub(crate) fn record(&mut self, history: &[ApiMessage], messages: usize, overhead: usize, usage: &Usage) {
+        let heuristic = overhead + history[..messages.min(history.len())].iter().map(message_tokens).sum::<usize>();
+        if heuristic > 0 && usage.input_tokens > 0 {
+            self.ratio = (usage.input_tokens as f64 / heuristic as f64).clamp(0.25, 4.0);
+        }
+        self.calibration = Some(Calibration {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            messages,
+        });
+        self.usage.last_input_tokens = usage.input_tokens;
+        self.usage.total_input_tokens += u64::from(usage.input_tokens);
+        self.usage.total_output_tokens += u64::from(usage.output_tokens);
+    }
+
+    /// Estimated input tokens for sending `history` with `overhead` heuristic tokens.
+    ///
+    /// Uses the last reported usage for the part of history that was already
+    /// sent, and the calibrated heuristic for what was added since.
+    pub(crate) fn estimate(&mut self, history: &[ApiMessage], overhead: usize) -> u32 {
+        let estimate = match self.calibration {
+            Some(c) if c.messages < history.len() => {
+                let added: usize = history[c.messages + 1..].iter().map(message_tokens).sum();
+                c.input_tokens as usize + c.output_tokens as usize + self.scale(added)
+            }
+            Some(c) if c.messages == history.len() => c.input_tokens as usize,
+            _ => self.scale(overhead + history.iter().map(message_tokens).sum::<usize>()),
+        };
+        self.usage.estimated_tokens = estimate.min(u32::MAX as usize) as u32;
+        self.usage.estimated_tokens
+    }
+
+    /// Number of leading messages to remove so the rest fits in `target`
+    /// tokens. Always a turn boundary, and never removes the current turn.
+    pub(crate) fn compaction_point(&self, history: &[ApiMessage], overhead: usize, target: u32) -> usize {
+        let starts = turn_starts(history);
+        let mut remaining = self.scale(overhead + history.iter().map(message_tokens).sum::<usize>());
+        let mut cut = 0;
+        for window in starts.windows(2) {
+            if remaining <= target as usize {
+                break;
+            }
+            let (start, end) = (window[0], window[1]);
+            remaining = remaining.saturating_sub(self.scale(history[start..end].iter().map(message_tokens).sum()));
+            cut = end;
+        }
+        cut
+    }
+
+    /// History changed shape: reported usage no longer lines up with it.
+    pub(crate) fn compacted(&mut self, turns: usize, summarized: bool) {
+        self.calibration = None;
+        self.usage.compacted_turns += turns;
+        if summarized {
+            self.usage.summarized_turns += turns;
+        }
+    }
+
+    pub(crate) fn set_budget(&mut self, budget: Option<u32>) {
+        self.usage.budget = budget;
+    }
+
+    /// The summary changes the system prompt, so usage no longer lines up.
+    pub(crate) fn set_summary(&mut self, summary: Option<String>) {
+        self.summary = summary;
+        self.calibration = None;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.summary = None;
+        self.calibration = None;
+        self.usage = ContextUsage { budget: self.usage.budget, ..ContextUsage::default() };
+    }
+
+    fn scale(&self, tokens: usize) -> usize {
+        (tokens as f64 * self.ratio).ceil() as usize
+    }
+}
+
+/// Heuristic token count for a piece of text.
+pub(crate) fn text_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+fn message_tokens(message: &ApiMessage) -> usize {
+    MESSAGE_OVERHEAD_TOKENS + content_tokens(&message.content)
+}
+
+fn content_tokens(content: &ApiContent) -> usize {
+    match content {
+        ApiContent::Text(text) => text_tokens(text),
+        ApiContent::Blocks(blocks) => blocks.iter().map(block_tokens).sum(),
+    }
+}
+
+fn block_tokens(block: &ContentBlock) -> usize {
+    match block {
+        ContentBlock::Text { text } => text_tokens(text),
+        ContentBlock::Image { .. } | ContentBlock::Document { .. } => MEDIA_TOKENS,
+        ContentBlock::ToolUse { id, name, input } => text_tokens(id) + text_tokens(name) + text_tokens(&input.to_string()),
+        ContentBlock::ToolResult { tool_use_id, content, .. } => text_tokens(tool_use_id) + content_tokens(content),
+    }
+}
+
+/// A user message that is not just tool results.
+fn is_turn_start(message: &ApiMessage) -> bool {
+    message.role == "user"
+        && match &message.content {
+            ApiContent::Text(_) => true,
+            ApiContent::Blocks(blocks) => !blocks.iter().any(|b| matches!(b, ContentBlock::ToolResult { .. })),
+        }
+}
+
+/// Indices where turns start.
+///
+/// The last entry is the turn in progress. Compaction walks `windows(2)`, so
+/// it never reaches past that start and the current turn is never removed.
+fn turn_starts(history: &[ApiMessage]) -> Vec<usize> {
+    let mut starts: Vec<usize> = history
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| is_turn_start(m))
+        .map(|(i, _)| i)
+        .collect();
+    if starts.first() != Some(&0) {
+        // History that does not start on a turn boundary can only be cut at the next one.
+        starts.insert(0, 0);
+    }
+    starts
+}
+
+/// Number of turns in `messages` (which start on a turn boundary).
+pub(crate) fn count_turns(messages: &[ApiMessage]) -> usize {
+    messages.iter().filter(|m| is_turn_start(m)).count()
+}
+
+/// Plain-text transcript handed to the summarizer.
+pub(crate) fn render_transcript(messages: &[ApiMessage]) -> String {
+    let mut out = String::new();
+    for message in messages {
+        let speaker = if message.role == "assistant" { "Assistant" } else { "User" };
+        match &message.content {
+            ApiContent::Text(text) => out.push_str(&format!("{speaker}: {text}\n")),
+            ApiContent::Blocks(blocks) => {
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text } => out.push_str(&format!("{speaker}: {text}\n")),
+                        ContentBlock::Image { .. } => out.push_str(&format!("{speaker}: [image]\n")),
+                        ContentBlock::Document { title, .. } => {
+                            out.push_str(&format!("{speaker}: [document {}]\n", title.as_deref().unwrap_or_default()))
+                        }
+                        ContentBlock::ToolUse { name, input, .. } => {
+                            out.push_str(&format!("Assistant called tool {name} with {input}\n"))
+                        }
+                        ContentBlock::ToolResult { content, .. } => {
+                            out.push_str(&format!("Tool result: {}\n", content.to_text()))
+                        }
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+pub(crate) const SUMMARY_SYSTEM_PROMPT: &str = "You maintain a running summary of a conversation between a user and an \
+assistant that can call tools. Merge the previous summary (if any) with the new transcript. Keep facts, decisions, \
+names, numbers, open tasks and tool results that later turns may depend on. Drop pleasantries. Reply with the summary only.";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(text: &str) -> ApiMessage {
+        ApiMessage { role: "user".to_string(), content: ApiContent::Text(text.to_string()) }
+    }
+
+    fn assistant(text: &str) -> ApiMessage {
+        ApiMessage {
+            role: "assistant".to_string(),
+            content: ApiContent::Blocks(vec![ContentBlock::Text { text: text.to_string() }]),
+        }
+    }
+
+    fn tool_round(id: &str) -> [ApiMessage; 2] {
+        [
+            ApiMessage {
+                role: "assistant".to_string(),
+                content: ApiContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: id.to_string(),
+                    name: "echo".to_string(),
+                    input: serde_json::json!({"message": "x".repeat(400)}),
+                }]),
+            },
+            ApiMessage {
+                role: "user".to_string(),
+                content: ApiContent::Blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: id.to_string(),
+                    content: "y".repeat(400).into(),
+                    is_error: false,
+                }]),
+            },
+        ]
+    }
+
+    fn history() -> Vec<ApiMessage> {
+        let mut history = vec![user("first question")];
+        history.extend(tool_round("t1"));
+        history.push(assistant("first answer"));
+        history.push(user("second question"));
+        history.push(assistant("second answer"));
+        history.push(user("third question"));
+        history.extend(tool_round("t2"));
+        history
+    }
+
+    #[test]
+    fn test_compaction_keeps_tool_pairs_and_current_turn() {
+        let window = ContextWindow::new(ContextPolicy::default());
+        let history = history();
+        assert_eq!(turn_starts(&history), vec![0, 4, 6]);
+
+        // Enough room for everything but the first turn
+        assert_eq!(window.compaction_point(&history, 0, 300), 4);
+        // Nothing fits: everything up to the current turn goes, the current turn stays
+        assert_eq!(window.compaction_point(&history, 0, 1), 6);
+        assert_eq!(count_turns(&history[..6]), 2);
+        // Already fits
+        assert_eq!(window.compaction_point(&history, 0, 10_000), 0);
+    }
+
+    #[test]
+    fn test_estimate_uses_reported_usage() {
+        let mut window = ContextWindow::new(ContextPolicy::default());
+        let mut history = vec![user("hello")];
+        let heuristic = window.estimate(&history, 100);
+        assert_eq!(heuristic, 100 + 4 + 2);
+
+        // The API counted twice as many tokens as the heuristic.
+        window.record(&history, 1, 100, &Usage { input_tokens: 212, output_tokens: 10 });
+        history.push(assistant("hi"));
+        history.push(user("12345678"));
+        // 212 sent + 10 generated + 2 * (4 + 2) for the new user message
+        assert_eq!(window.estimate(&history, 100), 212 + 10 + 12);
+        assert_eq!(window.usage().total_input_tokens, 212);
+
+        window.compacted(1, false);
+        assert_eq!(window.estimate(&history, 100), 2 * (100 + 6 + 5 + 6) as u32);
+    }
+
+    #[test]
+    fn test_transcript() {
+        let transcript = render_transcript(&history()[..4]);
+        assert!(transcript.starts_with("User: first question\nAssistant called tool echo with"));
+        assert!(transcript.ends_with("Assistant: first answer\n"));
+    }
+}
@@ -1,3 +1,9 @@
 pub mod claude_agent;
+pub mod context;
+pub mod structured;
+pub mod tools;
 
 pub use claude_agent::ClaudeAgent;
+pub use context::{ContextPolicy, ContextStrategy, ContextUsage};
+pub use structured::OutputSchema;
+pub use tools::{Approval, ApprovalRequest, ToolApprover, ToolPolicy};
@@ -0,0 +1,110 @@
+//! Schema-constrained replies for `ClaudeAgent::process_structured`.
+
+use jsonschema::Validator;
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+use pixelcore_claw::types::OutputFormat;
+
+/// The JSON Schema a structured reply must match
+#[derive(Debug, Clone)]
+pub struct OutputSchema {
+    pub name: String,
+    pub description: Option<String>,
+    pub schema: Value,
+    /// Model calls before giving up; each retry is told what failed validation.
+    pub max_attempts: usize,
+}
+
+impl OutputSchema {
+    /// `name` becomes a tool name on Anthropic, so characters outside
+    /// `[A-Za-z0-9_-]` are replaced.
+    pub fn new(name: impl Into<String>, schema: Value) -> Self {
+        let name: String = name
+            .into()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .take(64)
+            .collect();
+        Self { name, description: None, schema, max_attempts: 3 }
+    }
+
+    /// Schema derived from `T` with `schemars`.
+    pub fn of<T: JsonSchema>() -> Self {
+        let schema = schemars::schema_for!(T);
+        Self::new(T::schema_name(), schema.to_value())
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Backends need an object at the root; anything else is wrapped in
+    /// `{"value": ...}` and unwrapped by `unwrap_value`.
+    fn wrapped(&self) -> bool {
+        self.schema.get("type").and_then(Value::as_str) != Some("object")
+    }
+
+    pub(crate) fn output_format(&self) -> OutputFormat {
+        let mut schema = self.schema.clone();
+        if let Some(object) = schema.as_object_mut() {
+            object.remove("$schema");
+        }
+        if self.wrapped() {
+            let defs = schema.as_object_mut().and_then(|o| o.remove("$defs"));
+            schema = json!({ "type": "object", "properties": { "value": schema }, "required": ["value"] });
+            if let Some(defs) = defs {
+                schema["$defs"] = defs;
+            }
+        }
+        OutputFormat { name: self.name.clone(), description: self.description.clone(), schema }
+    }
+
+    pub(crate) fn unwrap_value(&self, mut value: Value) -> Value {
+        if self.wrapped() {
+            if let Some(inner) = value.get_mut("value") {
+                return inner.take();
+            }
+        }
+        value
+    }
+
+    pub(crate) fn validator(&self) -> Result<Validator, String> {
+        jsonschema::validator_for(&self.schema).map_err(|e| format!("invalid schema '{}': {e}", self.name))
+    }
+}
+
+/// Validation errors for `value`, empty if it conforms.
+pub(crate) fn validation_errors(validator: &Validator, value: &Value) -> Vec<String> {
+    validator
+        .iter_errors(value)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            if path.is_empty() { e.to_string() } else { format!("{path}: {e}") }
+        })
+        .collect()
+}
+
+/// Parse a JSON reply, tolerating a surrounding markdown code fence.
+pub(crate) fn parse_json_text(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|t| t.strip_suffix("```"))
+        .unwrap_or(text);
+    serde_json::from_str(text.trim()).map_err(|e| format!("not valid JSON: {e}"))
+}
+
+pub(crate) fn retry_prompt(errors: &[String]) -> String {
+    format!(
+        "The reply does not match the required schema:\n- {}\nReply again with corrected JSON.",
+        errors.join("\n- ")
+    )
+}
@@ -0,0 +1,80 @@
+//! How `ClaudeAgent` runs the tool calls the model asks for.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use pixelcore_runtime::AgentId;
+
+/// Limits for tool execution within one `process` call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    /// Tool calls of one assistant turn that may run at the same time.
+    pub max_concurrency: usize,
+    /// Per-call limit; a call that runs longer is reported to the model as failed.
+    pub timeout: Duration,
+    /// Model round-trips with tool calls before the turn is ended. The model's
+    /// text from the last round is returned and its remaining tool calls are dropped.
+    pub max_rounds: usize,
+}
+
+impl ToolPolicy {
+    pub fn with_max_concurrency(mut self, n: usize) -> Self {
+        self.max_concurrency = n.max(1);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_rounds(mut self, rounds: usize) -> Self {
+        self.max_rounds = rounds;
+        self
+    }
+}
+
+impl Default for ToolPolicy {
+    fn default() -> Self {
+        Self { max_concurrency: 4, timeout: Duration::from_secs(60), max_rounds: 10 }
+    }
+}
+
+/// A tool call waiting for approval
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    pub agent_id: AgentId,
+    pub agent_name: String,
+    pub tool_use_id: String,
+    pub skill: String,
+    pub input: serde_json::Value,
+}
+
+/// Answer from a `ToolApprover`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    Approve,
+    /// The call is not run; the reason is reported to the model.
+    Deny(String),
+}
+
+/// Human-in-the-loop gate for sensitive skills.
+///
+/// Called before each gated call runs, one call at a time, so an interactive
+/// approver never sees two prompts at once.
+#[async_trait]
+pub trait ToolApprover: Send + Sync {
+    async fn approve(&self, request: &ApprovalRequest) -> Approval;
+}
+
+/// Synchronous closures work as approvers, e.g. a terminal y/n prompt.
+#[async_trait]
+impl<F> ToolApprover for F
+where
+    F: Fn(&ApprovalRequest) -> Approval + Send + Sync,
+{
+    async fn approve(&self, request: &ApprovalRequest) -> Approval {
+        self(request)
+    }
+}
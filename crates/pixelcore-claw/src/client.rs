@@ -1,11 +1,22 @@
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response};
 use crate::error::ClawError;
+use crate::retry::{RateLimiter, RetryPolicy};
+use crate::stream::{AnthropicDecoder, LlmStream, OpenAiDecoder};
 use crate::types::{LlmRequest, LlmResponse, OpenAiResponse};
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
 const SILICONFLOW_API_URL: &str = "https://api.siliconflow.cn/v1/chat/completions";
 
+/// Anything that can serve completions: a single `ClawClient` or a `ClawRouter`.
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse, ClawError>;
+
+    async fn complete_stream(&self, request: LlmRequest) -> Result<LlmStream, ClawError>;
+}
+
 pub enum ApiBackend {
     Anthropic,
     OpenAiCompat { base_url: String },
@@ -15,23 +26,29 @@ pub struct ClawClient {
     client: Client,
     api_key: String,
     backend: ApiBackend,
+    anthropic_url: String,
+    retry: RetryPolicy,
+    limiter: Option<RateLimiter>,
 }
 
 impl ClawClient {
     pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_backend(api_key, ApiBackend::Anthropic)
+    }
+
+    fn with_backend(api_key: impl Into<String>, backend: ApiBackend) -> Self {
         Self {
             client: Client::new(),
             api_key: api_key.into(),
-            backend: ApiBackend::Anthropic,
+            backend,
+            anthropic_url: ANTHROPIC_API_URL.to_string(),
+            retry: RetryPolicy::default(),
+            limiter: None,
         }
     }
 
     pub fn with_openai_compat(api_key: impl Into<String>, base_url: impl Into<String>) -> Self {
-        Self {
-            client: Client::new(),
-            api_key: api_key.into(),
-            backend: ApiBackend::OpenAiCompat { base_url: base_url.into() },
-        }
+        Self::with_backend(api_key, ApiBackend::OpenAiCompat { base_url: base_url.into() })
     }
 
     pub fn siliconflow(api_key: impl Into<String>) -> Self {
@@ -44,7 +61,39 @@ impl ClawClient {
         Ok(Self::new(api_key))
     }
 
-    pub async fn complete(&self, request: LlmRequest) -> Result<LlmResponse, ClawError> {
+    /// Override the Anthropic messages endpoint (proxies, gateways, tests).
+    pub fn with_anthropic_url(mut self, url: impl Into<String>) -> Self {
+        self.anthropic_url = url.into();
+        self
+    }
+
+    /// Replace the default retry policy; `RetryPolicy::none()` disables retries.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Throttle requests through a token bucket. Clone the limiter to share it
+    /// between clients so that all agents on one API key stay under its limits.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Send a completion request, retrying transient failures per the retry policy.
+    pub async fn complete(&self, mut request: LlmRequest) -> Result<LlmResponse, ClawError> {
+        request.load_media().await?;
+        self.retry
+            .run(self.limiter.as_ref(), || self.complete_once(&request))
+            .await
+    }
+
+    async fn complete_once(&self, request: &LlmRequest) -> Result<LlmResponse, ClawError> {
+        let request = request.clone();
         match &self.backend {
             ApiBackend::Anthropic => self.complete_anthropic(request).await,
             ApiBackend::OpenAiCompat { base_url } => {
@@ -53,62 +102,92 @@ impl ClawClient {
         }
     }
 
-    async fn complete_anthropic(&self, request: LlmRequest) -> Result<LlmResponse, ClawError> {
-        let response = self.client
-            .post(ANTHROPIC_API_URL)
+    /// Stream a completion as server-sent events.
+    ///
+    /// Both backends are normalised into the same `StreamEvent` sequence; use
+    /// `LlmStream::collect` to obtain the final `LlmResponse`. Only opening the
+    /// stream is retried; errors after the first event are returned as-is.
+    pub async fn complete_stream(&self, mut request: LlmRequest) -> Result<LlmStream, ClawError> {
+        request.stream = true;
+        request.load_media().await?;
+        self.retry
+            .run(self.limiter.as_ref(), || async {
+                match &self.backend {
+                    ApiBackend::Anthropic => {
+                        let response = send(self.anthropic_request().json(&request.anthropic_body())).await?;
+                        Ok(LlmStream::new(response, Box::new(AnthropicDecoder)))
+                    }
+                    ApiBackend::OpenAiCompat { base_url } => {
+                        let response = send(self.openai_request(base_url).json(&request.to_openai())).await?;
+                        Ok(LlmStream::new(response, Box::new(OpenAiDecoder::default())))
+                    }
+                }
+            })
+            .await
+    }
+
+    fn anthropic_request(&self) -> RequestBuilder {
+        self.client
+            .post(&self.anthropic_url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", ANTHROPIC_VERSION)
             .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
-
-        let status = response.status().as_u16();
-        if status == 429 {
-            let retry_after = response
-                .headers()
-                .get("retry-after")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(60);
-            return Err(ClawError::RateLimited { retry_after });
-        }
-        if !response.status().is_success() {
-            let body = response.text().await.unwrap_or_default();
-            return Err(ClawError::Api { status, message: body });
-        }
+    }
+
+    fn openai_request(&self, url: &str) -> RequestBuilder {
+        self.client
+            .post(url)
+            .bearer_auth(&self.api_key)
+            .header("content-type", "application/json")
+    }
 
+    async fn complete_anthropic(&self, request: LlmRequest) -> Result<LlmResponse, ClawError> {
+        let response = send(self.anthropic_request().json(&request.anthropic_body())).await?;
         Ok(response.json::<LlmResponse>().await?)
     }
 
     async fn complete_openai(&self, request: LlmRequest, url: String) -> Result<LlmResponse, ClawError> {
         // Convert to OpenAI chat format
         let openai_req = request.to_openai();
-
-        let response = self.client
-            .post(&url)
-            .bearer_auth(&self.api_key)
-            .header("content-type", "application/json")
-            .json(&openai_req)
-            .send()
-            .await?;
-
-        let status = response.status().as_u16();
-        if status == 429 {
-            let retry_after = response
-                .headers()
-                .get("retry-after")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(60);
-            return Err(ClawError::RateLimited { retry_after });
-        }
-        if !response.status().is_success() {
-            let body = response.text().await.unwrap_or_default();
-            return Err(ClawError::Api { status, message: body });
-        }
-
+        let response = send(self.openai_request(&url).json(&openai_req)).await?;
         let oai: OpenAiResponse = response.json().await?;
         Ok(oai.into_llm_response())
     }
 }
+
+#[async_trait]
+impl LlmClient for ClawClient {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse, ClawError> {
+        ClawClient::complete(self, request).await
+    }
+
+    async fn complete_stream(&self, request: LlmRequest) -> Result<LlmStream, ClawError> {
+        ClawClient::complete_stream(self, request).await
+    }
+}
+
+/// Send a request and map error statuses to `ClawError`.
+async fn send(request: RequestBuilder) -> Result<Response, ClawError> {
+    let response = request.send().await?;
+
+    let status = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .map(|secs| secs.max(0.0).ceil() as u64);
+    if status == 429 {
+        return Err(ClawError::RateLimited { retry_after: retry_after.unwrap_or(60) });
+    }
+    // 529 is Anthropic's "overloaded"; 503 is the generic equivalent.
+    if status == 503 || status == 529 {
+        let message = response.text().await.unwrap_or_default();
+        return Err(ClawError::Overloaded { status, message, retry_after });
+    }
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(ClawError::Api { status, message: body });
+    }
+    Ok(response)
+}
@@ -17,9 +17,57 @@ pub enum ClawError {
     #[error("Rate limited, retry after {retry_after}s")]
     RateLimited { retry_after: u64 },
 
+    #[error("API overloaded ({status}): {message}")]
+    Overloaded { status: u16, message: String, retry_after: Option<u64> },
+
+    #[error("Gave up after {attempts} attempts: {source}")]
+    RetriesExhausted { attempts: u32, source: Box<ClawError> },
+
+    #[error("No provider serves model '{0}'")]
+    NoProvider(String),
+
+    #[error("All providers failed for model '{model}': {}", describe_failures(.failures))]
+    ProvidersExhausted { model: String, failures: Vec<(String, ClawError)> },
+
     #[error("Provider not supported: {0}")]
     UnsupportedProvider(String),
 
     #[error("{0}")]
     Other(String),
 }
+
+impl ClawError {
+    /// Whether the request may succeed if sent again unchanged.
+    ///
+    /// Covers rate limiting, overload, gateway and server errors and failures
+    /// to connect; client errors such as 400/401 are never retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClawError::RateLimited { .. } | ClawError::Overloaded { .. } => true,
+            ClawError::Api { status, .. } => matches!(status, 408 | 409 | 500 | 502 | 504),
+            ClawError::Http(e) => e.is_connect() || e.is_timeout(),
+            ClawError::RetriesExhausted { source, .. } => source.is_retryable(),
+            ClawError::ProvidersExhausted { failures, .. } => failures.iter().any(|(_, e)| e.is_retryable()),
+            _ => false,
+        }
+    }
+
+    /// Server-provided delay before the request should be retried.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            ClawError::RateLimited { retry_after } => Some(std::time::Duration::from_secs(*retry_after)),
+            ClawError::Overloaded { retry_after, .. } => retry_after.map(std::time::Duration::from_secs),
+            ClawError::RetriesExhausted { source, .. } => source.retry_after(),
+            ClawError::ProvidersExhausted { failures, .. } => failures.iter().filter_map(|(_, e)| e.retry_after()).min(),
+            _ => None,
+        }
+    }
+}
+
+fn describe_failures(failures: &[(String, ClawError)]) -> String {
+    failures
+        .iter()
+        .map(|(provider, error)| format!("{provider}: {error}"))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -4,13 +4,21 @@ pub mod types;
 pub mod mcp_types;
 pub mod stdio_transport;
 pub mod local_mcp;
+pub mod stream;
+pub mod retry;
+pub mod router;
+pub mod mcp_server;
 
-pub use client::ClawClient;
+pub use client::{ClawClient, LlmClient};
 pub use error::ClawError;
 pub use types::{LlmRequest, LlmResponse, Tool, ToolCall, ToolResult, OpenAiRequest, OpenAiResponse};
 pub use mcp_types::*;
 pub use stdio_transport::StdioTransport;
-pub use local_mcp::LocalMcpClient;
+pub use local_mcp::{LocalMcpClient, McpNotifications};
+pub use mcp_server::{McpServer, McpToolProvider, McpResourceProvider};
+pub use stream::{LlmStream, StreamEvent, StreamAccumulator};
+pub use retry::{RetryPolicy, RateLimiter};
+pub use router::{ClawRouter, Provider, ProviderStats};
 
 // Back-compat re-exports
 pub use client::ClawClient as McpClient;
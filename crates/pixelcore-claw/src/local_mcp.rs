@@ -1,11 +1,17 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use tokio::sync::{broadcast, mpsc};
 use crate::stdio_transport::StdioTransport;
 use crate::mcp_types::*;
 
 /// 本地 MCP 客户端
+///
+/// 所有方法都可以在多个任务中并发调用，响应按请求 id 分发。
 pub struct LocalMcpClient {
     transport: Arc<StdioTransport>,
     request_id: AtomicU64,
@@ -17,6 +23,11 @@ impl LocalMcpClient {
     /// 创建并初始化一个新的本地 MCP 客户端
     pub async fn new(command: &str, args: &[&str]) -> Result<Self> {
         let transport = StdioTransport::spawn(command, args).await?;
+        Self::connect(transport).await
+    }
+
+    /// 在已建立的传输上初始化客户端
+    pub async fn connect(transport: StdioTransport) -> Result<Self> {
         let mut client = Self {
             transport: Arc::new(transport),
             request_id: AtomicU64::new(1),
@@ -38,9 +49,10 @@ impl LocalMcpClient {
     /// 初始化 MCP 连接
     async fn initialize(&mut self) -> Result<()> {
         let params = InitializeParams {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: MCP_PROTOCOL_VERSION.to_string(),
             capabilities: ClientCapabilities {
-                experimental: None,
+                roots: Some(serde_json::json!({ "listChanged": false })),
+                ..Default::default()
             },
             client_info: ClientInfo {
                 name: "pixelcore".to_string(),
@@ -48,42 +60,49 @@ impl LocalMcpClient {
             },
         };
 
-        let request = JsonRpcRequest::new(
-            self.next_id(),
-            "initialize",
-            Some(serde_json::to_value(params)?),
-        );
-
-        let response = self.transport.call(request).await?;
+        let result: InitializeResult = self
+            .request("initialize", Some(serde_json::to_value(params)?))
+            .await
+            .context("MCP initialization failed")?;
 
-        if let Some(error) = response.error {
-            anyhow::bail!("MCP initialization failed: {} (code: {})", error.message, error.code);
-        }
-
-        let result: InitializeResult = serde_json::from_value(
-            response.result.context("Missing result in initialize response")?
-        )?;
+        tracing::info!(
+            "MCP server initialized: {} v{} (protocol {})",
+            result.server_info.name,
+            result.server_info.version,
+            result.protocol_version
+        );
 
         self.server_info = Some(result.server_info);
         self.capabilities = Some(result.capabilities);
 
-        tracing::info!(
-            "MCP server initialized: {} v{}",
-            self.server_info.as_ref().unwrap().name,
-            self.server_info.as_ref().unwrap().version
-        );
-
         // 发送 initialized 通知
-        let notification = JsonRpcRequest::new(
-            Value::Null,
-            "notifications/initialized",
-            None,
-        );
-        self.transport.send_request(&notification).await?;
+        self.transport.notify("notifications/initialized", None).await?;
 
         Ok(())
     }
 
+    /// 发送请求并解析结果
+    async fn request<R: DeserializeOwned>(&self, method: &str, params: Option<Value>) -> Result<R> {
+        let request = JsonRpcRequest::new(self.next_id(), method, params);
+        let response = self.transport.call(request).await?;
+        Self::parse_response(method, response)
+    }
+
+    fn parse_response<R: DeserializeOwned>(method: &str, response: JsonRpcResponse) -> Result<R> {
+        if let Some(error) = response.error {
+            anyhow::bail!("{} failed: {} (code: {})", method, error.message, error.code);
+        }
+
+        let result = response.result
+            .with_context(|| format!("Missing result in {} response", method))?;
+        serde_json::from_value(result)
+            .with_context(|| format!("Invalid {} response", method))
+    }
+
+    fn page_params(cursor: Option<String>) -> Option<Value> {
+        cursor.map(|cursor| serde_json::json!({ "cursor": cursor }))
+    }
+
     /// 获取服务器信息
     pub fn server_info(&self) -> Option<&ServerInfo> {
         self.server_info.as_ref()
@@ -94,25 +113,34 @@ impl LocalMcpClient {
         self.capabilities.as_ref()
     }
 
-    /// 列出所有可用的工具
-    pub async fn list_tools(&self) -> Result<Vec<McpTool>> {
-        let request = JsonRpcRequest::new(
-            self.next_id(),
-            "tools/list",
-            None,
-        );
-
-        let response = self.transport.call(request).await?;
+    /// 获取底层传输
+    pub fn transport(&self) -> &Arc<StdioTransport> {
+        &self.transport
+    }
 
-        if let Some(error) = response.error {
-            anyhow::bail!("Failed to list tools: {} (code: {})", error.message, error.code);
-        }
+    /// 订阅服务器通知
+    pub fn notifications(&self) -> McpNotifications {
+        McpNotifications { receiver: self.transport.subscribe() }
+    }
 
-        let result: ListToolsResult = serde_json::from_value(
-            response.result.context("Missing result in tools/list response")?
-        )?;
+    /// 检查连接是否可用
+    pub async fn ping(&self) -> Result<()> {
+        let _: Value = self.request("ping", None).await?;
+        Ok(())
+    }
 
-        Ok(result.tools)
+    /// 列出所有可用的工具（自动翻页）
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>> {
+        let mut tools = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page: ListToolsResult = self.request("tools/list", Self::page_params(cursor)).await?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tools),
+            }
+        }
     }
 
     /// 调用一个工具
@@ -120,25 +148,121 @@ impl LocalMcpClient {
         let params = CallToolParams {
             name: name.to_string(),
             arguments,
+            meta: None,
         };
 
-        let request = JsonRpcRequest::new(
-            self.next_id(),
-            "tools/call",
-            Some(serde_json::to_value(params)?),
-        );
+        self.request("tools/call", Some(serde_json::to_value(params)?)).await
+    }
 
-        let response = self.transport.call(request).await?;
+    /// 调用一个工具，并把服务器的进度通知转发到 `progress`
+    pub async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Option<Value>,
+        progress: mpsc::UnboundedSender<ProgressParams>,
+    ) -> Result<CallToolResult> {
+        let id = self.next_id();
+        let token = Value::from(id);
+        let params = CallToolParams {
+            name: name.to_string(),
+            arguments,
+            meta: Some(serde_json::json!({ "progressToken": token })),
+        };
+        let request = JsonRpcRequest::new(id, "tools/call", Some(serde_json::to_value(params)?));
 
-        if let Some(error) = response.error {
-            anyhow::bail!("Tool call failed: {} (code: {})", error.message, error.code);
+        // 先订阅再发送，避免错过最早的进度通知
+        let mut notifications = self.notifications();
+        let call = self.transport.call(request);
+        tokio::pin!(call);
+
+        let response = loop {
+            tokio::select! {
+                response = &mut call => break response?,
+                Some(notification) = notifications.recv() => {
+                    if let McpNotification::Progress(update) = notification {
+                        if update.progress_token == token {
+                            let _ = progress.send(update);
+                        }
+                    }
+                }
+            }
+        };
+
+        Self::parse_response("tools/call", response)
+    }
+
+    /// 调用一个工具，超时后取消请求
+    ///
+    /// 超时会向服务器发送 `notifications/cancelled`。
+    pub async fn call_tool_with_timeout(
+        &self,
+        name: &str,
+        arguments: Option<Value>,
+        timeout: Duration,
+    ) -> Result<CallToolResult> {
+        tokio::time::timeout(timeout, self.call_tool(name, arguments))
+            .await
+            .with_context(|| format!("Tool call '{}' timed out after {:?}", name, timeout))?
+    }
+
+    /// 列出所有资源（自动翻页）
+    pub async fn list_resources(&self) -> Result<Vec<McpResource>> {
+        let mut resources = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page: ListResourcesResult = self.request("resources/list", Self::page_params(cursor)).await?;
+            resources.extend(page.resources);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(resources),
+            }
         }
+    }
+
+    /// 读取一个资源
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        let params = ResourceParams { uri: uri.to_string() };
+        let result: ReadResourceResult = self
+            .request("resources/read", Some(serde_json::to_value(params)?))
+            .await?;
+        Ok(result.contents)
+    }
+
+    /// 订阅资源变更，之后会收到 `McpNotification::ResourceUpdated`
+    pub async fn subscribe_resource(&self, uri: &str) -> Result<()> {
+        let params = ResourceParams { uri: uri.to_string() };
+        let _: Value = self.request("resources/subscribe", Some(serde_json::to_value(params)?)).await?;
+        Ok(())
+    }
+
+    /// 取消资源订阅
+    pub async fn unsubscribe_resource(&self, uri: &str) -> Result<()> {
+        let params = ResourceParams { uri: uri.to_string() };
+        let _: Value = self.request("resources/unsubscribe", Some(serde_json::to_value(params)?)).await?;
+        Ok(())
+    }
 
-        let result: CallToolResult = serde_json::from_value(
-            response.result.context("Missing result in tools/call response")?
-        )?;
+    /// 列出所有提示模板（自动翻页）
+    pub async fn list_prompts(&self) -> Result<Vec<McpPrompt>> {
+        let mut prompts = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page: ListPromptsResult = self.request("prompts/list", Self::page_params(cursor)).await?;
+            prompts.extend(page.prompts);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(prompts),
+            }
+        }
+    }
 
-        Ok(result)
+    /// 获取并填充一个提示模板
+    pub async fn get_prompt(&self, name: &str, arguments: HashMap<String, String>) -> Result<GetPromptResult> {
+        let params = GetPromptParams {
+            name: name.to_string(),
+            arguments: (!arguments.is_empty()).then_some(arguments),
+        };
+        self.request("prompts/get", Some(serde_json::to_value(params)?)).await
     }
 
     /// 检查服务器是否还在运行
@@ -152,9 +276,22 @@ impl LocalMcpClient {
     }
 }
 
-impl Drop for LocalMcpClient {
-    fn drop(&mut self) {
-        // 尝试优雅关闭
-        // 注意：这里不能使用 async，所以只能尽力而为
+/// 服务器通知订阅
+pub struct McpNotifications {
+    receiver: broadcast::Receiver<JsonRpcNotification>,
+}
+
+impl McpNotifications {
+    /// 等待下一条通知，连接关闭后返回 `None`
+    pub async fn recv(&mut self) -> Option<McpNotification> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(notification) => return Some(McpNotification::from_rpc(notification)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Missed {} MCP notifications", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
     }
 }
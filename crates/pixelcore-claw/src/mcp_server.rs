@@ -0,0 +1,705 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::Instant;
+use pixelcore_storage::{Storage, StorageError};
+use crate::mcp_types::*;
+
+/// 服务器支持的协议版本，客户端请求其中之一时原样返回
+const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[MCP_PROTOCOL_VERSION, "2024-11-05"];
+
+/// MCP 规范定义的“资源不存在”错误码
+pub const RESOURCE_NOT_FOUND: i32 = -32002;
+
+/// 向 MCP 服务器提供工具
+#[async_trait]
+pub trait McpToolProvider: Send + Sync {
+    /// 当前可用的工具
+    fn list_tools(&self) -> Vec<McpTool>;
+
+    /// 调用工具
+    ///
+    /// 工具执行失败应返回 `is_error` 为 true 的结果；只有请求本身无效
+    /// （例如工具不存在）时才返回 `Err`。
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, JsonRpcError>;
+}
+
+/// 向 MCP 服务器提供资源
+#[async_trait]
+pub trait McpResourceProvider: Send + Sync {
+    async fn list_resources(&self) -> Result<Vec<McpResource>, JsonRpcError>;
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>, JsonRpcError>;
+}
+
+/// `Storage` 中的每个键作为一个 `storage://<key>` 资源，内容为 JSON 文本
+#[async_trait]
+impl McpResourceProvider for Storage {
+    async fn list_resources(&self) -> Result<Vec<McpResource>, JsonRpcError> {
+        let mut keys = self.keys()
+            .map_err(|e| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e.to_string()))?;
+        keys.sort();
+        Ok(keys
+            .into_iter()
+            .map(|key| McpResource {
+                uri: format!("storage://{}", key),
+                name: key,
+                description: None,
+                mime_type: Some("application/json".to_string()),
+                size: None,
+            })
+            .collect())
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>, JsonRpcError> {
+        let not_found = || JsonRpcError::new(RESOURCE_NOT_FOUND, format!("Resource not found: {}", uri));
+        let key = uri.strip_prefix("storage://").ok_or_else(not_found)?;
+        let value = match self.get(key) {
+            Ok(value) => value,
+            Err(StorageError::NotFound(_)) => return Err(not_found()),
+            Err(e) => return Err(JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e.to_string())),
+        };
+        Ok(vec![ResourceContents {
+            uri: uri.to_string(),
+            mime_type: Some("application/json".to_string()),
+            text: Some(serde_json::to_string_pretty(&value).unwrap_or_default()),
+            blob: None,
+        }])
+    }
+}
+
+/// MCP 服务器
+///
+/// 把一个 `McpToolProvider`（通常是 `SkillRegistry`）和可选的资源提供者
+/// 通过 stdio 或 Streamable HTTP 暴露给其他 MCP 客户端。
+#[derive(Clone)]
+pub struct McpServer {
+    info: ServerInfo,
+    instructions: Option<String>,
+    tools: Arc<dyn McpToolProvider>,
+    resources: Option<Arc<dyn McpResourceProvider>>,
+}
+
+impl McpServer {
+    pub fn new(name: impl Into<String>, version: impl Into<String>, tools: Arc<dyn McpToolProvider>) -> Self {
+        Self {
+            info: ServerInfo { name: name.into(), version: version.into() },
+            instructions: None,
+            tools,
+            resources: None,
+        }
+    }
+
+    /// 同时提供资源
+    pub fn with_resources(mut self, resources: Arc<dyn McpResourceProvider>) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    /// 初始化时返回给客户端的使用说明
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    pub fn info(&self) -> &ServerInfo {
+        &self.info
+    }
+
+    /// 处理一条 JSON-RPC 消息或批量消息，通知和响应不产生回复
+    pub async fn handle(&self, payload: Value) -> Option<Value> {
+        match payload {
+            Value::Array(messages) if messages.is_empty() => Some(Self::reply(JsonRpcResponse::failure(
+                Value::Null,
+                JsonRpcError::new(JsonRpcError::INVALID_REQUEST, "Empty batch"),
+            ))),
+            Value::Array(messages) => {
+                let mut replies = Vec::new();
+                for message in messages {
+                    if let Some(reply) = self.handle_message(message).await {
+                        replies.push(Self::reply(reply));
+                    }
+                }
+                (!replies.is_empty()).then_some(Value::Array(replies))
+            }
+            message => self.handle_message(message).await.map(Self::reply),
+        }
+    }
+
+    fn reply(response: JsonRpcResponse) -> Value {
+        serde_json::to_value(response).unwrap_or(Value::Null)
+    }
+
+    async fn handle_message(&self, message: Value) -> Option<JsonRpcResponse> {
+        let request = match JsonRpcMessage::from_value(message) {
+            Ok(JsonRpcMessage::Request(request)) => request,
+            Ok(JsonRpcMessage::Notification(notification)) => {
+                tracing::debug!("Received notification: {}", notification.method);
+                return None;
+            }
+            Ok(JsonRpcMessage::Response(_)) => return None,
+            Err(e) => {
+                return Some(JsonRpcResponse::failure(
+                    Value::Null,
+                    JsonRpcError::new(JsonRpcError::INVALID_REQUEST, e.to_string()),
+                ));
+            }
+        };
+
+        let id = request.id.clone();
+        Some(match self.dispatch(request).await {
+            Ok(result) => JsonRpcResponse::success(id, result),
+            Err(error) => JsonRpcResponse::failure(id, error),
+        })
+    }
+
+    async fn dispatch(&self, request: JsonRpcRequest) -> Result<Value, JsonRpcError> {
+        let params = request.params.unwrap_or(Value::Null);
+        let result = match request.method.as_str() {
+            "initialize" => serde_json::to_value(self.initialize(&params)),
+            "ping" => Ok(serde_json::json!({})),
+            "tools/list" => serde_json::to_value(ListToolsResult {
+                tools: self.tools.list_tools(),
+                next_cursor: None,
+            }),
+            "tools/call" => {
+                let params: CallToolParams = parse_params(params)?;
+                let arguments = params.arguments.unwrap_or_else(|| serde_json::json!({}));
+                serde_json::to_value(self.tools.call_tool(&params.name, arguments).await?)
+            }
+            "resources/list" => {
+                let resources = self.resource_provider(&request.method)?.list_resources().await?;
+                serde_json::to_value(ListResourcesResult { resources, next_cursor: None })
+            }
+            "resources/read" => {
+                let params: ResourceParams = parse_params(params)?;
+                let contents = self.resource_provider(&request.method)?.read_resource(&params.uri).await?;
+                serde_json::to_value(ReadResourceResult { contents })
+            }
+            method => return Err(JsonRpcError::method_not_found(method)),
+        };
+        result.map_err(|e| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e.to_string()))
+    }
+
+    fn initialize(&self, params: &Value) -> InitializeResult {
+        let requested = params
+            .get("protocolVersion")
+            .or_else(|| params.get("protocol_version"))
+            .and_then(Value::as_str);
+        let protocol_version = requested
+            .filter(|v| SUPPORTED_PROTOCOL_VERSIONS.contains(v))
+            .unwrap_or(MCP_PROTOCOL_VERSION);
+
+        InitializeResult {
+            protocol_version: protocol_version.to_string(),
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability { list_changed: Some(false) }),
+                resources: self.resources.as_ref().map(|_| ResourcesCapability {
+                    subscribe: Some(false),
+                    list_changed: Some(false),
+                }),
+                ..Default::default()
+            },
+            server_info: self.info.clone(),
+            instructions: self.instructions.clone(),
+        }
+    }
+
+    fn resource_provider(&self, method: &str) -> Result<&Arc<dyn McpResourceProvider>, JsonRpcError> {
+        self.resources.as_ref().ok_or_else(|| JsonRpcError::method_not_found(method))
+    }
+
+    /// 通过标准输入输出提供服务，直到标准输入关闭
+    pub async fn serve_stdio(&self) -> Result<()> {
+        self.serve_io(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// 在任意读写流上提供换行分隔的 JSON-RPC 服务
+    ///
+    /// 请求并发处理；收到 `notifications/cancelled` 时中止对应请求且不再回复。
+    /// 输入关闭后会等待进行中的请求完成再返回。
+    pub async fn serve_io<R, W>(&self, reader: R, writer: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let writer = Arc::new(tokio::sync::Mutex::new(writer));
+        let running: Arc<Mutex<HashMap<String, AbortHandle>>> = Arc::default();
+        let mut tasks = JoinSet::new();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Some(line) = lines.next_line().await.context("Failed to read MCP request")? {
+            while tasks.try_join_next().is_some() {}
+            if line.trim().is_empty() {
+                continue;
+            }
+            let payload: Value = match serde_json::from_str(&line) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    let error = JsonRpcResponse::failure(
+                        Value::Null,
+                        JsonRpcError::new(JsonRpcError::PARSE_ERROR, e.to_string()),
+                    );
+                    write_line(&writer, &Self::reply(error)).await?;
+                    continue;
+                }
+            };
+
+            if payload.get("method").and_then(Value::as_str) == Some("notifications/cancelled") {
+                let request_id = payload["params"].get("requestId").map(Value::to_string);
+                if let Some(handle) = request_id.and_then(|id| running.lock().unwrap().remove(&id)) {
+                    tracing::debug!("Request cancelled by client");
+                    handle.abort();
+                }
+                continue;
+            }
+
+            let key = payload.get("id").filter(|id| !id.is_null()).map(Value::to_string);
+            let server = self.clone();
+            let task_writer = writer.clone();
+            let task_running = running.clone();
+            let task_key = key.clone();
+            // 持有锁直到登记完成，避免任务在登记前结束而丢失回复
+            let mut running_guard = running.lock().unwrap();
+            let task = tasks.spawn(async move {
+                let reply = server.handle(payload).await;
+                // 已被取消的请求不再回复
+                let still_running = match &task_key {
+                    Some(key) => task_running.lock().unwrap().remove(key).is_some(),
+                    None => true,
+                };
+                if let (Some(reply), true) = (reply, still_running) {
+                    if let Err(e) = write_line(&task_writer, &reply).await {
+                        tracing::warn!("Failed to write MCP response: {}", e);
+                    }
+                }
+            });
+            if let Some(key) = key {
+                running_guard.insert(key, task);
+            }
+        }
+
+        // 输入结束后等待进行中的请求写完回复
+        while tasks.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    /// 在 `addr` 上提供 Streamable HTTP 服务，端点为 `/mcp`
+    pub async fn serve_http(&self, addr: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(addr).await.context("Failed to bind MCP HTTP server")?;
+        self.serve_http_listener(listener).await
+    }
+
+    /// 在已绑定的监听器上提供 Streamable HTTP 服务
+    ///
+    /// 只实现请求/响应模式：`POST /mcp` 返回 `application/json`，不提供
+    /// 服务器主动推送的 SSE 流（`GET` 返回 405）。初始化时分配
+    /// `Mcp-Session-Id`，之后的请求必须携带，`DELETE` 结束会话。空闲超过
+    /// 30 分钟的会话过期；会话数达到上限时淘汰最久未使用的会话。
+    pub async fn serve_http_listener(&self, listener: TcpListener) -> Result<()> {
+        tracing::info!("MCP HTTP server listening on {}", listener.local_addr()?);
+        let sessions: Arc<Mutex<HttpSessions>> = Arc::default();
+
+        loop {
+            let (stream, peer) = listener.accept().await.context("Failed to accept MCP connection")?;
+            let server = self.clone();
+            let sessions = sessions.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = stream.into_split();
+                let mut reader = BufReader::new(read);
+                loop {
+                    let request = match read_http_request(&mut reader).await {
+                        Ok(Some(request)) => request,
+                        Ok(None) => break,
+                        Err(e) => {
+                            tracing::debug!("Bad HTTP request from {}: {}", peer, e);
+                            let _ = write_http_response(&mut write, &HttpResponse::status(400)).await;
+                            break;
+                        }
+                    };
+                    let close = request.header("connection").is_some_and(|v| v.eq_ignore_ascii_case("close"));
+                    let response = server.handle_http(request, &sessions).await;
+                    if write_http_response(&mut write, &response).await.is_err() || close {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    async fn handle_http(&self, request: HttpRequest, sessions: &Mutex<HttpSessions>) -> HttpResponse {
+        if request.path.split('?').next() != Some("/mcp") {
+            return HttpResponse::status(404);
+        }
+        // 防止 DNS 重绑定：只接受本机页面发起的浏览器请求
+        if let Some(origin) = request.header("origin") {
+            if !is_local_origin(origin) {
+                return HttpResponse::status(403);
+            }
+        }
+
+        let session = request.header("mcp-session-id").map(str::to_string);
+        match request.method.as_str() {
+            "POST" => {}
+            "DELETE" => {
+                let removed = session.is_some_and(|id| sessions.lock().unwrap().close(&id));
+                return HttpResponse::status(if removed { 200 } else { 404 });
+            }
+            _ => return HttpResponse::status(405).with_header("Allow", "POST, DELETE"),
+        }
+
+        let payload: Value = match serde_json::from_slice(&request.body) {
+            Ok(payload) => payload,
+            Err(e) => {
+                let error = JsonRpcResponse::failure(
+                    Value::Null,
+                    JsonRpcError::new(JsonRpcError::PARSE_ERROR, e.to_string()),
+                );
+                return HttpResponse::json(400, &Self::reply(error));
+            }
+        };
+
+        let initializing = match &payload {
+            Value::Array(messages) => messages.iter().any(|m| m["method"] == "initialize"),
+            message => message["method"] == "initialize",
+        };
+        let new_session = if initializing {
+            Some(sessions.lock().unwrap().open())
+        } else {
+            match session {
+                None => return HttpResponse::status(400),
+                Some(id) if !sessions.lock().unwrap().touch(&id) => return HttpResponse::status(404),
+                Some(_) => None,
+            }
+        };
+
+        let response = match self.handle(payload).await {
+            Some(reply) => HttpResponse::json(200, &reply),
+            None => HttpResponse::status(202),
+        };
+        match new_session {
+            Some(id) => response.with_header("Mcp-Session-Id", &id),
+            None => response,
+        }
+    }
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, JsonRpcError> {
+    serde_json::from_value(params).map_err(|e| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, e.to_string()))
+}
+
+async fn write_line<W: AsyncWrite + Unpin>(writer: &tokio::sync::Mutex<W>, message: &Value) -> Result<()> {
+    let mut json = serde_json::to_string(message)?;
+    json.push('\n');
+    let mut writer = writer.lock().await;
+    writer.write_all(json.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin.split("://").nth(1).unwrap_or(origin);
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => host.split(':').next().unwrap_or(host),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+// ── 最小 HTTP/1.1 实现 ──────────────────────────────────────────────────────
+
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+const MAX_SESSIONS: usize = 1024;
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// HTTP 会话表，记录每个会话最后一次使用的时间
+#[derive(Default)]
+struct HttpSessions {
+    last_used: HashMap<String, Instant>,
+}
+
+impl HttpSessions {
+    /// 分配新会话；已满时先清理过期会话，仍然满则淘汰最久未使用的
+    fn open(&mut self) -> String {
+        let now = Instant::now();
+        if self.last_used.len() >= MAX_SESSIONS {
+            self.last_used.retain(|_, used| now.duration_since(*used) < SESSION_IDLE_TIMEOUT);
+        }
+        if self.last_used.len() >= MAX_SESSIONS {
+            if let Some(oldest) = self.last_used.iter().min_by_key(|(_, used)| **used).map(|(id, _)| id.clone()) {
+                self.last_used.remove(&oldest);
+            }
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        self.last_used.insert(id.clone(), now);
+        id
+    }
+
+    /// 会话仍然有效时刷新其使用时间
+    fn touch(&mut self, id: &str) -> bool {
+        let now = Instant::now();
+        match self.last_used.get_mut(id) {
+            Some(used) if now.duration_since(*used) < SESSION_IDLE_TIMEOUT => {
+                *used = now;
+                true
+            }
+            Some(_) => {
+                self.last_used.remove(id);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn close(&mut self, id: &str) -> bool {
+        self.last_used.remove(id).is_some()
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+struct HttpResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn status(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: serde_json::to_vec(body).unwrap_or_default(),
+        }
+    }
+
+    fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+async fn read_http_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> std::io::Result<Option<HttpRequest>> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut line = String::new();
+    if read_line_limited(reader, &mut line, MAX_HEADER_BYTES).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = Vec::new();
+    let mut header_bytes = line.len();
+    loop {
+        line.clear();
+        let n = read_line_limited(reader, &mut line, MAX_HEADER_BYTES - header_bytes).await?;
+        if n == 0 {
+            return Err(invalid("connection closed in headers"));
+        }
+        header_bytes += n;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .map(|(_, v)| v.parse::<usize>().map_err(|_| invalid("bad content-length")))
+        .transpose()?
+        .unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err(invalid("body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Some(HttpRequest { method, path, headers, body }))
+}
+
+/// 读取一行，最多 `limit` 字节；超过时返回错误而不是继续缓冲
+async fn read_line_limited<R: AsyncBufRead + Unpin>(reader: &mut R, line: &mut String, limit: usize) -> std::io::Result<usize> {
+    let n = (&mut *reader).take(limit as u64).read_line(line).await?;
+    if n == limit && !line.ends_with('\n') {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "headers too large"));
+    }
+    Ok(n)
+}
+
+async fn write_http_response<W: AsyncWrite + Unpin>(writer: &mut W, response: &HttpResponse) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Error",
+    };
+    let mut head = format!("HTTP/1.1 {} {}\r\nContent-Length: {}\r\n", response.status, reason, response.body.len());
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(&response.body).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Echo;
+
+    #[async_trait]
+    impl McpToolProvider for Echo {
+        fn list_tools(&self) -> Vec<McpTool> {
+            vec![McpTool {
+                name: "echo".to_string(),
+                description: "Echo the input".to_string(),
+                input_schema: json!({"type": "object"}),
+            }]
+        }
+
+        async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, JsonRpcError> {
+            if name != "echo" {
+                return Err(JsonRpcError::new(JsonRpcError::INVALID_PARAMS, format!("Unknown tool: {}", name)));
+            }
+            if arguments.get("wait").is_some() {
+                std::future::pending::<()>().await;
+            }
+            Ok(CallToolResult {
+                content: vec![ToolContent::text(arguments.to_string())],
+                structured_content: None,
+                is_error: None,
+            })
+        }
+    }
+
+    fn server() -> McpServer {
+        let storage = Storage::new();
+        storage.set("notes:1", json!({"title": "hi"})).unwrap();
+        McpServer::new("test", "1.0", Arc::new(Echo)).with_resources(Arc::new(storage))
+    }
+
+    #[tokio::test]
+    async fn test_handle_requests() {
+        let server = server();
+        let init = server
+            .handle(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2024-11-05"}}))
+            .await
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert!(init["result"]["capabilities"]["resources"].is_object());
+
+        assert!(server.handle(json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await.is_none());
+
+        let batch = server
+            .handle(json!([
+                {"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "echo", "arguments": {"x": 1}}},
+                {"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "nope"}},
+                {"jsonrpc": "2.0", "id": 4, "method": "resources/read", "params": {"uri": "storage://notes:1"}},
+                {"jsonrpc": "2.0", "id": 5, "method": "resources/read", "params": {"uri": "storage://missing"}},
+                {"jsonrpc": "2.0", "id": 6, "method": "prompts/list"}
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(batch[0]["result"]["content"][0]["text"], "{\"x\":1}");
+        assert_eq!(batch[1]["error"]["code"], JsonRpcError::INVALID_PARAMS);
+        assert!(batch[2]["result"]["contents"][0]["text"].as_str().unwrap().contains("hi"));
+        assert_eq!(batch[3]["error"]["code"], RESOURCE_NOT_FOUND);
+        assert_eq!(batch[4]["error"]["code"], JsonRpcError::METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_stdio_cancellation() {
+        let (client, server_io) = tokio::io::duplex(4096);
+        let (read, write) = tokio::io::split(server_io);
+        tokio::spawn(async move { server().serve_io(read, write).await });
+
+        let (read, mut write) = tokio::io::split(client);
+        let mut lines = BufReader::new(read).lines();
+        let messages = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "echo", "arguments": {"wait": true}}}),
+            json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 1}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "ping"}),
+        ];
+        for message in messages {
+            write.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+
+        // 只有 ping 得到回复
+        let reply: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(reply["id"], 2);
+        write.shutdown().await.unwrap();
+        assert!(lines.next_line().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_header_line_is_bounded() {
+        let long = format!("GET /mcp HTTP/1.1\r\nX-Filler: {}", "a".repeat(MAX_HEADER_BYTES * 4));
+        let mut reader = BufReader::new(long.as_bytes());
+        let err = read_http_request(&mut reader).await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let ok = "POST /mcp HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}";
+        let request = read_http_request(&mut BufReader::new(ok.as_bytes())).await.unwrap().unwrap();
+        assert_eq!(request.body, b"{}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_http_sessions_expire_and_are_capped() {
+        let mut sessions = HttpSessions::default();
+        let idle = sessions.open();
+        let active = sessions.open();
+        tokio::time::advance(SESSION_IDLE_TIMEOUT / 2).await;
+        assert!(sessions.touch(&active));
+        tokio::time::advance(SESSION_IDLE_TIMEOUT / 2).await;
+        assert!(!sessions.touch(&idle));
+        assert!(sessions.touch(&active));
+
+        for _ in 0..MAX_SESSIONS * 2 {
+            sessions.open();
+        }
+        assert_eq!(sessions.last_used.len(), MAX_SESSIONS);
+    }
+
+    #[test]
+    fn test_local_origin() {
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://[::1]:8080"));
+        assert!(!is_local_origin("https://evil.example"));
+    }
+}
@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// 协商时请求的 MCP 协议版本
+pub const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+
+// MCP 规范使用 camelCase 字段名；为兼容早期使用 snake_case 的服务器，
+// 反序列化时同时接受两种写法。
+
 /// JSON-RPC 2.0 请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
@@ -22,6 +28,25 @@ impl JsonRpcRequest {
     }
 }
 
+/// JSON-RPC 2.0 通知（没有 id，不需要响应）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl JsonRpcNotification {
+    pub fn new(method: impl Into<String>, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
 /// JSON-RPC 2.0 响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcResponse {
@@ -33,6 +58,26 @@ pub struct JsonRpcResponse {
     pub error: Option<JsonRpcError>,
 }
 
+impl JsonRpcResponse {
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn failure(id: Value, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
 /// JSON-RPC 2.0 错误
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcError {
@@ -42,19 +87,66 @@ pub struct JsonRpcError {
     pub data: Option<Value>,
 }
 
+impl JsonRpcError {
+    pub const PARSE_ERROR: i32 = -32700;
+    pub const INVALID_REQUEST: i32 = -32600;
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    pub const INVALID_PARAMS: i32 = -32602;
+    pub const INTERNAL_ERROR: i32 = -32603;
+
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(Self::METHOD_NOT_FOUND, format!("Method not found: {}", method))
+    }
+}
+
+/// 从对端收到的一条 JSON-RPC 消息
+#[derive(Debug, Clone)]
+pub enum JsonRpcMessage {
+    Request(JsonRpcRequest),
+    Notification(JsonRpcNotification),
+    Response(JsonRpcResponse),
+}
+
+impl JsonRpcMessage {
+    /// 按字段区分消息类型：带 `method` 和 `id` 的是请求，只带 `method` 的是通知，
+    /// 其余带 `id` 的是响应
+    pub fn from_value(value: Value) -> serde_json::Result<Self> {
+        let has_method = value.get("method").is_some();
+        let has_id = value.get("id").is_some_and(|id| !id.is_null());
+        if has_method && has_id {
+            serde_json::from_value(value).map(Self::Request)
+        } else if has_method {
+            serde_json::from_value(value).map(Self::Notification)
+        } else {
+            serde_json::from_value(value).map(Self::Response)
+        }
+    }
+}
+
 /// MCP 初始化请求参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InitializeParams {
+    #[serde(alias = "protocol_version")]
     pub protocol_version: String,
     pub capabilities: ClientCapabilities,
+    #[serde(alias = "client_info")]
     pub client_info: ClientInfo,
 }
 
 /// 客户端能力
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ClientCapabilities {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub experimental: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<Value>,
 }
 
 /// 客户端信息
@@ -66,24 +158,52 @@ pub struct ClientInfo {
 
 /// MCP 初始化响应结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InitializeResult {
+    #[serde(alias = "protocol_version")]
     pub protocol_version: String,
     pub capabilities: ServerCapabilities,
+    #[serde(alias = "server_info")]
     pub server_info: ServerInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
 }
 
 /// 服务器能力
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServerCapabilities {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tools: Option<ToolsCapability>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourcesCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<PromptsCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub experimental: Option<Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ToolsCapability {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, alias = "list_changed", skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesCapability {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscribe: Option<bool>,
+    #[serde(default, alias = "list_changed", skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptsCapability {
+    #[serde(default, alias = "list_changed", skip_serializing_if = "Option::is_none")]
     pub list_changed: Option<bool>,
 }
 
@@ -94,33 +214,53 @@ pub struct ServerInfo {
     pub version: String,
 }
 
+/// 分页请求参数（`*/list` 方法共用）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PaginatedParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
 /// MCP 工具定义
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct McpTool {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(alias = "input_schema")]
     pub input_schema: Value,
 }
 
 /// 工具列表响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ListToolsResult {
     pub tools: Vec<McpTool>,
+    #[serde(default, alias = "next_cursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// 工具调用请求参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallToolParams {
     pub name: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub arguments: Option<Value>,
+    /// 请求元数据，例如 `progressToken`
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
 }
 
 /// 工具调用响应结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CallToolResult {
     pub content: Vec<ToolContent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// 工具按 `outputSchema` 返回的结构化结果
+    #[serde(default, alias = "structured_content", skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(default, alias = "is_error", skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
 }
 
@@ -131,13 +271,265 @@ pub enum ToolContent {
     #[serde(rename = "text")]
     Text { text: String },
     #[serde(rename = "image")]
-    Image { data: String, mime_type: String },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType", alias = "mime_type")]
+        mime_type: String,
+    },
+    #[serde(rename = "audio")]
+    Audio {
+        data: String,
+        #[serde(rename = "mimeType", alias = "mime_type")]
+        mime_type: String,
+    },
+    /// 嵌入的资源内容
     #[serde(rename = "resource")]
-    Resource { uri: String, mime_type: Option<String>, text: Option<String> },
+    Resource { resource: ResourceContents },
 }
 
 impl ToolContent {
     pub fn text(text: impl Into<String>) -> Self {
         Self::Text { text: text.into() }
     }
+
+    pub fn image(data: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        Self::Image { data: data.into(), mime_type: mime_type.into() }
+    }
+
+    pub fn resource(resource: ResourceContents) -> Self {
+        Self::Resource { resource }
+    }
+
+    /// 文本内容（包括文本资源）
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text { text } => Some(text),
+            Self::Resource { resource } => resource.text.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// MCP 资源描述
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, alias = "mime_type", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// 资源内容：文本资源带 `text`，二进制资源带 base64 编码的 `blob`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(default, alias = "mime_type", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// 资源列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourcesResult {
+    pub resources: Vec<McpResource>,
+    #[serde(default, alias = "next_cursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// 资源读取 / 订阅请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceParams {
+    pub uri: String,
+}
+
+/// 资源读取响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    pub contents: Vec<ResourceContents>,
+}
+
+/// MCP 提示模板描述
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpPrompt {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<PromptArgument>,
+}
+
+/// 提示模板参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// 提示模板列表响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPromptsResult {
+    pub prompts: Vec<McpPrompt>,
+    #[serde(default, alias = "next_cursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// 获取提示模板的请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPromptParams {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<std::collections::HashMap<String, String>>,
+}
+
+/// 提示模板中的一条消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptMessage {
+    /// "user" 或 "assistant"
+    pub role: String,
+    pub content: ToolContent,
+}
+
+/// 获取提示模板的响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPromptResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub messages: Vec<PromptMessage>,
+}
+
+/// `notifications/progress` 参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressParams {
+    #[serde(alias = "progress_token")]
+    pub progress_token: Value,
+    pub progress: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// `notifications/cancelled` 参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledParams {
+    #[serde(alias = "request_id")]
+    pub request_id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// 服务器发来的通知
+#[derive(Debug, Clone)]
+pub enum McpNotification {
+    /// `notifications/tools/list_changed`
+    ToolsListChanged,
+    /// `notifications/resources/list_changed`
+    ResourcesListChanged,
+    /// `notifications/prompts/list_changed`
+    PromptsListChanged,
+    /// `notifications/resources/updated`
+    ResourceUpdated { uri: String },
+    /// `notifications/progress`
+    Progress(ProgressParams),
+    /// `notifications/cancelled`
+    Cancelled(CancelledParams),
+    /// `notifications/message`
+    Log { level: String, logger: Option<String>, data: Value },
+    /// 其他未识别的通知
+    Other(JsonRpcNotification),
+}
+
+impl McpNotification {
+    pub fn from_rpc(notification: JsonRpcNotification) -> Self {
+        let params = notification.params.clone().unwrap_or(Value::Null);
+        let parsed = match notification.method.as_str() {
+            "notifications/tools/list_changed" => Some(Self::ToolsListChanged),
+            "notifications/resources/list_changed" => Some(Self::ResourcesListChanged),
+            "notifications/prompts/list_changed" => Some(Self::PromptsListChanged),
+            "notifications/resources/updated" => params
+                .get("uri")
+                .and_then(Value::as_str)
+                .map(|uri| Self::ResourceUpdated { uri: uri.to_string() }),
+            "notifications/progress" => serde_json::from_value(params).ok().map(Self::Progress),
+            "notifications/cancelled" => serde_json::from_value(params).ok().map(Self::Cancelled),
+            "notifications/message" => Some(Self::Log {
+                level: params.get("level").and_then(Value::as_str).unwrap_or("info").to_string(),
+                logger: params.get("logger").and_then(Value::as_str).map(str::to_string),
+                data: params.get("data").cloned().unwrap_or(Value::Null),
+            }),
+            _ => None,
+        };
+        parsed.unwrap_or(Self::Other(notification))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_accepts_snake_and_camel_case() {
+        let snake: InitializeResult = serde_json::from_value(json!({
+            "protocol_version": "2024-11-05",
+            "capabilities": {"tools": {"list_changed": true}},
+            "server_info": {"name": "s", "version": "1"}
+        })).unwrap();
+        let camel: InitializeResult = serde_json::from_value(json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {"tools": {"listChanged": true}, "resources": {"subscribe": true}},
+            "serverInfo": {"name": "s", "version": "1"}
+        })).unwrap();
+        assert_eq!(snake.capabilities.tools.unwrap().list_changed, Some(true));
+        assert_eq!(camel.capabilities.resources.unwrap().subscribe, Some(true));
+
+        let result: CallToolResult = serde_json::from_value(json!({
+            "content": [
+                {"type": "image", "data": "aGk=", "mimeType": "image/png"},
+                {"type": "resource", "resource": {"uri": "file:///a.txt", "text": "hi"}}
+            ],
+            "isError": false
+        })).unwrap();
+        assert!(matches!(&result.content[0], ToolContent::Image { mime_type, .. } if mime_type == "image/png"));
+        assert_eq!(result.content[1].as_text(), Some("hi"));
+
+        // 发送时使用规范的 camelCase
+        let out = serde_json::to_value(&result).unwrap();
+        assert_eq!(out["isError"], json!(false));
+        assert_eq!(out["content"][0]["mimeType"], json!("image/png"));
+    }
+
+    #[test]
+    fn test_classifies_messages() {
+        let msg = JsonRpcMessage::from_value(json!({"jsonrpc": "2.0", "id": 1, "result": {}})).unwrap();
+        assert!(matches!(msg, JsonRpcMessage::Response(_)));
+        let msg = JsonRpcMessage::from_value(json!({"jsonrpc": "2.0", "id": "a", "method": "ping"})).unwrap();
+        assert!(matches!(msg, JsonRpcMessage::Request(_)));
+        let msg = JsonRpcMessage::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": {"progressToken": 3, "progress": 1.0, "total": 2.0}
+        })).unwrap();
+        let JsonRpcMessage::Notification(n) = msg else { panic!("expected notification") };
+        match McpNotification::from_rpc(n) {
+            McpNotification::Progress(p) => assert_eq!(p.total, Some(2.0)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,280 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::error::ClawError;
+
+// ── Retry policy ─────────────────────────────────────────────────────────────
+
+/// How `ClawClient` retries failed requests.
+///
+/// Only transient errors are retried (see `ClawError::is_retryable`). The delay
+/// grows exponentially with full jitter, a server-provided `retry-after` takes
+/// precedence, and `max_elapsed` caps the total time spent on one call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; `0` disables retrying.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Fraction of the computed delay that is randomised (`0.0` – `1.0`).
+    pub jitter: f64,
+    /// Give up once this much time has passed since the first attempt.
+    pub max_elapsed: Option<Duration>,
+    pub respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_elapsed: Some(Duration::from_secs(120)),
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self { max_retries, ..Default::default() }
+    }
+
+    /// Never retry.
+    pub fn none() -> Self {
+        Self::new(0)
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_max_elapsed(mut self, max_elapsed: Option<Duration>) -> Self {
+        self.max_elapsed = max_elapsed;
+        self
+    }
+
+    pub fn with_retry_after(mut self, respect: bool) -> Self {
+        self.respect_retry_after = respect;
+        self
+    }
+
+    /// Backoff before retry number `attempt` (0-based), ignoring `retry-after`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(attempt as i32);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+        let jittered = capped * (1.0 - self.jitter * random_unit());
+        Duration::from_secs_f64(jittered.max(0.0))
+    }
+
+    /// Delay before retrying `error`, or `None` if it should not be retried.
+    pub fn delay_for(&self, attempt: u32, error: &ClawError) -> Option<Duration> {
+        if attempt >= self.max_retries || !error.is_retryable() {
+            return None;
+        }
+        match error.retry_after() {
+            Some(retry_after) if self.respect_retry_after => Some(retry_after),
+            _ => Some(self.backoff(attempt)),
+        }
+    }
+
+    /// Run `call` until it succeeds, fails permanently, or the policy is exhausted.
+    pub(crate) async fn run<T, F, Fut>(&self, limiter: Option<&RateLimiter>, mut call: F) -> Result<T, ClawError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, ClawError>>,
+    {
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = limiter {
+                limiter.acquire().await;
+            }
+            let error = match call().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            // A server-side rate limit pauses every client sharing the limiter.
+            if let (Some(limiter), ClawError::RateLimited { .. }) = (limiter, &error) {
+                if let Some(retry_after) = error.retry_after() {
+                    limiter.pause_for(retry_after).await;
+                }
+            }
+
+            let Some(delay) = self.delay_for(attempt, &error) else {
+                return Err(exhausted(attempt, error));
+            };
+            if let Some(max_elapsed) = self.max_elapsed {
+                if started.elapsed() + delay > max_elapsed {
+                    return Err(exhausted(attempt, error));
+                }
+            }
+            tracing::debug!(attempt = attempt + 1, delay_ms = delay.as_millis() as u64, error = %error, "retrying request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn exhausted(retries: u32, error: ClawError) -> ClawError {
+    if retries == 0 {
+        return error;
+    }
+    ClawError::RetriesExhausted { attempts: retries + 1, source: Box::new(error) }
+}
+
+/// A uniformly distributed value in `[0, 1)`.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(Instant::now().elapsed().as_nanos());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// ── Client-side rate limiting ────────────────────────────────────────────────
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    paused_until: Option<Instant>,
+}
+
+/// Token-bucket rate limiter.
+///
+/// Clones share the same bucket, so one limiter can be handed to every
+/// `ClawClient` (and therefore every agent) that uses the same API key.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RateLimiter {
+    /// Allow bursts of `capacity` requests, refilled at `refill_per_sec`.
+    pub fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        Self {
+            capacity,
+            refill_per_sec: refill_per_sec.max(f64::MIN_POSITIVE),
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+                paused_until: None,
+            })),
+        }
+    }
+
+    /// `requests` per minute with a burst of the same size.
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, requests as f64 / 60.0)
+    }
+
+    /// Wait until a request may be sent.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                match bucket.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        bucket.paused_until = None;
+                        let refill = (now - bucket.last_refill).as_secs_f64() * self.refill_per_sec;
+                        bucket.tokens = (bucket.tokens + refill).min(self.capacity);
+                        bucket.last_refill = now;
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec)
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Hold back all requests for `duration` (e.g. after a 429 with `retry-after`).
+    pub async fn pause_for(&self, duration: Duration) {
+        let mut bucket = self.bucket.lock().await;
+        let until = Instant::now() + duration;
+        if bucket.paused_until.is_none_or(|current| current < until) {
+            bucket.paused_until = Some(until);
+        }
+    }
+
+    /// Tokens currently available.
+    pub async fn available(&self) -> f64 {
+        let bucket = self.bucket.lock().await;
+        let refill = bucket.last_refill.elapsed().as_secs_f64() * self.refill_per_sec;
+        (bucket.tokens + refill).min(self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(350))
+            .with_jitter(0.0);
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(350));
+
+        let jittered = policy.with_jitter(0.5);
+        for _ in 0..100 {
+            let delay = jittered.backoff(1);
+            assert!(delay > Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_only_transient_errors_are_retried() {
+        let policy = RetryPolicy::new(2);
+        let overloaded = ClawError::Overloaded { status: 529, message: String::new(), retry_after: None };
+        assert!(policy.delay_for(0, &overloaded).is_some());
+        assert!(policy.delay_for(2, &overloaded).is_none());
+        assert!(policy.delay_for(0, &ClawError::Api { status: 400, message: String::new() }).is_none());
+        assert_eq!(
+            policy.delay_for(0, &ClawError::RateLimited { retry_after: 7 }),
+            Some(Duration::from_secs(7))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter_refills_and_pauses() {
+        let limiter = RateLimiter::new(2, 10.0);
+        let shared = limiter.clone();
+        let start = Instant::now();
+        limiter.acquire().await;
+        shared.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(1));
+
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        shared.pause_for(Duration::from_secs(2)).await;
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_secs(2));
+    }
+}
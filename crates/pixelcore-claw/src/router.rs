@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::client::{ClawClient, LlmClient};
+use crate::error::ClawError;
+use crate::stream::LlmStream;
+use crate::types::{LlmRequest, LlmResponse};
+
+/// A backend registered with a `ClawRouter`.
+///
+/// Aliases map logical model names such as `fast` or `smart` to this
+/// provider's concrete model ids.
+pub struct Provider {
+    name: String,
+    client: Box<dyn LlmClient>,
+    aliases: HashMap<String, String>,
+    passthrough: bool,
+}
+
+impl Provider {
+    pub fn new(name: impl Into<String>, client: ClawClient) -> Self {
+        Self::with_client(name, client)
+    }
+
+    pub fn with_client(name: impl Into<String>, client: impl LlmClient + 'static) -> Self {
+        Self {
+            name: name.into(),
+            client: Box::new(client),
+            aliases: HashMap::new(),
+            passthrough: true,
+        }
+    }
+
+    /// Map logical model `alias` to `model` on this provider.
+    pub fn alias(mut self, alias: impl Into<String>, model: impl Into<String>) -> Self {
+        self.aliases.insert(alias.into(), model.into());
+        self
+    }
+
+    /// Only serve aliased models; concrete model ids are not forwarded.
+    pub fn aliases_only(mut self) -> Self {
+        self.passthrough = false;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Concrete model for `model` on this provider, if it serves it.
+    ///
+    /// `is_alias` says whether `model` is an alias known to the router; such
+    /// names are never forwarded verbatim to providers that lack the alias.
+    fn resolve(&self, model: &str, is_alias: bool) -> Option<String> {
+        match self.aliases.get(model) {
+            Some(concrete) => Some(concrete.clone()),
+            None if self.passthrough && !is_alias => Some(model.to_string()),
+            None => None,
+        }
+    }
+}
+
+/// Per-provider request statistics.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProviderStats {
+    pub requests: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub rate_limited: u64,
+    /// Sum of latencies of successful requests.
+    pub total_latency_ms: u64,
+    pub last_latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    cooldown_until: Option<Instant>,
+}
+
+impl ProviderStats {
+    pub fn avg_latency_ms(&self) -> Option<f64> {
+        (self.successes > 0).then(|| self.total_latency_ms as f64 / self.successes as f64)
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.requests as f64
+        }
+    }
+
+    /// Whether the provider is being skipped after a rate limit.
+    pub fn cooling_down(&self) -> bool {
+        self.cooldown_until.is_some_and(|until| until > Instant::now())
+    }
+}
+
+/// Routes completions across an ordered list of providers.
+///
+/// `LlmRequest::model` may name an alias (`fast`, `smart`, ...) or a concrete
+/// model id. Providers are tried in registration order; when one fails the
+/// next provider serving the model is used. A provider that answers with a
+/// rate limit moves to the back of the queue until its `retry-after` has passed.
+///
+/// Each provider's `ClawClient` still applies its own retry policy before the
+/// router fails over; use `RetryPolicy::none()` on the clients to fail over
+/// immediately.
+pub struct ClawRouter {
+    providers: Vec<Provider>,
+    stats: Mutex<HashMap<String, ProviderStats>>,
+}
+
+impl Default for ClawRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClawRouter {
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Append a provider; earlier providers are preferred.
+    pub fn with_provider(mut self, provider: Provider) -> Self {
+        self.stats
+            .lock()
+            .unwrap()
+            .insert(provider.name.clone(), ProviderStats::default());
+        self.providers.push(provider);
+        self
+    }
+
+    pub fn providers(&self) -> impl Iterator<Item = &str> {
+        self.providers.iter().map(|p| p.name.as_str())
+    }
+
+    /// Providers able to serve `model`, in the order they will be tried,
+    /// paired with the concrete model id each one will receive.
+    pub fn route(&self, model: &str) -> Vec<(&str, String)> {
+        let is_alias = self.providers.iter().any(|p| p.aliases.contains_key(model));
+        let candidates: Vec<_> = self
+            .providers
+            .iter()
+            .filter_map(|p| p.resolve(model, is_alias).map(|m| (p, m)))
+            .collect();
+
+        // Providers cooling down after a rate limit go last rather than being dropped.
+        let stats = self.stats.lock().unwrap();
+        let cooling = |p: &Provider| stats.get(&p.name).is_some_and(ProviderStats::cooling_down);
+        let (ready, cooling): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|(p, _)| !cooling(p));
+        ready
+            .into_iter()
+            .chain(cooling)
+            .map(|(p, m)| (p.name.as_str(), m))
+            .collect()
+    }
+
+    /// Snapshot of the statistics for every provider.
+    pub fn stats(&self) -> HashMap<String, ProviderStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub fn provider_stats(&self, name: &str) -> Option<ProviderStats> {
+        self.stats.lock().unwrap().get(name).cloned()
+    }
+
+    fn provider(&self, name: &str) -> &Provider {
+        self.providers.iter().find(|p| p.name == name).expect("routed provider exists")
+    }
+
+    fn record(&self, provider: &str, started: Instant, result: Result<(), &ClawError>) {
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(provider.to_string()).or_default();
+        entry.requests += 1;
+        match result {
+            Ok(()) => {
+                let latency = started.elapsed().as_millis() as u64;
+                entry.successes += 1;
+                entry.total_latency_ms += latency;
+                entry.last_latency_ms = Some(latency);
+                entry.cooldown_until = None;
+            }
+            Err(error) => {
+                entry.failures += 1;
+                entry.last_error = Some(error.to_string());
+                if let Some(retry_after) = rate_limit(error) {
+                    entry.rate_limited += 1;
+                    entry.cooldown_until = Some(Instant::now() + retry_after);
+                }
+            }
+        }
+    }
+
+    /// Try each provider in turn until `call` succeeds.
+    async fn dispatch<'a, T, F, Fut>(&'a self, request: LlmRequest, mut call: F) -> Result<T, ClawError>
+    where
+        F: FnMut(&'a Provider, LlmRequest) -> Fut,
+        Fut: std::future::Future<Output = Result<T, ClawError>>,
+    {
+        let route = self.route(&request.model);
+        if route.is_empty() {
+            return Err(ClawError::NoProvider(request.model));
+        }
+
+        let mut failures = Vec::new();
+        for (name, model) in route {
+            let provider = self.provider(name);
+            let mut routed = request.clone();
+            routed.model = model;
+            let started = Instant::now();
+            match call(provider, routed).await {
+                Ok(value) => {
+                    self.record(name, started, Ok(()));
+                    return Ok(value);
+                }
+                Err(error) => {
+                    tracing::warn!(provider = name, model = %request.model, error = %error, "provider failed, failing over");
+                    self.record(name, started, Err(&error));
+                    failures.push((name.to_string(), error));
+                }
+            }
+        }
+        Err(ClawError::ProvidersExhausted { model: request.model, failures })
+    }
+}
+
+/// Rate-limit delay carried by `error`, looking through retry wrappers.
+fn rate_limit(error: &ClawError) -> Option<Duration> {
+    match error {
+        ClawError::RateLimited { retry_after } => Some(Duration::from_secs(*retry_after)),
+        ClawError::RetriesExhausted { source, .. } => rate_limit(source),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl LlmClient for ClawRouter {
+    async fn complete(&self, request: LlmRequest) -> Result<LlmResponse, ClawError> {
+        self.dispatch(request, |provider, request| provider.client.complete(request))
+            .await
+    }
+
+    /// Fails over only while opening the stream; once events flow the stream
+    /// belongs to the provider that answered.
+    async fn complete_stream(&self, request: LlmRequest) -> Result<LlmStream, ClawError> {
+        self.dispatch(request, |provider, request| provider.client.complete_stream(request))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ContentBlock, Usage};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Fails with `error` for the first `failures` calls, then echoes the model.
+    struct Scripted {
+        failures: usize,
+        error: fn() -> ClawError,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LlmClient for Scripted {
+        async fn complete(&self, request: LlmRequest) -> Result<LlmResponse, ClawError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err((self.error)());
+            }
+            Ok(LlmResponse {
+                id: "msg".to_string(),
+                model: request.model,
+                stop_reason: Some("end_turn".to_string()),
+                content: vec![ContentBlock::Text { text: "ok".to_string() }],
+                usage: Usage { input_tokens: 1, output_tokens: 1 },
+            })
+        }
+
+        async fn complete_stream(&self, _request: LlmRequest) -> Result<LlmStream, ClawError> {
+            Err(ClawError::Other("not streamed".to_string()))
+        }
+    }
+
+    fn scripted(failures: usize, error: fn() -> ClawError) -> (Scripted, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        (Scripted { failures, error, calls: calls.clone() }, calls)
+    }
+
+    fn request(model: &str) -> LlmRequest {
+        LlmRequest {
+            model: model.to_string(),
+            max_tokens: 16,
+            messages: Vec::new(),
+            system: None,
+            tools: None,
+            temperature: None,
+            stream: false,
+            tool_choice: None,
+            output_format: None,
+        }
+    }
+
+    fn rate_limited() -> ClawError {
+        ClawError::RateLimited { retry_after: 60 }
+    }
+
+    #[tokio::test]
+    async fn test_resolves_aliases_per_provider() {
+        let (a, _) = scripted(0, rate_limited);
+        let (b, _) = scripted(0, rate_limited);
+        let router = ClawRouter::new()
+            .with_provider(Provider::with_client("anthropic", a).alias("fast", "claude-haiku").alias("smart", "claude-sonnet"))
+            .with_provider(Provider::with_client("openai", b).alias("fast", "gpt-mini").aliases_only());
+
+        assert_eq!(router.route("fast"), vec![("anthropic", "claude-haiku".to_string()), ("openai", "gpt-mini".to_string())]);
+        assert_eq!(router.route("smart"), vec![("anthropic", "claude-sonnet".to_string())]);
+        assert_eq!(router.route("claude-opus"), vec![("anthropic", "claude-opus".to_string())]);
+
+        let response = router.complete(request("smart")).await.unwrap();
+        assert_eq!(response.model, "claude-sonnet");
+
+        let router = ClawRouter::new();
+        assert!(matches!(router.complete(request("fast")).await, Err(ClawError::NoProvider(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_and_records_stats() {
+        let (a, a_calls) = scripted(usize::MAX, rate_limited);
+        let (b, b_calls) = scripted(0, rate_limited);
+        let router = ClawRouter::new()
+            .with_provider(Provider::with_client("primary", a).alias("fast", "a-fast"))
+            .with_provider(Provider::with_client("backup", b).alias("fast", "b-fast"));
+
+        let response = router.complete(request("fast")).await.unwrap();
+        assert_eq!(response.model, "b-fast");
+
+        let primary = router.provider_stats("primary").unwrap();
+        assert_eq!((primary.requests, primary.failures, primary.rate_limited), (1, 1, 1));
+        assert!(primary.cooling_down());
+        let backup = router.provider_stats("backup").unwrap();
+        assert_eq!((backup.requests, backup.successes), (1, 1));
+        assert!(backup.avg_latency_ms().is_some());
+
+        // The rate-limited provider is now tried last.
+        assert_eq!(router.route("fast")[0].0, "backup");
+        router.complete(request("fast")).await.unwrap();
+        assert_eq!(a_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(b_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reports_every_failure_when_exhausted() {
+        let (a, _) = scripted(usize::MAX, rate_limited);
+        let (b, _) = scripted(usize::MAX, || ClawError::Api { status: 401, message: "bad key".to_string() });
+        let router = ClawRouter::new()
+            .with_provider(Provider::with_client("a", a))
+            .with_provider(Provider::with_client("b", b));
+
+        let err = router.complete(request("some-model")).await.unwrap_err();
+        let ClawError::ProvidersExhausted { model, failures } = &err else { panic!("unexpected {err}") };
+        assert_eq!(model, "some-model");
+        assert_eq!(failures.len(), 2);
+        assert!(err.is_retryable());
+        assert!(err.to_string().contains("b: API error 401"));
+    }
+}
@@ -1,16 +1,36 @@
+use std::collections::HashMap;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, ChildStdout, Command};
-use tokio::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tokio::task::JoinHandle;
 use anyhow::{Context, Result};
-use crate::mcp_types::{JsonRpcRequest, JsonRpcResponse};
+use serde::Serialize;
+use serde_json::Value;
+use crate::mcp_types::{
+    CancelledParams, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+};
+
+type Writer = Arc<Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+type Pending = Arc<std::sync::Mutex<HashMap<String, oneshot::Sender<JsonRpcResponse>>>>;
+
+/// 通知广播通道容量，订阅者落后太多时会丢失最早的通知
+const NOTIFICATION_CAPACITY: usize = 256;
 
 /// Stdio 传输层，用于与本地 MCP 服务器通信
+///
+/// 后台任务持续读取服务器输出：响应按请求 id 交给等待中的调用者，通知广播给
+/// 所有订阅者，服务器发起的请求（如 `ping`）由传输层直接应答。因此多个请求
+/// 可以并发进行，通知也不会被误当作响应。
 pub struct StdioTransport {
-    child: Arc<Mutex<Child>>,
-    stdin: Arc<Mutex<ChildStdin>>,
-    stdout: Arc<Mutex<BufReader<ChildStdout>>>,
+    child: Option<Arc<Mutex<Child>>>,
+    writer: Writer,
+    pending: Pending,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    closed: Arc<AtomicBool>,
+    reader: JoinHandle<()>,
 }
 
 impl StdioTransport {
@@ -29,77 +49,233 @@ impl StdioTransport {
         let stdout = child.stdout.take()
             .context("Failed to get stdout")?;
 
-        Ok(Self {
-            child: Arc::new(Mutex::new(child)),
-            stdin: Arc::new(Mutex::new(stdin)),
-            stdout: Arc::new(Mutex::new(BufReader::new(stdout))),
-        })
+        let mut transport = Self::from_io(stdout, stdin);
+        transport.child = Some(Arc::new(Mutex::new(child)));
+        Ok(transport)
     }
 
-    /// 发送 JSON-RPC 请求
-    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<()> {
-        let json = serde_json::to_string(request)?;
-        let mut stdin = self.stdin.lock().await;
+    /// 在任意读写流上建立传输（例如测试中的内存管道）
+    pub fn from_io<R, W>(reader: R, writer: W) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let writer: Writer = Arc::new(Mutex::new(Box::new(writer)));
+        let pending: Pending = Arc::default();
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+        let closed = Arc::new(AtomicBool::new(false));
 
-        // MCP 使用换行符分隔的 JSON
-        stdin.write_all(json.as_bytes()).await?;
-        stdin.write_all(b"\n").await?;
-        stdin.flush().await?;
+        let reader = tokio::spawn(read_loop(
+            reader,
+            writer.clone(),
+            pending.clone(),
+            notifications.clone(),
+            closed.clone(),
+        ));
 
-        tracing::debug!("Sent request: {}", json);
-        Ok(())
+        Self { child: None, writer, pending, notifications, closed, reader }
     }
 
-    /// 接收 JSON-RPC 响应
-    pub async fn receive_response(&self) -> Result<JsonRpcResponse> {
-        let mut stdout = self.stdout.lock().await;
-        let mut line = String::new();
+    /// 发送 JSON-RPC 请求（不等待响应）
+    pub async fn send_request(&self, request: &JsonRpcRequest) -> Result<()> {
+        write_message(&self.writer, request).await
+    }
 
-        stdout.read_line(&mut line).await
-            .context("Failed to read response from MCP server")?;
+    /// 发送 JSON-RPC 通知
+    pub async fn notify(&self, method: &str, params: Option<Value>) -> Result<()> {
+        write_message(&self.writer, &JsonRpcNotification::new(method, params)).await
+    }
 
-        if line.is_empty() {
+    /// 发送请求并等待对应 id 的响应
+    ///
+    /// 如果在收到响应前丢弃返回的 future（例如外层超时），会向服务器发送
+    /// `notifications/cancelled`。
+    pub async fn call(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let key = request.id.to_string();
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(key.clone(), tx);
+        // 读取任务先标记关闭再清空等待项，所以插入后再检查一次即可避免永久等待
+        if self.closed.load(Ordering::SeqCst) {
+            self.pending.lock().unwrap().remove(&key);
             anyhow::bail!("MCP server closed connection");
         }
 
-        tracing::debug!("Received response: {}", line.trim());
+        let mut guard = PendingGuard {
+            key,
+            id: request.id.clone(),
+            pending: self.pending.clone(),
+            writer: self.writer.clone(),
+            sent: false,
+            done: false,
+        };
 
-        let response: JsonRpcResponse = serde_json::from_str(&line)
-            .context("Failed to parse JSON-RPC response")?;
+        self.send_request(&request).await?;
+        guard.sent = true;
 
+        let response = rx.await.map_err(|_| anyhow::anyhow!("MCP server closed connection"))?;
+        guard.done = true;
         Ok(response)
     }
 
-    /// 发送请求并等待响应
-    pub async fn call(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
-        self.send_request(&request).await?;
-        self.receive_response().await
+    /// 订阅服务器通知
+    pub fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.notifications.subscribe()
+    }
+
+    /// 等待响应的请求数
+    pub fn pending_requests(&self) -> usize {
+        self.pending.lock().unwrap().len()
     }
 
     /// 检查进程是否还在运行
     pub async fn is_alive(&self) -> bool {
-        let mut child = self.child.lock().await;
-        child.try_wait().ok().flatten().is_none()
+        if self.closed.load(Ordering::SeqCst) {
+            return false;
+        }
+        match &self.child {
+            Some(child) => child.lock().await.try_wait().ok().flatten().is_none(),
+            None => true,
+        }
     }
 
     /// 终止进程
     pub async fn kill(&self) -> Result<()> {
-        let mut child = self.child.lock().await;
-        child.kill().await.context("Failed to kill MCP server process")
+        self.reader.abort();
+        self.closed.store(true, Ordering::SeqCst);
+        self.pending.lock().unwrap().clear();
+        match &self.child {
+            Some(child) => child.lock().await.kill().await.context("Failed to kill MCP server process"),
+            None => Ok(()),
+        }
     }
 
     /// 等待进程退出
     pub async fn wait(&self) -> Result<std::process::ExitStatus> {
-        let mut child = self.child.lock().await;
+        let child = self.child.as_ref().context("Transport is not attached to a process")?;
+        let mut child = child.lock().await;
         child.wait().await.context("Failed to wait for MCP server process")
     }
 }
 
 impl Drop for StdioTransport {
     fn drop(&mut self) {
+        self.reader.abort();
         // 尝试终止进程（非阻塞）
-        if let Ok(mut child) = self.child.try_lock() {
-            let _ = child.start_kill();
+        if let Some(child) = &self.child {
+            if let Ok(mut child) = child.try_lock() {
+                let _ = child.start_kill();
+            }
+        }
+    }
+}
+
+/// 请求未完成时被丢弃：移除等待项，已发出的请求通知服务器取消
+struct PendingGuard {
+    key: String,
+    id: Value,
+    pending: Pending,
+    writer: Writer,
+    sent: bool,
+    done: bool,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        self.pending.lock().unwrap().remove(&self.key);
+        if !self.sent {
+            return;
+        }
+
+        let Ok(handle) = tokio::runtime::Handle::try_current() else { return };
+        let writer = self.writer.clone();
+        let params = CancelledParams {
+            request_id: self.id.clone(),
+            reason: Some("Request cancelled by client".to_string()),
+        };
+        tracing::debug!("Cancelling MCP request {}", self.key);
+        handle.spawn(async move {
+            let notification = JsonRpcNotification::new(
+                "notifications/cancelled",
+                serde_json::to_value(params).ok(),
+            );
+            let _ = write_message(&writer, &notification).await;
+        });
+    }
+}
+
+/// MCP 使用换行符分隔的 JSON
+async fn write_message<T: Serialize>(writer: &Writer, message: &T) -> Result<()> {
+    let json = serde_json::to_string(message)?;
+    let mut writer = writer.lock().await;
+
+    writer.write_all(json.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await?;
+
+    tracing::debug!("Sent message: {}", json);
+    Ok(())
+}
+
+async fn read_loop<R: AsyncRead + Unpin>(
+    reader: R,
+    writer: Writer,
+    pending: Pending,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    closed: Arc<AtomicBool>,
+) {
+    let mut lines = BufReader::new(reader).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!("Failed to read from MCP server: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        tracing::debug!("Received message: {}", line.trim());
+
+        let message = serde_json::from_str(&line).and_then(JsonRpcMessage::from_value);
+        match message {
+            Ok(JsonRpcMessage::Response(response)) => {
+                let sender = pending.lock().unwrap().remove(&response.id.to_string());
+                match sender {
+                    Some(sender) => {
+                        let _ = sender.send(response);
+                    }
+                    None => tracing::debug!("Dropping response for unknown request {}", response.id),
+                }
+            }
+            Ok(JsonRpcMessage::Notification(notification)) => {
+                // 没有订阅者时发送失败，忽略即可
+                let _ = notifications.send(notification);
+            }
+            Ok(JsonRpcMessage::Request(request)) => {
+                let response = match request.method.as_str() {
+                    "ping" => JsonRpcResponse::success(request.id, serde_json::json!({})),
+                    "roots/list" => JsonRpcResponse::success(request.id, serde_json::json!({ "roots": [] })),
+                    method => JsonRpcResponse::failure(request.id, JsonRpcError::method_not_found(method)),
+                };
+                // 在单独的任务中写回，避免读取循环被写端阻塞
+                let writer = writer.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = write_message(&writer, &response).await {
+                        tracing::warn!("Failed to answer MCP server request: {}", e);
+                    }
+                });
+            }
+            Err(e) => tracing::warn!("Ignoring malformed message from MCP server: {}", e),
         }
     }
+
+    closed.store(true, Ordering::SeqCst);
+    // 丢弃所有等待项，唤醒调用者并返回连接已关闭
+    pending.lock().unwrap().clear();
 }
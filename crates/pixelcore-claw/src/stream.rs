@@ -0,0 +1,605 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ClawError;
+use crate::types::{ContentBlock, LlmResponse, Usage};
+use pixelcore_runtime::streaming::{ResponseChunk, StreamingSender};
+
+// ── Unified stream events ────────────────────────────────────────────────────
+
+/// A backend-independent streaming event.
+///
+/// `index` identifies the content block the delta belongs to, matching the
+/// position of the block in the final `LlmResponse::content`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    MessageStart { id: String, model: String, input_tokens: u32 },
+    TextDelta { index: usize, text: String },
+    ToolUseStart { index: usize, id: String, name: String },
+    /// A fragment of the tool input JSON; fragments concatenate to the full input.
+    ToolInputDelta { index: usize, partial_json: String },
+    BlockStop { index: usize },
+    MessageDelta { stop_reason: Option<String>, output_tokens: Option<u32> },
+    MessageStop,
+}
+
+// ── SSE framing ──────────────────────────────────────────────────────────────
+
+/// One server-sent event.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental SSE parser; feed raw bytes as they arrive.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: String,
+    pending: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of bytes and return every event it completes.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        // Keep incomplete UTF-8 sequences until the next chunk arrives.
+        self.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        let rest = self.pending.split_off(valid);
+        self.buffer.push_str(&String::from_utf8_lossy(&self.pending));
+        self.pending = rest;
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.find('\n') {
+            let line: String = self.buffer.drain(..=pos).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Flush a trailing event that was not followed by a blank line.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let rest = std::mem::take(&mut self.buffer);
+        if !rest.is_empty() {
+            if let Some(event) = self.line(rest.trim_end_matches('\r')) {
+                return Some(event);
+            }
+        }
+        self.line("")
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            if self.data.is_empty() && self.event.is_none() {
+                return None;
+            }
+            return Some(SseEvent {
+                event: self.event.take(),
+                data: std::mem::take(&mut self.data).join("\n"),
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
+
+// ── Backend decoders ─────────────────────────────────────────────────────────
+
+/// Translates backend-specific SSE payloads into `StreamEvent`s.
+pub trait StreamDecoder: Send {
+    fn decode(&mut self, event: &SseEvent) -> Result<Vec<StreamEvent>, ClawError>;
+}
+
+/// Decoder for the Anthropic Messages streaming format.
+#[derive(Debug, Default)]
+pub struct AnthropicDecoder;
+
+impl StreamDecoder for AnthropicDecoder {
+    fn decode(&mut self, event: &SseEvent) -> Result<Vec<StreamEvent>, ClawError> {
+        if event.data.is_empty() {
+            return Ok(Vec::new());
+        }
+        let payload: Value = serde_json::from_str(&event.data)?;
+        let kind = payload["type"].as_str().or(event.event.as_deref()).unwrap_or_default();
+        let index = payload["index"].as_u64().unwrap_or(0) as usize;
+
+        let events = match kind {
+            "message_start" => {
+                let message = &payload["message"];
+                vec![StreamEvent::MessageStart {
+                    id: message["id"].as_str().unwrap_or_default().to_string(),
+                    model: message["model"].as_str().unwrap_or_default().to_string(),
+                    input_tokens: message["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32,
+                }]
+            }
+            "content_block_start" => {
+                let block = &payload["content_block"];
+                match block["type"].as_str() {
+                    Some("tool_use") => vec![StreamEvent::ToolUseStart {
+                        index,
+                        id: block["id"].as_str().unwrap_or_default().to_string(),
+                        name: block["name"].as_str().unwrap_or_default().to_string(),
+                    }],
+                    Some("text") => vec![StreamEvent::TextDelta {
+                        index,
+                        text: block["text"].as_str().unwrap_or_default().to_string(),
+                    }],
+                    _ => Vec::new(),
+                }
+            }
+            "content_block_delta" => {
+                let delta = &payload["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => vec![StreamEvent::TextDelta {
+                        index,
+                        text: delta["text"].as_str().unwrap_or_default().to_string(),
+                    }],
+                    Some("input_json_delta") => vec![StreamEvent::ToolInputDelta {
+                        index,
+                        partial_json: delta["partial_json"].as_str().unwrap_or_default().to_string(),
+                    }],
+                    _ => Vec::new(),
+                }
+            }
+            "content_block_stop" => vec![StreamEvent::BlockStop { index }],
+            "message_delta" => vec![StreamEvent::MessageDelta {
+                stop_reason: payload["delta"]["stop_reason"].as_str().map(str::to_string),
+                output_tokens: payload["usage"]["output_tokens"].as_u64().map(|n| n as u32),
+            }],
+            "message_stop" => vec![StreamEvent::MessageStop],
+            "error" => {
+                let error = &payload["error"];
+                return Err(ClawError::Api {
+                    status: 0,
+                    message: format!(
+                        "{}: {}",
+                        error["type"].as_str().unwrap_or("error"),
+                        error["message"].as_str().unwrap_or_default()
+                    ),
+                });
+            }
+            // ping and unknown events
+            _ => Vec::new(),
+        };
+        Ok(events)
+    }
+}
+
+/// Decoder for the OpenAI `chat.completions` delta format.
+///
+/// Text is reported as block 0; tool call `i` becomes block `i + 1`.
+#[derive(Debug, Default)]
+pub struct OpenAiDecoder {
+    started: bool,
+    text_open: bool,
+    tools_open: BTreeSet<usize>,
+    stopped: bool,
+}
+
+impl StreamDecoder for OpenAiDecoder {
+    fn decode(&mut self, event: &SseEvent) -> Result<Vec<StreamEvent>, ClawError> {
+        let data = event.data.trim();
+        if data.is_empty() || self.stopped {
+            return Ok(Vec::new());
+        }
+        let mut events = Vec::new();
+        if data == "[DONE]" {
+            self.close_blocks(&mut events);
+            events.push(StreamEvent::MessageStop);
+            self.stopped = true;
+            return Ok(events);
+        }
+
+        let payload: Value = serde_json::from_str(data)?;
+        if let Some(error) = payload.get("error") {
+            return Err(ClawError::Api {
+                status: 0,
+                message: error["message"].as_str().unwrap_or(&error.to_string()).to_string(),
+            });
+        }
+        if !self.started {
+            self.started = true;
+            events.push(StreamEvent::MessageStart {
+                id: payload["id"].as_str().unwrap_or_default().to_string(),
+                model: payload["model"].as_str().unwrap_or_default().to_string(),
+                input_tokens: payload["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+            });
+        }
+
+        if let Some(choice) = payload["choices"].get(0) {
+            let delta = &choice["delta"];
+            if let Some(text) = delta["content"].as_str() {
+                if !text.is_empty() {
+                    self.text_open = true;
+                    events.push(StreamEvent::TextDelta { index: 0, text: text.to_string() });
+                }
+            }
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                let index = call["index"].as_u64().unwrap_or(0) as usize + 1;
+                if self.tools_open.insert(index) {
+                    events.push(StreamEvent::ToolUseStart {
+                        index,
+                        id: call["id"].as_str().unwrap_or_default().to_string(),
+                        name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                    });
+                }
+                if let Some(arguments) = call["function"]["arguments"].as_str() {
+                    if !arguments.is_empty() {
+                        events.push(StreamEvent::ToolInputDelta { index, partial_json: arguments.to_string() });
+                    }
+                }
+            }
+            if let Some(reason) = choice["finish_reason"].as_str() {
+                self.close_blocks(&mut events);
+                events.push(StreamEvent::MessageDelta {
+                    stop_reason: Some(reason.to_string()),
+                    output_tokens: payload["usage"]["completion_tokens"].as_u64().map(|n| n as u32),
+                });
+            }
+        }
+
+        // With `stream_options.include_usage` the usage arrives in a final chunk without choices.
+        if payload["choices"].as_array().is_some_and(|c| c.is_empty()) {
+            if let Some(output_tokens) = payload["usage"]["completion_tokens"].as_u64() {
+                events.push(StreamEvent::MessageDelta { stop_reason: None, output_tokens: Some(output_tokens as u32) });
+            }
+        }
+        Ok(events)
+    }
+}
+
+impl OpenAiDecoder {
+    fn close_blocks(&mut self, events: &mut Vec<StreamEvent>) {
+        if std::mem::take(&mut self.text_open) {
+            events.push(StreamEvent::BlockStop { index: 0 });
+        }
+        for index in std::mem::take(&mut self.tools_open) {
+            events.push(StreamEvent::BlockStop { index });
+        }
+    }
+}
+
+// ── Accumulation ─────────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+enum PartialBlock {
+    Text(String),
+    ToolUse { id: String, name: String, input: String },
+}
+
+/// Rebuilds a complete `LlmResponse` from stream events.
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    id: String,
+    model: String,
+    stop_reason: Option<String>,
+    input_tokens: u32,
+    output_tokens: u32,
+    blocks: BTreeMap<usize, PartialBlock>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::MessageStart { id, model, input_tokens } => {
+                self.id = id.clone();
+                self.model = model.clone();
+                self.input_tokens = *input_tokens;
+            }
+            StreamEvent::TextDelta { index, text } => {
+                if let PartialBlock::Text(buffer) =
+                    self.blocks.entry(*index).or_insert_with(|| PartialBlock::Text(String::new()))
+                {
+                    buffer.push_str(text);
+                }
+            }
+            StreamEvent::ToolUseStart { index, id, name } => {
+                self.blocks.insert(*index, PartialBlock::ToolUse { id: id.clone(), name: name.clone(), input: String::new() });
+            }
+            StreamEvent::ToolInputDelta { index, partial_json } => {
+                if let Some(PartialBlock::ToolUse { input, .. }) = self.blocks.get_mut(index) {
+                    input.push_str(partial_json);
+                }
+            }
+            StreamEvent::MessageDelta { stop_reason, output_tokens } => {
+                if stop_reason.is_some() {
+                    self.stop_reason = stop_reason.clone();
+                }
+                if let Some(tokens) = output_tokens {
+                    self.output_tokens = *tokens;
+                }
+            }
+            StreamEvent::BlockStop { .. } | StreamEvent::MessageStop => {}
+        }
+    }
+
+    /// The complete tool-use block at `index`, once its input has been received.
+    pub fn tool_use(&self, index: usize) -> Option<ContentBlock> {
+        match self.blocks.get(&index)? {
+            PartialBlock::ToolUse { id, name, input } => Some(ContentBlock::ToolUse {
+                id: id.clone(),
+                name: name.clone(),
+                input: parse_tool_input(input),
+            }),
+            PartialBlock::Text(_) => None,
+        }
+    }
+
+    pub fn finish(self) -> LlmResponse {
+        let content = self
+            .blocks
+            .into_values()
+            .filter_map(|block| match block {
+                PartialBlock::Text(text) if text.is_empty() => None,
+                PartialBlock::Text(text) => Some(ContentBlock::Text { text }),
+                PartialBlock::ToolUse { id, name, input } => {
+                    Some(ContentBlock::ToolUse { id, name, input: parse_tool_input(&input) })
+                }
+            })
+            .collect();
+        LlmResponse {
+            id: self.id,
+            model: self.model,
+            stop_reason: self.stop_reason,
+            content,
+            usage: Usage { input_tokens: self.input_tokens, output_tokens: self.output_tokens },
+        }
+    }
+}
+
+fn parse_tool_input(input: &str) -> Value {
+    if input.trim().is_empty() {
+        return Value::Object(Default::default());
+    }
+    serde_json::from_str(input).unwrap_or(Value::Null)
+}
+
+// ── Response stream ──────────────────────────────────────────────────────────
+
+/// A streaming completion returned by `ClawClient::complete_stream`.
+pub struct LlmStream {
+    response: Option<Response>,
+    parser: SseParser,
+    decoder: Box<dyn StreamDecoder>,
+    queue: VecDeque<StreamEvent>,
+    accumulator: StreamAccumulator,
+}
+
+impl LlmStream {
+    pub(crate) fn new(response: Response, decoder: Box<dyn StreamDecoder>) -> Self {
+        Self {
+            response: Some(response),
+            parser: SseParser::new(),
+            decoder,
+            queue: VecDeque::new(),
+            accumulator: StreamAccumulator::new(),
+        }
+    }
+
+    /// Receive the next event; `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<Result<StreamEvent, ClawError>> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                self.accumulator.push(&event);
+                return Some(Ok(event));
+            }
+
+            let response = self.response.as_mut()?;
+            let sse_events = match response.chunk().await {
+                Ok(Some(bytes)) => self.parser.feed(&bytes),
+                Ok(None) => {
+                    // Connection closed: flush whatever is still buffered.
+                    self.response = None;
+                    self.parser.finish().into_iter().collect()
+                }
+                Err(e) => {
+                    self.response = None;
+                    return Some(Err(e.into()));
+                }
+            };
+            for sse in &sse_events {
+                match self.decoder.decode(sse) {
+                    Ok(events) => self.queue.extend(events),
+                    Err(e) => {
+                        self.response = None;
+                        self.queue.clear();
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The complete tool-use block at `index` (call after its `BlockStop`).
+    pub fn tool_use(&self, index: usize) -> Option<ContentBlock> {
+        self.accumulator.tool_use(index)
+    }
+
+    /// Drain the stream and return the assembled response.
+    pub async fn collect(mut self) -> Result<LlmResponse, ClawError> {
+        while let Some(event) = self.next().await {
+            event?;
+        }
+        Ok(self.accumulator.finish())
+    }
+
+    /// Drain the stream, forwarding text deltas and finished tool calls to `sender`.
+    ///
+    /// Text arrives as `Text` chunks; each completed tool call is sent as a
+    /// `Status` chunk whose metadata holds the `tool_use` block.
+    pub async fn forward_to(mut self, sender: &StreamingSender) -> Result<LlmResponse, ClawError> {
+        while let Some(event) = self.next().await {
+            match event? {
+                StreamEvent::TextDelta { text, .. } if !text.is_empty() => {
+                    let _ = sender.send_text(text);
+                }
+                StreamEvent::BlockStop { index } => {
+                    if let Some(block) = self.tool_use(index) {
+                        let ContentBlock::ToolUse { name, .. } = &block else { continue };
+                        let mut chunk = ResponseChunk::status(format!("tool_use: {name}"));
+                        chunk.metadata = serde_json::to_value(&block).ok();
+                        let _ = sender.send_chunk(chunk);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(self.accumulator.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(decoder: &mut dyn StreamDecoder, raw: &str, split: usize) -> Vec<StreamEvent> {
+        let mut parser = SseParser::new();
+        let mut events = Vec::new();
+        for chunk in raw.as_bytes().chunks(split) {
+            for sse in parser.feed(chunk) {
+                events.extend(decoder.decode(&sse).unwrap());
+            }
+        }
+        if let Some(sse) = parser.finish() {
+            events.extend(decoder.decode(&sse).unwrap());
+        }
+        events
+    }
+
+    const ANTHROPIC: &str = "event: message_start\n\
+data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"claude\",\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n\
+event: content_block_start\n\
+data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n\
+event: ping\n\
+data: {\"type\":\"ping\"}\n\n\
+event: content_block_delta\n\
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Héllo\"}}\n\n\
+event: content_block_delta\n\
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" there\"}}\n\n\
+event: content_block_stop\n\
+data: {\"type\":\"content_block_stop\",\"index\":0}\n\n\
+event: content_block_start\n\
+data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"search\",\"input\":{}}}\n\n\
+event: content_block_delta\n\
+data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"q\\\": \\\"ru\"}}\n\n\
+event: content_block_delta\n\
+data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"st\\\"}\"}}\n\n\
+event: content_block_stop\n\
+data: {\"type\":\"content_block_stop\",\"index\":1}\n\n\
+event: message_delta\n\
+data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":30}}\n\n\
+event: message_stop\n\
+data: {\"type\":\"message_stop\"}\n\n";
+
+    const OPENAI: &str = "data: {\"id\":\"chatcmpl-1\",\"model\":\"gpt\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hi\"}}]}\n\n\
+data: {\"id\":\"chatcmpl-1\",\"model\":\"gpt\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" you\"}}]}\n\n\
+data: {\"id\":\"chatcmpl-1\",\"model\":\"gpt\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"search\",\"arguments\":\"{\\\"q\\\":\"}}]}}]}\n\n\
+data: {\"id\":\"chatcmpl-1\",\"model\":\"gpt\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"rust\\\"}\"}}]}}]}\n\n\
+data: {\"id\":\"chatcmpl-1\",\"model\":\"gpt\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n\
+data: {\"id\":\"chatcmpl-1\",\"model\":\"gpt\",\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":21}}\n\n\
+data: [DONE]\n\n";
+
+    #[test]
+    fn test_anthropic_stream_assembles_text_and_tool_use() {
+        // Split into small chunks so lines and UTF-8 sequences straddle chunk boundaries.
+        let events = decode_all(&mut AnthropicDecoder, ANTHROPIC, 7);
+        assert!(events.contains(&StreamEvent::TextDelta { index: 0, text: "Héllo".to_string() }));
+        assert!(events.contains(&StreamEvent::ToolUseStart {
+            index: 1,
+            id: "toolu_1".to_string(),
+            name: "search".to_string(),
+        }));
+        assert_eq!(events.last(), Some(&StreamEvent::MessageStop));
+
+        let mut accumulator = StreamAccumulator::new();
+        events.iter().for_each(|e| accumulator.push(e));
+        let response = accumulator.finish();
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.usage.input_tokens, 12);
+        assert_eq!(response.usage.output_tokens, 30);
+        assert_eq!(response.content.len(), 2);
+        match &response.content[0] {
+            ContentBlock::Text { text } => assert_eq!(text, "Héllo there"),
+            other => panic!("unexpected block {other:?}"),
+        }
+        match &response.content[1] {
+            ContentBlock::ToolUse { name, input, .. } => {
+                assert_eq!(name, "search");
+                assert_eq!(input, &serde_json::json!({"q": "rust"}));
+            }
+            other => panic!("unexpected block {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_openai_stream_assembles_text_and_tool_calls() {
+        let events = decode_all(&mut OpenAiDecoder::default(), OPENAI, 11);
+        assert!(events.contains(&StreamEvent::BlockStop { index: 1 }));
+        assert_eq!(events.last(), Some(&StreamEvent::MessageStop));
+
+        let mut accumulator = StreamAccumulator::new();
+        events.iter().for_each(|e| accumulator.push(e));
+        let response = accumulator.finish();
+        assert_eq!(response.id, "chatcmpl-1");
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(response.usage.output_tokens, 21);
+        match &response.content[..] {
+            [ContentBlock::Text { text }, ContentBlock::ToolUse { id, input, .. }] => {
+                assert_eq!(text, "Hi you");
+                assert_eq!(id, "call_1");
+                assert_eq!(input, &serde_json::json!({"q": "rust"}));
+            }
+            other => panic!("unexpected content {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_sse_parser_handles_crlf_comments_and_multiline_data() {
+        let mut parser = SseParser::new();
+        let events = parser.feed(b": keep-alive\r\nevent: a\r\ndata: one\r\ndata: two\r\n\r\ndata: tail");
+        assert_eq!(events, vec![SseEvent { event: Some("a".to_string()), data: "one\ntwo".to_string() }]);
+        assert_eq!(parser.finish(), Some(SseEvent { event: None, data: "tail".to_string() }));
+    }
+
+    #[test]
+    fn test_stream_errors_are_reported() {
+        let error = SseEvent {
+            event: Some("error".to_string()),
+            data: "{\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}".to_string(),
+        };
+        let err = AnthropicDecoder.decode(&error).unwrap_err();
+        assert!(err.to_string().contains("Overloaded"));
+    }
+}
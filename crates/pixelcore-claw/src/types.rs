@@ -1,6 +1,12 @@
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 use pixelcore_runtime::message::{Message, MessageRole};
 
+use crate::error::ClawError;
+use crate::mcp_types::ToolContent;
+
 // ── Unified request/response types ──────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +20,37 @@ pub struct LlmRequest {
     pub tools: Option<Vec<Tool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+    /// Request a server-sent event stream (set by `ClawClient::complete_stream`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Constrain the reply to JSON matching a schema. Not part of the wire
+    /// format: backends translate it (see `anthropic_body` and `to_openai`).
+    #[serde(skip)]
+    pub output_format: Option<OutputFormat>,
+}
+
+/// Whether and which tool the model must call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    Auto,
+    /// Some tool, model's pick
+    Any,
+    Tool { name: String },
+    None,
+}
+
+/// A JSON Schema the reply must conform to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputFormat {
+    /// Also the name of the forced tool on Anthropic
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Must describe an object
+    pub schema: serde_json::Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,12 +75,166 @@ pub enum ApiContent {
     Blocks(Vec<ContentBlock>),
 }
 
+impl ApiContent {
+    /// The text parts, concatenated.
+    pub fn to_text(&self) -> String {
+        match self {
+            ApiContent::Text(text) => text.clone(),
+            ApiContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(""),
+        }
+    }
+}
+
+impl From<String> for ApiContent {
+    fn from(text: String) -> Self {
+        ApiContent::Text(text)
+    }
+}
+
+impl From<&str> for ApiContent {
+    fn from(text: &str) -> Self {
+        ApiContent::Text(text.to_string())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentBlock {
     Text { text: String },
+    Image { source: MediaSource },
+    /// A PDF; each page is seen as both text and image.
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
     ToolUse { id: String, name: String, input: serde_json::Value },
-    ToolResult { tool_use_id: String, content: String },
+    ToolResult {
+        tool_use_id: String,
+        /// A string, or text / image / document blocks.
+        content: ApiContent,
+        /// The tool failed; `content` describes the error.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
+    },
+}
+
+impl ContentBlock {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentBlock::Text { text: text.into() }
+    }
+
+    /// An image read from `path` when the request is sent.
+    pub fn image_file(path: impl Into<PathBuf>) -> Self {
+        ContentBlock::Image { source: MediaSource::Path { path: path.into(), media_type: None } }
+    }
+
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ContentBlock::Image { source: MediaSource::Base64 { media_type: media_type.into(), data: data.into() } }
+    }
+
+    /// A PDF read from `path` when the request is sent.
+    pub fn document_file(path: impl Into<PathBuf>) -> Self {
+        ContentBlock::Document { source: MediaSource::Path { path: path.into(), media_type: None }, title: None }
+    }
+
+    pub fn document_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ContentBlock::Document {
+            source: MediaSource::Base64 { media_type: media_type.into(), data: data.into() },
+            title: None,
+        }
+    }
+
+    /// Convert MCP tool content. Audio has no counterpart and yields `None`;
+    /// so do binary resources other than images and PDFs.
+    pub fn from_mcp(content: &ToolContent) -> Option<Self> {
+        match content {
+            ToolContent::Text { text } => Some(ContentBlock::text(text.clone())),
+            ToolContent::Image { data, mime_type } => Some(ContentBlock::image_base64(mime_type.clone(), data.clone())),
+            ToolContent::Audio { .. } => None,
+            ToolContent::Resource { resource } => match (&resource.text, &resource.blob) {
+                (Some(text), _) => Some(ContentBlock::text(text.clone())),
+                (None, Some(blob)) => {
+                    let media_type = resource.mime_type.clone().unwrap_or_default();
+                    if media_type.starts_with("image/") {
+                        Some(ContentBlock::image_base64(media_type, blob.clone()))
+                    } else if media_type == "application/pdf" {
+                        Some(ContentBlock::Document {
+                            source: MediaSource::Base64 { media_type, data: blob.clone() },
+                            title: Some(resource.uri.clone()),
+                        })
+                    } else {
+                        None
+                    }
+                }
+                (None, None) => None,
+            },
+        }
+    }
+}
+
+/// Where the bytes of an image or document come from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Base64 { media_type: String, data: String },
+    /// A local file. Kept as a path in history and inlined as `Base64` by
+    /// `LlmRequest::load_media` just before the request is sent.
+    Path {
+        path: PathBuf,
+        /// Guessed from the extension when absent.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        media_type: Option<String>,
+    },
+}
+
+impl MediaSource {
+    /// Read a `Path` source into a `Base64` one.
+    pub async fn load(&self) -> Result<MediaSource, ClawError> {
+        match self {
+            MediaSource::Base64 { .. } => Ok(self.clone()),
+            MediaSource::Path { path, media_type } => {
+                let media_type = match media_type {
+                    Some(media_type) => media_type.clone(),
+                    None => guess_media_type(path)
+                        .ok_or_else(|| ClawError::Other(format!("unknown media type: {}", path.display())))?
+                        .to_string(),
+                };
+                let bytes = tokio::fs::read(path)
+                    .await
+                    .map_err(|e| ClawError::Other(format!("failed to read {}: {e}", path.display())))?;
+                let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+                Ok(MediaSource::Base64 { media_type, data })
+            }
+        }
+    }
+
+    /// `data:` URL for backends that take images as URLs. `None` for unloaded paths.
+    pub fn data_url(&self) -> Option<String> {
+        match self {
+            MediaSource::Base64 { media_type, data } => Some(format!("data:{media_type};base64,{data}")),
+            MediaSource::Path { .. } => None,
+        }
+    }
+}
+
+fn guess_media_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        _ => return None,
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,6 +261,52 @@ pub struct ToolCall {
 pub struct ToolResult {
     pub tool_use_id: String,
     pub content: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl LlmRequest {
+    /// The Messages API body. An `output_format` becomes a tool the model is
+    /// forced to call, so the reply arrives as that tool's `input`.
+    pub fn anthropic_body(&self) -> Cow<'_, LlmRequest> {
+        let Some(format) = &self.output_format else { return Cow::Borrowed(self) };
+        let mut request = self.clone();
+        request.tools.get_or_insert_with(Vec::new).push(Tool {
+            name: format.name.clone(),
+            description: format.description.clone().unwrap_or_else(|| "Respond with the final answer.".to_string()),
+            input_schema: format.schema.clone(),
+        });
+        request.tool_choice = Some(ToolChoice::Tool { name: format.name.clone() });
+        Cow::Owned(request)
+    }
+
+    /// Inline every file-path media source as base64 so that the request can
+    /// be sent. A no-op for requests without `MediaSource::Path` sources.
+    pub async fn load_media(&mut self) -> Result<(), ClawError> {
+        for message in &mut self.messages {
+            if let ApiContent::Blocks(blocks) = &mut message.content {
+                load_blocks(blocks).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn load_blocks(blocks: &mut [ContentBlock]) -> Result<(), ClawError> {
+    for block in blocks {
+        match block {
+            ContentBlock::Image { source } | ContentBlock::Document { source, .. } => {
+                if matches!(source, MediaSource::Path { .. }) {
+                    *source = source.load().await?;
+                }
+            }
+            ContentBlock::ToolResult { content: ApiContent::Blocks(inner), .. } => {
+                Box::pin(load_blocks(inner)).await?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
 }
 
 // ── Conversions ──────────────────────────────────────────────────────────────
@@ -100,7 +337,13 @@ pub struct OpenAiRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<OpenAiTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_choice: Option<String>,
+    pub tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -121,7 +364,7 @@ pub struct OpenAiFunction {
 pub struct OpenAiMessage {
     pub role: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
+    pub content: Option<OpenAiContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<OpenAiToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -132,13 +375,88 @@ pub struct OpenAiMessage {
 
 impl OpenAiMessage {
     pub fn user(content: impl Into<String>) -> Self {
-        Self { role: "user".to_string(), content: Some(content.into()), tool_calls: None, tool_call_id: None, name: None }
+        Self { role: "user".to_string(), content: Some(OpenAiContent::Text(content.into())), tool_calls: None, tool_call_id: None, name: None }
     }
     pub fn system(content: impl Into<String>) -> Self {
-        Self { role: "system".to_string(), content: Some(content.into()), tool_calls: None, tool_call_id: None, name: None }
+        Self { role: "system".to_string(), content: Some(OpenAiContent::Text(content.into())), tool_calls: None, tool_call_id: None, name: None }
     }
     pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
-        Self { role: "tool".to_string(), content: Some(content.into()), tool_calls: None, tool_call_id: Some(tool_call_id.into()), name: None }
+        Self { role: "tool".to_string(), content: Some(OpenAiContent::Text(content.into())), tool_calls: None, tool_call_id: Some(tool_call_id.into()), name: None }
+    }
+}
+
+/// Message content: a string, or an array of parts for multimodal input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OpenAiContent {
+    Text(String),
+    Parts(Vec<OpenAiContentPart>),
+}
+
+impl OpenAiContent {
+    /// Plain text when every part is text, parts otherwise.
+    fn from_parts(parts: Vec<OpenAiContentPart>) -> Self {
+        if parts.iter().all(|p| matches!(p, OpenAiContentPart::Text { .. })) {
+            OpenAiContent::Text(OpenAiContent::Parts(parts).to_text())
+        } else {
+            OpenAiContent::Parts(parts)
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            OpenAiContent::Text(text) => text.clone(),
+            OpenAiContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    OpenAiContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(""),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OpenAiContentPart {
+    Text { text: String },
+    ImageUrl { image_url: OpenAiImageUrl },
+    File { file: OpenAiFile },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAiImageUrl {
+    /// An http(s) or `data:` URL
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAiFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// A `data:` URL
+    pub file_data: String,
+}
+
+/// The OpenAI part for a text, image or document block.
+fn openai_part(block: &ContentBlock) -> Option<OpenAiContentPart> {
+    let unloaded = |source: &MediaSource| match source {
+        MediaSource::Path { path, .. } => format!("[file not loaded: {}]", path.display()),
+        MediaSource::Base64 { .. } => String::new(),
+    };
+    match block {
+        ContentBlock::Text { text } => Some(OpenAiContentPart::Text { text: text.clone() }),
+        ContentBlock::Image { source } => Some(match source.data_url() {
+            Some(url) => OpenAiContentPart::ImageUrl { image_url: OpenAiImageUrl { url } },
+            None => OpenAiContentPart::Text { text: unloaded(source) },
+        }),
+        ContentBlock::Document { source, title } => Some(match source.data_url() {
+            Some(file_data) => OpenAiContentPart::File { file: OpenAiFile { filename: title.clone(), file_data } },
+            None => OpenAiContentPart::Text { text: unloaded(source) },
+        }),
+        ContentBlock::ToolUse { .. } | ContentBlock::ToolResult { .. } => None,
     }
 }
 
@@ -185,7 +503,7 @@ impl LlmRequest {
         for m in &self.messages {
             match &m.content {
                 ApiContent::Text(s) => {
-                    messages.push(OpenAiMessage { role: m.role.clone(), content: Some(s.clone()), tool_calls: None, tool_call_id: None, name: None });
+                    messages.push(OpenAiMessage { role: m.role.clone(), content: Some(OpenAiContent::Text(s.clone())), tool_calls: None, tool_call_id: None, name: None });
                 }
                 ApiContent::Blocks(blocks) => {
                     // assistant message with tool_use blocks
@@ -201,29 +519,44 @@ impl LlmRequest {
                         _ => None,
                     }).collect();
 
-                    let text: String = blocks.iter().filter_map(|b| match b {
-                        ContentBlock::Text { text } => Some(text.as_str()),
-                        _ => None,
-                    }).collect::<Vec<_>>().join("");
-
                     if !tool_calls.is_empty() {
+                        let text = ApiContent::Blocks(blocks.clone()).to_text();
                         messages.push(OpenAiMessage {
                             role: m.role.clone(),
-                            content: if text.is_empty() { None } else { Some(text) },
+                            content: if text.is_empty() { None } else { Some(OpenAiContent::Text(text)) },
                             tool_calls: Some(tool_calls),
                             tool_call_id: None,
                             name: None,
                         });
-                    } else {
-                        // tool result blocks
-                        for b in blocks {
-                            if let ContentBlock::ToolResult { tool_use_id, content } = b {
-                                messages.push(OpenAiMessage::tool_result(tool_use_id, content));
+                        continue;
+                    }
+
+                    // Tool messages only carry text, so images and documents
+                    // from tool results follow in a user message.
+                    let mut parts: Vec<OpenAiContentPart> = Vec::new();
+                    for b in blocks {
+                        if let ContentBlock::ToolResult { tool_use_id, content, is_error } = b {
+                            let mut text = content.to_text();
+                            if let ApiContent::Blocks(inner) = content {
+                                let media: Vec<OpenAiContentPart> = inner.iter()
+                                    .filter(|b| matches!(b, ContentBlock::Image { .. } | ContentBlock::Document { .. }))
+                                    .filter_map(openai_part)
+                                    .collect();
+                                if !media.is_empty() {
+                                    text.push_str(&format!("\n[{} attachment(s) follow]", media.len()));
+                                    parts.push(OpenAiContentPart::Text { text: format!("Attachments from tool call {tool_use_id}:") });
+                                    parts.extend(media);
+                                }
                             }
+                            if *is_error {
+                                text = format!("Error: {text}");
+                            }
+                            messages.push(OpenAiMessage::tool_result(tool_use_id, text));
                         }
-                        if !text.is_empty() {
-                            messages.push(OpenAiMessage { role: m.role.clone(), content: Some(text), tool_calls: None, tool_call_id: None, name: None });
-                        }
+                    }
+                    parts.extend(blocks.iter().filter_map(openai_part));
+                    if !parts.is_empty() {
+                        messages.push(OpenAiMessage { role: m.role.clone(), content: Some(OpenAiContent::from_parts(parts)), tool_calls: None, tool_call_id: None, name: None });
                     }
                 }
             }
@@ -244,7 +577,22 @@ impl LlmRequest {
             max_tokens: self.max_tokens,
             temperature: self.temperature,
             tools,
-            tool_choice: None,
+            tool_choice: self.tool_choice.as_ref().map(|choice| match choice {
+                ToolChoice::Auto => serde_json::json!("auto"),
+                ToolChoice::Any => serde_json::json!("required"),
+                ToolChoice::None => serde_json::json!("none"),
+                ToolChoice::Tool { name } => serde_json::json!({ "type": "function", "function": { "name": name } }),
+            }),
+            response_format: self.output_format.as_ref().map(|format| {
+                let mut json_schema = serde_json::json!({ "name": format.name, "schema": format.schema });
+                if let Some(description) = &format.description {
+                    json_schema["description"] = serde_json::json!(description);
+                }
+                serde_json::json!({ "type": "json_schema", "json_schema": json_schema })
+            }),
+            stream: self.stream,
+            // Ask for a trailing usage chunk so streamed responses report token counts.
+            stream_options: self.stream.then(|| serde_json::json!({ "include_usage": true })),
         }
     }
 }
@@ -259,9 +607,10 @@ impl OpenAiResponse {
         let msg = choice.message;
 
         let mut blocks: Vec<ContentBlock> = Vec::new();
-        if let Some(text) = &msg.content {
+        if let Some(content) = &msg.content {
+            let text = content.to_text();
             if !text.is_empty() {
-                blocks.push(ContentBlock::Text { text: text.clone() });
+                blocks.push(ContentBlock::Text { text });
             }
         }
         if let Some(tool_calls) = msg.tool_calls {
@@ -288,3 +637,152 @@ impl OpenAiResponse {
 // ── Back-compat aliases (drop after full migration) ──────────────────────────
 pub type McpRequest = LlmRequest;
 pub type McpResponse = LlmResponse;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(messages: Vec<ApiMessage>) -> LlmRequest {
+        LlmRequest {
+            model: "m".to_string(),
+            max_tokens: 100,
+            messages,
+            system: None,
+            tools: None,
+            temperature: None,
+            stream: false,
+            tool_choice: None,
+            output_format: None,
+        }
+    }
+
+    #[test]
+    fn test_multimodal_blocks_serialize_like_the_messages_api() {
+        let block = ContentBlock::image_base64("image/png", "AAAA");
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            json!({"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}})
+        );
+
+        let result: ContentBlock = serde_json::from_value(json!({
+            "type": "tool_result",
+            "tool_use_id": "t1",
+            "content": [
+                {"type": "text", "text": "page 1"},
+                {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBE"}}
+            ]
+        }))
+        .unwrap();
+        let ContentBlock::ToolResult { content: ApiContent::Blocks(blocks), is_error: false, .. } = result else {
+            panic!("expected block content");
+        };
+        assert!(matches!(blocks[1], ContentBlock::Document { .. }));
+    }
+
+    #[test]
+    fn test_to_openai_translates_images_and_tool_results() {
+        let req = request(vec![
+            ApiMessage {
+                role: "user".to_string(),
+                content: ApiContent::Blocks(vec![
+                    ContentBlock::text("What is this?"),
+                    ContentBlock::image_base64("image/png", "AAAA"),
+                ]),
+            },
+            ApiMessage {
+                role: "assistant".to_string(),
+                content: ApiContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: "t1".to_string(),
+                    name: "chart".to_string(),
+                    input: json!({}),
+                }]),
+            },
+            ApiMessage {
+                role: "user".to_string(),
+                content: ApiContent::Blocks(vec![
+                    ContentBlock::ToolResult {
+                        tool_use_id: "t1".to_string(),
+                        content: ApiContent::Blocks(vec![
+                            ContentBlock::text("chart drawn"),
+                            ContentBlock::image_base64("image/png", "BBBB"),
+                        ]),
+                        is_error: false,
+                    },
+                    ContentBlock::ToolResult {
+                        tool_use_id: "t2".to_string(),
+                        content: "no data".into(),
+                        is_error: true,
+                    },
+                ]),
+            },
+        ]);
+        let messages = serde_json::to_value(req.to_openai().messages).unwrap();
+
+        assert_eq!(
+            messages[0]["content"],
+            json!([
+                {"type": "text", "text": "What is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+            ])
+        );
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["content"], "chart drawn\n[1 attachment(s) follow]");
+        assert_eq!(messages[3]["content"], "Error: no data");
+        assert_eq!(messages[4]["role"], "user");
+        assert_eq!(messages[4]["content"][1]["image_url"]["url"], "data:image/png;base64,BBBB");
+    }
+
+    #[tokio::test]
+    async fn test_load_media_inlines_file_sources() {
+        let path = std::env::temp_dir().join(format!("claw-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"png!").unwrap();
+        let mut req = request(vec![ApiMessage {
+            role: "user".to_string(),
+            content: ApiContent::Blocks(vec![ContentBlock::ToolResult {
+                tool_use_id: "t1".to_string(),
+                content: ApiContent::Blocks(vec![ContentBlock::image_file(&path)]),
+                is_error: false,
+            }]),
+        }]);
+        req.load_media().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let ApiContent::Blocks(blocks) = &req.messages[0].content else { panic!() };
+        let ContentBlock::ToolResult { content: ApiContent::Blocks(inner), .. } = &blocks[0] else { panic!() };
+        let ContentBlock::Image { source } = &inner[0] else { panic!() };
+        assert_eq!(source, &MediaSource::Base64 { media_type: "image/png".to_string(), data: "cG5nIQ==".to_string() });
+
+        let mut missing = request(vec![ApiMessage {
+            role: "user".to_string(),
+            content: ApiContent::Blocks(vec![ContentBlock::document_file("/nonexistent.pdf")]),
+        }]);
+        assert!(missing.load_media().await.is_err());
+    }
+
+    #[test]
+    fn test_output_format_per_backend() {
+        let mut req = request(vec![ApiMessage { role: "user".to_string(), content: "Name a city".into() }]);
+        req.output_format = Some(OutputFormat {
+            name: "city".to_string(),
+            description: None,
+            schema: json!({"type": "object", "properties": {"name": {"type": "string"}}}),
+        });
+
+        let body = serde_json::to_value(req.anthropic_body()).unwrap();
+        assert_eq!(body["tools"][0]["name"], "city");
+        assert_eq!(body["tools"][0]["input_schema"]["properties"]["name"]["type"], "string");
+        assert_eq!(body["tool_choice"], json!({"type": "tool", "name": "city"}));
+        assert!(body.get("output_format").is_none());
+
+        let body = serde_json::to_value(req.to_openai()).unwrap();
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(body["response_format"]["json_schema"]["name"], "city");
+        assert!(body["response_format"]["json_schema"].get("description").is_none());
+        assert!(body.get("tools").is_none());
+
+        req.output_format.as_mut().unwrap().description = Some("A city".to_string());
+        let body = serde_json::to_value(req.to_openai()).unwrap();
+        assert_eq!(body["response_format"]["json_schema"]["description"], "A city");
+    }
+}
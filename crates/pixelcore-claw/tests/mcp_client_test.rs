@@ -0,0 +1,223 @@
+//! `LocalMcpClient` against an in-process MCP server speaking over a duplex pipe.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use pixelcore_claw::{LocalMcpClient, McpNotification, StdioTransport, ToolContent};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+
+type ServerWriter = Arc<Mutex<tokio::io::WriteHalf<tokio::io::DuplexStream>>>;
+
+async fn send(writer: &ServerWriter, message: Value) {
+    let mut line = message.to_string();
+    line.push('\n');
+    let _ = writer.lock().await.write_all(line.as_bytes()).await;
+}
+
+/// Answer one request. Notifications and responses from the client are
+/// forwarded to `seen`.
+async fn handle(message: Value, writer: ServerWriter, seen: mpsc::UnboundedSender<Value>) {
+    let Some(id) = message.get("id").cloned() else {
+        let _ = seen.send(message);
+        return;
+    };
+    let Some(method) = message["method"].as_str() else {
+        let _ = seen.send(message);
+        return;
+    };
+    let params = &message["params"];
+
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {"tools": {"listChanged": true}, "resources": {"subscribe": true}, "prompts": {}},
+            "serverInfo": {"name": "mock", "version": "1.0"}
+        }),
+        "tools/list" => match params["cursor"].as_str() {
+            None => json!({
+                "tools": [{"name": "fast", "description": "", "inputSchema": {"type": "object"}}],
+                "nextCursor": "page-2"
+            }),
+            Some(_) => json!({
+                "tools": [{"name": "slow", "inputSchema": {"type": "object"}}]
+            }),
+        },
+        "tools/call" => match params["name"].as_str().unwrap_or_default() {
+            "fast" => json!({"content": [{"type": "text", "text": "fast"}]}),
+            "slow" => {
+                if let Some(token) = params["_meta"].get("progressToken") {
+                    for step in 1..=2 {
+                        send(&writer, json!({
+                            "jsonrpc": "2.0",
+                            "method": "notifications/progress",
+                            "params": {"progressToken": token, "progress": step, "total": 2}
+                        })).await;
+                    }
+                }
+                // A notification the client must not mistake for the response.
+                send(&writer, json!({"jsonrpc": "2.0", "method": "notifications/message", "params": {"level": "info", "data": "working"}})).await;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                json!({"content": [
+                    {"type": "text", "text": "slow"},
+                    {"type": "image", "data": "iVBORw0KGgo=", "mimeType": "image/png"}
+                ]})
+            }
+            "hang" => std::future::pending::<Value>().await,
+            other => json!({"content": [{"type": "text", "text": other}], "isError": true}),
+        },
+        "resources/list" => json!({"resources": [{"uri": "mem://notes", "name": "notes", "mimeType": "text/plain"}]}),
+        "resources/read" => json!({"contents": [{"uri": params["uri"], "mimeType": "text/plain", "text": "hello"}]}),
+        "resources/subscribe" => {
+            let uri = params["uri"].clone();
+            let writer = writer.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                send(&writer, json!({"jsonrpc": "2.0", "method": "notifications/resources/updated", "params": {"uri": uri}})).await;
+            });
+            json!({})
+        }
+        "prompts/list" => json!({"prompts": [{"name": "greet", "arguments": [{"name": "who", "required": true}]}]}),
+        "prompts/get" => json!({
+            "messages": [{"role": "user", "content": {"type": "text", "text": format!("Hello {}", params["arguments"]["who"].as_str().unwrap_or("?"))}}]
+        }),
+        _ => {
+            send(&writer, json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32601, "message": "not found"}})).await;
+            return;
+        }
+    };
+    send(&writer, json!({"jsonrpc": "2.0", "id": id, "result": result})).await;
+}
+
+/// Start a mock server and connect a client to it.
+async fn connect() -> (LocalMcpClient, ServerWriter, mpsc::UnboundedReceiver<Value>) {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let (server_read, server_write) = tokio::io::split(server_io);
+    let writer: ServerWriter = Arc::new(Mutex::new(server_write));
+    let (seen_tx, seen_rx) = mpsc::unbounded_channel();
+
+    let server_writer = writer.clone();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(server_read).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let message: Value = serde_json::from_str(&line).unwrap();
+            // Each request is handled on its own task so replies can arrive out of order.
+            tokio::spawn(handle(message, server_writer.clone(), seen_tx.clone()));
+        }
+    });
+
+    let (client_read, client_write) = tokio::io::split(client_io);
+    let client = LocalMcpClient::connect(StdioTransport::from_io(client_read, client_write))
+        .await
+        .unwrap();
+    (client, writer, seen_rx)
+}
+
+async fn next_seen(seen: &mut mpsc::UnboundedReceiver<Value>, method: &str) -> Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(2), seen.recv())
+            .await
+            .expect("timed out waiting for client message")
+            .expect("server stopped");
+        if message["method"] == method || (method.is_empty() && message.get("method").is_none()) {
+            return message;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_calls_and_notifications() {
+    let (client, _, mut seen) = connect().await;
+    assert_eq!(client.server_info().unwrap().name, "mock");
+    assert_eq!(next_seen(&mut seen, "notifications/initialized").await.get("id"), None);
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), ["fast", "slow"]);
+
+    let mut notifications = client.notifications();
+    // The slow call is issued first but answered last.
+    let (slow, fast) = tokio::join!(client.call_tool("slow", None), async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        client.call_tool("fast", None).await
+    });
+    assert_eq!(fast.unwrap().content[0].as_text(), Some("fast"));
+    let slow = slow.unwrap();
+    assert_eq!(slow.content[0].as_text(), Some("slow"));
+    assert!(matches!(&slow.content[1], ToolContent::Image { mime_type, .. } if mime_type == "image/png"));
+
+    let mut saw_log = false;
+    while let Ok(Some(notification)) = tokio::time::timeout(Duration::from_millis(50), notifications.recv()).await {
+        saw_log |= matches!(notification, McpNotification::Log { ref level, .. } if level == "info");
+    }
+    assert!(saw_log);
+    assert_eq!(client.transport().pending_requests(), 0);
+}
+
+#[tokio::test]
+async fn test_progress_and_cancellation() {
+    let (client, _, mut seen) = connect().await;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let result = client.call_tool_with_progress("slow", None, tx).await.unwrap();
+    assert_eq!(result.content[0].as_text(), Some("slow"));
+    let first = rx.recv().await.unwrap();
+    let second = rx.recv().await.unwrap();
+    assert_eq!((first.progress, second.progress, second.total), (1.0, 2.0, Some(2.0)));
+
+    let err = client
+        .call_tool_with_timeout("hang", None, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("timed out"));
+    let cancelled = next_seen(&mut seen, "notifications/cancelled").await;
+    assert!(cancelled["params"]["requestId"].is_u64());
+    assert_eq!(client.transport().pending_requests(), 0);
+}
+
+#[tokio::test]
+async fn test_resources_prompts_and_server_requests() {
+    let (client, writer, mut seen) = connect().await;
+    assert_eq!(client.capabilities().unwrap().resources.as_ref().unwrap().subscribe, Some(true));
+
+    let resources = client.list_resources().await.unwrap();
+    assert_eq!(resources[0].mime_type.as_deref(), Some("text/plain"));
+    let contents = client.read_resource("mem://notes").await.unwrap();
+    assert_eq!(contents[0].text.as_deref(), Some("hello"));
+
+    let mut notifications = client.notifications();
+    client.subscribe_resource("mem://notes").await.unwrap();
+    let update = tokio::time::timeout(Duration::from_secs(2), notifications.recv()).await.unwrap();
+    assert!(matches!(update, Some(McpNotification::ResourceUpdated { ref uri }) if uri == "mem://notes"));
+
+    let prompts = client.list_prompts().await.unwrap();
+    assert!(prompts[0].arguments[0].required);
+    let prompt = client
+        .get_prompt("greet", HashMap::from([("who".to_string(), "Ada".to_string())]))
+        .await
+        .unwrap();
+    assert_eq!(prompt.messages[0].content.as_text(), Some("Hello Ada"));
+
+    let err = client.call_tool("missing", None).await.unwrap();
+    assert_eq!(err.is_error, Some(true));
+
+    // Requests from the server are answered by the transport.
+    send(&writer, json!({"jsonrpc": "2.0", "id": "srv-1", "method": "ping"})).await;
+    let reply = next_seen(&mut seen, "").await;
+    assert_eq!(reply["id"], "srv-1");
+    assert_eq!(reply["result"], json!({}));
+}
+
+#[tokio::test]
+async fn test_pending_calls_fail_when_server_exits() {
+    let (client, writer, _) = connect().await;
+    let call = client.call_tool("hang", None);
+    let close = async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        writer.lock().await.shutdown().await.unwrap();
+    };
+    let (result, _) = tokio::join!(call, close);
+    assert!(result.unwrap_err().to_string().contains("closed"));
+    assert!(!client.is_alive().await);
+}
@@ -0,0 +1,141 @@
+//! `McpServer` over stdio-style pipes and Streamable HTTP.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use pixelcore_claw::{
+    CallToolResult, JsonRpcError, LocalMcpClient, McpServer, McpTool, McpToolProvider, StdioTransport, ToolContent,
+};
+use pixelcore_storage::Storage;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+struct Upper;
+
+#[async_trait]
+impl McpToolProvider for Upper {
+    fn list_tools(&self) -> Vec<McpTool> {
+        vec![McpTool {
+            name: "upper".to_string(),
+            description: "Upper-case a string".to_string(),
+            input_schema: json!({"type": "object", "properties": {"text": {"type": "string"}}}),
+        }]
+    }
+
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, JsonRpcError> {
+        if name != "upper" {
+            return Err(JsonRpcError::new(JsonRpcError::INVALID_PARAMS, format!("Unknown tool: {}", name)));
+        }
+        let Some(text) = arguments["text"].as_str() else {
+            return Ok(CallToolResult {
+                content: vec![ToolContent::text("missing text")],
+                structured_content: None,
+                is_error: Some(true),
+            });
+        };
+        Ok(CallToolResult {
+            content: vec![ToolContent::text(text.to_uppercase())],
+            structured_content: None,
+            is_error: None,
+        })
+    }
+}
+
+fn server() -> McpServer {
+    let storage = Storage::new();
+    storage.set("greeting", json!("hello")).unwrap();
+    McpServer::new("upper-server", "0.1.0", Arc::new(Upper)).with_resources(Arc::new(storage))
+}
+
+#[tokio::test]
+async fn test_local_client_round_trip() {
+    let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+    let (read, write) = tokio::io::split(server_io);
+    tokio::spawn(async move { server().serve_io(read, write).await });
+
+    let (read, write) = tokio::io::split(client_io);
+    let client = LocalMcpClient::connect(StdioTransport::from_io(read, write)).await.unwrap();
+    assert_eq!(client.server_info().unwrap().name, "upper-server");
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools[0].name, "upper");
+    let result = client.call_tool("upper", Some(json!({"text": "abc"}))).await.unwrap();
+    assert_eq!(result.content[0].as_text(), Some("ABC"));
+    let result = client.call_tool("upper", Some(json!({}))).await.unwrap();
+    assert_eq!(result.is_error, Some(true));
+    assert!(client.call_tool("lower", None).await.is_err());
+
+    let resources = client.list_resources().await.unwrap();
+    assert_eq!(resources[0].uri, "storage://greeting");
+    let contents = client.read_resource("storage://greeting").await.unwrap();
+    assert_eq!(contents[0].text.as_deref(), Some("\"hello\""));
+    client.ping().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_streamable_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+    tokio::spawn(async move { server().serve_http_listener(listener).await });
+    let http = reqwest::Client::new();
+
+    let init = http
+        .post(&url)
+        .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2025-03-26"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(init.status(), 200);
+    let session = init.headers()["mcp-session-id"].to_str().unwrap().to_string();
+    let body: Value = init.json().await.unwrap();
+    assert_eq!(body["result"]["serverInfo"]["name"], "upper-server");
+
+    // Requests outside a session are rejected.
+    let response = http
+        .post(&url)
+        .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = http
+        .post(&url)
+        .header("Mcp-Session-Id", &session)
+        .json(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+
+    let response = http
+        .post(&url)
+        .header("Mcp-Session-Id", &session)
+        .json(&json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "upper", "arguments": {"text": "hi"}}}))
+        .send()
+        .await
+        .unwrap();
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["result"]["content"][0]["text"], "HI");
+
+    let response = http
+        .post(&url)
+        .header("Origin", "https://attacker.example")
+        .header("Mcp-Session-Id", &session)
+        .json(&json!({"jsonrpc": "2.0", "id": 4, "method": "ping"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+
+    assert_eq!(http.get(&url).send().await.unwrap().status(), 405);
+    assert_eq!(http.delete(&url).header("Mcp-Session-Id", &session).send().await.unwrap().status(), 200);
+    let response = http
+        .post(&url)
+        .header("Mcp-Session-Id", &session)
+        .json(&json!({"jsonrpc": "2.0", "id": 5, "method": "ping"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
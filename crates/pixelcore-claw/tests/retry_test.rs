@@ -0,0 +1,170 @@
+//! Retry and rate-limit behaviour of `ClawClient` against a local mock server.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use pixelcore_claw::types::{ApiContent, ApiMessage};
+use pixelcore_claw::{ClawClient, ClawError, LlmRequest, RateLimiter, RetryPolicy};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const OK_BODY: &str = r#"{"id":"msg_1","model":"claude-test","stop_reason":"end_turn","content":[{"type":"text","text":"hi"}],"usage":{"input_tokens":1,"output_tokens":1}}"#;
+
+/// A scripted HTTP response: status, extra headers, body.
+struct Scripted(u16, &'static str, &'static str);
+
+/// Serve `script` in order (the last entry repeats) and count requests.
+async fn mock_server(script: Vec<Scripted>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/v1/messages", listener.local_addr().unwrap());
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let script = Arc::new(script);
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else { break };
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            let script = script.clone();
+            tokio::spawn(async move {
+                read_request(&mut socket).await;
+                let Scripted(status, headers, body) = &script[n.min(script.len() - 1)];
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n{}",
+                    status,
+                    body.len(),
+                    headers,
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+    (url, hits)
+}
+
+/// Consume headers and the body announced by `content-length`.
+async fn read_request(socket: &mut tokio::net::TcpStream) {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let Ok(n) = socket.read(&mut chunk).await else { return };
+        if n == 0 {
+            return;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        let text = String::from_utf8_lossy(&buf);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length = text[..end]
+                .lines()
+                .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap_or(0)))
+                .unwrap_or(0);
+            if buf.len() >= end + 4 + length {
+                return;
+            }
+        }
+    }
+}
+
+fn request() -> LlmRequest {
+    LlmRequest {
+        model: "claude-test".to_string(),
+        max_tokens: 16,
+        messages: vec![ApiMessage { role: "user".to_string(), content: ApiContent::Text("hello".to_string()) }],
+        system: None,
+        tools: None,
+        temperature: None,
+        stream: false,
+        tool_choice: None,
+        output_format: None,
+    }
+}
+
+fn fast_policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy::new(max_retries)
+        .with_backoff(Duration::from_millis(10), Duration::from_millis(50))
+        .with_jitter(0.0)
+}
+
+#[tokio::test]
+async fn test_retries_overloaded_until_success() {
+    let (url, hits) = mock_server(vec![
+        Scripted(529, "", r#"{"error":"overloaded"}"#),
+        Scripted(503, "", "unavailable"),
+        Scripted(200, "", OK_BODY),
+    ])
+    .await;
+    let client = ClawClient::new("test-key").with_anthropic_url(url).with_retry_policy(fast_policy(3));
+
+    let response = client.complete(request()).await.unwrap();
+    assert_eq!(response.id, "msg_1");
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_honors_retry_after() {
+    let (url, hits) = mock_server(vec![
+        Scripted(429, "retry-after: 1\r\n", "slow down"),
+        Scripted(200, "", OK_BODY),
+    ])
+    .await;
+    let client = ClawClient::new("test-key").with_anthropic_url(url).with_retry_policy(fast_policy(2));
+
+    let start = Instant::now();
+    client.complete(request()).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_client_errors_are_not_retried() {
+    let (url, hits) = mock_server(vec![Scripted(400, "", "bad request")]).await;
+    let client = ClawClient::new("test-key").with_anthropic_url(url).with_retry_policy(fast_policy(3));
+
+    let err = client.complete(request()).await.unwrap_err();
+    assert!(matches!(err, ClawError::Api { status: 400, .. }));
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_retries_or_elapsed() {
+    let (url, hits) = mock_server(vec![Scripted(500, "", "boom")]).await;
+    let client = ClawClient::new("test-key").with_anthropic_url(url.clone()).with_retry_policy(fast_policy(2));
+    let err = client.complete(request()).await.unwrap_err();
+    assert!(matches!(err, ClawError::RetriesExhausted { attempts: 3, .. }));
+    assert!(err.is_retryable());
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+    // retry-after beyond the wall-time budget stops immediately.
+    let (url, hits) = mock_server(vec![Scripted(529, "retry-after: 30\r\n", "overloaded")]).await;
+    let policy = fast_policy(5).with_max_elapsed(Some(Duration::from_secs(1)));
+    let client = ClawClient::new("test-key").with_anthropic_url(url).with_retry_policy(policy);
+    let start = Instant::now();
+    let err = client.complete(request()).await.unwrap_err();
+    assert!(matches!(err, ClawError::Overloaded { status: 529, retry_after: Some(30), .. }));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_shared_rate_limiter_throttles_clients() {
+    let (url, hits) = mock_server(vec![Scripted(200, "", OK_BODY)]).await;
+    // Burst of 2, then one request every 200ms across both clients.
+    let limiter = RateLimiter::new(2, 5.0);
+    let a = ClawClient::new("test-key").with_anthropic_url(url.clone()).with_rate_limiter(limiter.clone());
+    let b = ClawClient::new("test-key").with_anthropic_url(url).with_rate_limiter(limiter);
+
+    let start = Instant::now();
+    let (r1, r2) = tokio::join!(a.complete(request()), b.complete(request()));
+    r1.unwrap();
+    r2.unwrap();
+    assert!(start.elapsed() < Duration::from_millis(150));
+
+    let (r3, r4) = tokio::join!(a.complete(request()), b.complete(request()));
+    r3.unwrap();
+    r4.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(350));
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+}
@@ -86,6 +86,8 @@ pub struct FlowMetrics {
     pub window_start: Instant,
     /// 当前任务开始时间
     pub current_task_start: Option<Instant>,
+    /// 正在执行（已开始、未结束）的任务数
+    pub in_flight: u32,
 }
 
 impl FlowMetrics {
@@ -97,6 +99,7 @@ impl FlowMetrics {
             recent_response_times: Vec::new(),
             window_start: Instant::now(),
             current_task_start: None,
+            in_flight: 0,
         }
     }
 
@@ -106,10 +109,12 @@ impl FlowMetrics {
             self.task_switches += 1;
         }
         self.current_task_start = Some(Instant::now());
+        self.in_flight += 1;
     }
 
     /// 记录任务完成
     pub fn task_completed(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
         if let Some(start) = self.current_task_start.take() {
             let duration = start.elapsed().as_millis() as u64;
             self.recent_response_times.push(duration);
@@ -123,6 +128,7 @@ impl FlowMetrics {
 
     /// 记录任务失败
     pub fn task_failed(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
         self.current_task_start = None;
         self.tasks_failed += 1;
     }
@@ -176,7 +182,7 @@ impl FlowMetrics {
         (self.task_switches as f64) / (elapsed / 60.0)
     }
 
-    /// 重置指标（开始新的时间窗口）
+    /// 重置指标（开始新的时间窗口）；仍在执行的任务数保留
     pub fn reset(&mut self) {
         self.tasks_completed = 0;
         self.tasks_failed = 0;
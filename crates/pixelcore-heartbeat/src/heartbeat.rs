@@ -1,6 +1,8 @@
+use std::future::Future;
 use std::time::Duration;
 use tokio::time;
 use pixelcore_runtime::event::{Event, EventBus, EventKind};
+use pixelcore_runtime::AgentId;
 
 #[derive(Debug, Clone)]
 pub struct HeartbeatConfig {
@@ -39,4 +41,30 @@ impl Heartbeat {
             let _ = self.event_bus.publish(event);
         }
     }
+
+    /// 为单个 Agent 发送心跳
+    ///
+    /// 每个周期调用一次 `probe`，返回 `true` 时发布带 `agent_id` 的
+    /// `HeartbeatTick`。超过一个周期仍未返回视为失败，因此卡住的探测会被
+    /// `LivenessMonitor` 记为漏跳。`probe` 可以是 `LocalMcpClient::ping`、
+    /// 子进程的 `is_alive` 等。
+    pub async fn run_agent<F, Fut>(&self, agent_id: AgentId, probe: F)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let mut interval = time::interval(self.config.interval);
+        loop {
+            interval.tick().await;
+            if !time::timeout(self.config.interval, probe()).await.unwrap_or(false) {
+                continue;
+            }
+            let event = Event::new(
+                EventKind::HeartbeatTick,
+                &self.config.source,
+                serde_json::json!({ "agent_id": agent_id.to_string(), "ts": chrono::Utc::now().to_rfc3339() }),
+            );
+            let _ = self.event_bus.publish(event);
+        }
+    }
 }
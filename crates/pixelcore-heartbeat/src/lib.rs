@@ -1,9 +1,13 @@
 pub mod flow;
 pub mod heartbeat;
+pub mod liveness;
 pub mod monitor;
+pub mod schedule;
 pub mod scheduler;
 
 pub use flow::{FlowLevel, FlowMetrics, FlowState, FlowStateMachine, FlowStateMachineConfig};
 pub use heartbeat::{Heartbeat, HeartbeatConfig};
-pub use monitor::FlowMonitor;
-pub use scheduler::Scheduler;
+pub use liveness::{Liveness, LivenessCause, LivenessConfig, LivenessMonitor};
+pub use monitor::{AgentLoad, FlowMonitor};
+pub use schedule::{JobOptions, MisfirePolicy, Schedule};
+pub use scheduler::{JobRun, JobState, RunOutcome, Scheduler, SchedulerError};
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+use pixelcore_runtime::event::{Event, EventBus, EventKind};
+use pixelcore_runtime::AgentId;
+
+/// Agent 的存活状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Liveness {
+    /// 按时心跳，且没有卡住的任务
+    Alive,
+    /// 漏掉了少量心跳
+    Suspect,
+    /// 漏掉的心跳超过阈值，或任务执行超时
+    Dead,
+}
+
+/// 判定为 `Dead` 的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LivenessCause {
+    MissedBeats,
+    HungTask,
+}
+
+/// 存活检测配置
+#[derive(Debug, Clone)]
+pub struct LivenessConfig {
+    /// 预期的心跳间隔，按毫秒计，不足 1 毫秒按 1 毫秒处理
+    pub beat_interval: Duration,
+    /// 连续漏掉多少次心跳后变为 `Suspect`
+    pub suspect_after: u32,
+    /// 连续漏掉多少次心跳后变为 `Dead`
+    pub dead_after: u32,
+    /// 单个任务执行超过该时长视为卡死（`None` 不检测）
+    pub hang_timeout: Option<Duration>,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            beat_interval: Duration::from_secs(10),
+            suspect_after: 1,
+            dead_after: 3,
+            hang_timeout: Some(Duration::from_secs(300)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AgentLiveness {
+    state: Liveness,
+    /// 最近一次心跳；收到第一次心跳后才开始检测漏跳
+    last_beat: Option<Instant>,
+    in_flight: u32,
+    /// 当前任务开始（或上一个任务结束）的时间
+    busy_since: Option<Instant>,
+}
+
+impl AgentLiveness {
+    fn new() -> Self {
+        Self { state: Liveness::Alive, last_beat: None, in_flight: 0, busy_since: None }
+    }
+
+    fn missed_beats(&self, now: Instant, interval: Duration) -> u32 {
+        match self.last_beat {
+            Some(last) => (now.duration_since(last).as_millis() / interval.as_millis().max(1)) as u32,
+            None => 0,
+        }
+    }
+
+    fn hung(&self, now: Instant, hang_timeout: Option<Duration>) -> bool {
+        match (hang_timeout, self.busy_since) {
+            (Some(timeout), Some(since)) => self.in_flight > 0 && now.duration_since(since) >= timeout,
+            _ => false,
+        }
+    }
+}
+
+/// 存活监控器
+///
+/// 通过事件总线接收心跳和任务事件：
+/// - 带 `agent_id` 的 `HeartbeatTick`（见 `Heartbeat::run_agent`）记为一次心跳，
+///   任务完成或失败也算作心跳
+/// - `TaskStarted` 之后迟迟没有 `TaskCompleted`/`TaskFailed` 视为任务卡死
+///
+/// 状态变化时发布 `Custom("liveness_changed")` 事件，payload 包含
+/// `agent_id`、`old_state`、`new_state`、`missed_beats` 和 `cause`。
+pub struct LivenessMonitor {
+    agents: Arc<RwLock<HashMap<AgentId, AgentLiveness>>>,
+    event_bus: EventBus,
+    config: LivenessConfig,
+}
+
+impl LivenessMonitor {
+    pub fn new(event_bus: EventBus, mut config: LivenessConfig) -> Self {
+        // 检查周期是心跳间隔的一半，不能为零
+        config.beat_interval = config.beat_interval.max(Duration::from_millis(1));
+        Self {
+            agents: Arc::new(RwLock::new(HashMap::new())),
+            event_bus,
+            config,
+        }
+    }
+
+    /// 注册需要检测的 Agent
+    pub async fn register_agent(&self, agent_id: AgentId) {
+        self.agents.write().await.insert(agent_id, AgentLiveness::new());
+    }
+
+    /// 取消注册 Agent
+    pub async fn unregister_agent(&self, agent_id: &AgentId) {
+        self.agents.write().await.remove(agent_id);
+    }
+
+    /// 获取 Agent 的存活状态
+    pub async fn state(&self, agent_id: &AgentId) -> Option<Liveness> {
+        self.agents.read().await.get(agent_id).map(|a| a.state)
+    }
+
+    /// 记录一次心跳
+    pub async fn beat(&self, agent_id: &AgentId) {
+        Self::record(&self.agents, agent_id, &EventKind::HeartbeatTick).await;
+    }
+
+    /// Agent 重启或替换后重置为 `Alive`，清空进行中的任务
+    pub async fn reset(&self, agent_id: &AgentId) {
+        let mut agents = self.agents.write().await;
+        if let Some(agent) = agents.get_mut(agent_id) {
+            let old = agent.state;
+            let beating = agent.last_beat.is_some();
+            *agent = AgentLiveness::new();
+            if beating {
+                agent.last_beat = Some(Instant::now());
+            }
+            if old != Liveness::Alive {
+                self.publish(agent_id, old, Liveness::Alive, 0, None);
+            }
+        }
+    }
+
+    /// 根据漏跳次数和任务时长重新评估所有 Agent，返回状态发生变化的
+    /// `(agent_id, 旧状态, 新状态)`
+    pub async fn check(&self) -> Vec<(AgentId, Liveness, Liveness)> {
+        let now = Instant::now();
+        let mut changes = Vec::new();
+        let mut agents = self.agents.write().await;
+        for (id, agent) in agents.iter_mut() {
+            let missed = agent.missed_beats(now, self.config.beat_interval);
+            let (state, cause) = if agent.hung(now, self.config.hang_timeout) {
+                (Liveness::Dead, Some(LivenessCause::HungTask))
+            } else if missed >= self.config.dead_after {
+                (Liveness::Dead, Some(LivenessCause::MissedBeats))
+            } else if missed >= self.config.suspect_after {
+                (Liveness::Suspect, None)
+            } else {
+                (Liveness::Alive, None)
+            };
+
+            if state != agent.state {
+                self.publish(id, agent.state, state, missed, cause);
+                changes.push((*id, agent.state, state));
+                agent.state = state;
+            }
+        }
+        changes
+    }
+
+    /// 启动监控：监听事件，并按心跳间隔的一半定期检查
+    pub async fn run(self: &Arc<Self>) {
+        let mut receiver = self.event_bus.subscribe();
+        let agents = Arc::clone(&self.agents);
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        if let Some(agent_id) = Self::extract_agent_id(&event) {
+                            Self::record(&agents, &agent_id, &event.kind).await;
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("LivenessMonitor lagged, skipped {} events", n);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let monitor = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(monitor.config.beat_interval / 2);
+            loop {
+                interval.tick().await;
+                monitor.check().await;
+            }
+        });
+    }
+
+    async fn record(agents: &RwLock<HashMap<AgentId, AgentLiveness>>, agent_id: &AgentId, kind: &EventKind) {
+        let mut agents = agents.write().await;
+        let Some(agent) = agents.get_mut(agent_id) else { return };
+        let now = Instant::now();
+        match kind {
+            EventKind::HeartbeatTick => agent.last_beat = Some(now),
+            EventKind::TaskStarted => {
+                if agent.in_flight == 0 {
+                    agent.busy_since = Some(now);
+                }
+                agent.in_flight += 1;
+            }
+            EventKind::TaskCompleted | EventKind::TaskFailed => {
+                agent.in_flight = agent.in_flight.saturating_sub(1);
+                // 还有任务在执行时，从这次完成开始重新计时
+                agent.busy_since = (agent.in_flight > 0).then_some(now);
+                if agent.last_beat.is_some() {
+                    agent.last_beat = Some(now);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn publish(&self, agent_id: &AgentId, old: Liveness, new: Liveness, missed: u32, cause: Option<LivenessCause>) {
+        let event = Event::new(
+            EventKind::Custom("liveness_changed".to_string()),
+            format!("agent:{}", agent_id),
+            serde_json::json!({
+                "agent_id": agent_id,
+                "old_state": old,
+                "new_state": new,
+                "missed_beats": missed,
+                "cause": cause,
+            }),
+        );
+        let _ = self.event_bus.publish(event);
+    }
+
+    fn extract_agent_id(event: &Event) -> Option<AgentId> {
+        event.payload.get("agent_id").and_then(|v| v.as_str())?.parse().ok()
+    }
+}
@@ -1,10 +1,22 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use pixelcore_runtime::event::{Event, EventBus, EventKind};
 use pixelcore_runtime::AgentId;
 use crate::flow::{FlowState, FlowStateMachine, FlowStateMachineConfig};
 
+/// Agent 的实时负载
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AgentLoad {
+    /// 正在执行的任务数
+    pub in_flight: u32,
+    /// 当前时间窗口内的错误率（0.0-1.0）
+    pub error_rate: f64,
+    /// 综合心流分数（0.0-1.0）
+    pub flow_score: f64,
+}
+
 /// 心流监控器
 /// 监听 Agent 的事件并更新心流状态
 pub struct FlowMonitor {
@@ -43,6 +55,16 @@ impl FlowMonitor {
         machines.get(agent_id).map(|m| m.state().clone())
     }
 
+    /// 获取 Agent 的实时负载，未注册的 Agent 返回 None
+    pub async fn load(&self, agent_id: &AgentId) -> Option<AgentLoad> {
+        let machines = self.state_machines.read().await;
+        machines.get(agent_id).map(|m| AgentLoad {
+            in_flight: m.metrics().in_flight,
+            error_rate: m.metrics().error_rate(),
+            flow_score: m.calculate_flow_score_public(),
+        })
+    }
+
     /// 获取 Agent 的心流指标（用于调试）
     pub async fn get_metrics_debug(&self, agent_id: &AgentId) -> Option<String> {
         let machines = self.state_machines.read().await;
@@ -0,0 +1,198 @@
+use std::str::FromStr;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use crate::scheduler::SchedulerError;
+
+/// When a scheduled job fires.
+///
+/// Deserializing validates like the constructors do, so stored state cannot
+/// carry a zero interval or a broken cron expression.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", try_from = "RawSchedule")]
+pub enum Schedule {
+    /// Fixed rate; the first run is immediate.
+    Interval { every: Duration },
+    /// Cron expression evaluated in an IANA time zone.
+    Cron { expression: String, timezone: String },
+}
+
+/// `Schedule` as written, before validation.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RawSchedule {
+    Interval { every: Duration },
+    Cron { expression: String, timezone: String },
+}
+
+impl TryFrom<RawSchedule> for Schedule {
+    type Error = SchedulerError;
+
+    fn try_from(raw: RawSchedule) -> Result<Self, Self::Error> {
+        let schedule = match raw {
+            RawSchedule::Interval { every } => Self::Interval { every },
+            RawSchedule::Cron { expression, timezone } => Self::Cron { expression, timezone },
+        };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+}
+
+impl Schedule {
+    /// A fixed-rate schedule. A zero interval is rejected: it would fire
+    /// continuously.
+    pub fn every(interval: Duration) -> Result<Self, SchedulerError> {
+        let schedule = Self::Interval { every: interval };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    /// A cron schedule in UTC. See `cron_in`.
+    pub fn cron(expression: &str) -> Result<Self, SchedulerError> {
+        Self::cron_in(expression, "UTC")
+    }
+
+    /// A cron schedule in `timezone` (e.g. `Europe/Berlin`), so that
+    /// `0 9 * * Mon-Fri` means 9am local time across DST changes.
+    ///
+    /// Takes the usual five fields (minute to day of week), the six or seven
+    /// field form with seconds and years, or a shortcut such as `@daily`.
+    pub fn cron_in(expression: &str, timezone: &str) -> Result<Self, SchedulerError> {
+        parse_cron(expression)?;
+        parse_timezone(timezone)?;
+        Ok(Self::Cron { expression: expression.trim().to_string(), timezone: timezone.to_string() })
+    }
+
+    /// Check a schedule built without the constructors.
+    pub fn validate(&self) -> Result<(), SchedulerError> {
+        match self {
+            Self::Interval { every } if every.is_zero() => Err(SchedulerError::ZeroInterval),
+            Self::Interval { .. } => Ok(()),
+            Self::Cron { expression, timezone } => {
+                parse_cron(expression)?;
+                parse_timezone(timezone)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// The first fire time strictly after `after`, or `None` if the schedule is exhausted.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Interval { every } => Some(after + chrono::Duration::from_std(*every).ok()?),
+            Self::Cron { expression, timezone } => {
+                let (schedule, tz) = (parse_cron(expression).ok()?, parse_timezone(timezone).ok()?);
+                schedule.after(&after.with_timezone(&tz)).next().map(|t| t.with_timezone(&Utc))
+            }
+        }
+    }
+
+    /// The first fire time for a job registered at `now`.
+    pub(crate) fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Interval { .. } => Some(now),
+            Self::Cron { .. } => self.next_after(now),
+        }
+    }
+
+    /// Fire times from `from` (inclusive) up to `until` (inclusive), at most `limit`.
+    pub(crate) fn fire_times(&self, from: DateTime<Utc>, until: DateTime<Utc>, limit: usize) -> Vec<DateTime<Utc>> {
+        let mut times = Vec::new();
+        let mut next = Some(from);
+        while let Some(time) = next.filter(|t| *t <= until && times.len() < limit) {
+            times.push(time);
+            next = self.next_after(time);
+        }
+        times
+    }
+}
+
+/// The `cron` crate wants a seconds field; a five-field expression gets `0` prepended.
+///
+/// Zero steps (`*/0`) are rejected by the `cron` crate itself.
+fn parse_cron(expression: &str) -> Result<cron::Schedule, SchedulerError> {
+    let expression = expression.trim();
+    let normalized = if expression.split_whitespace().count() == 5 {
+        format!("0 {expression}")
+    } else {
+        expression.to_string()
+    };
+    cron::Schedule::from_str(&normalized).map_err(|e| SchedulerError::InvalidCron {
+        expression: expression.to_string(),
+        message: e.to_string(),
+    })
+}
+
+fn parse_timezone(timezone: &str) -> Result<Tz, SchedulerError> {
+    timezone.parse().map_err(|_| SchedulerError::InvalidTimezone(timezone.to_string()))
+}
+
+/// What to do with fire times that passed while no process ran the job
+/// (downtime, a pause, or a run that overran the next fire time by more than
+/// `JobOptions::misfire_grace`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// Drop them and wait for the next fire time.
+    Skip,
+    /// Run once now for all of them.
+    #[default]
+    FireOnce,
+    /// Run once for each, back to back (capped at `JobOptions::max_catch_up`).
+    FireAll,
+}
+
+/// Per-job settings for `Scheduler::register_job`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobOptions {
+    pub misfire: MisfirePolicy,
+    /// How late a run may start and still count as on time.
+    pub misfire_grace: Duration,
+    /// Upper bound of a random delay added before each run, to spread load
+    /// when many jobs share a fire time.
+    pub jitter: Duration,
+    /// Runs kept in the job's history.
+    pub history_limit: usize,
+    /// Most runs `MisfirePolicy::FireAll` makes up in one go.
+    pub max_catch_up: usize,
+}
+
+impl JobOptions {
+    pub fn with_misfire(mut self, policy: MisfirePolicy) -> Self {
+        self.misfire = policy;
+        self
+    }
+
+    pub fn with_misfire_grace(mut self, grace: Duration) -> Self {
+        self.misfire_grace = grace;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
+
+    pub fn with_max_catch_up(mut self, limit: usize) -> Self {
+        self.max_catch_up = limit.max(1);
+        self
+    }
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        Self {
+            misfire: MisfirePolicy::default(),
+            misfire_grace: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            history_limit: 20,
+            max_catch_up: 100,
+        }
+    }
+}
@@ -1,51 +1,342 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::task::JoinHandle;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::Notify;
+use tokio::task::{AbortHandle, JoinHandle};
 use tokio::time;
+use tracing::{info, warn};
+use uuid::Uuid;
+use pixelcore_storage::{Storage, StorageError, StorageValue};
+use crate::schedule::{JobOptions, MisfirePolicy, Schedule};
 
-type AsyncTaskFn = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + 'static>;
+type AsyncTaskFn = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send + Sync + 'static>;
 
+const JOB_PREFIX: &str = "scheduler_job:";
+const LEASE_PREFIX: &str = "scheduler_lease:";
+
+#[derive(Debug, Error)]
+pub enum SchedulerError {
+    #[error("Invalid cron expression '{expression}': {message}")]
+    InvalidCron { expression: String, message: String },
+
+    #[error("Unknown time zone: {0}")]
+    InvalidTimezone(String),
+
+    #[error("Schedule interval must be greater than zero")]
+    ZeroInterval,
+
+    #[error("Job not registered: {0}")]
+    JobNotFound(String),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RunOutcome {
+    Succeeded,
+    Failed { error: String },
+    /// Aborted mid-run because the lease could not be renewed, so another
+    /// instance may already be running the job.
+    LeaseLost { error: String },
+}
+
+/// One execution of a job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRun {
+    /// The fire time this run was for.
+    pub scheduled_for: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub outcome: RunOutcome,
+    /// The scheduler instance that ran it.
+    pub owner: Uuid,
+}
+
+/// Persisted state of a job, shared by every scheduler using the same `Storage`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobState {
+    pub name: String,
+    pub schedule: Schedule,
+    /// `None` once a cron schedule has no fire times left.
+    pub next_run: Option<DateTime<Utc>>,
+    pub paused: bool,
+    pub run_count: u64,
+    /// Most recent runs, oldest first.
+    pub history: VecDeque<JobRun>,
+}
+
+impl JobState {
+    fn new(name: &str, schedule: &Schedule, now: DateTime<Utc>) -> Self {
+        Self {
+            name: name.to_string(),
+            schedule: schedule.clone(),
+            next_run: schedule.first_run(now),
+            paused: false,
+            run_count: 0,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+/// Ownership of a job within a cluster; only the holder runs it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Lease {
+    owner: Uuid,
+    expires_at: DateTime<Utc>,
+}
+
+/// Where the scheduler reads the current time.
+#[derive(Debug, Clone, Copy)]
+enum Clock {
+    System,
+    /// Wall time when the clock was created, advanced by `tokio::time`.
+    Tokio { wall: DateTime<Utc>, start: time::Instant },
+}
+
+impl Clock {
+    fn now(&self) -> DateTime<Utc> {
+        match self {
+            Self::System => Utc::now(),
+            Self::Tokio { wall, start } => {
+                *wall + chrono::Duration::from_std(start.elapsed()).unwrap_or_default()
+            }
+        }
+    }
+}
+
+struct Job {
+    schedule: Schedule,
+    options: JobOptions,
+    task: AsyncTaskFn,
+    wake: Arc<Notify>,
+    runner: Option<AbortHandle>,
+}
+
+/// Runs jobs on intervals or cron schedules.
+///
+/// Job state (next fire time, pause flag, run history) lives in `Storage`, so
+/// it survives restarts when the storage is persistent. Several schedulers,
+/// in one process or many, can share a storage: a job only runs in the
+/// instance holding its lease, which is renewed while it runs and taken over
+/// by another instance once it expires. A job's runs never overlap.
 pub struct Scheduler {
-    tasks: HashMap<String, (Duration, AsyncTaskFn)>,
+    storage: Storage,
+    owner: Uuid,
+    lease_ttl: Duration,
+    jobs: Mutex<HashMap<String, Job>>,
+    started: AtomicBool,
+    clock: Clock,
 }
 
 impl Scheduler {
+    /// A scheduler with in-memory state.
     pub fn new() -> Self {
-        Self { tasks: HashMap::new() }
+        Self {
+            storage: Storage::new(),
+            owner: Uuid::new_v4(),
+            lease_ttl: Duration::from_secs(30),
+            jobs: Mutex::new(HashMap::new()),
+            started: AtomicBool::new(false),
+            clock: Clock::System,
+        }
+    }
+
+    /// Keep job state and leases in `storage`.
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// How long a job lease lasts without renewal, i.e. how quickly another
+    /// instance takes over a job after its owner dies.
+    pub fn with_lease_ttl(mut self, ttl: Duration) -> Self {
+        self.lease_ttl = ttl.max(Duration::from_millis(10));
+        self
+    }
+
+    /// Measure time with `tokio::time` from now on instead of reading the
+    /// system clock, so that a paused runtime (`tokio::time::pause`) drives
+    /// the schedule. Meant for tests and simulations: this clock does not
+    /// follow system clock adjustments.
+    pub fn with_tokio_clock(mut self) -> Self {
+        self.clock = Clock::Tokio { wall: Utc::now(), start: time::Instant::now() };
+        self
+    }
+
+    /// The ID this instance holds leases under.
+    pub fn owner(&self) -> Uuid {
+        self.owner
     }
 
     /// Register a sync closure.
-    pub fn register(&mut self, name: impl Into<String>, interval: Duration, f: impl Fn() + Send + Sync + 'static) {
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero, like `tokio::time::interval`. Use
+    /// `register_job` to handle an invalid schedule as an error.
+    pub fn register(&self, name: impl Into<String>, interval: Duration, f: impl Fn() + Send + Sync + 'static) {
         let f = Arc::new(f);
         self.register_async(name, interval, move || {
             let f = f.clone();
-            Box::pin(async move { f() })
+            async move { f() }
         });
     }
 
     /// Register an async closure.
-    pub fn register_async<F, Fut>(&mut self, name: impl Into<String>, interval: Duration, f: F)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero, like `tokio::time::interval`. Use
+    /// `register_job` to handle an invalid schedule as an error.
+    pub fn register_async<F, Fut>(&self, name: impl Into<String>, interval: Duration, f: F)
     where
         F: Fn() -> Fut + Send + Sync + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
-        let wrapped: AsyncTaskFn = Arc::new(move || Box::pin(f()) as Pin<Box<dyn Future<Output = ()> + Send>>);
-        self.tasks.insert(name.into(), (interval, wrapped));
+        assert!(!interval.is_zero(), "`interval` must be non-zero");
+        let f = Arc::new(f);
+        let schedule = Schedule::Interval { every: interval };
+        self.register_job(name, schedule, JobOptions::default(), move || {
+            let f = f.clone();
+            async move {
+                f().await;
+                Ok::<(), String>(())
+            }
+        })
+        .expect("a non-zero interval is a valid schedule");
+    }
+
+    /// Register a job whose errors are recorded in its run history.
+    ///
+    /// Replaces a job of the same name. Once the scheduler is running the job
+    /// starts immediately. Fails without registering anything if `schedule`
+    /// is invalid (see `Schedule::validate`).
+    pub fn register_job<F, Fut, E>(
+        &self,
+        name: impl Into<String>,
+        schedule: Schedule,
+        options: JobOptions,
+        f: F,
+    ) -> Result<(), SchedulerError>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display + 'static,
+    {
+        schedule.validate()?;
+        let name = name.into();
+        let task: AsyncTaskFn = Arc::new(move || {
+            let fut = f();
+            Box::pin(async move { fut.await.map_err(|e| e.to_string()) })
+        });
+        let job = Job { schedule, options, task, wake: Arc::new(Notify::new()), runner: None };
+
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(old) = jobs.insert(name.clone(), job) {
+            if let Some(runner) = old.runner {
+                runner.abort();
+            }
+        }
+        if self.started.load(Ordering::SeqCst) {
+            self.spawn_runner(&name, jobs.get_mut(&name).unwrap());
+        }
+        Ok(())
     }
 
-    pub fn spawn_all(self) -> Vec<JoinHandle<()>> {
-        self.tasks.into_values().map(|(interval, f)| {
-            tokio::spawn(async move {
-                let mut ticker = time::interval(interval);
-                loop {
-                    ticker.tick().await;
-                    f().await;
-                }
-            })
-        }).collect()
+    /// Start every registered job; jobs registered later start on registration.
+    pub fn spawn_all(&self) -> Vec<JoinHandle<()>> {
+        self.started.store(true, Ordering::SeqCst);
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.iter_mut()
+            .filter(|(_, job)| job.runner.is_none())
+            .map(|(name, job)| self.spawn_runner(name, job))
+            .collect()
+    }
+
+    fn spawn_runner(&self, name: &str, job: &mut Job) -> JoinHandle<()> {
+        let runner = Runner {
+            name: name.to_string(),
+            schedule: job.schedule.clone(),
+            options: job.options.clone(),
+            task: job.task.clone(),
+            wake: job.wake.clone(),
+            storage: self.storage.clone(),
+            owner: self.owner,
+            lease_ttl: self.lease_ttl,
+            clock: self.clock,
+        };
+        let handle = tokio::spawn(runner.run());
+        job.runner = Some(handle.abort_handle());
+        handle
+    }
+
+    /// Stop firing a job until `resume`; a run in progress finishes. The
+    /// flag is persisted, so it applies to every instance sharing the storage.
+    pub fn pause(&self, name: &str) -> Result<(), SchedulerError> {
+        self.set_paused(name, true)
+    }
+
+    /// Fire times missed while paused are handled by the job's `MisfirePolicy`.
+    pub fn resume(&self, name: &str) -> Result<(), SchedulerError> {
+        self.set_paused(name, false)
+    }
+
+    fn set_paused(&self, name: &str, paused: bool) -> Result<(), SchedulerError> {
+        let (schedule, wake) = {
+            let jobs = self.jobs.lock().unwrap();
+            let job = jobs.get(name).ok_or_else(|| SchedulerError::JobNotFound(name.to_string()))?;
+            (job.schedule.clone(), job.wake.clone())
+        };
+        update_state(&self.storage, self.clock, name, &schedule, |state| state.paused = paused)?;
+        wake.notify_one();
+        Ok(())
+    }
+
+    /// Stop a job and delete its state and history. Returns false if no job
+    /// of that name was registered.
+    pub fn unregister(&self, name: &str) -> Result<bool, SchedulerError> {
+        let Some(job) = self.jobs.lock().unwrap().remove(name) else { return Ok(false) };
+        if let Some(runner) = job.runner {
+            runner.abort();
+        }
+        self.storage.delete(&job_key(name))?;
+        let lease_key = lease_key(name);
+        if let Some(current) = get_value(&self.storage, &lease_key)? {
+            let lease: Lease = serde_json::from_value(current.clone())?;
+            if lease.owner == self.owner {
+                self.storage.compare_and_swap(&lease_key, Some(&current), None)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Persisted state of a job, `None` if it has not started yet.
+    pub fn job(&self, name: &str) -> Result<Option<JobState>, SchedulerError> {
+        load_state(&self.storage, name)
+    }
+
+    /// Recent runs of a job, oldest first.
+    pub fn history(&self, name: &str) -> Result<Vec<JobRun>, SchedulerError> {
+        Ok(self.job(name)?.map(|state| state.history.into()).unwrap_or_default())
+    }
+
+    /// Names of the registered jobs, sorted.
+    pub fn job_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.jobs.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
     }
 }
 
@@ -54,3 +345,214 @@ impl Default for Scheduler {
         Self::new()
     }
 }
+
+fn job_key(name: &str) -> String {
+    format!("{JOB_PREFIX}{name}")
+}
+
+fn lease_key(name: &str) -> String {
+    format!("{LEASE_PREFIX}{name}")
+}
+
+fn get_value(storage: &Storage, key: &str) -> Result<Option<StorageValue>, SchedulerError> {
+    match storage.get(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(StorageError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn load_state(storage: &Storage, name: &str) -> Result<Option<JobState>, SchedulerError> {
+    get_value(storage, &job_key(name))?
+        .map(|value| serde_json::from_value(value).map_err(SchedulerError::from))
+        .transpose()
+}
+
+/// Read-modify-write of a job's state with compare-and-swap, so concurrent
+/// writers (a runner recording a run, `pause` from another thread) never lose
+/// each other's changes. Creates the state if missing and adopts `schedule`
+/// if the registered schedule changed.
+fn update_state(
+    storage: &Storage,
+    clock: Clock,
+    name: &str,
+    schedule: &Schedule,
+    mut update: impl FnMut(&mut JobState),
+) -> Result<JobState, SchedulerError> {
+    let key = job_key(name);
+    loop {
+        let current = get_value(storage, &key)?;
+        let now = clock.now();
+        let mut state = match &current {
+            Some(value) => serde_json::from_value(value.clone())?,
+            None => JobState::new(name, schedule, now),
+        };
+        if state.schedule != *schedule {
+            state.schedule = schedule.clone();
+            state.next_run = schedule.first_run(now);
+        }
+        update(&mut state);
+        if storage.compare_and_swap(&key, current.as_ref(), Some(serde_json::to_value(&state)?))? {
+            storage.flush()?;
+            return Ok(state);
+        }
+    }
+}
+
+/// Aborts a spawned job run if the runner is aborted mid-run (`unregister`).
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// The loop driving one job in one scheduler instance.
+struct Runner {
+    name: String,
+    schedule: Schedule,
+    options: JobOptions,
+    task: AsyncTaskFn,
+    wake: Arc<Notify>,
+    storage: Storage,
+    owner: Uuid,
+    lease_ttl: Duration,
+    clock: Clock,
+}
+
+impl Runner {
+    async fn run(self) {
+        loop {
+            let wait = match self.tick().await {
+                Ok(wait) => wait,
+                Err(e) => {
+                    warn!("Scheduled job '{}' failed to update its state: {}", self.name, e);
+                    Some(self.lease_ttl)
+                }
+            };
+            match wait {
+                Some(wait) => {
+                    tokio::select! {
+                        _ = time::sleep(wait) => {}
+                        _ = self.wake.notified() => {}
+                    }
+                }
+                None => self.wake.notified().await,
+            }
+        }
+    }
+
+    /// Runs the job if it is due and this instance holds the lease. Returns
+    /// how long to wait before the next check (`None`: until woken).
+    async fn tick(&self) -> Result<Option<Duration>, SchedulerError> {
+        let state = match load_state(&self.storage, &self.name)? {
+            Some(state) if state.schedule == self.schedule => state,
+            _ => update_state(&self.storage, self.clock, &self.name, &self.schedule, |_| {})?,
+        };
+        let now = self.clock.now();
+        if state.paused {
+            return Ok(None);
+        }
+        let Some(due) = state.next_run else { return Ok(None) };
+        if due > now {
+            return Ok(Some((due - now).to_std().unwrap_or_default()));
+        }
+        if !self.acquire_lease()? {
+            return Ok(Some(self.lease_ttl));
+        }
+        // Another instance may have run it while we waited for the lease.
+        let state = load_state(&self.storage, &self.name)?.unwrap_or(state);
+        let Some(due) = state.next_run.filter(|due| *due <= now && !state.paused) else {
+            return Ok(Some(Duration::ZERO));
+        };
+
+        let missed = self.schedule.fire_times(due, now, self.options.max_catch_up);
+        let last = *missed.last().unwrap_or(&due);
+        let late = (now - due).to_std().unwrap_or_default() > self.options.misfire_grace;
+        let runs = match self.options.misfire {
+            _ if !late => vec![last],
+            MisfirePolicy::Skip => Vec::new(),
+            MisfirePolicy::FireOnce => vec![last],
+            MisfirePolicy::FireAll => missed.clone(),
+        };
+        if late {
+            info!("Scheduled job '{}' missed {} fire time(s), running {}", self.name, missed.len(), runs.len());
+        }
+
+        // Advance first, so a crash mid-run is not followed by a rerun of the same fire time.
+        let next = self.schedule.next_after(last);
+        update_state(&self.storage, self.clock, &self.name, &self.schedule, |state| state.next_run = next)?;
+
+        for scheduled_for in runs {
+            let run = self.execute(scheduled_for).await;
+            let lost = matches!(run.outcome, RunOutcome::LeaseLost { .. });
+            let limit = self.options.history_limit;
+            update_state(&self.storage, self.clock, &self.name, &self.schedule, |state| {
+                state.run_count += 1;
+                state.history.push_back(run.clone());
+                while state.history.len() > limit {
+                    state.history.pop_front();
+                }
+            })?;
+            if lost {
+                return Ok(Some(self.lease_ttl));
+            }
+        }
+        Ok(Some(Duration::ZERO))
+    }
+
+    async fn execute(&self, scheduled_for: DateTime<Utc>) -> JobRun {
+        if !self.options.jitter.is_zero() {
+            let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.options.jitter);
+            time::sleep(jitter).await;
+        }
+
+        let started_at = self.clock.now();
+        let handle = tokio::spawn((self.task)());
+        let _guard = AbortOnDrop(handle.abort_handle());
+        tokio::pin!(handle);
+        let outcome = loop {
+            tokio::select! {
+                result = &mut handle => break match result {
+                    Ok(Ok(())) => RunOutcome::Succeeded,
+                    Ok(Err(error)) => RunOutcome::Failed { error },
+                    Err(e) if e.is_panic() => RunOutcome::Failed { error: "job panicked".to_string() },
+                    Err(e) => RunOutcome::Failed { error: e.to_string() },
+                },
+                _ = time::sleep(self.lease_ttl / 3) => {
+                    // Dropping the guard on return aborts the task.
+                    match self.acquire_lease() {
+                        Ok(true) => {}
+                        Ok(false) => break RunOutcome::LeaseLost {
+                            error: "lease taken over by another instance".to_string(),
+                        },
+                        Err(e) => break RunOutcome::LeaseLost { error: format!("failed to renew lease: {}", e) },
+                    }
+                }
+            }
+        };
+        match &outcome {
+            RunOutcome::Succeeded => {}
+            RunOutcome::Failed { error } => warn!("Scheduled job '{}' failed: {}", self.name, error),
+            RunOutcome::LeaseLost { error } => warn!("Scheduled job '{}' was aborted: {}", self.name, error),
+        }
+        JobRun { scheduled_for, started_at, finished_at: self.clock.now(), outcome, owner: self.owner }
+    }
+
+    /// Take or renew the job's lease; false while another instance holds it.
+    fn acquire_lease(&self) -> Result<bool, SchedulerError> {
+        let key = lease_key(&self.name);
+        let now = self.clock.now();
+        let current = get_value(&self.storage, &key)?;
+        if let Some(value) = &current {
+            let lease: Lease = serde_json::from_value(value.clone())?;
+            if lease.owner != self.owner && lease.expires_at > now {
+                return Ok(false);
+            }
+        }
+        let ttl = chrono::Duration::from_std(self.lease_ttl).unwrap_or(chrono::Duration::seconds(30));
+        let lease = Lease { owner: self.owner, expires_at: now + ttl };
+        Ok(self.storage.compare_and_swap(&key, current.as_ref(), Some(serde_json::to_value(&lease)?))?)
+    }
+}
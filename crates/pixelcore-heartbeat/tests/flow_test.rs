@@ -69,3 +69,20 @@ fn test_flow_state_machine_task_tracking() {
     // 验证任务被记录
     assert_eq!(fsm.metrics().tasks_completed, 5);
 }
+
+#[test]
+fn test_flow_metrics_in_flight() {
+    let mut metrics = FlowMetrics::new();
+
+    metrics.task_started();
+    metrics.task_started();
+    assert_eq!(metrics.in_flight, 2);
+
+    metrics.task_completed();
+    metrics.task_failed();
+    assert_eq!(metrics.in_flight, 0);
+
+    // 多余的完成事件不会让计数变成负数
+    metrics.task_completed();
+    assert_eq!(metrics.in_flight, 0);
+}
@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use pixelcore_heartbeat::{Heartbeat, HeartbeatConfig, Liveness, LivenessConfig, LivenessMonitor};
+use pixelcore_runtime::event::{Event, EventBus, EventKind};
+use uuid::Uuid;
+
+fn config() -> LivenessConfig {
+    LivenessConfig {
+        beat_interval: Duration::from_millis(20),
+        suspect_after: 1,
+        dead_after: 3,
+        hang_timeout: Some(Duration::from_millis(80)),
+    }
+}
+
+#[tokio::test]
+async fn test_missed_beats_mark_agent_dead() {
+    let event_bus = EventBus::new();
+    let monitor = Arc::new(LivenessMonitor::new(event_bus.clone(), config()));
+    let agent_id = Uuid::new_v4();
+    monitor.register_agent(agent_id).await;
+    monitor.run().await;
+    let mut events = event_bus.subscribe();
+
+    // 探测成功时持续心跳
+    let healthy = Arc::new(AtomicBool::new(true));
+    let heartbeat = Heartbeat::new(
+        HeartbeatConfig { interval: Duration::from_millis(10), source: "probe".to_string() },
+        event_bus.clone(),
+    );
+    let h = healthy.clone();
+    tokio::spawn(async move {
+        heartbeat.run_agent(agent_id, move || {
+            let h = h.clone();
+            async move { h.load(Ordering::SeqCst) }
+        }).await
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(monitor.state(&agent_id).await, Some(Liveness::Alive));
+
+    // 子进程退出后探测失败
+    healthy.store(false, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    assert_eq!(monitor.state(&agent_id).await, Some(Liveness::Dead));
+
+    let mut transitions = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if event.kind == EventKind::Custom("liveness_changed".to_string()) {
+            transitions.push((event.payload["new_state"].clone(), event.payload["cause"].clone()));
+        }
+    }
+    assert_eq!(
+        transitions,
+        vec![
+            (serde_json::json!("suspect"), serde_json::Value::Null),
+            (serde_json::json!("dead"), serde_json::json!("missed_beats")),
+        ]
+    );
+
+    monitor.reset(&agent_id).await;
+    assert_eq!(monitor.state(&agent_id).await, Some(Liveness::Alive));
+}
+
+#[tokio::test]
+async fn test_hung_task_marks_agent_dead() {
+    let event_bus = EventBus::new();
+    let monitor = Arc::new(LivenessMonitor::new(event_bus.clone(), config()));
+    let agent_id = Uuid::new_v4();
+    monitor.register_agent(agent_id).await;
+    monitor.run().await;
+
+    let task = |kind| Event::new(kind, "coordinator", serde_json::json!({ "agent_id": agent_id.to_string() }));
+    event_bus.publish(task(EventKind::TaskStarted)).unwrap();
+    event_bus.publish(task(EventKind::TaskCompleted)).unwrap();
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    // 没有心跳探测的 Agent 不会因漏跳被判死
+    assert_eq!(monitor.state(&agent_id).await, Some(Liveness::Alive));
+
+    event_bus.publish(task(EventKind::TaskStarted)).unwrap();
+    tokio::time::sleep(Duration::from_millis(140)).await;
+    assert_eq!(monitor.state(&agent_id).await, Some(Liveness::Dead));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_zero_beat_interval_still_checks() {
+    let event_bus = EventBus::new();
+    let config = LivenessConfig { beat_interval: Duration::ZERO, hang_timeout: None, ..config() };
+    let monitor = Arc::new(LivenessMonitor::new(event_bus, config));
+    let agent_id = Uuid::new_v4();
+    monitor.register_agent(agent_id).await;
+    monitor.run().await;
+    monitor.beat(&agent_id).await;
+
+    // 间隔按 1 毫秒处理，检查任务不会因为零间隔而 panic
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(monitor.state(&agent_id).await, Some(Liveness::Dead));
+}
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{TimeZone, Utc};
+use pixelcore_heartbeat::{JobOptions, JobState, MisfirePolicy, RunOutcome, Schedule, Scheduler, SchedulerError};
+use pixelcore_storage::Storage;
+
+#[test]
+fn test_cron_schedule_in_time_zone() {
+    // 上海 9 点 = UTC 1 点
+    let schedule = Schedule::cron_in("0 9 * * *", "Asia/Shanghai").unwrap();
+    let after = Utc.with_ymd_and_hms(2025, 3, 1, 2, 0, 0).unwrap();
+    assert_eq!(schedule.next_after(after), Some(Utc.with_ymd_and_hms(2025, 3, 2, 1, 0, 0).unwrap()));
+
+    // 带秒字段的六段式
+    let schedule = Schedule::cron("30 0 12 * * *").unwrap();
+    assert_eq!(schedule.next_after(after), Some(Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 30).unwrap()));
+
+    assert!(matches!(Schedule::cron("not a cron"), Err(SchedulerError::InvalidCron { .. })));
+    assert!(matches!(Schedule::cron("*/0 * * * *"), Err(SchedulerError::InvalidCron { .. })));
+    assert!(matches!(Schedule::cron_in("0 9 * * *", "Mars/Olympus"), Err(SchedulerError::InvalidTimezone(_))));
+}
+
+#[test]
+fn test_zero_interval_is_rejected() {
+    assert!(matches!(Schedule::every(Duration::ZERO), Err(SchedulerError::ZeroInterval)));
+
+    let stored = serde_json::json!({"type": "interval", "every": {"secs": 0, "nanos": 0}});
+    assert!(serde_json::from_value::<Schedule>(stored).is_err());
+    let stored = serde_json::json!({"type": "cron", "expression": "*/0 * * * *", "timezone": "UTC"});
+    assert!(serde_json::from_value::<Schedule>(stored).is_err());
+
+    let scheduler = Scheduler::new();
+    let built = Schedule::Interval { every: Duration::ZERO };
+    let result = scheduler.register_job("spin", built, JobOptions::default(), || async { Ok::<(), String>(()) });
+    assert!(matches!(result, Err(SchedulerError::ZeroInterval)));
+    assert!(scheduler.job_names().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "`interval` must be non-zero")]
+fn test_register_panics_on_zero_interval() {
+    Scheduler::new().register("spin", Duration::ZERO, || {});
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_interval_job_records_history_and_pauses() {
+    let scheduler = Scheduler::new().with_tokio_clock();
+    let count = Arc::new(AtomicUsize::new(0));
+    let c = count.clone();
+    let schedule = Schedule::every(Duration::from_millis(20)).unwrap();
+    scheduler
+        .register_job("tick", schedule, JobOptions::default(), move || {
+            let n = c.fetch_add(1, Ordering::SeqCst);
+            async move { if n == 0 { Err("first run fails") } else { Ok(()) } }
+        })
+        .unwrap();
+    scheduler.spawn_all();
+
+    // Runs at 0, 20, ..., 100 ms
+    tokio::time::sleep(Duration::from_millis(110)).await;
+    let history = scheduler.history("tick").unwrap();
+    assert_eq!(history.len(), 6);
+    assert_eq!(history[0].outcome, RunOutcome::Failed { error: "first run fails".to_string() });
+    assert_eq!(history[1].outcome, RunOutcome::Succeeded);
+
+    scheduler.pause("tick").unwrap();
+    let paused_at = count.load(Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(count.load(Ordering::SeqCst), paused_at);
+    assert!(scheduler.job("tick").unwrap().unwrap().paused);
+
+    scheduler.resume("tick").unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(count.load(Ordering::SeqCst) > paused_at);
+
+    assert!(scheduler.unregister("tick").unwrap());
+    assert!(scheduler.job("tick").unwrap().is_none());
+    assert!(matches!(scheduler.pause("tick"), Err(SchedulerError::JobNotFound(_))));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_runs_never_overlap() {
+    let scheduler = Scheduler::new().with_tokio_clock();
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+    let (r, m) = (running.clone(), max_running.clone());
+    // 每次执行耗时超过间隔
+    scheduler.register_async("slow", Duration::from_millis(10), move || {
+        let (r, m) = (r.clone(), m.clone());
+        async move {
+            let now = r.fetch_add(1, Ordering::SeqCst) + 1;
+            m.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            r.fetch_sub(1, Ordering::SeqCst);
+        }
+    });
+    scheduler.spawn_all();
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(max_running.load(Ordering::SeqCst), 1);
+    assert!(scheduler.job("slow").unwrap().unwrap().run_count >= 2);
+}
+
+/// 预置一个一小时前就该执行、每 10 分钟一次的任务，模拟停机期间错过的触发
+async fn runs_after_downtime(policy: MisfirePolicy) -> (usize, JobState) {
+    let storage = Storage::new();
+    let schedule = Schedule::every(Duration::from_secs(600)).unwrap();
+    let state = JobState {
+        name: "report".to_string(),
+        schedule: schedule.clone(),
+        next_run: Some(Utc::now() - chrono::Duration::minutes(60) - chrono::Duration::seconds(5)),
+        paused: false,
+        run_count: 0,
+        history: VecDeque::new(),
+    };
+    storage.set("scheduler_job:report", serde_json::to_value(&state).unwrap()).unwrap();
+
+    let scheduler = Scheduler::new().with_storage(storage).with_tokio_clock();
+    let count = Arc::new(AtomicUsize::new(0));
+    let c = count.clone();
+    scheduler.register_job("report", schedule, JobOptions::default().with_misfire(policy), move || {
+        c.fetch_add(1, Ordering::SeqCst);
+        async { Ok::<(), String>(()) }
+    }).unwrap();
+    scheduler.spawn_all();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    (count.load(Ordering::SeqCst), scheduler.job("report").unwrap().unwrap())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_misfire_policies() {
+    let (runs, state) = runs_after_downtime(MisfirePolicy::Skip).await;
+    assert_eq!(runs, 0);
+    assert!(state.next_run.unwrap() > Utc::now());
+
+    let (runs, state) = runs_after_downtime(MisfirePolicy::FireOnce).await;
+    assert_eq!(runs, 1);
+    assert_eq!(state.history.len(), 1);
+
+    let (runs, state) = runs_after_downtime(MisfirePolicy::FireAll).await;
+    assert_eq!(runs, 7);
+    let gaps: Vec<i64> = state
+        .history
+        .iter()
+        .zip(state.history.iter().skip(1))
+        .map(|(a, b)| (b.scheduled_for - a.scheduled_for).num_minutes())
+        .collect();
+    assert_eq!(gaps, vec![10; 6]);
+    assert!(state.next_run.unwrap() > Utc::now());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_lease_keeps_job_on_one_instance() {
+    let storage = Storage::new();
+    let count = Arc::new(AtomicUsize::new(0));
+    let schedulers: Vec<Scheduler> = (0..3)
+        .map(|_| {
+            let scheduler = Scheduler::new()
+                .with_storage(storage.clone())
+                .with_lease_ttl(Duration::from_secs(5))
+                .with_tokio_clock();
+            let c = count.clone();
+            scheduler
+                .register("shared", Duration::from_millis(20), move || {
+                    c.fetch_add(1, Ordering::SeqCst);
+                });
+            scheduler
+        })
+        .collect();
+    for scheduler in &schedulers {
+        scheduler.spawn_all();
+    }
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let history = schedulers[0].history("shared").unwrap();
+    assert!(history.len() >= 3);
+    assert!(history.iter().all(|run| run.owner == history[0].owner));
+    // 同一个触发时间不会被多个实例重复执行（允许一次尚未记录的执行）
+    let calls = count.load(Ordering::SeqCst) as u64;
+    let recorded = schedulers[0].job("shared").unwrap().unwrap().run_count;
+    assert!(recorded <= calls && calls <= recorded + 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_run_is_aborted_when_lease_is_taken_over() {
+    let storage = Storage::new();
+    let scheduler = Scheduler::new()
+        .with_storage(storage.clone())
+        .with_lease_ttl(Duration::from_secs(3))
+        .with_tokio_clock();
+    let finished = Arc::new(AtomicUsize::new(0));
+    let f = finished.clone();
+    scheduler
+        .register_async("long", Duration::from_secs(3600), move || {
+            let f = f.clone();
+            async move {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                f.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    scheduler.spawn_all();
+
+    // 执行到一半时租约被另一个实例接管
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let lease = serde_json::json!({
+        "owner": uuid::Uuid::new_v4(),
+        "expires_at": Utc::now() + chrono::Duration::hours(1),
+    });
+    storage.set("scheduler_lease:long", lease).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(15)).await;
+    assert_eq!(finished.load(Ordering::SeqCst), 0);
+    let history = scheduler.history("long").unwrap();
+    assert_eq!(history.len(), 1);
+    assert!(matches!(&history[0].outcome, RunOutcome::LeaseLost { error } if error.contains("another instance")));
+}
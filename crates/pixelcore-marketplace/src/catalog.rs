@@ -1,4 +1,4 @@
-use pixelcore_registry::{AgentListing, AgentRegistry, AgentFilter, AgentStatus};
+use pixelcore_registry::{AgentListing, AgentRegistry, AgentFilter, AgentSort, AgentStatus, PageRequest};
 use anyhow::Result;
 use uuid::Uuid;
 
@@ -51,41 +51,27 @@ impl ServiceCatalog {
 
     /// 获取热门服务 (按交易量排序)
     pub fn get_popular(&self, limit: usize) -> Result<Vec<AgentListing>> {
-        let mut agents = self.registry.list_published(0, 1000)?;
-
-        // 按交易量排序
-        agents.sort_by(|a, b| b.total_transactions.cmp(&a.total_transactions));
-
-        // 取前 N 个
-        agents.truncate(limit);
-
-        Ok(agents)
+        self.top(AgentSort::Transactions, limit)
     }
 
     /// 获取高评分服务 (按信誉排序)
     pub fn get_top_rated(&self, limit: usize) -> Result<Vec<AgentListing>> {
-        let mut agents = self.registry.list_published(0, 1000)?;
-
-        // 按信誉分数排序
-        agents.sort_by(|a, b| {
-            b.reputation_score.partial_cmp(&a.reputation_score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        // 取前 N 个
-        agents.truncate(limit);
-
-        Ok(agents)
+        self.top(AgentSort::Reputation, limit)
     }
 
     /// 获取新上架服务 (按创建时间排序)
     pub fn get_newest(&self, limit: usize) -> Result<Vec<AgentListing>> {
-        let mut agents = self.registry.list_published(0, 1000)?;
-
-        // 按创建时间排序 (已经是降序)
-        agents.truncate(limit);
+        self.top(AgentSort::Newest, limit)
+    }
 
-        Ok(agents)
+    /// 按指定排序取前 N 个已发布服务，排序在数据库中完成
+    fn top(&self, sort: AgentSort, limit: usize) -> Result<Vec<AgentListing>> {
+        let filter = AgentFilter {
+            status: Some(AgentStatus::Published),
+            ..Default::default()
+        };
+        let page = self.registry.search_page(&filter, &PageRequest::new(limit).sorted_by(sort))?;
+        Ok(page.items)
     }
 
     /// 获取服务详情
@@ -93,10 +79,10 @@ impl ServiceCatalog {
         self.registry.get(agent_id)
     }
 
-    /// 搜索服务
+    /// 搜索服务 (全文检索名称、描述和能力，按相关度排序)
     pub fn search(&self, query: &str) -> Result<Vec<AgentListing>> {
         let filter = AgentFilter {
-            name: Some(query.to_string()),
+            query: Some(query.to_string()),
             status: Some(AgentStatus::Published),
             ..Default::default()
         };
@@ -105,7 +91,10 @@ impl ServiceCatalog {
 
     /// 获取服务统计信息
     pub fn get_statistics(&self) -> Result<CatalogStatistics> {
-        let all_agents = self.registry.list_published(0, 10000)?;
+        let all_agents = self.registry.search(&AgentFilter {
+            status: Some(AgentStatus::Published),
+            ..Default::default()
+        })?;
 
         let total_services = all_agents.len();
         let total_transactions: u64 = all_agents.iter()
@@ -1,5 +1,6 @@
-use pixelcore_registry::{AgentListing, AgentRegistry};
+use pixelcore_registry::{AgentFilter, AgentListing, AgentRegistry, AgentStatus};
 use anyhow::Result;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
 /// 服务需求描述
@@ -31,9 +32,11 @@ impl ServiceDiscovery {
     }
 
     /// 发现满足需求的服务
+    ///
+    /// 已过停用日期的服务不会被返回, 已弃用的服务排在其他服务之后
     pub fn discover(&self, requirement: &ServiceRequirement) -> Result<Vec<AgentListing>> {
-        // 获取所有已发布的 Agent
-        let all_agents = self.registry.list_published(0, 10000)?;
+        // 获取所有可用的 Agent
+        let all_agents = self.available()?;
 
         // 过滤满足条件的 Agent
         let matched: Vec<AgentListing> = all_agents
@@ -44,6 +47,21 @@ impl ServiceDiscovery {
         Ok(matched)
     }
 
+    /// 已发布且未过停用日期的 Agent, 已弃用的排在最后
+    fn available(&self) -> Result<Vec<AgentListing>> {
+        let now = Utc::now();
+        let mut agents: Vec<AgentListing> = self.registry
+            .search(&AgentFilter {
+                status: Some(AgentStatus::Published),
+                ..Default::default()
+            })?
+            .into_iter()
+            .filter(|agent| !agent.is_sunset(now))
+            .collect();
+        agents.sort_by_key(|agent| agent.is_deprecated());
+        Ok(agents)
+    }
+
     /// 检查 Agent 是否满足需求
     fn matches_requirement(&self, agent: &AgentListing, req: &ServiceRequirement) -> bool {
         // 检查必需技能
@@ -126,7 +144,7 @@ impl ServiceDiscovery {
 
     /// 发现免费服务
     pub fn discover_free_services(&self) -> Result<Vec<AgentListing>> {
-        let all_agents = self.registry.list_published(0, 10000)?;
+        let all_agents = self.available()?;
 
         let free_agents: Vec<AgentListing> = all_agents
             .into_iter()
@@ -140,7 +158,7 @@ impl ServiceDiscovery {
 
     /// 发现高性能服务 (响应时间快)
     pub fn discover_fast_services(&self, max_response_ms: u64) -> Result<Vec<AgentListing>> {
-        let all_agents = self.registry.list_published(0, 10000)?;
+        let all_agents = self.available()?;
 
         let fast_agents: Vec<AgentListing> = all_agents
             .into_iter()
@@ -152,7 +170,7 @@ impl ServiceDiscovery {
 
     /// 发现高可用服务
     pub fn discover_reliable_services(&self, min_availability: f64) -> Result<Vec<AgentListing>> {
-        let all_agents = self.registry.list_published(0, 10000)?;
+        let all_agents = self.available()?;
 
         let reliable_agents: Vec<AgentListing> = all_agents
             .into_iter()
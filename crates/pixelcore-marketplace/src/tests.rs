@@ -1,6 +1,6 @@
 use super::*;
 use pixelcore_registry::{
-    AgentListing, AgentRegistry, Capability, PricingModel, ServiceLevel,
+    AgentListing, AgentRegistry, Capability, Deprecation, PricingModel, ServiceLevel,
 };
 use uuid::Uuid;
 use anyhow::Result;
@@ -132,6 +132,30 @@ fn test_service_discovery() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_discovery_respects_deprecation() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+
+    let old = registry.register(create_test_agent("Old Calculator", vec!["calculate"], 0.0, 4.0, 10))?;
+    let legacy = registry.register(create_test_agent("Legacy Calculator", vec!["calculate"], 0.0, 4.0, 10))?;
+    registry.register(create_test_agent("Calculator", vec!["calculate"], 0.0, 4.0, 10))?;
+    for id in [&old, &legacy] {
+        registry.publish_version(id, "1.0.0")?;
+    }
+
+    // 已弃用的排在最后, 已过停用日期的不再被发现
+    registry.deprecate(&old, "1.0.0", Deprecation::new("Use Calculator"))?;
+    let sunset = chrono::Utc::now() - chrono::Duration::minutes(1);
+    registry.deprecate(&legacy, "1.0.0", Deprecation::new("Retired").with_sunset(sunset))?;
+
+    let discovery = ServiceDiscovery::new(registry);
+    let names: Vec<String> = discovery.discover_by_skill("calculate")?.into_iter().map(|a| a.name).collect();
+    assert_eq!(names, vec!["Calculator", "Old Calculator"]);
+    assert_eq!(discovery.discover_free_services()?.len(), 2);
+
+    Ok(())
+}
+
 #[test]
 fn test_smart_matcher() -> Result<()> {
     let agent1 = create_test_agent("Calculator Pro", vec!["calculate", "convert_units"], 0.01, 4.5, 100);
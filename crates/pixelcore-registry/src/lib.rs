@@ -1,14 +1,16 @@
 //! Agent Registry - Agent 注册表系统
 //!
-//! 提供 Agent 的注册、发布、查询和管理功能
+//! 提供 Agent 的注册、发布、查询和管理功能, 以及语义化版本管理
 
 mod models;
 mod registry;
 mod storage;
+mod versions;
 
 pub use models::*;
 pub use registry::*;
 pub use storage::*;
+pub use versions::*;
 
 #[cfg(test)]
 mod tests;
@@ -82,6 +82,9 @@ pub struct AgentListing {
     pub created_at: DateTime<Utc>,
     /// 更新时间
     pub updated_at: DateTime<Utc>,
+    /// 当前版本的弃用说明
+    #[serde(default)]
+    pub deprecation: Option<Deprecation>,
 }
 
 impl AgentListing {
@@ -110,9 +113,20 @@ impl AgentListing {
             total_transactions: 0,
             created_at: now,
             updated_at: now,
+            deprecation: None,
         }
     }
 
+    /// 当前版本是否已弃用
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecation.is_some()
+    }
+
+    /// 当前版本是否已过停用日期
+    pub fn is_sunset(&self, now: DateTime<Utc>) -> bool {
+        self.deprecation.as_ref().is_some_and(|d| d.is_sunset(now))
+    }
+
     /// 发布 Agent
     pub fn publish(&mut self) {
         self.status = AgentStatus::Published;
@@ -132,6 +146,45 @@ impl AgentListing {
     }
 }
 
+/// 弃用说明
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deprecation {
+    /// 提示给调用方的说明
+    pub message: String,
+    /// 停用日期, 之后该版本不再被解析和发现
+    pub sunset_at: Option<DateTime<Utc>>,
+    /// 建议迁移到的版本
+    pub replacement: Option<String>,
+    /// 弃用时间
+    pub deprecated_at: DateTime<Utc>,
+}
+
+impl Deprecation {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            sunset_at: None,
+            replacement: None,
+            deprecated_at: Utc::now(),
+        }
+    }
+
+    pub fn with_sunset(mut self, sunset_at: DateTime<Utc>) -> Self {
+        self.sunset_at = Some(sunset_at);
+        self
+    }
+
+    pub fn with_replacement(mut self, version: impl Into<String>) -> Self {
+        self.replacement = Some(version.into());
+        self
+    }
+
+    /// 是否已过停用日期
+    pub fn is_sunset(&self, now: DateTime<Utc>) -> bool {
+        self.sunset_at.is_some_and(|at| at <= now)
+    }
+}
+
 /// Agent 搜索过滤器
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentFilter {
@@ -145,6 +198,63 @@ pub struct AgentFilter {
     pub skill_name: Option<String>,
     /// 最低信誉分数
     pub min_reputation: Option<f64>,
-    /// 最大价格 (按次计费)
+    /// 最大价格 (按次计费, 免费 Agent 视为 0; 按小时计费和订阅制的 Agent 无法比较, 设置后不会出现在结果中)
     pub max_price: Option<f64>,
+    /// 全文搜索 (名称、描述和能力描述, 按词前缀匹配)
+    pub query: Option<String>,
+}
+
+/// 搜索结果排序方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentSort {
+    /// 最新创建的在前
+    #[default]
+    Newest,
+    /// 信誉分数从高到低
+    Reputation,
+    /// 价格从低到高
+    Price,
+    /// 交易数从多到少
+    Transactions,
+    /// 全文搜索相关度 (需设置 `AgentFilter::query`, 否则按最新排序)
+    Relevance,
+}
+
+/// 分页请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageRequest {
+    pub sort: AgentSort,
+    /// 每页条数
+    pub limit: usize,
+    /// 上一页返回的 `Page::next_cursor`
+    pub cursor: Option<String>,
+}
+
+impl PageRequest {
+    pub fn new(limit: usize) -> Self {
+        Self { sort: AgentSort::default(), limit, cursor: None }
+    }
+
+    pub fn sorted_by(mut self, sort: AgentSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn after(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+}
+
+/// 一页搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page {
+    pub items: Vec<AgentListing>,
+    /// 下一页的游标, 没有更多结果时为 `None`
+    ///
+    /// 游标记录的是本页最后一条的排序键和 ID, 因此翻页期间插入或删除其他数据不会
+    /// 让已返回的结果错位。但排序键本身可能在翻页期间变化 (如评分、交易数、价格),
+    /// 变化的条目可能重复出现或被跳过; 需要一致快照时应按 `Newest` 等不变的键排序
+    pub next_cursor: Option<String>,
 }
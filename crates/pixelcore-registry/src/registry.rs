@@ -1,6 +1,9 @@
-use crate::models::{AgentListing, AgentFilter, AgentStatus};
-use crate::storage::RegistryStorage;
+use crate::models::{AgentListing, AgentFilter, AgentSort, AgentStatus, Deprecation, Page, PageRequest};
+use crate::storage::{RegistryStorage, SearchCursor};
+use crate::versions::{compare_capabilities, parse_version, AgentVersion, CompatibilityReport, VersionError};
 use anyhow::{Context, Result};
+use chrono::Utc;
+use semver::{Version, VersionReq};
 use std::path::Path;
 use uuid::Uuid;
 
@@ -61,6 +64,8 @@ impl AgentRegistry {
     }
 
     /// 更新 Agent 信息
+    ///
+    /// 只修改当前的 listing, 已发布的版本快照不受影响
     pub fn update(&self, listing: AgentListing) -> Result<()> {
         self.storage.save(&listing)
             .context("Failed to update agent")?;
@@ -82,67 +87,219 @@ impl AgentRegistry {
         self.storage.list(offset, limit)
     }
 
-    /// 搜索 Agent
+    /// 搜索 Agent (返回全部匹配结果; 有全文检索词时按相关度排序, 否则最新创建的在前)
     pub fn search(&self, filter: &AgentFilter) -> Result<Vec<AgentListing>> {
-        // 简单实现: 先获取所有,然后过滤
-        // TODO: 在数据库层面实现更高效的搜索
-        let all_listings = self.storage.list(0, 1000)?;
+        let sort = if filter.query.is_some() { AgentSort::Relevance } else { AgentSort::Newest };
+        let rows = self.storage.query(filter, sort, None, 0, None)?;
+        Ok(rows.into_iter().map(|(listing, _)| listing).collect())
+    }
 
-        let filtered: Vec<AgentListing> = all_listings
-            .into_iter()
-            .filter(|listing| {
-                // 按名称过滤
-                if let Some(ref name) = filter.name {
-                    if !listing.name.to_lowercase().contains(&name.to_lowercase()) {
-                        return false;
-                    }
-                }
-
-                // 按所有者过滤
-                if let Some(owner_id) = filter.owner_id {
-                    if listing.owner_id != owner_id {
-                        return false;
-                    }
-                }
-
-                // 按状态过滤
-                if let Some(status) = filter.status {
-                    if listing.status != status {
-                        return false;
-                    }
-                }
-
-                // 按技能名称过滤
-                if let Some(ref skill_name) = filter.skill_name {
-                    let has_skill = listing.capabilities.iter().any(|cap| {
-                        cap.skill_name.to_lowercase().contains(&skill_name.to_lowercase())
-                    });
-                    if !has_skill {
-                        return false;
-                    }
-                }
-
-                // 按最低信誉过滤
-                if let Some(min_reputation) = filter.min_reputation {
-                    if listing.reputation_score < min_reputation {
-                        return false;
-                    }
-                }
-
-                true
-            })
-            .collect();
-
-        Ok(filtered)
+    /// 分页搜索 Agent
+    ///
+    /// 把上一页的 `next_cursor` 放入 `PageRequest::cursor` 获取下一页, 游标只能
+    /// 用于创建它时的排序方式。
+    pub fn search_page(&self, filter: &AgentFilter, page: &PageRequest) -> Result<Page> {
+        let after = page.cursor.as_deref().map(SearchCursor::decode).transpose()?;
+        let mut rows = self.storage.query(filter, page.sort, after.as_ref(), 0, Some(page.limit.saturating_add(1)))?;
+
+        let has_more = rows.len() > page.limit;
+        rows.truncate(page.limit);
+        let next_cursor = if has_more {
+            rows.last().map(|(_, cursor)| cursor.encode())
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items: rows.into_iter().map(|(listing, _)| listing).collect(),
+            next_cursor,
+        })
     }
 
     /// 获取已发布的 Agent 列表
-    pub fn list_published(&self, _offset: usize, _limit: usize) -> Result<Vec<AgentListing>> {
+    pub fn list_published(&self, offset: usize, limit: usize) -> Result<Vec<AgentListing>> {
         let filter = AgentFilter {
             status: Some(AgentStatus::Published),
             ..Default::default()
         };
-        self.search(&filter)
+        let rows = self.storage.query(&filter, AgentSort::Newest, None, offset, Some(limit))?;
+        Ok(rows.into_iter().map(|(listing, _)| listing).collect())
+    }
+
+    /// 把当前 listing 发布为不可变的新版本
+    ///
+    /// 当前 listing 代表最新的版本线, 所以新版本必须高于所有已发布版本, 否则返回
+    /// `VersionError::NotLatest`; 给旧版本线发布补丁请用 `publish_backport`。
+    /// 与前一个版本比较能力 schema, 有破坏性变化但版本号没有相应递增时返回
+    /// `VersionError::BreakingChange`。发布后 listing 的版本号随之更新并清除弃用说明,
+    /// 上架状态保持不变 (暂停或下架的 Agent 不会因此重新上架)。
+    pub fn publish_version(&self, id: &Uuid, version: &str) -> Result<CompatibilityReport> {
+        let version = parse_version(version)?;
+        let listing = self.storage.get(id)?
+            .context("Agent not found")?;
+        let versions = self.storage.versions(id)?;
+        if let Some(latest) = versions.last().filter(|latest| latest.version > version) {
+            return Err(VersionError::NotLatest { version, latest: latest.version.clone() }.into());
+        }
+
+        let (report, listing) = self.insert_version(id, version, listing, &versions)?;
+        self.storage.save(&listing)?;
+        Ok(report)
+    }
+
+    /// 以已发布的 `base` 版本为内容, 给旧版本线发布补丁 (如 2.0.0 已发布时发布 1.0.1)
+    ///
+    /// 快照取自 `base`, 经 `update` 修改后与低于新版本的最高版本比较。当前 listing
+    /// 保持不变, 因此新版本必须低于某个已发布版本, 否则返回 `VersionError::NotBackport`。
+    pub fn publish_backport(
+        &self,
+        id: &Uuid,
+        base: &str,
+        version: &str,
+        update: impl FnOnce(&mut AgentListing),
+    ) -> Result<CompatibilityReport> {
+        let base = parse_version(base)?;
+        let version = parse_version(version)?;
+        let versions = self.storage.versions(id)?;
+        if versions.last().is_none_or(|latest| latest.version < version) {
+            return Err(VersionError::NotBackport(version).into());
+        }
+        let mut listing = versions.iter()
+            .find(|v| v.version == base)
+            .ok_or_else(|| VersionError::VersionNotFound(base.to_string()))?
+            .listing
+            .clone();
+        update(&mut listing);
+        listing.id = *id;
+
+        let (report, _) = self.insert_version(id, version, listing, &versions)?;
+        Ok(report)
+    }
+
+    /// 检查兼容性并保存 `listing` 为版本 `version` 的快照, 返回报告和快照内容
+    fn insert_version(
+        &self,
+        id: &Uuid,
+        version: Version,
+        mut listing: AgentListing,
+        versions: &[AgentVersion],
+    ) -> Result<(CompatibilityReport, AgentListing)> {
+        if versions.iter().any(|v| v.version == version) {
+            return Err(VersionError::AlreadyPublished(version).into());
+        }
+
+        let previous = versions.iter().rev().find(|v| v.version < version);
+        let report = CompatibilityReport {
+            from: previous.map(|v| v.version.clone()),
+            to: version.clone(),
+            changes: previous
+                .map(|v| compare_capabilities(&v.listing.capabilities, &listing.capabilities))
+                .unwrap_or_default(),
+        };
+        report.check_bump()?;
+
+        listing.version = version.to_string();
+        listing.deprecation = None;
+        listing.updated_at = Utc::now();
+        // 快照记录的是已发布的内容; listing 本身的上架状态由调用方决定
+        let snapshot = AgentVersion {
+            agent_id: *id,
+            version: version.clone(),
+            listing: AgentListing { status: AgentStatus::Published, ..listing.clone() },
+            published_at: listing.updated_at,
+            deprecation: None,
+        };
+        if !self.storage.insert_version(&snapshot)? {
+            return Err(VersionError::AlreadyPublished(version).into());
+        }
+        Ok((report, listing))
+    }
+
+    /// 获取 Agent 的所有已发布版本 (从低到高)
+    pub fn versions(&self, id: &Uuid) -> Result<Vec<AgentVersion>> {
+        self.storage.versions(id)
+    }
+
+    /// 获取指定版本
+    pub fn get_version(&self, id: &Uuid, version: &str) -> Result<Option<AgentVersion>> {
+        let version = parse_version(version)?;
+        Ok(self.storage.versions(id)?.into_iter().find(|v| v.version == version))
+    }
+
+    /// 按语义化版本范围 (如 `^1.2`、`>=1.0, <2.0`) 解析出最高的匹配版本
+    ///
+    /// 已过停用日期的版本不参与解析; 已弃用但未停用的版本仍可解析, 调用方可以
+    /// 通过 `AgentVersion::deprecation` 提示用户。
+    pub fn resolve(&self, id: &Uuid, requirement: &str) -> Result<AgentVersion> {
+        let req = VersionReq::parse(requirement)
+            .map_err(|e| VersionError::InvalidRequirement(requirement.to_string(), e))?;
+        let now = Utc::now();
+        self.storage
+            .versions(id)?
+            .into_iter()
+            .rev()
+            .find(|v| req.matches(&v.version) && !v.is_sunset(now))
+            .ok_or_else(|| VersionError::NoMatchingVersion(requirement.to_string()).into())
+    }
+
+    /// 把当前 listing 回滚到已发布的某个版本
+    ///
+    /// 恢复版本快照中的描述、能力、定价和 SLA, 保留状态、信誉和交易统计。
+    pub fn rollback(&self, id: &Uuid, version: &str) -> Result<()> {
+        let target = self.get_version(id, version)?
+            .ok_or_else(|| VersionError::VersionNotFound(version.to_string()))?;
+        let mut listing = self.storage.get(id)?
+            .context("Agent not found")?;
+
+        let snapshot = target.listing;
+        listing.name = snapshot.name;
+        listing.description = snapshot.description;
+        listing.version = snapshot.version;
+        listing.capabilities = snapshot.capabilities;
+        listing.pricing = snapshot.pricing;
+        listing.sla = snapshot.sla;
+        listing.deprecation = target.deprecation;
+        listing.updated_at = Utc::now();
+        self.storage.save(&listing)
+    }
+
+    /// 弃用某个版本; 如果是 listing 的当前版本, listing 也会带上弃用说明
+    pub fn deprecate(&self, id: &Uuid, version: &str, deprecation: Deprecation) -> Result<()> {
+        self.set_deprecation(id, version, Some(deprecation))
+    }
+
+    /// 撤销弃用
+    pub fn undeprecate(&self, id: &Uuid, version: &str) -> Result<()> {
+        self.set_deprecation(id, version, None)
+    }
+
+    fn set_deprecation(&self, id: &Uuid, version: &str, deprecation: Option<Deprecation>) -> Result<()> {
+        let version = parse_version(version)?;
+        if !self.storage.set_version_deprecation(id, &version, deprecation.as_ref())? {
+            return Err(VersionError::VersionNotFound(version.to_string()).into());
+        }
+
+        let mut listing = self.storage.get(id)?
+            .context("Agent not found")?;
+        if parse_version(&listing.version).is_ok_and(|current| current == version) {
+            listing.deprecation = deprecation;
+            listing.updated_at = Utc::now();
+            self.storage.save(&listing)?;
+        }
+        Ok(())
+    }
+
+    /// 比较两个已发布版本的能力 schema
+    pub fn compatibility(&self, id: &Uuid, from: &str, to: &str) -> Result<CompatibilityReport> {
+        let from = self.get_version(id, from)?
+            .ok_or_else(|| VersionError::VersionNotFound(from.to_string()))?;
+        let to = self.get_version(id, to)?
+            .ok_or_else(|| VersionError::VersionNotFound(to.to_string()))?;
+        Ok(CompatibilityReport {
+            changes: compare_capabilities(&from.listing.capabilities, &to.listing.capabilities),
+            from: Some(from.version),
+            to: to.version,
+        })
     }
 
     /// 统计 Agent 数量
@@ -1,10 +1,16 @@
-use crate::models::{AgentListing, AgentStatus};
-use anyhow::{Context, Result};
-use rusqlite::{params, Connection, OptionalExtension};
+use crate::models::{AgentFilter, AgentListing, AgentSort, AgentStatus, Deprecation, PricingModel};
+use crate::versions::AgentVersion;
+use anyhow::{bail, Context, Result};
+use rusqlite::types::Value;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+const LISTING_COLUMNS: &str = "l.id, l.name, l.description, l.version, l.owner_id, l.capabilities, l.pricing, l.sla,
+     l.status, l.reputation_score, l.total_transactions, l.created_at, l.updated_at, l.deprecation";
+
 /// Agent 注册表存储
 pub struct RegistryStorage {
     conn: Arc<Mutex<Connection>>,
@@ -15,6 +21,7 @@ impl RegistryStorage {
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
         let conn = Connection::open(db_path)
             .context("Failed to open database")?;
+        register_functions(&conn)?;
 
         let storage = Self {
             conn: Arc::new(Mutex::new(conn)),
@@ -28,6 +35,7 @@ impl RegistryStorage {
     pub fn in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()
             .context("Failed to create in-memory database")?;
+        register_functions(&conn)?;
 
         let storage = Self {
             conn: Arc::new(Mutex::new(conn)),
@@ -39,7 +47,7 @@ impl RegistryStorage {
 
     /// 初始化数据库 schema
     fn init_schema(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let mut conn = self.conn.lock().unwrap();
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS agent_listings (
@@ -60,34 +68,146 @@ impl RegistryStorage {
             [],
         ).context("Failed to create agent_listings table")?;
 
-        // 创建索引
+        // 旧版本的表没有价格列, 补齐后需要回填
+        let mut needs_rebuild = false;
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('agent_listings')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        if !columns.iter().any(|c| c == "price") {
+            conn.execute_batch(
+                "ALTER TABLE agent_listings ADD COLUMN price REAL NOT NULL DEFAULT 0.0;
+                 ALTER TABLE agent_listings ADD COLUMN pricing_type TEXT NOT NULL DEFAULT 'Free';",
+            ).context("Failed to add pricing columns")?;
+            needs_rebuild = true;
+        }
+        if !columns.iter().any(|c| c == "deprecation") {
+            conn.execute("ALTER TABLE agent_listings ADD COLUMN deprecation TEXT", [])
+                .context("Failed to add deprecation column")?;
+        }
+
+        // 已发布的不可变版本
         conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_owner_id ON agent_listings(owner_id)",
+            "CREATE TABLE IF NOT EXISTS agent_versions (
+                agent_id TEXT NOT NULL,
+                version TEXT NOT NULL,
+                listing TEXT NOT NULL,
+                published_at TEXT NOT NULL,
+                deprecation TEXT,
+                PRIMARY KEY (agent_id, version)
+            )",
             [],
+        ).context("Failed to create agent_versions table")?;
+
+        // 能力表 (按技能过滤) 和全文索引 (名称、描述、能力描述)
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS agent_capabilities (
+                agent_id TEXT NOT NULL,
+                skill_name TEXT NOT NULL,
+                description TEXT NOT NULL
+            );
+            CREATE VIRTUAL TABLE IF NOT EXISTS agent_listings_fts USING fts5(
+                id UNINDEXED, name, description, capabilities
+            );",
+        ).context("Failed to create search tables")?;
+
+        // 创建索引
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_owner_id ON agent_listings(owner_id);
+             CREATE INDEX IF NOT EXISTS idx_status ON agent_listings(status);
+             CREATE INDEX IF NOT EXISTS idx_status_created ON agent_listings(status, created_at, id);
+             CREATE INDEX IF NOT EXISTS idx_reputation ON agent_listings(reputation_score, id);
+             CREATE INDEX IF NOT EXISTS idx_transactions ON agent_listings(total_transactions, id);
+             CREATE INDEX IF NOT EXISTS idx_price ON agent_listings(price, id);
+             CREATE INDEX IF NOT EXISTS idx_created ON agent_listings(created_at, id);
+             CREATE INDEX IF NOT EXISTS idx_capabilities_agent ON agent_capabilities(agent_id);
+             CREATE INDEX IF NOT EXISTS idx_capabilities_skill ON agent_capabilities(skill_name COLLATE NOCASE);",
         )?;
 
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_status ON agent_listings(status)",
-            [],
+        let listings: i64 = conn.query_row("SELECT COUNT(*) FROM agent_listings", [], |row| row.get(0))?;
+        let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM agent_listings_fts", [], |row| row.get(0))?;
+        if needs_rebuild || listings != indexed {
+            let tx = conn.transaction()?;
+            Self::rebuild_index(&tx)?;
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// 根据 listing 表重建价格列、能力表和全文索引
+    fn rebuild_index(tx: &Transaction) -> Result<()> {
+        tx.execute_batch("DELETE FROM agent_capabilities; DELETE FROM agent_listings_fts;")?;
+        let listings: Vec<AgentListing> = tx
+            .prepare(&format!("SELECT {LISTING_COLUMNS} FROM agent_listings l"))?
+            .query_map([], |row| Ok(Self::read_listing(row)))?
+            .map(|result| result?)
+            .collect::<Result<_>>()?;
+        for listing in &listings {
+            let (pricing_type, price) = Self::price_columns(&listing.pricing);
+            tx.execute(
+                "UPDATE agent_listings SET pricing_type = ?1, price = ?2 WHERE id = ?3",
+                params![pricing_type, price, listing.id.to_string()],
+            )?;
+            Self::index_listing(tx, listing)?;
+        }
+        Ok(())
+    }
+
+    /// 价格类型和用于过滤、排序的价格
+    fn price_columns(pricing: &PricingModel) -> (&'static str, f64) {
+        match pricing {
+            PricingModel::Free => ("Free", 0.0),
+            PricingModel::PerCall { price } => ("PerCall", *price),
+            PricingModel::PerHour { price } => ("PerHour", *price),
+            PricingModel::Subscription { monthly_price } => ("Subscription", *monthly_price),
+        }
+    }
+
+    fn index_listing(tx: &Transaction, listing: &AgentListing) -> Result<()> {
+        let id = listing.id.to_string();
+        for cap in &listing.capabilities {
+            tx.execute(
+                "INSERT INTO agent_capabilities (agent_id, skill_name, description) VALUES (?1, ?2, ?3)",
+                params![id, cap.skill_name, cap.description],
+            )?;
+        }
+        let capabilities = listing
+            .capabilities
+            .iter()
+            .map(|cap| format!("{} {}", cap.skill_name, cap.description))
+            .collect::<Vec<_>>()
+            .join("\n");
+        tx.execute(
+            "INSERT INTO agent_listings_fts (id, name, description, capabilities) VALUES (?1, ?2, ?3, ?4)",
+            params![id, listing.name, listing.description, capabilities],
         )?;
+        Ok(())
+    }
 
+    fn unindex_listing(tx: &Transaction, id: &str) -> Result<()> {
+        tx.execute("DELETE FROM agent_capabilities WHERE agent_id = ?1", params![id])?;
+        tx.execute("DELETE FROM agent_listings_fts WHERE id = ?1", params![id])?;
         Ok(())
     }
 
     /// 保存 Agent 列表
     pub fn save(&self, listing: &AgentListing) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let mut conn = self.conn.lock().unwrap();
 
         let capabilities_json = serde_json::to_string(&listing.capabilities)?;
         let pricing_json = serde_json::to_string(&listing.pricing)?;
         let sla_json = serde_json::to_string(&listing.sla)?;
         let status_str = format!("{:?}", listing.status);
+        let (pricing_type, price) = Self::price_columns(&listing.pricing);
+        let deprecation_json = listing.deprecation.as_ref().map(serde_json::to_string).transpose()?;
 
-        conn.execute(
+        let tx = conn.transaction()?;
+        tx.execute(
             "INSERT OR REPLACE INTO agent_listings
             (id, name, description, version, owner_id, capabilities, pricing, sla,
-             status, reputation_score, total_transactions, created_at, updated_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+             status, reputation_score, total_transactions, created_at, updated_at, pricing_type, price, deprecation)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
             params![
                 listing.id.to_string(),
                 listing.name,
@@ -102,154 +222,262 @@ impl RegistryStorage {
                 listing.total_transactions as i64,
                 listing.created_at.to_rfc3339(),
                 listing.updated_at.to_rfc3339(),
+                pricing_type,
+                price,
+                deprecation_json,
             ],
         ).context("Failed to save agent listing")?;
+        Self::unindex_listing(&tx, &listing.id.to_string())?;
+        Self::index_listing(&tx, listing)?;
+        tx.commit()?;
 
         Ok(())
     }
 
+    fn read_listing(row: &Row) -> Result<AgentListing> {
+        let id: String = row.get(0)?;
+        let owner_id: String = row.get(4)?;
+        let capabilities_json: String = row.get(5)?;
+        let pricing_json: String = row.get(6)?;
+        let sla_json: String = row.get(7)?;
+        let status_str: String = row.get(8)?;
+        let total_transactions: i64 = row.get(10)?;
+        let created_at: String = row.get(11)?;
+        let updated_at: String = row.get(12)?;
+        let deprecation_json: Option<String> = row.get(13)?;
+
+        Ok(AgentListing {
+            id: Uuid::parse_str(&id)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            version: row.get(3)?,
+            owner_id: Uuid::parse_str(&owner_id)?,
+            capabilities: serde_json::from_str(&capabilities_json)?,
+            pricing: serde_json::from_str(&pricing_json)?,
+            sla: serde_json::from_str(&sla_json)?,
+            status: match status_str.as_str() {
+                "Draft" => AgentStatus::Draft,
+                "Published" => AgentStatus::Published,
+                "Paused" => AgentStatus::Paused,
+                "Archived" => AgentStatus::Archived,
+                _ => AgentStatus::Draft,
+            },
+            reputation_score: row.get(9)?,
+            total_transactions: total_transactions as u64,
+            created_at: chrono::DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&chrono::Utc),
+            updated_at: chrono::DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&chrono::Utc),
+            deprecation: deprecation_json.as_deref().map(serde_json::from_str).transpose()?,
+        })
+    }
+
     /// 根据 ID 获取 Agent
     pub fn get(&self, id: &Uuid) -> Result<Option<AgentListing>> {
         let conn = self.conn.lock().unwrap();
 
         let result = conn.query_row(
-            "SELECT id, name, description, version, owner_id, capabilities, pricing, sla,
-                    status, reputation_score, total_transactions, created_at, updated_at
-             FROM agent_listings WHERE id = ?1",
+            &format!("SELECT {LISTING_COLUMNS} FROM agent_listings l WHERE l.id = ?1"),
             params![id.to_string()],
-            |row| {
-                let id: String = row.get(0)?;
-                let name: String = row.get(1)?;
-                let description: String = row.get(2)?;
-                let version: String = row.get(3)?;
-                let owner_id: String = row.get(4)?;
-                let capabilities_json: String = row.get(5)?;
-                let pricing_json: String = row.get(6)?;
-                let sla_json: String = row.get(7)?;
-                let status_str: String = row.get(8)?;
-                let reputation_score: f64 = row.get(9)?;
-                let total_transactions: i64 = row.get(10)?;
-                let created_at: String = row.get(11)?;
-                let updated_at: String = row.get(12)?;
-
-                Ok((
-                    id, name, description, version, owner_id,
-                    capabilities_json, pricing_json, sla_json, status_str,
-                    reputation_score, total_transactions, created_at, updated_at
-                ))
-            },
+            |row| Ok(Self::read_listing(row)),
         ).optional()?;
 
-        if let Some((
-            id, name, description, version, owner_id,
-            capabilities_json, pricing_json, sla_json, status_str,
-            reputation_score, total_transactions, created_at, updated_at
-        )) = result {
-            let listing = AgentListing {
-                id: Uuid::parse_str(&id)?,
-                name,
-                description,
-                version,
-                owner_id: Uuid::parse_str(&owner_id)?,
-                capabilities: serde_json::from_str(&capabilities_json)?,
-                pricing: serde_json::from_str(&pricing_json)?,
-                sla: serde_json::from_str(&sla_json)?,
-                status: match status_str.as_str() {
-                    "Draft" => AgentStatus::Draft,
-                    "Published" => AgentStatus::Published,
-                    "Paused" => AgentStatus::Paused,
-                    "Archived" => AgentStatus::Archived,
-                    _ => AgentStatus::Draft,
-                },
-                reputation_score,
-                total_transactions: total_transactions as u64,
-                created_at: chrono::DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&chrono::Utc),
-                updated_at: chrono::DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&chrono::Utc),
-            };
-
-            Ok(Some(listing))
-        } else {
-            Ok(None)
-        }
+        result.transpose()
     }
 
     /// 删除 Agent
     pub fn delete(&self, id: &Uuid) -> Result<bool> {
-        let conn = self.conn.lock().unwrap();
+        let mut conn = self.conn.lock().unwrap();
 
-        let rows_affected = conn.execute(
+        let tx = conn.transaction()?;
+        let rows_affected = tx.execute(
             "DELETE FROM agent_listings WHERE id = ?1",
             params![id.to_string()],
         )?;
+        Self::unindex_listing(&tx, &id.to_string())?;
+        tx.execute("DELETE FROM agent_versions WHERE agent_id = ?1", params![id.to_string()])?;
+        tx.commit()?;
 
         Ok(rows_affected > 0)
     }
 
     /// 列出所有 Agent (带分页)
     pub fn list(&self, offset: usize, limit: usize) -> Result<Vec<AgentListing>> {
+        let rows = self.query(&AgentFilter::default(), AgentSort::Newest, None, offset, Some(limit))?;
+        Ok(rows.into_iter().map(|(listing, _)| listing).collect())
+    }
+
+    /// 按过滤条件查询
+    ///
+    /// 结果按 `sort` 排序, 相同排序键按 ID 排序以保证顺序稳定。`after` 是上一页
+    /// 最后一条的游标 (见 `SearchCursor`), 返回的每条结果都附带自己的游标。
+    pub fn query(
+        &self,
+        filter: &AgentFilter,
+        sort: AgentSort,
+        after: Option<&SearchCursor>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<(AgentListing, SearchCursor)>> {
+        let fts_query = filter.query.as_deref().and_then(fts_query);
+        let sort = match sort {
+            AgentSort::Relevance if fts_query.is_none() => AgentSort::Newest,
+            sort => sort,
+        };
+        let (key_expr, descending) = match sort {
+            AgentSort::Newest => ("l.created_at", true),
+            AgentSort::Reputation => ("l.reputation_score", true),
+            AgentSort::Price => ("l.price", false),
+            AgentSort::Transactions => ("l.total_transactions", true),
+            AgentSort::Relevance => ("bm25(agent_listings_fts)", false),
+        };
+
+        let mut sql = format!("SELECT {LISTING_COLUMNS}, {key_expr} AS sort_key FROM ");
+        let mut clauses = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+        match &fts_query {
+            Some(query) => {
+                sql.push_str("agent_listings_fts JOIN agent_listings l ON l.id = agent_listings_fts.id");
+                clauses.push("agent_listings_fts MATCH ?".to_string());
+                values.push(Value::Text(query.clone()));
+            }
+            None => sql.push_str("agent_listings l"),
+        }
+
+        if let Some(name) = &filter.name {
+            clauses.push("instr(unicode_lower(l.name), ?) > 0".to_string());
+            values.push(Value::Text(name.to_lowercase()));
+        }
+        if let Some(owner_id) = filter.owner_id {
+            clauses.push("l.owner_id = ?".to_string());
+            values.push(Value::Text(owner_id.to_string()));
+        }
+        if let Some(status) = filter.status {
+            clauses.push("l.status = ?".to_string());
+            values.push(Value::Text(format!("{:?}", status)));
+        }
+        if let Some(skill_name) = &filter.skill_name {
+            clauses.push(
+                "EXISTS (SELECT 1 FROM agent_capabilities c WHERE c.agent_id = l.id AND instr(unicode_lower(c.skill_name), ?) > 0)"
+                    .to_string(),
+            );
+            values.push(Value::Text(skill_name.to_lowercase()));
+        }
+        if let Some(min_reputation) = filter.min_reputation {
+            clauses.push("l.reputation_score >= ?".to_string());
+            values.push(Value::Real(min_reputation));
+        }
+        if let Some(max_price) = filter.max_price {
+            clauses.push("l.pricing_type IN ('Free', 'PerCall') AND l.price <= ?".to_string());
+            values.push(Value::Real(max_price));
+        }
+
+        // 游标之后: 排序键更靠后, 或排序键相同且 ID 更靠后
+        if let Some(cursor) = after {
+            if cursor.sort != sort {
+                bail!("Cursor was created for a different sort order");
+            }
+            let op = if descending { "<" } else { ">" };
+            clauses.push(format!("({key_expr} {op} ? OR ({key_expr} = ? AND l.id {op} ?))"));
+            values.push(cursor.key.clone());
+            values.push(cursor.key.clone());
+            values.push(Value::Text(cursor.id.to_string()));
+        }
+
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+        let direction = if descending { "DESC" } else { "ASC" };
+        sql.push_str(&format!(" ORDER BY sort_key {direction}, l.id {direction} LIMIT ? OFFSET ?"));
+        values.push(Value::Integer(limit.map(|l| l as i64).unwrap_or(-1)));
+        values.push(Value::Integer(offset as i64));
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(values), |row| {
+                let key: Value = row.get(14)?;
+                Ok(Self::read_listing(row).map(|listing| {
+                    let cursor = SearchCursor { sort, key, id: listing.id };
+                    (listing, cursor)
+                }))
+            })?
+            .map(|result| result?)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(rows)
+    }
+
+    /// 保存新版本, 版本已存在时返回 `false` (已发布的版本不可覆盖)
+    pub fn insert_version(&self, version: &AgentVersion) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+
+        let rows_affected = conn.execute(
+            "INSERT OR IGNORE INTO agent_versions (agent_id, version, listing, published_at, deprecation)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                version.agent_id.to_string(),
+                version.version.to_string(),
+                serde_json::to_string(&version.listing)?,
+                version.published_at.to_rfc3339(),
+                version.deprecation.as_ref().map(serde_json::to_string).transpose()?,
+            ],
+        ).context("Failed to save agent version")?;
+
+        Ok(rows_affected > 0)
+    }
+
+    /// 获取 Agent 的所有已发布版本 (按语义化版本从低到高)
+    pub fn versions(&self, agent_id: &Uuid) -> Result<Vec<AgentVersion>> {
         let conn = self.conn.lock().unwrap();
 
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, version, owner_id, capabilities, pricing, sla,
-                    status, reputation_score, total_transactions, created_at, updated_at
-             FROM agent_listings
-             ORDER BY created_at DESC
-             LIMIT ?1 OFFSET ?2"
+            "SELECT agent_id, version, listing, published_at, deprecation FROM agent_versions WHERE agent_id = ?1",
         )?;
-
-        let listings = stmt.query_map(params![limit as i64, offset as i64], |row| {
-            let id: String = row.get(0)?;
-            let name: String = row.get(1)?;
-            let description: String = row.get(2)?;
-            let version: String = row.get(3)?;
-            let owner_id: String = row.get(4)?;
-            let capabilities_json: String = row.get(5)?;
-            let pricing_json: String = row.get(6)?;
-            let sla_json: String = row.get(7)?;
-            let status_str: String = row.get(8)?;
-            let reputation_score: f64 = row.get(9)?;
-            let total_transactions: i64 = row.get(10)?;
-            let created_at: String = row.get(11)?;
-            let updated_at: String = row.get(12)?;
-
-            Ok((
-                id, name, description, version, owner_id,
-                capabilities_json, pricing_json, sla_json, status_str,
-                reputation_score, total_transactions, created_at, updated_at
-            ))
-        })?
-        .filter_map(|result| {
-            result.ok().and_then(|(
-                id, name, description, version, owner_id,
-                capabilities_json, pricing_json, sla_json, status_str,
-                reputation_score, total_transactions, created_at, updated_at
-            )| {
-                Some(AgentListing {
-                    id: Uuid::parse_str(&id).ok()?,
-                    name,
-                    description,
-                    version,
-                    owner_id: Uuid::parse_str(&owner_id).ok()?,
-                    capabilities: serde_json::from_str(&capabilities_json).ok()?,
-                    pricing: serde_json::from_str(&pricing_json).ok()?,
-                    sla: serde_json::from_str(&sla_json).ok()?,
-                    status: match status_str.as_str() {
-                        "Draft" => AgentStatus::Draft,
-                        "Published" => AgentStatus::Published,
-                        "Paused" => AgentStatus::Paused,
-                        "Archived" => AgentStatus::Archived,
-                        _ => AgentStatus::Draft,
-                    },
-                    reputation_score,
-                    total_transactions: total_transactions as u64,
-                    created_at: chrono::DateTime::parse_from_rfc3339(&created_at).ok()?.with_timezone(&chrono::Utc),
-                    updated_at: chrono::DateTime::parse_from_rfc3339(&updated_at).ok()?.with_timezone(&chrono::Utc),
+        let mut versions = stmt
+            .query_map(params![agent_id.to_string()], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?
+            .map(|row| {
+                let (agent_id, version, listing, published_at, deprecation) = row?;
+                Ok(AgentVersion {
+                    agent_id: Uuid::parse_str(&agent_id)?,
+                    version: version.parse()?,
+                    listing: serde_json::from_str(&listing)?,
+                    published_at: chrono::DateTime::parse_from_rfc3339(&published_at)?.with_timezone(&chrono::Utc),
+                    deprecation: deprecation.as_deref().map(serde_json::from_str).transpose()?,
                 })
             })
-        })
-        .collect();
+            .collect::<Result<Vec<_>>>()?;
 
-        Ok(listings)
+        versions.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(versions)
+    }
+
+    /// 设置或清除某个版本的弃用说明, 版本不存在时返回 `false`
+    pub fn set_version_deprecation(
+        &self,
+        agent_id: &Uuid,
+        version: &semver::Version,
+        deprecation: Option<&Deprecation>,
+    ) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+
+        let rows_affected = conn.execute(
+            "UPDATE agent_versions SET deprecation = ?1 WHERE agent_id = ?2 AND version = ?3",
+            params![
+                deprecation.map(serde_json::to_string).transpose()?,
+                agent_id.to_string(),
+                version.to_string(),
+            ],
+        )?;
+
+        Ok(rows_affected > 0)
     }
 
     /// 统计 Agent 数量
@@ -265,3 +493,62 @@ impl RegistryStorage {
         Ok(count as usize)
     }
 }
+
+/// 分页游标: 排序方式、最后一条的排序键和 ID
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchCursor {
+    sort: AgentSort,
+    key: Value,
+    id: Uuid,
+}
+
+impl SearchCursor {
+    /// 编码为 `Page::next_cursor` 中的不透明字符串
+    pub fn encode(&self) -> String {
+        let key = match &self.key {
+            Value::Integer(i) => format!("i{i}"),
+            Value::Real(f) => format!("r{f}"),
+            Value::Text(s) => format!("t{s}"),
+            _ => "n".to_string(),
+        };
+        let sort = serde_json::to_value(self.sort).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+        format!("{sort}:{}:{key}", self.id)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        let mut parts = cursor.splitn(3, ':');
+        let (Some(sort), Some(id), Some(key)) = (parts.next(), parts.next(), parts.next()) else {
+            bail!("Invalid cursor: {cursor}");
+        };
+        let sort: AgentSort = serde_json::from_value(serde_json::Value::String(sort.to_string()))
+            .with_context(|| format!("Invalid cursor: {cursor}"))?;
+        let key = match key.split_at(key.len().min(1)) {
+            ("i", i) => Value::Integer(i.parse().with_context(|| format!("Invalid cursor: {cursor}"))?),
+            ("r", f) => Value::Real(f.parse().with_context(|| format!("Invalid cursor: {cursor}"))?),
+            ("t", s) => Value::Text(s.to_string()),
+            _ => bail!("Invalid cursor: {cursor}"),
+        };
+        Ok(Self { sort, key, id: Uuid::parse_str(id).with_context(|| format!("Invalid cursor: {cursor}"))? })
+    }
+}
+
+/// 注册自定义 SQL 函数: `unicode_lower(text)` 按 Unicode 规则转小写
+/// (SQLite 自带的 `lower` 和 `LIKE` 只处理 ASCII)
+fn register_functions(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "unicode_lower",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(ctx.get::<String>(0)?.to_lowercase()),
+    )
+    .context("Failed to register SQL functions")
+}
+
+/// 把用户输入转换为 FTS5 查询: 每个词加引号并按前缀匹配, 词之间为 AND
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
@@ -180,3 +180,383 @@ fn test_agent_search() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_name_and_skill_filters_ignore_unicode_case() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    registry.register(listing("Éclair", "Übersetzen", "Translate", PricingModel::Free, 1.0))?;
+    registry.register(listing("100% Agent", "under_score", "Literal wildcards", PricingModel::Free, 1.0))?;
+
+    let results = registry.search(&AgentFilter { name: Some("écl".to_string()), ..Default::default() })?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "Éclair");
+    let results = registry.search(&AgentFilter { skill_name: Some("übers".to_string()), ..Default::default() })?;
+    assert_eq!(results.len(), 1);
+
+    // `%` 和 `_` 按字面匹配
+    let results = registry.search(&AgentFilter { name: Some("0% a".to_string()), ..Default::default() })?;
+    assert_eq!(results.len(), 1);
+    assert!(registry.search(&AgentFilter { name: Some("%".to_string()), skill_name: Some("u_der".to_string()), ..Default::default() })?.is_empty());
+    Ok(())
+}
+
+fn listing(name: &str, skill: &str, skill_description: &str, pricing: PricingModel, reputation: f64) -> AgentListing {
+    let mut listing = AgentListing::new(
+        name.to_string(),
+        format!("{name} agent"),
+        "1.0.0".to_string(),
+        Uuid::new_v4(),
+        vec![Capability {
+            skill_name: skill.to_string(),
+            description: skill_description.to_string(),
+            input_schema: serde_json::json!({}),
+            output_schema: serde_json::json!({}),
+        }],
+        pricing,
+        ServiceLevel {
+            response_time_ms: 100,
+            availability_percent: 99.0,
+            max_concurrent_requests: 1,
+        },
+    );
+    listing.reputation_score = reputation;
+    listing.status = AgentStatus::Published;
+    listing
+}
+
+#[test]
+fn test_search_has_no_result_cap() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    for i in 0..1100 {
+        registry.register(listing(&format!("Agent {i}"), "echo", "Echo", PricingModel::Free, 0.0))?;
+    }
+
+    let filter = AgentFilter { status: Some(AgentStatus::Published), ..Default::default() };
+    assert_eq!(registry.search(&filter)?.len(), 1100);
+    assert_eq!(registry.list_published(1090, 50)?.len(), 10);
+    assert_eq!(registry.list_published(0, 5)?.len(), 5);
+    Ok(())
+}
+
+#[test]
+fn test_cursor_pagination_is_stable() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    // 大量相同信誉分数, 依赖 ID 作为次序
+    for i in 0..25 {
+        registry.register(listing(&format!("Agent {i}"), "echo", "Echo", PricingModel::Free, (i % 3) as f64))?;
+    }
+
+    let filter = AgentFilter::default();
+    let mut seen = Vec::new();
+    let mut request = PageRequest::new(4).sorted_by(AgentSort::Reputation);
+    loop {
+        let page = registry.search_page(&filter, &request)?;
+        assert!(page.items.len() <= 4);
+        seen.extend(page.items);
+        // 翻页期间插入的数据不影响后续页
+        if seen.len() == 8 {
+            registry.register(listing("Late", "echo", "Echo", PricingModel::Free, 5.0))?;
+        }
+        match page.next_cursor {
+            Some(cursor) => request = request.after(cursor),
+            None => break,
+        }
+    }
+
+    assert_eq!(seen.len(), 25);
+    let ids: std::collections::HashSet<_> = seen.iter().map(|l| l.id).collect();
+    assert_eq!(ids.len(), 25);
+    assert!(seen.windows(2).all(|w| w[0].reputation_score >= w[1].reputation_score));
+
+    let cursor = registry.search_page(&filter, &PageRequest::new(1).sorted_by(AgentSort::Price))?.next_cursor.unwrap();
+    let wrong_sort = PageRequest::new(1).sorted_by(AgentSort::Newest).after(cursor);
+    assert!(registry.search_page(&filter, &wrong_sort).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_full_text_search_and_price_filter() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    registry.register(listing("Polyglot", "translate", "Translate documents between languages", PricingModel::PerCall { price: 0.05 }, 4.0))?;
+    registry.register(listing("Linguist", "grammar", "Fix grammar in several languages", PricingModel::Free, 3.0))?;
+    registry.register(listing("Accountant", "tax", "Prepare tax returns", PricingModel::Subscription { monthly_price: 10.0 }, 5.0))?;
+
+    // 匹配能力描述, 按词前缀匹配
+    let filter = AgentFilter { query: Some("languag".to_string()), ..Default::default() };
+    let page = registry.search_page(&filter, &PageRequest::new(10).sorted_by(AgentSort::Relevance))?;
+    let mut names: Vec<_> = page.items.iter().map(|l| l.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["Linguist", "Polyglot"]);
+
+    let first = registry.search_page(&filter, &PageRequest::new(1).sorted_by(AgentSort::Relevance))?;
+    let request = PageRequest::new(1).sorted_by(AgentSort::Relevance).after(first.next_cursor.unwrap());
+    let second = registry.search_page(&filter, &request)?;
+    assert_eq!(second.items.len(), 1);
+    assert_ne!(first.items[0].id, second.items[0].id);
+    assert!(second.next_cursor.is_none());
+
+    let filter = AgentFilter { query: Some("translate languages".to_string()), ..Default::default() };
+    let results = registry.search(&filter)?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "Polyglot");
+
+    // 特殊字符不会破坏查询
+    let filter = AgentFilter { query: Some("\"tax* OR".to_string()), ..Default::default() };
+    assert!(registry.search(&filter)?.is_empty());
+
+    let filter = AgentFilter { max_price: Some(0.1), ..Default::default() };
+    let page = registry.search_page(&filter, &PageRequest::new(10).sorted_by(AgentSort::Price))?;
+    let names: Vec<_> = page.items.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["Linguist", "Polyglot"]);
+
+    // 更新后全文索引同步
+    let mut accountant = registry.search(&AgentFilter { name: Some("account".to_string()), ..Default::default() })?.remove(0);
+    accountant.description = "Handles bookkeeping in many languages".to_string();
+    registry.update(accountant.clone())?;
+    let filter = AgentFilter { query: Some("bookkeeping".to_string()), ..Default::default() };
+    assert_eq!(registry.search(&filter)?.len(), 1);
+    registry.delete(&accountant.id)?;
+    assert!(registry.search(&filter)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_existing_database_is_migrated() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("registry.db");
+    let old = listing("Legacy", "summarize", "Summarize long reports", PricingModel::PerCall { price: 0.2 }, 1.0);
+    {
+        // 旧版本的表结构
+        let conn = rusqlite::Connection::open(&path)?;
+        conn.execute(
+            "CREATE TABLE agent_listings (
+                id TEXT PRIMARY KEY, name TEXT NOT NULL, description TEXT NOT NULL, version TEXT NOT NULL,
+                owner_id TEXT NOT NULL, capabilities TEXT NOT NULL, pricing TEXT NOT NULL, sla TEXT NOT NULL,
+                status TEXT NOT NULL, reputation_score REAL NOT NULL DEFAULT 0.0,
+                total_transactions INTEGER NOT NULL DEFAULT 0, created_at TEXT NOT NULL, updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "INSERT INTO agent_listings VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'Published', 1.0, 0, ?9, ?9)",
+            rusqlite::params![
+                old.id.to_string(), old.name, old.description, old.version, old.owner_id.to_string(),
+                serde_json::to_string(&old.capabilities)?, serde_json::to_string(&old.pricing)?,
+                serde_json::to_string(&old.sla)?, old.created_at.to_rfc3339(),
+            ],
+        )?;
+    }
+
+    let registry = AgentRegistry::new(&path)?;
+    let filter = AgentFilter { query: Some("reports".to_string()), max_price: Some(0.5), ..Default::default() };
+    assert_eq!(registry.search(&filter)?.len(), 1);
+    let filter = AgentFilter { max_price: Some(0.1), ..Default::default() };
+    assert!(registry.search(&filter)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_corrupt_rows_fail_search() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("registry.db");
+    {
+        let registry = AgentRegistry::new(&path)?;
+        registry.register(listing("Good", "echo", "Echo", PricingModel::Free, 1.0))?;
+        registry.register(listing("Broken", "echo", "Echo", PricingModel::Free, 2.0))?;
+    }
+    rusqlite::Connection::open(&path)?.execute("UPDATE agent_listings SET sla = 'not json' WHERE name = 'Broken'", [])?;
+
+    // 解码失败的行不能被静默丢弃
+    let registry = AgentRegistry::new(&path)?;
+    assert!(registry.search(&AgentFilter::default()).is_err());
+    Ok(())
+}
+
+fn capability(input_schema: serde_json::Value, output_schema: serde_json::Value) -> Vec<Capability> {
+    vec![Capability {
+        skill_name: "translate".to_string(),
+        description: "Translate text".to_string(),
+        input_schema,
+        output_schema,
+    }]
+}
+
+#[test]
+fn test_published_versions_are_immutable_and_resolvable() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    let mut agent = listing("Translator", "translate", "Translate text", PricingModel::Free, 4.0);
+    agent.capabilities = capability(
+        serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}),
+        serde_json::json!({"type": "object", "properties": {"translation": {"type": "string"}}}),
+    );
+    let id = registry.register(agent.clone())?;
+    let report = registry.publish_version(&id, "1.0.0")?;
+    assert!(report.from.is_none());
+
+    // 新增可选输入字段和输出字段是兼容变化
+    agent.capabilities = capability(
+        serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}, "target": {"type": "string"}}, "required": ["text"]}),
+        serde_json::json!({"type": "object", "properties": {"translation": {"type": "string"}, "confidence": {"type": "number"}}}),
+    );
+    registry.update(agent)?;
+    let report = registry.publish_version(&id, "1.1.0")?;
+    assert_eq!(report.from, Some(semver::Version::new(1, 0, 0)));
+    assert!(!report.is_breaking());
+    assert_eq!(report.changes.len(), 2);
+
+    let err = registry.publish_version(&id, "1.1.0").unwrap_err();
+    assert!(matches!(err.downcast_ref::<VersionError>(), Some(VersionError::AlreadyPublished(_))));
+
+    assert_eq!(registry.get(&id)?.unwrap().version, "1.1.0");
+    assert_eq!(registry.resolve(&id, "^1.0")?.version.to_string(), "1.1.0");
+    assert_eq!(registry.resolve(&id, "~1.0")?.version.to_string(), "1.0.0");
+    let err = registry.resolve(&id, "^2").unwrap_err();
+    assert!(matches!(err.downcast_ref::<VersionError>(), Some(VersionError::NoMatchingVersion(_))));
+
+    // 快照不随 listing 修改
+    let v1 = registry.get_version(&id, "1.0.0")?.unwrap();
+    assert!(v1.listing.capabilities[0].input_schema["properties"].get("target").is_none());
+
+    registry.rollback(&id, "1.0.0")?;
+    let current = registry.get(&id)?.unwrap();
+    assert_eq!(current.version, "1.0.0");
+    assert_eq!(current.capabilities[0].input_schema, v1.listing.capabilities[0].input_schema);
+    assert_eq!(current.reputation_score, 4.0);
+    assert_eq!(registry.versions(&id)?.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_breaking_changes_require_major_bump() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    let mut agent = listing("Translator", "translate", "Translate text", PricingModel::Free, 4.0);
+    agent.capabilities = capability(
+        serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}),
+        serde_json::json!({"type": "object", "properties": {"translation": {"type": "string"}, "language": {"type": "string"}}}),
+    );
+    let id = registry.register(agent.clone())?;
+    registry.publish_version(&id, "1.0.0")?;
+
+    // 新的必填输入字段, 删除输出字段
+    agent.capabilities = capability(
+        serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}, "target": {"type": "string"}}, "required": ["text", "target"]}),
+        serde_json::json!({"type": "object", "properties": {"translation": {"type": "string"}}}),
+    );
+    registry.update(agent)?;
+
+    let err = registry.publish_version(&id, "1.1.0").unwrap_err();
+    match err.downcast_ref::<VersionError>() {
+        Some(VersionError::BreakingChange { required, changes, .. }) => {
+            assert_eq!(*required, "major");
+            assert_eq!(changes.len(), 2);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+    assert!(registry.get_version(&id, "1.1.0")?.is_none());
+
+    let report = registry.publish_version(&id, "2.0.0")?;
+    let breaking: Vec<&str> = report.breaking_changes().map(|c| c.path.as_str()).collect();
+    assert_eq!(breaking, vec!["input", "output.properties.language"]);
+
+    let report = registry.compatibility(&id, "1.0.0", "2.0.0")?;
+    assert!(report.is_breaking());
+
+    // 删除能力同样是破坏性变化
+    let old = capability(serde_json::json!({}), serde_json::json!({}));
+    let changes = compare_capabilities(&old, &[]);
+    assert!(changes[0].breaking);
+
+    Ok(())
+}
+
+#[test]
+fn test_backported_patch_uses_old_version_line() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    let mut agent = listing("Translator", "translate", "Translate text", PricingModel::Free, 4.0);
+    agent.capabilities = capability(
+        serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}),
+        serde_json::json!({"type": "object", "properties": {"translation": {"type": "string"}}}),
+    );
+    let id = registry.register(agent.clone())?;
+    registry.publish_version(&id, "1.0.0")?;
+
+    // 2.x 删除了输出字段
+    agent.capabilities = capability(
+        serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}),
+        serde_json::json!({"type": "object", "properties": {}}),
+    );
+    registry.update(agent)?;
+    registry.publish_version(&id, "2.0.0")?;
+
+    // 当前 listing 属于 2.x, 不能作为 1.0.1 发布
+    let err = registry.publish_version(&id, "1.0.1").unwrap_err();
+    assert!(matches!(err.downcast_ref::<VersionError>(), Some(VersionError::NotLatest { .. })));
+
+    let report = registry.publish_backport(&id, "1.0.0", "1.0.1", |listing| {
+        listing.description = "Translate text (patched)".to_string();
+    })?;
+    assert_eq!(report.from, Some(semver::Version::new(1, 0, 0)));
+    assert!(!report.is_breaking());
+
+    let patch = registry.get_version(&id, "1.0.1")?.unwrap();
+    assert_eq!(patch.listing.version, "1.0.1");
+    assert_eq!(patch.listing.description, "Translate text (patched)");
+    assert!(patch.listing.capabilities[0].output_schema["properties"].get("translation").is_some());
+    assert_eq!(registry.get(&id)?.unwrap().version, "2.0.0");
+    assert_eq!(registry.resolve(&id, "^1")?.version.to_string(), "1.0.1");
+
+    let err = registry.publish_backport(&id, "2.0.0", "3.0.0", |_| {}).unwrap_err();
+    assert!(matches!(err.downcast_ref::<VersionError>(), Some(VersionError::NotBackport(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_deprecation_and_sunset() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    let id = registry.register(listing("Translator", "translate", "Translate text", PricingModel::Free, 4.0))?;
+    registry.publish_version(&id, "1.0.0")?;
+    registry.publish_version(&id, "1.1.0")?;
+
+    registry.deprecate(&id, "1.0.0", Deprecation::new("Use 1.1").with_replacement("1.1.0"))?;
+    assert!(!registry.get(&id)?.unwrap().is_deprecated());
+    // 弃用但未停用的版本仍可解析
+    let v1 = registry.resolve(&id, "=1.0.0")?;
+    assert_eq!(v1.deprecation.unwrap().replacement.as_deref(), Some("1.1.0"));
+
+    let sunset = chrono::Utc::now() - chrono::Duration::hours(1);
+    registry.deprecate(&id, "1.1.0", Deprecation::new("Moving to v2").with_sunset(sunset))?;
+    let current = registry.get(&id)?.unwrap();
+    assert!(current.is_deprecated());
+    assert!(current.is_sunset(chrono::Utc::now()));
+    assert_eq!(registry.resolve(&id, "^1")?.version.to_string(), "1.0.0");
+
+    registry.undeprecate(&id, "1.1.0")?;
+    assert!(!registry.get(&id)?.unwrap().is_deprecated());
+    assert_eq!(registry.resolve(&id, "^1")?.version.to_string(), "1.1.0");
+
+    let err = registry.deprecate(&id, "3.0.0", Deprecation::new("missing")).unwrap_err();
+    assert!(matches!(err.downcast_ref::<VersionError>(), Some(VersionError::VersionNotFound(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_publishing_version_keeps_listing_status() -> Result<()> {
+    let registry = AgentRegistry::in_memory()?;
+    let id = registry.register(listing("Summarizer", "summarize", "Summarize text", PricingModel::Free, 4.0))?;
+    registry.publish(&id)?;
+    registry.publish_version(&id, "1.0.0")?;
+    registry.pause(&id)?;
+
+    // 暂停期间发布新版本不会重新上架
+    registry.publish_version(&id, "1.1.0")?;
+    let current = registry.get(&id)?.unwrap();
+    assert_eq!(current.status, AgentStatus::Paused);
+    assert_eq!(current.version, "1.1.0");
+    assert_eq!(registry.get_version(&id, "1.1.0")?.unwrap().listing.status, AgentStatus::Published);
+    assert!(registry.list_published(0, 10)?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use crate::models::{AgentListing, Capability, Deprecation};
+use chrono::{DateTime, Utc};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// 版本管理错误
+#[derive(Debug, Error)]
+pub enum VersionError {
+    #[error("Invalid version '{0}': {1}")]
+    InvalidVersion(String, semver::Error),
+
+    #[error("Invalid version requirement '{0}': {1}")]
+    InvalidRequirement(String, semver::Error),
+
+    #[error("Version {0} is already published")]
+    AlreadyPublished(Version),
+
+    #[error("Version {version} is older than the latest published version {latest}; publish it as a backport")]
+    NotLatest { version: Version, latest: Version },
+
+    #[error("Version {0} is newer than every published version and cannot be a backport")]
+    NotBackport(Version),
+
+    #[error("Version {0} not found")]
+    VersionNotFound(String),
+
+    #[error("No published version matches '{0}'")]
+    NoMatchingVersion(String),
+
+    #[error("Version {version} has breaking changes since {previous} and needs a {required} bump: {changes:?}")]
+    BreakingChange {
+        version: Version,
+        previous: Version,
+        required: &'static str,
+        changes: Vec<String>,
+    },
+}
+
+/// 已发布的不可变版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentVersion {
+    pub agent_id: Uuid,
+    pub version: Version,
+    /// 发布时的完整快照
+    pub listing: AgentListing,
+    pub published_at: DateTime<Utc>,
+    /// 弃用说明 (发布后唯一可修改的部分)
+    pub deprecation: Option<Deprecation>,
+}
+
+impl AgentVersion {
+    /// 是否已过停用日期
+    pub fn is_sunset(&self, now: DateTime<Utc>) -> bool {
+        self.deprecation.as_ref().is_some_and(|d| d.is_sunset(now))
+    }
+}
+
+/// 单项 schema 变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaChange {
+    pub skill_name: String,
+    /// 变化位置, 如 `input.properties.text`
+    pub path: String,
+    pub description: String,
+    /// 是否会破坏现有调用方
+    pub breaking: bool,
+}
+
+/// 两个版本之间的兼容性报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompatibilityReport {
+    /// 比较的基准版本 (首次发布时为 `None`)
+    pub from: Option<Version>,
+    pub to: Version,
+    pub changes: Vec<SchemaChange>,
+}
+
+impl CompatibilityReport {
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|c| c.breaking)
+    }
+
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &SchemaChange> {
+        self.changes.iter().filter(|c| c.breaking)
+    }
+
+    /// 有破坏性变化时检查版本号是否正确递增: 1.x 以上需要升主版本,
+    /// 0.x 需要升次版本
+    pub(crate) fn check_bump(&self) -> Result<(), VersionError> {
+        let Some(previous) = &self.from else { return Ok(()) };
+        if !self.is_breaking() {
+            return Ok(());
+        }
+        let (bumped, required) = if previous.major == 0 {
+            (self.to.major > 0 || self.to.minor > previous.minor, "minor")
+        } else {
+            (self.to.major > previous.major, "major")
+        };
+        if bumped {
+            return Ok(());
+        }
+        Err(VersionError::BreakingChange {
+            version: self.to.clone(),
+            previous: previous.clone(),
+            required,
+            changes: self.breaking_changes().map(|c| format!("{}: {} ({})", c.skill_name, c.description, c.path)).collect(),
+        })
+    }
+}
+
+/// 比较两个版本的能力列表
+///
+/// 输入 schema 只能放宽 (新版本要接受旧调用方的所有输入), 输出 schema 只能收紧
+/// (旧调用方依赖的字段和取值不能消失)。删除能力是破坏性变化, 新增能力不是。
+pub fn compare_capabilities(old: &[Capability], new: &[Capability]) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    for old_cap in old {
+        match new.iter().find(|c| c.skill_name.eq_ignore_ascii_case(&old_cap.skill_name)) {
+            Some(new_cap) => {
+                let mut diff = SchemaDiff { skill_name: &old_cap.skill_name, changes: &mut changes };
+                diff.compare(&old_cap.input_schema, &new_cap.input_schema, "input", Direction::Input);
+                diff.compare(&old_cap.output_schema, &new_cap.output_schema, "output", Direction::Output);
+            }
+            None => changes.push(SchemaChange {
+                skill_name: old_cap.skill_name.clone(),
+                path: String::new(),
+                description: "capability removed".to_string(),
+                breaking: true,
+            }),
+        }
+    }
+    for new_cap in new {
+        if !old.iter().any(|c| c.skill_name.eq_ignore_ascii_case(&new_cap.skill_name)) {
+            changes.push(SchemaChange {
+                skill_name: new_cap.skill_name.clone(),
+                path: String::new(),
+                description: "capability added".to_string(),
+                breaking: false,
+            });
+        }
+    }
+    changes
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Input,
+    Output,
+}
+
+struct SchemaDiff<'a> {
+    skill_name: &'a str,
+    changes: &'a mut Vec<SchemaChange>,
+}
+
+impl SchemaDiff<'_> {
+    fn push(&mut self, path: &str, description: String, breaking: bool) {
+        self.changes.push(SchemaChange {
+            skill_name: self.skill_name.to_string(),
+            path: path.to_string(),
+            description,
+            breaking,
+        });
+    }
+
+    fn compare(&mut self, old: &Value, new: &Value, path: &str, direction: Direction) {
+        // 输入: 旧的取值范围必须包含在新的里; 输出: 新的必须包含在旧的里
+        let (narrow, wide) = match direction {
+            Direction::Input => (old, new),
+            Direction::Output => (new, old),
+        };
+
+        let (old_types, new_types) = (types(old), types(new));
+        if old_types != new_types {
+            let breaking = !types_within(&types(narrow), &types(wide));
+            self.push(path, format!("type changed from {} to {}", describe(&old_types), describe(&new_types)), breaking);
+        }
+
+        let (old_enum, new_enum) = (old.get("enum"), new.get("enum"));
+        if old_enum != new_enum {
+            let breaking = match (narrow.get("enum").and_then(Value::as_array), wide.get("enum").and_then(Value::as_array)) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(narrow), Some(wide)) => narrow.iter().any(|v| !wide.contains(v)),
+            };
+            self.push(path, "allowed values changed".to_string(), breaking);
+        }
+
+        let (old_required, new_required) = (required(old), required(new));
+        for field in new_required.difference(&old_required) {
+            self.push(path, format!("field '{field}' became required"), direction == Direction::Input);
+        }
+        for field in old_required.difference(&new_required) {
+            self.push(path, format!("field '{field}' is no longer required"), direction == Direction::Output);
+        }
+
+        let (old_props, new_props) = (properties(old), properties(new));
+        for (name, old_schema) in &old_props {
+            let field_path = format!("{path}.properties.{name}");
+            match new_props.iter().find(|(n, _)| n == name) {
+                Some((_, new_schema)) => self.compare(old_schema, new_schema, &field_path, direction),
+                None => {
+                    let breaking = match direction {
+                        Direction::Input => new.get("additionalProperties") == Some(&Value::Bool(false)),
+                        Direction::Output => true,
+                    };
+                    self.push(&field_path, "field removed".to_string(), breaking);
+                }
+            }
+        }
+        for (name, _) in &new_props {
+            if !old_props.iter().any(|(n, _)| n == name) {
+                let breaking = direction == Direction::Output && old.get("additionalProperties") == Some(&Value::Bool(false));
+                self.push(&format!("{path}.properties.{name}"), "field added".to_string(), breaking);
+            }
+        }
+
+        if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+            self.compare(old_items, new_items, &format!("{path}.items"), direction);
+        }
+    }
+}
+
+/// schema 允许的类型, `None` 表示不限制
+fn types(schema: &Value) -> Option<BTreeSet<String>> {
+    match schema.get("type")? {
+        Value::String(t) => Some(BTreeSet::from([t.clone()])),
+        Value::Array(ts) => Some(ts.iter().filter_map(Value::as_str).map(str::to_string).collect()),
+        _ => None,
+    }
+}
+
+/// `narrow` 中的每个类型是否都被 `wide` 接受 (`number` 包含 `integer`)
+fn types_within(narrow: &Option<BTreeSet<String>>, wide: &Option<BTreeSet<String>>) -> bool {
+    match (narrow, wide) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(narrow), Some(wide)) => narrow
+            .iter()
+            .all(|t| wide.contains(t) || (t == "integer" && wide.contains("number"))),
+    }
+}
+
+fn describe(types: &Option<BTreeSet<String>>) -> String {
+    match types {
+        Some(types) => types.iter().cloned().collect::<Vec<_>>().join("|"),
+        None => "any".to_string(),
+    }
+}
+
+fn required(schema: &Value) -> BTreeSet<String> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|fields| fields.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn properties(schema: &Value) -> Vec<(String, Value)> {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|props| props.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// 解析版本号 (允许前导 `v`)
+pub(crate) fn parse_version(version: &str) -> Result<Version, VersionError> {
+    let trimmed = version.trim();
+    let trimmed = trimmed.strip_prefix('v').unwrap_or(trimmed);
+    Version::parse(trimmed).map_err(|e| VersionError::InvalidVersion(version.to_string(), e))
+}
@@ -1,71 +1,265 @@
 use crate::models::{ReputationRecord, ReputationLevel};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// 评分参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringConfig {
+    /// 评价权重的半衰期, 越早的评价权重越低
+    pub half_life: Duration,
+    /// 贝叶斯平均的先验评分
+    pub prior_rating: f64,
+    /// 先验相当于多少条 (满权重) 评价
+    pub prior_weight: f64,
+    /// 成功率的先验
+    pub prior_success_rate: f64,
+    /// 成功率先验相当于多少笔交易
+    pub prior_transactions: f64,
+    /// 未验证 (没有对应交易) 评价的权重, 默认不计入
+    pub unverified_weight: f64,
+    /// 没有信誉记录的评价者的可信度, 默认与信誉最低的评价者相同,
+    /// 避免新注册的身份比已有低分记录的评价者更可信
+    pub default_reviewer_trust: f64,
+    /// 交易量得分达到满分所需的交易数
+    pub volume_saturation: u64,
+    /// 各项权重: 评分、成功率、交易量、响应时间
+    pub rating_weight: f64,
+    pub success_weight: f64,
+    pub volume_weight: f64,
+    pub response_weight: f64,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            half_life: Duration::days(90),
+            prior_rating: 3.0,
+            prior_weight: 10.0,
+            prior_success_rate: 0.8,
+            prior_transactions: 10.0,
+            unverified_weight: 0.0,
+            default_reviewer_trust: 0.25,
+            volume_saturation: 200,
+            rating_weight: 0.4,
+            success_weight: 0.3,
+            volume_weight: 0.2,
+            response_weight: 0.1,
+        }
+    }
+}
+
+/// 评价者信息: 可信度和与被评价 Agent 串通的评价者
+#[derive(Debug, Clone, Default)]
+pub struct ReviewerContext {
+    /// 评价者可信度 (0.0 - 1.0), 未列出的使用 `ScoringConfig::default_reviewer_trust`
+    pub trust: HashMap<Uuid, f64>,
+    /// 与被评价 Agent 处于同一互评团伙的评价者
+    pub colluding: HashSet<Uuid>,
+}
+
+/// 评价被排除的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    /// 自己评价自己
+    SelfReview,
+    /// 同一笔交易的重复评价
+    DuplicateTransaction,
+    /// 未验证且未验证评价权重为 0
+    Unverified,
+    /// 评价者与被评价 Agent 互评串通
+    Collusion,
+    /// 评价者可信度为 0
+    UntrustedReviewer,
+}
+
+/// 单条评价在评分中的权重
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewWeight {
+    pub review_id: Uuid,
+    pub reviewer_id: Uuid,
+    pub rating: u8,
+    /// 时间衰减系数
+    pub decay: f64,
+    /// 评价者可信度
+    pub trust: f64,
+    /// 最终权重 = 衰减 × 可信度 × (未验证权重)
+    pub weight: f64,
+    pub excluded: Option<ExclusionReason>,
+}
+
+/// 分项得分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreComponent {
+    pub name: String,
+    /// 分项得分 (0-5)
+    pub value: f64,
+    pub weight: f64,
+    /// 对总分的贡献 = 得分 × 权重
+    pub contribution: f64,
+}
+
+/// 可解释的信誉分数明细
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub agent_id: Uuid,
+    /// 总分 (0-5)
+    pub score: f64,
+    /// 所有评价的简单平均分
+    pub raw_average_rating: f64,
+    /// 加权后的贝叶斯平均分
+    pub bayesian_rating: f64,
+    /// 有效评价数 (权重之和)
+    pub effective_review_count: f64,
+    /// 贝叶斯平滑后的成功率
+    pub adjusted_success_rate: f64,
+    pub components: Vec<ScoreComponent>,
+    pub reviews: Vec<ReviewWeight>,
+    pub computed_at: DateTime<Utc>,
+}
 
 /// 信誉计算器
-pub struct ReputationCalculator;
+pub struct ReputationCalculator {
+    config: ScoringConfig,
+}
 
 impl ReputationCalculator {
     /// 创建新的信誉计算器
     pub fn new() -> Self {
-        Self
+        Self::with_config(ScoringConfig::default())
+    }
+
+    /// 使用自定义参数创建
+    pub fn with_config(config: ScoringConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ScoringConfig {
+        &self.config
     }
 
     /// 计算信誉分数
     ///
-    /// 综合考虑多个因素:
-    /// - 平均评分 (40%)
+    /// 综合考虑多个因素 (权重见 `ScoringConfig`):
+    /// - 贝叶斯平均评分 (40%)
     /// - 成功率 (30%)
     /// - 交易数量 (20%)
     /// - 响应时间 (10%)
     pub fn calculate_score(&self, record: &ReputationRecord) -> f64 {
-        let mut score = 0.0;
+        self.breakdown(record, &ReviewerContext::default(), Utc::now()).score
+    }
+
+    /// 计算分数及明细
+    ///
+    /// 评价权重 = 时间衰减 × 评价者可信度, 未验证评价再乘以
+    /// `unverified_weight`; 自评、同一交易的重复评价和互评团伙的评价不计入。
+    /// 评分和成功率都与先验做贝叶斯平均, 样本少的 Agent 不会因为几条好评排到最前。
+    pub fn breakdown(&self, record: &ReputationRecord, context: &ReviewerContext, now: DateTime<Utc>) -> ScoreBreakdown {
+        let config = &self.config;
+        let half_life_secs = config.half_life.num_seconds().max(1) as f64;
 
-        // 1. 平均评分 (权重 40%)
-        let avg_rating = record.average_rating();
-        score += avg_rating * 0.4;
+        let mut seen_transactions = HashSet::new();
+        let mut reviews = Vec::with_capacity(record.reviews.len());
+        let (mut weighted_sum, mut weight_sum) = (0.0, 0.0);
+        let mut ordered: Vec<_> = record.reviews.iter().collect();
+        ordered.sort_by_key(|r| r.created_at);
+        for review in ordered {
+            let age_secs = (now - review.created_at).num_seconds().max(0) as f64;
+            let decay = 0.5f64.powf(age_secs / half_life_secs);
+            let trust = context
+                .trust
+                .get(&review.reviewer_id)
+                .copied()
+                .unwrap_or(config.default_reviewer_trust)
+                .clamp(0.0, 1.0);
+            let verification = if review.verified { 1.0 } else { config.unverified_weight };
 
-        // 2. 成功率 (权重 30%)
-        let success_rate = record.success_rate();
-        score += success_rate * 5.0 * 0.3; // 归一化到 0-5
+            let excluded = if review.reviewer_id == record.agent_id {
+                Some(ExclusionReason::SelfReview)
+            } else if !seen_transactions.insert(review.transaction_id) {
+                Some(ExclusionReason::DuplicateTransaction)
+            } else if context.colluding.contains(&review.reviewer_id) {
+                Some(ExclusionReason::Collusion)
+            } else if verification <= 0.0 {
+                Some(ExclusionReason::Unverified)
+            } else if trust <= 0.0 {
+                Some(ExclusionReason::UntrustedReviewer)
+            } else {
+                None
+            };
+            let weight = if excluded.is_some() { 0.0 } else { decay * trust * verification };
 
-        // 3. 交易数量 (权重 20%)
-        let transaction_score = self.calculate_transaction_score(record.total_transactions);
-        score += transaction_score * 0.2;
+            weighted_sum += weight * review.rating as f64;
+            weight_sum += weight;
+            reviews.push(ReviewWeight {
+                review_id: review.id,
+                reviewer_id: review.reviewer_id,
+                rating: review.rating,
+                decay,
+                trust,
+                weight,
+                excluded,
+            });
+        }
+
+        let bayesian_rating = (config.prior_weight * config.prior_rating + weighted_sum)
+            / (config.prior_weight + weight_sum).max(f64::EPSILON);
+        let adjusted_success_rate = (config.prior_transactions * config.prior_success_rate
+            + record.successful_transactions as f64)
+            / (config.prior_transactions + record.total_transactions as f64).max(f64::EPSILON);
 
-        // 4. 响应时间 (权重 10%)
-        let response_score = self.calculate_response_score(record.average_response_time_ms);
-        score += response_score * 0.1;
+        let component = |name: &str, value: f64, weight: f64| ScoreComponent {
+            name: name.to_string(),
+            value,
+            weight,
+            contribution: value * weight,
+        };
+        let components = vec![
+            component("rating", bayesian_rating, config.rating_weight),
+            component("success_rate", adjusted_success_rate * 5.0, config.success_weight),
+            component("volume", self.calculate_transaction_score(record.total_transactions), config.volume_weight),
+            component("response_time", self.calculate_response_score(record.average_response_time_ms), config.response_weight),
+        ];
+        let score = components.iter().map(|c| c.contribution).sum::<f64>().clamp(0.0, 5.0);
 
-        // 确保分数在 0-5 之间
-        score.clamp(0.0, 5.0)
+        ScoreBreakdown {
+            agent_id: record.agent_id,
+            score,
+            raw_average_rating: record.average_rating(),
+            bayesian_rating,
+            effective_review_count: weight_sum,
+            adjusted_success_rate,
+            components,
+            reviews,
+            computed_at: now,
+        }
     }
 
-    /// 计算交易数量得分 (0-5)
+    /// 计算交易数量得分 (0-5), 按对数增长, 达到 `volume_saturation` 时满分
     fn calculate_transaction_score(&self, count: u64) -> f64 {
-        match count {
-            0 => 0.0,
-            1..=10 => 2.0,
-            11..=50 => 3.5,
-            51..=200 => 4.5,
-            _ => 5.0,
-        }
+        let saturation = self.config.volume_saturation.max(1) as f64;
+        (5.0 * (1.0 + count as f64).ln() / (1.0 + saturation).ln()).min(5.0)
     }
 
-    /// 计算响应时间得分 (0-5)
+    /// 计算响应时间得分 (1-5), 500ms 以内满分, 之后时间每翻一倍扣 1 分
     fn calculate_response_score(&self, response_time_ms: u64) -> f64 {
-        match response_time_ms {
-            0..=500 => 5.0,      // 极快
-            501..=1000 => 4.5,   // 很快
-            1001..=2000 => 4.0,  // 快
-            2001..=5000 => 3.0,  // 一般
-            5001..=10000 => 2.0, // 慢
-            _ => 1.0,            // 很慢
+        if response_time_ms <= 500 {
+            return 5.0;
         }
+        (5.0 - (response_time_ms as f64 / 500.0).log2()).clamp(1.0, 5.0)
     }
 
     /// 更新信誉记录
     pub fn update_record(&self, record: &mut ReputationRecord) {
+        self.update_record_with(record, &ReviewerContext::default());
+    }
+
+    /// 结合评价者信息更新信誉记录
+    pub fn update_record_with(&self, record: &mut ReputationRecord, context: &ReviewerContext) {
         // 重新计算分数
-        record.score = self.calculate_score(record);
+        record.score = self.breakdown(record, context, Utc::now()).score;
 
         // 更新等级
         record.level = ReputationLevel::from_transaction_count(record.total_transactions);
@@ -81,6 +275,12 @@ impl ReputationCalculator {
         success: bool,
         response_time_ms: u64,
     ) {
+        Self::apply_transaction(record, success, response_time_ms);
+        self.update_record(record);
+    }
+
+    /// 只更新交易统计, 不重新计算分数
+    pub(crate) fn apply_transaction(record: &mut ReputationRecord, success: bool, response_time_ms: u64) {
         record.total_transactions += 1;
         if success {
             record.successful_transactions += 1;
@@ -93,8 +293,6 @@ impl ReputationCalculator {
             let total_time = record.average_response_time_ms * (record.total_transactions - 1);
             record.average_response_time_ms = (total_time + response_time_ms) / record.total_transactions;
         }
-
-        self.update_record(record);
     }
 
     /// 检测异常评分 (可能的刷单行为)
@@ -136,6 +334,27 @@ impl ReputationCalculator {
             }
         }
 
+        // 4. 检查评价者是否过于集中
+        if record.reviews.len() >= 5 {
+            let mut per_reviewer: HashMap<Uuid, usize> = HashMap::new();
+            for review in &record.reviews {
+                *per_reviewer.entry(review.reviewer_id).or_insert(0) += 1;
+            }
+            let max = per_reviewer.values().copied().max().unwrap_or(0);
+            if max as f64 / record.reviews.len() as f64 > 0.3 {
+                anomalies.push("评价者过于集中: 单个评价者贡献 30%+ 评价".to_string());
+            }
+        }
+
+        // 5. 检查自评和同一交易的重复评价
+        if record.reviews.iter().any(|r| r.reviewer_id == record.agent_id) {
+            anomalies.push("存在自评".to_string());
+        }
+        let transactions: HashSet<Uuid> = record.reviews.iter().map(|r| r.transaction_id).collect();
+        if transactions.len() < record.reviews.len() {
+            anomalies.push("同一交易存在多条评价".to_string());
+        }
+
         anomalies
     }
 
@@ -0,0 +1,226 @@
+use crate::models::Review;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// 互评团伙
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollusionRing {
+    /// 团伙成员 (按 ID 排序)
+    pub members: Vec<Uuid>,
+    /// 成员之间的互评数量
+    pub internal_reviews: usize,
+    /// 成员收到的评价中来自团伙内部的比例 (取成员中的最小值)
+    pub internal_ratio: f64,
+    /// 成员之间有评价关系的有序对占全部有序对的比例
+    pub density: f64,
+}
+
+impl CollusionRing {
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.members.binary_search(id).is_ok()
+    }
+}
+
+/// 基于评价图的串通检测
+///
+/// 把 "评价者 -> 被评价 Agent" 看作有向边。单向的评价环 (A 评 B, B 评 C, C 评 A)
+/// 在正常交易中很常见, 不算串通; 只有互相评价的成员对, 以及边密度足够高的强连通
+/// 分量才构成可疑关系。按可疑关系连通的 Agent 组成候选团伙, 再逐个检查成员:
+/// 收到的评价中来自团伙内部的比例低于阈值的成员被移出, 直到剩下的成员都满足条件。
+#[derive(Debug, Clone)]
+pub struct CollusionDetector {
+    /// 团伙最少成员数
+    pub min_ring_size: usize,
+    /// 每个成员收到的评价中来自团伙内部的最低占比
+    pub min_internal_ratio: f64,
+    /// 没有互评时, 强连通分量被视为团伙所需的最低边密度
+    pub min_density: f64,
+}
+
+impl Default for CollusionDetector {
+    fn default() -> Self {
+        Self {
+            min_ring_size: 2,
+            min_internal_ratio: 0.5,
+            min_density: 0.75,
+        }
+    }
+}
+
+impl CollusionDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在一组评价中查找互评团伙
+    ///
+    /// 团伙不会跨越评价图的连通部分, 所以传入某个连通部分的全部评价即可得到
+    /// 其中的团伙。
+    pub fn detect(&self, reviews: &[Review]) -> Vec<CollusionRing> {
+        let mut edges = HashSet::new();
+        let mut graph: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        // 每个 Agent 收到的评价的评价者 (每条评价一项)
+        let mut incoming: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for review in reviews.iter().filter(|r| r.reviewer_id != r.agent_id) {
+            incoming.entry(review.agent_id).or_default().push(review.reviewer_id);
+            if edges.insert((review.reviewer_id, review.agent_id)) {
+                graph.entry(review.reviewer_id).or_default().push(review.agent_id);
+                graph.entry(review.agent_id).or_default();
+            }
+        }
+
+        // 可疑关系: 互评的成员对, 以及高密度强连通分量中的成员
+        let mut links: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        let mut link = |a: Uuid, b: Uuid| {
+            links.entry(a).or_default().push(b);
+            links.entry(b).or_default().push(a);
+        };
+        for &(from, to) in &edges {
+            if from < to && edges.contains(&(to, from)) {
+                link(from, to);
+            }
+        }
+        for component in strongly_connected_components(&graph) {
+            if component.len() >= 2 && density(&component, &graph) >= self.min_density {
+                for pair in component.windows(2) {
+                    link(pair[0], pair[1]);
+                }
+            }
+        }
+
+        let mut rings: Vec<CollusionRing> = connected_components(&links)
+            .into_iter()
+            .filter_map(|candidates| self.confirm(candidates, &incoming, &graph))
+            .collect();
+        rings.sort_by(|a, b| a.members.cmp(&b.members));
+        rings
+    }
+
+    /// 反复移出内部评价占比不足的成员, 剩余成员足够多时构成团伙
+    fn confirm(
+        &self,
+        candidates: Vec<Uuid>,
+        incoming: &HashMap<Uuid, Vec<Uuid>>,
+        graph: &HashMap<Uuid, Vec<Uuid>>,
+    ) -> Option<CollusionRing> {
+        let mut members: HashSet<Uuid> = candidates.into_iter().collect();
+        loop {
+            let internal: HashMap<Uuid, usize> = members
+                .iter()
+                .map(|id| {
+                    let reviewers = incoming.get(id).map(Vec::as_slice).unwrap_or_default();
+                    (*id, reviewers.iter().filter(|reviewer| members.contains(reviewer)).count())
+                })
+                .collect();
+            let ratio = |id: &Uuid| {
+                let received = incoming.get(id).map_or(0, Vec::len);
+                internal.get(id).copied().unwrap_or(0) as f64 / received.max(1) as f64
+            };
+            let before = members.len();
+            members.retain(|id| ratio(id) >= self.min_internal_ratio);
+            if members.len() < self.min_ring_size.max(2) {
+                return None;
+            }
+            if members.len() == before {
+                let mut sorted: Vec<Uuid> = members.iter().copied().collect();
+                sorted.sort();
+                return Some(CollusionRing {
+                    internal_reviews: internal.values().sum(),
+                    internal_ratio: sorted.iter().map(ratio).fold(1.0, f64::min),
+                    density: density(&sorted, graph),
+                    members: sorted,
+                });
+            }
+        }
+    }
+}
+
+/// 成员之间有评价关系的有序对占全部有序对的比例
+fn density(members: &[Uuid], graph: &HashMap<Uuid, Vec<Uuid>>) -> f64 {
+    let n = members.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let set: HashSet<&Uuid> = members.iter().collect();
+    let internal: usize = members
+        .iter()
+        .map(|a| graph.get(a).map_or(0, |targets| targets.iter().filter(|b| set.contains(b)).count()))
+        .sum();
+    internal as f64 / (n * (n - 1)) as f64
+}
+
+/// 无向图的连通分量
+fn connected_components(links: &HashMap<Uuid, Vec<Uuid>>) -> Vec<Vec<Uuid>> {
+    let mut seen = HashSet::new();
+    let mut components = Vec::new();
+    for &start in links.keys() {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut component = Vec::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            component.push(node);
+            for &next in &links[&node] {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// Kosaraju 算法 (迭代实现, 避免大图上递归过深)
+fn strongly_connected_components(graph: &HashMap<Uuid, Vec<Uuid>>) -> Vec<Vec<Uuid>> {
+    // 第一遍: 按完成顺序记录节点
+    let mut order = Vec::with_capacity(graph.len());
+    let mut visited = HashSet::new();
+    for &start in graph.keys() {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut stack = vec![(start, 0usize)];
+        while let Some((node, next)) = stack.last_mut() {
+            let edges = &graph[node];
+            if let Some(&target) = edges.get(*next) {
+                *next += 1;
+                if visited.insert(target) {
+                    stack.push((target, 0));
+                }
+            } else {
+                order.push(*node);
+                stack.pop();
+            }
+        }
+    }
+
+    // 第二遍: 在反向图上按完成顺序倒序遍历
+    let mut reversed: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for (&from, targets) in graph {
+        for &to in targets {
+            reversed.entry(to).or_default().push(from);
+        }
+    }
+    let mut assigned = HashSet::new();
+    let mut components = Vec::new();
+    for &start in order.iter().rev() {
+        if !assigned.insert(start) {
+            continue;
+        }
+        let mut component = Vec::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            component.push(node);
+            for &next in reversed.get(&node).into_iter().flatten() {
+                if assigned.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
@@ -1,15 +1,17 @@
 //! Reputation System - Agent 信誉管理系统
 //!
-//! 提供评分、评价、信誉计算和等级管理功能
+//! 提供评分、评价、信誉计算和等级管理功能, 以及基于评价图的互评串通检测
 
 mod models;
 mod storage;
 mod calculator;
+mod collusion;
 mod manager;
 
 pub use models::*;
 pub use storage::*;
 pub use calculator::*;
+pub use collusion::*;
 pub use manager::*;
 
 #[cfg(test)]
@@ -1,14 +1,27 @@
 use crate::models::{ReputationRecord, Review, ReputationStats};
 use crate::storage::ReputationStorage;
-use crate::calculator::ReputationCalculator;
-use anyhow::{Context, Result};
+use crate::calculator::{ReputationCalculator, ReviewerContext, ScoreBreakdown, ScoringConfig};
+use crate::collusion::{CollusionDetector, CollusionRing};
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::Mutex;
 use uuid::Uuid;
 
 /// 信誉管理器
+///
+/// 每次评价或交易变化后重新计算分数: 评价者的可信度取自其自身的信誉分数,
+/// 与被评价 Agent 处于同一互评团伙的评价者不计入。
+///
+/// 互评团伙的检测结果缓存在管理器中。新增评价时只重新检测评价者和被评价 Agent
+/// 所在的连通部分 (团伙不会跨越连通部分), 团伙归属因此发生变化的 Agent 会一并
+/// 重新计算。多个管理器共用同一数据库时, 其他管理器写入的评价
+/// 要等到 [`ReputationManager::recalculate_all`] 才会反映到团伙检测中。
 pub struct ReputationManager {
     storage: ReputationStorage,
     calculator: ReputationCalculator,
+    detector: CollusionDetector,
+    rings: Mutex<Option<Vec<CollusionRing>>>,
 }
 
 impl ReputationManager {
@@ -16,14 +29,27 @@ impl ReputationManager {
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
         let storage = ReputationStorage::new(db_path)?;
         let calculator = ReputationCalculator::new();
-        Ok(Self { storage, calculator })
+        Ok(Self { storage, calculator, detector: CollusionDetector::default(), rings: Mutex::new(None) })
     }
 
     /// 创建内存管理器
     pub fn in_memory() -> Result<Self> {
         let storage = ReputationStorage::in_memory()?;
         let calculator = ReputationCalculator::new();
-        Ok(Self { storage, calculator })
+        Ok(Self { storage, calculator, detector: CollusionDetector::default(), rings: Mutex::new(None) })
+    }
+
+    /// 使用自定义评分参数
+    pub fn with_scoring(mut self, config: ScoringConfig) -> Self {
+        self.calculator = ReputationCalculator::with_config(config);
+        self
+    }
+
+    /// 使用自定义串通检测参数
+    pub fn with_collusion_detector(mut self, detector: CollusionDetector) -> Self {
+        self.detector = detector;
+        self.rings = Mutex::new(None);
+        self
     }
 
     /// 获取或创建信誉记录
@@ -53,41 +79,144 @@ impl ReputationManager {
         let review = Review::new(transaction_id, agent_id, reviewer_id, rating, comment);
         let review_id = review.id;
 
-        // 保存评价
-        self.storage.save_review(&review)?;
+        // 保存评价; 新评价可能形成或拆散所在连通部分中的互评团伙
+        let (rings, changed) = {
+            let mut cached = self.rings.lock().unwrap();
+            let current = self.load_rings(&mut cached)?.clone();
+            self.storage.save_review(&review)?;
 
-        // 更新信誉记录
+            let connected = self.storage.list_connected_reviews(&agent_id)?;
+            let agents: HashSet<Uuid> = connected.iter().flat_map(|r| [r.agent_id, r.reviewer_id]).collect();
+            let (before, mut rings): (Vec<_>, Vec<_>) = current
+                .into_iter()
+                .partition(|ring| ring.members.iter().any(|id| agents.contains(id)));
+            let after = self.detector.detect(&connected);
+            let changed = ring_changes(&before, &after);
+            rings.extend(after);
+            rings.sort_by(|a, b| a.members.cmp(&b.members));
+            *cached = Some(rings.clone());
+            (rings, changed)
+        };
+
+        // 更新信誉记录, 以及团伙归属变化的其他 Agent
         record.add_review(review);
-        self.calculator.update_record(&mut record);
-        self.storage.save_record(&record)?;
+        self.refresh(&mut record, &rings)?;
+        for id in changed {
+            if id == agent_id {
+                continue;
+            }
+            if let Some(mut member) = self.storage.get_record(&id)? {
+                self.refresh(&mut member, &rings)?;
+            }
+        }
 
         Ok(review_id)
     }
 
     /// 验证评价
+    ///
+    /// 评价的 `transaction_id` 必须是通过 [`ReputationManager::record_transaction`]
+    /// 记录的交易, 且交易双方就是被评价 Agent 和评价者。
     pub fn verify_review(&self, review_id: &Uuid, agent_id: &Uuid) -> Result<()> {
-        let mut record = self.get_or_create_record(agent_id)?;
+        let Some(mut record) = self.storage.get_record(agent_id)? else {
+            bail!("Agent {} has no reputation record", agent_id);
+        };
+        let Some(review) = record.reviews.iter_mut().find(|r| r.id == *review_id) else {
+            bail!("Review {} not found for agent {}", review_id, agent_id);
+        };
 
-        // 找到并验证评价
-        if let Some(review) = record.reviews.iter_mut().find(|r| r.id == *review_id) {
-            review.verify();
-            self.storage.save_review(review)?;
+        match self.storage.get_transaction_parties(&review.transaction_id)? {
+            Some((agent, client)) if agent == review.agent_id && client == review.reviewer_id => {}
+            _ => bail!(
+                "Review {} does not match a recorded transaction between {} and {}",
+                review_id,
+                review.reviewer_id,
+                review.agent_id
+            ),
         }
 
-        Ok(())
+        review.verify();
+        self.storage.save_review(review)?;
+        self.refresh(&mut record, &self.rings()?)
     }
 
     /// 记录交易
+    ///
+    /// 交易 ID 只能记录一次, 之后客户对这笔交易的评价可以通过
+    /// [`ReputationManager::verify_review`] 验证。
     pub fn record_transaction(
         &self,
+        transaction_id: Uuid,
         agent_id: &Uuid,
+        client_id: &Uuid,
         success: bool,
         response_time_ms: u64,
     ) -> Result<()> {
         let mut record = self.get_or_create_record(agent_id)?;
-        self.calculator.record_transaction(&mut record, success, response_time_ms);
-        self.storage.save_record(&record)?;
-        Ok(())
+        self.storage.save_transaction(&transaction_id, agent_id, client_id)?;
+        ReputationCalculator::apply_transaction(&mut record, success, response_time_ms);
+        self.refresh(&mut record, &self.rings()?)
+    }
+
+    /// 获取可解释的分数明细
+    pub fn score_breakdown(&self, agent_id: &Uuid) -> Result<Option<ScoreBreakdown>> {
+        let Some(record) = self.storage.get_record(agent_id)? else {
+            return Ok(None);
+        };
+        let context = self.reviewer_context(&record, &self.rings()?)?;
+        Ok(Some(self.calculator.breakdown(&record, &context, chrono::Utc::now())))
+    }
+
+    /// 检测所有互评团伙
+    pub fn detect_collusion(&self) -> Result<Vec<CollusionRing>> {
+        self.rings()
+    }
+
+    /// 缓存的互评团伙
+    fn rings(&self) -> Result<Vec<CollusionRing>> {
+        let mut cached = self.rings.lock().unwrap();
+        Ok(self.load_rings(&mut cached)?.clone())
+    }
+
+    /// 首次使用时从全部评价检测互评团伙
+    fn load_rings<'a>(&self, cached: &'a mut Option<Vec<CollusionRing>>) -> Result<&'a Vec<CollusionRing>> {
+        if cached.is_none() {
+            *cached = Some(self.detector.detect(&self.storage.list_reviews()?));
+        }
+        Ok(cached.get_or_insert_with(Vec::new))
+    }
+
+    /// 重新计算分数并保存
+    fn refresh(&self, record: &mut ReputationRecord, rings: &[CollusionRing]) -> Result<()> {
+        let context = self.reviewer_context(record, rings)?;
+        self.calculator.update_record_with(record, &context);
+        self.storage.save_record(record)
+    }
+
+    /// 评价者可信度: 有信誉记录的按分数映射到 0.25 - 1.0, 新评价者使用默认值
+    fn reviewer_context(&self, record: &ReputationRecord, rings: &[CollusionRing]) -> Result<ReviewerContext> {
+        let reviewers: Vec<Uuid> = record
+            .reviews
+            .iter()
+            .map(|review| review.reviewer_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let trust = self
+            .storage
+            .get_scores(&reviewers)?
+            .into_iter()
+            .map(|(id, score)| (id, 0.25 + 0.75 * (score / 5.0).clamp(0.0, 1.0)))
+            .collect();
+
+        let colluding = rings
+            .iter()
+            .filter(|ring| ring.contains(&record.agent_id))
+            .flat_map(|ring| ring.members.iter().copied())
+            .filter(|id| *id != record.agent_id)
+            .collect();
+
+        Ok(ReviewerContext { trust, colluding })
     }
 
     /// 获取信誉记录
@@ -106,11 +235,20 @@ impl ReputationManager {
 
     /// 检测异常
     pub fn detect_anomaly(&self, agent_id: &Uuid) -> Result<Vec<String>> {
-        if let Some(record) = self.storage.get_record(agent_id)? {
-            Ok(self.calculator.detect_anomaly(&record))
-        } else {
-            Ok(Vec::new())
+        let Some(record) = self.storage.get_record(agent_id)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut anomalies = self.calculator.detect_anomaly(&record);
+        if let Some(ring) = self.rings()?.into_iter().find(|ring| ring.contains(agent_id)) {
+            anomalies.push(format!(
+                "疑似互评团伙: {} 个 Agent 之间互评 {} 次 (每个成员至少 {:.0}% 的评价来自团伙内部)",
+                ring.members.len(),
+                ring.internal_reviews,
+                ring.internal_ratio * 100.0
+            ));
         }
+        Ok(anomalies)
     }
 
     /// 计算趋势
@@ -124,8 +262,32 @@ impl ReputationManager {
 
     /// 重新计算所有信誉分数
     pub fn recalculate_all(&self) -> Result<usize> {
-        // 这是一个简化实现,实际应该遍历所有记录
-        // 由于我们没有实现 list_all 方法,这里返回 0
-        Ok(0)
+        let rings = self.detector.detect(&self.storage.list_reviews()?);
+        *self.rings.lock().unwrap() = Some(rings.clone());
+        let mut count = 0;
+        for agent_id in self.storage.list_agent_ids()? {
+            if let Some(mut record) = self.storage.get_record(&agent_id)? {
+                self.refresh(&mut record, &rings)?;
+                count += 1;
+            }
+        }
+        Ok(count)
     }
 }
+
+/// 团伙归属 (所在团伙的成员) 发生变化的 Agent
+fn ring_changes(before: &[CollusionRing], after: &[CollusionRing]) -> HashSet<Uuid> {
+    let membership = |rings: &[CollusionRing]| -> HashMap<Uuid, Vec<Uuid>> {
+        rings
+            .iter()
+            .flat_map(|ring| ring.members.iter().map(|id| (*id, ring.members.clone())))
+            .collect()
+    };
+    let (before, after) = (membership(before), membership(after));
+    before
+        .keys()
+        .chain(after.keys())
+        .filter(|id| before.get(id) != after.get(id))
+        .copied()
+        .collect()
+}
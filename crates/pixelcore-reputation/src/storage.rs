@@ -1,6 +1,7 @@
 use crate::models::{ReputationRecord, Review};
-use anyhow::{Context, Result};
-use rusqlite::{params, Connection, OptionalExtension};
+use anyhow::Result;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
@@ -68,6 +69,21 @@ impl ReputationStorage {
             [],
         )?;
 
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_reviews_reviewer ON reviews(reviewer_id)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS transactions (
+                id TEXT PRIMARY KEY,
+                agent_id TEXT NOT NULL,
+                client_id TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         Ok(())
     }
 
@@ -116,6 +132,8 @@ impl ReputationStorage {
                     average_response_time_ms, level_str, updated_at))
             },
         ).optional()?;
+        // 加载评价需要再次获取连接
+        drop(conn);
 
         if let Some((agent_id, score, total_transactions, successful_transactions,
                      average_response_time_ms, level_str, updated_at)) = record {
@@ -146,6 +164,63 @@ impl ReputationStorage {
         }
     }
 
+    /// 批量获取信誉分数 (不加载评价), 没有记录的 Agent 不出现在结果中
+    pub fn get_scores(&self, agent_ids: &[Uuid]) -> Result<HashMap<Uuid, f64>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut scores = HashMap::new();
+        // SQLite 限制单条语句的参数数量
+        for chunk in agent_ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = conn.prepare(&format!(
+                "SELECT agent_id, score FROM reputation_records WHERE agent_id IN ({})",
+                placeholders
+            ))?;
+            let rows = stmt.query_map(params_from_iter(chunk.iter().map(|id| id.to_string())), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })?;
+            for row in rows {
+                let (agent_id, score) = row?;
+                scores.insert(Uuid::parse_str(&agent_id)?, score);
+            }
+        }
+
+        Ok(scores)
+    }
+
+    /// 保存交易的双方, 同一交易 ID 只能保存一次
+    pub fn save_transaction(&self, transaction_id: &Uuid, agent_id: &Uuid, client_id: &Uuid) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO transactions (id, agent_id, client_id, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                transaction_id.to_string(),
+                agent_id.to_string(),
+                client_id.to_string(),
+                chrono::Utc::now().to_rfc3339(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// 获取交易的双方: (提供服务的 Agent, 客户)
+    pub fn get_transaction_parties(&self, transaction_id: &Uuid) -> Result<Option<(Uuid, Uuid)>> {
+        let conn = self.conn.lock().unwrap();
+
+        let parties = conn.query_row(
+            "SELECT agent_id, client_id FROM transactions WHERE id = ?1",
+            params![transaction_id.to_string()],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        ).optional()?;
+
+        match parties {
+            Some((agent_id, client_id)) => Ok(Some((Uuid::parse_str(&agent_id)?, Uuid::parse_str(&client_id)?))),
+            None => Ok(None),
+        }
+    }
+
     /// 保存评价
     pub fn save_review(&self, review: &Review) -> Result<()> {
         let conn = self.conn.lock().unwrap();
@@ -178,35 +253,86 @@ impl ReputationStorage {
              FROM reviews WHERE agent_id = ?1 ORDER BY created_at DESC"
         )?;
 
-        let reviews = stmt.query_map(params![agent_id.to_string()], |row| {
-            let id: String = row.get(0)?;
-            let transaction_id: String = row.get(1)?;
-            let agent_id: String = row.get(2)?;
-            let reviewer_id: String = row.get(3)?;
-            let rating: i32 = row.get(4)?;
-            let comment: String = row.get(5)?;
-            let verified: i32 = row.get(6)?;
-            let created_at: String = row.get(7)?;
-
-            Ok((id, transaction_id, agent_id, reviewer_id, rating, comment, verified, created_at))
-        })?
-        .filter_map(|result| {
-            result.ok().and_then(|(id, transaction_id, agent_id, reviewer_id, rating, comment, verified, created_at)| {
-                Some(Review {
-                    id: Uuid::parse_str(&id).ok()?,
-                    transaction_id: Uuid::parse_str(&transaction_id).ok()?,
-                    agent_id: Uuid::parse_str(&agent_id).ok()?,
-                    reviewer_id: Uuid::parse_str(&reviewer_id).ok()?,
-                    rating: rating as u8,
-                    comment,
-                    verified: verified != 0,
-                    created_at: chrono::DateTime::parse_from_rfc3339(&created_at).ok()?
-                        .with_timezone(&chrono::Utc),
-                })
-            })
-        })
-        .collect();
+        let reviews = stmt.query_map(params![agent_id.to_string()], Self::read_review)?
+            .filter_map(|result| result.ok().flatten())
+            .collect();
+
+        Ok(reviews)
+    }
+
+    /// 获取所有评价 (用于构建评价关系图)
+    pub fn list_reviews(&self) -> Result<Vec<Review>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, transaction_id, agent_id, reviewer_id, rating, comment, verified, created_at
+             FROM reviews ORDER BY created_at"
+        )?;
+
+        let reviews = stmt.query_map([], Self::read_review)?
+            .filter_map(|result| result.ok().flatten())
+            .collect();
+
+        Ok(reviews)
+    }
+
+    /// 获取与 Agent 通过评价关系 (不分方向) 相连的所有 Agent 收到的评价
+    pub fn list_connected_reviews(&self, agent_id: &Uuid) -> Result<Vec<Review>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE component(id) AS (
+                SELECT ?1
+                UNION
+                SELECT CASE WHEN r.agent_id = c.id THEN r.reviewer_id ELSE r.agent_id END
+                FROM reviews r JOIN component c ON r.agent_id = c.id OR r.reviewer_id = c.id
+             )
+             SELECT id, transaction_id, agent_id, reviewer_id, rating, comment, verified, created_at
+             FROM reviews WHERE agent_id IN (SELECT id FROM component) ORDER BY created_at"
+        )?;
+
+        let reviews = stmt.query_map(params![agent_id.to_string()], Self::read_review)?
+            .filter_map(|result| result.ok().flatten())
+            .collect();
 
         Ok(reviews)
     }
+
+    /// 获取所有有信誉记录的 Agent ID
+    pub fn list_agent_ids(&self) -> Result<Vec<Uuid>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare("SELECT agent_id FROM reputation_records")?;
+        let ids = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|result| result.ok().and_then(|id| Uuid::parse_str(&id).ok()))
+            .collect();
+
+        Ok(ids)
+    }
+
+    /// 解析评价行, 数据损坏时返回 `None`
+    fn read_review(row: &rusqlite::Row) -> rusqlite::Result<Option<Review>> {
+        let id: String = row.get(0)?;
+        let transaction_id: String = row.get(1)?;
+        let agent_id: String = row.get(2)?;
+        let reviewer_id: String = row.get(3)?;
+        let rating: i32 = row.get(4)?;
+        let comment: String = row.get(5)?;
+        let verified: i32 = row.get(6)?;
+        let created_at: String = row.get(7)?;
+
+        Ok((|| {
+            Some(Review {
+                id: Uuid::parse_str(&id).ok()?,
+                transaction_id: Uuid::parse_str(&transaction_id).ok()?,
+                agent_id: Uuid::parse_str(&agent_id).ok()?,
+                reviewer_id: Uuid::parse_str(&reviewer_id).ok()?,
+                rating: rating as u8,
+                comment,
+                verified: verified != 0,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at).ok()?
+                    .with_timezone(&chrono::Utc),
+            })
+        })())
+    }
 }
@@ -102,12 +102,17 @@ fn test_reputation_calculator() {
 fn test_reputation_manager() -> Result<()> {
     let manager = ReputationManager::in_memory()?;
     let agent_id = Uuid::new_v4();
+    let client_id = Uuid::new_v4();
+    let transaction_id = Uuid::new_v4();
+
+    // 记录交易
+    manager.record_transaction(transaction_id, &agent_id, &client_id, true, 500)?;
 
     // 添加评价
     let review_id = manager.add_review(
-        Uuid::new_v4(),
+        transaction_id,
         agent_id,
-        Uuid::new_v4(),
+        client_id,
         5,
         "Excellent service!".to_string(),
     )?;
@@ -121,10 +126,11 @@ fn test_reputation_manager() -> Result<()> {
 
     // 验证评价
     manager.verify_review(&review_id, &agent_id)?;
+    assert!(manager.get_record(&agent_id)?.unwrap().reviews[0].verified);
 
-    // 记录交易
-    manager.record_transaction(&agent_id, true, 500)?;
-    manager.record_transaction(&agent_id, true, 600)?;
+    // 交易 ID 不能重复记录
+    assert!(manager.record_transaction(transaction_id, &agent_id, &client_id, true, 500).is_err());
+    manager.record_transaction(Uuid::new_v4(), &agent_id, &client_id, true, 600)?;
 
     let updated_record = manager.get_record(&agent_id)?.unwrap();
     assert_eq!(updated_record.total_transactions, 2);
@@ -158,7 +164,7 @@ fn test_anomaly_detection() -> Result<()> {
 
     // 记录交易 (100% 成功率)
     for _ in 0..15 {
-        manager.record_transaction(&agent_id, true, 500)?;
+        manager.record_transaction(Uuid::new_v4(), &agent_id, &Uuid::new_v4(), true, 500)?;
     }
 
     let anomalies = manager.detect_anomaly(&agent_id)?;
@@ -193,3 +199,255 @@ fn test_reputation_stats() {
     assert_eq!(stats.one_star_count, 1);
     assert_eq!(stats.total_reviews, 7);
 }
+
+/// 记录一笔交易并提交、验证客户对它的评价
+fn transaction_review(manager: &ReputationManager, agent_id: Uuid, client_id: Uuid, rating: u8) -> Result<Uuid> {
+    let transaction_id = Uuid::new_v4();
+    manager.record_transaction(transaction_id, &agent_id, &client_id, true, 500)?;
+    let review_id = manager.add_review(transaction_id, agent_id, client_id, rating, "Test".to_string())?;
+    manager.verify_review(&review_id, &agent_id)?;
+    Ok(review_id)
+}
+
+fn verified_review(agent_id: Uuid, reviewer_id: Uuid, rating: u8, days_ago: i64) -> Review {
+    let mut review = Review::new(Uuid::new_v4(), agent_id, reviewer_id, rating, "Test".to_string());
+    review.created_at = chrono::Utc::now() - chrono::Duration::days(days_ago);
+    review.verify();
+    review
+}
+
+#[test]
+fn test_bayesian_rating_and_time_decay() {
+    let calculator = ReputationCalculator::new();
+    let now = chrono::Utc::now();
+
+    // 两条满分评价不应胜过五十条 4.8 分的评价
+    let newcomer_id = Uuid::new_v4();
+    let mut newcomer = ReputationRecord::new(newcomer_id);
+    for _ in 0..2 {
+        newcomer.add_review(verified_review(newcomer_id, Uuid::new_v4(), 5, 0));
+    }
+    let veteran_id = Uuid::new_v4();
+    let mut veteran = ReputationRecord::new(veteran_id);
+    for i in 0..50 {
+        veteran.add_review(verified_review(veteran_id, Uuid::new_v4(), if i % 5 == 0 { 4 } else { 5 }, 0));
+    }
+    let context = ReviewerContext::default();
+    let newcomer_breakdown = calculator.breakdown(&newcomer, &context, now);
+    let veteran_breakdown = calculator.breakdown(&veteran, &context, now);
+    assert_eq!(newcomer_breakdown.raw_average_rating, 5.0);
+    assert!(newcomer_breakdown.bayesian_rating < veteran_breakdown.bayesian_rating);
+
+    // 两年前的差评几乎不再有影响
+    let agent_id = Uuid::new_v4();
+    let mut recent_only = ReputationRecord::new(agent_id);
+    let mut record = ReputationRecord::new(agent_id);
+    for _ in 0..5 {
+        let review = verified_review(agent_id, Uuid::new_v4(), 5, 1);
+        recent_only.add_review(review.clone());
+        record.add_review(review);
+        record.add_review(verified_review(agent_id, Uuid::new_v4(), 1, 730));
+    }
+    let breakdown = calculator.breakdown(&record, &context, now);
+    assert_eq!(breakdown.raw_average_rating, 3.0);
+    let recent_rating = calculator.breakdown(&recent_only, &context, now).bayesian_rating;
+    assert!(recent_rating - breakdown.bayesian_rating < 0.01);
+    assert!(breakdown.reviews.iter().filter(|r| r.rating == 1).all(|r| r.decay < 0.01));
+
+    // 分项贡献之和就是总分
+    let total: f64 = breakdown.components.iter().map(|c| c.contribution).sum();
+    assert!((total - breakdown.score).abs() < 1e-9);
+}
+
+#[test]
+fn test_reviewer_weighting_and_exclusions() {
+    let calculator = ReputationCalculator::new();
+    let now = chrono::Utc::now();
+    let agent_id = Uuid::new_v4();
+
+    // 大量未验证的好评 (无对应交易) 不影响评分
+    let mut record = ReputationRecord::new(agent_id);
+    for _ in 0..30 {
+        record.add_review(Review::new(Uuid::new_v4(), agent_id, Uuid::new_v4(), 5, "Sybil".to_string()));
+    }
+    let breakdown = calculator.breakdown(&record, &ReviewerContext::default(), now);
+    assert_eq!(breakdown.bayesian_rating, calculator.config().prior_rating);
+    assert!(breakdown.reviews.iter().all(|r| r.excluded == Some(ExclusionReason::Unverified)));
+
+    // 自评和同一交易的重复评价被排除
+    let mut record = ReputationRecord::new(agent_id);
+    record.add_review(verified_review(agent_id, agent_id, 5, 0));
+    let first = verified_review(agent_id, Uuid::new_v4(), 5, 0);
+    let mut duplicate = verified_review(agent_id, Uuid::new_v4(), 5, 0);
+    duplicate.transaction_id = first.transaction_id;
+    record.add_review(first);
+    record.add_review(duplicate);
+    let reasons: Vec<_> = calculator
+        .breakdown(&record, &ReviewerContext::default(), now)
+        .reviews
+        .iter()
+        .map(|r| r.excluded)
+        .collect();
+    assert!(reasons.contains(&Some(ExclusionReason::SelfReview)));
+    assert!(reasons.contains(&Some(ExclusionReason::DuplicateTransaction)));
+    assert_eq!(reasons.iter().filter(|r| r.is_none()).count(), 1);
+
+    // 低可信度评价者的差评影响更小
+    let (trusted, untrusted) = (Uuid::new_v4(), Uuid::new_v4());
+    let mut context = ReviewerContext::default();
+    context.trust.insert(trusted, 1.0);
+    context.trust.insert(untrusted, 0.1);
+    let mut by_trusted = ReputationRecord::new(agent_id);
+    by_trusted.add_review(verified_review(agent_id, trusted, 1, 0));
+    let mut by_untrusted = ReputationRecord::new(agent_id);
+    by_untrusted.add_review(verified_review(agent_id, untrusted, 1, 0));
+    assert!(
+        calculator.breakdown(&by_trusted, &context, now).bayesian_rating
+            < calculator.breakdown(&by_untrusted, &context, now).bayesian_rating
+    );
+}
+
+#[test]
+fn test_collusion_ring_detection() -> Result<()> {
+    let manager = ReputationManager::in_memory()?;
+    let ring: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+    let honest = Uuid::new_v4();
+
+    // 三个 Agent 互相刷好评
+    for (i, &agent) in ring.iter().enumerate() {
+        for (j, &reviewer) in ring.iter().enumerate() {
+            if i != j {
+                transaction_review(&manager, agent, reviewer, 5)?;
+            }
+        }
+    }
+    // 正常 Agent 收到客户评价, 也评价过团伙成员一次
+    for _ in 0..3 {
+        transaction_review(&manager, honest, Uuid::new_v4(), 4)?;
+    }
+    manager.add_review(Uuid::new_v4(), ring[0], honest, 3, "Ok".to_string())?;
+
+    let rings = manager.detect_collusion()?;
+    assert_eq!(rings.len(), 1);
+    let mut members = ring.clone();
+    members.sort();
+    assert_eq!(rings[0].members, members);
+    assert!(!rings[0].contains(&honest));
+
+    let breakdown = manager.score_breakdown(&ring[0])?.unwrap();
+    assert_eq!(
+        breakdown.reviews.iter().filter(|r| r.excluded == Some(ExclusionReason::Collusion)).count(),
+        2
+    );
+    assert_eq!(breakdown.bayesian_rating, 3.0);
+    // 形成团伙的评价之后, 所有成员的分数都已按团伙重新计算
+    for member in &ring {
+        let breakdown = manager.score_breakdown(member)?.unwrap();
+        assert_eq!(manager.get_record(member)?.unwrap().score, breakdown.score);
+    }
+
+    let anomalies = manager.detect_anomaly(&ring[1])?;
+    assert!(anomalies.iter().any(|a| a.contains("互评团伙")));
+    assert!(manager.detect_anomaly(&honest)?.iter().all(|a| !a.contains("互评团伙")));
+
+    assert_eq!(manager.recalculate_all()?, 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_one_way_review_cycle_is_not_collusion() {
+    let detector = CollusionDetector::new();
+
+    // 每个 Agent 只评价下一个 Agent, 收到的评价也只来自上一个 Agent
+    for size in [3, 5] {
+        let agents: Vec<Uuid> = (0..size).map(|_| Uuid::new_v4()).collect();
+        let reviews: Vec<Review> = (0..size)
+            .map(|i| verified_review(agents[(i + 1) % size], agents[i], 5, 1))
+            .collect();
+        assert!(detector.detect(&reviews).is_empty());
+    }
+
+    // 互评的两个 Agent 中, 主要收到外部评价的一方不算团伙成员
+    let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let mut reviews = vec![verified_review(a, b, 5, 1), verified_review(b, a, 5, 1), verified_review(c, a, 5, 1)];
+    reviews.extend((0..3).map(|_| verified_review(b, Uuid::new_v4(), 4, 1)));
+    reviews.push(verified_review(a, c, 5, 1));
+    let rings = detector.detect(&reviews);
+    assert_eq!(rings.len(), 1);
+    assert!(rings[0].contains(&a) && rings[0].contains(&c) && !rings[0].contains(&b));
+    assert_eq!(rings[0].internal_ratio, 0.5);
+}
+
+#[test]
+fn test_verify_review_requires_matching_transaction() -> Result<()> {
+    let manager = ReputationManager::in_memory()?;
+    let (agent_id, client_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+    // 没有对应交易
+    let review_id = manager.add_review(Uuid::new_v4(), agent_id, client_id, 5, "Fake".to_string())?;
+    assert!(manager.verify_review(&review_id, &agent_id).is_err());
+
+    // 交易存在, 但评价者不是这笔交易的客户
+    let transaction_id = Uuid::new_v4();
+    manager.record_transaction(transaction_id, &agent_id, &client_id, true, 500)?;
+    let review_id = manager.add_review(transaction_id, agent_id, Uuid::new_v4(), 5, "Fake".to_string())?;
+    assert!(manager.verify_review(&review_id, &agent_id).is_err());
+    assert_eq!(manager.get_record(&agent_id)?.unwrap().verified_review_count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_reviewer_is_not_trusted_above_low_score_reviewer() -> Result<()> {
+    let manager = ReputationManager::in_memory()?;
+    let agent_id = Uuid::new_v4();
+
+    // 有记录但分数为 0 的评价者
+    let low = Uuid::new_v4();
+    manager.get_or_create_record(&low)?;
+    transaction_review(&manager, agent_id, low, 1)?;
+    transaction_review(&manager, agent_id, Uuid::new_v4(), 1)?;
+
+    let breakdown = manager.score_breakdown(&agent_id)?.unwrap();
+    let trust: Vec<f64> = breakdown.reviews.iter().map(|r| r.trust).collect();
+    assert_eq!(trust, vec![0.25, 0.25]);
+
+    Ok(())
+}
+
+#[test]
+fn test_new_review_only_rescans_its_component() -> Result<()> {
+    let manager = ReputationManager::in_memory()?;
+    let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+    let (c, d) = (Uuid::new_v4(), Uuid::new_v4());
+    manager.add_review(Uuid::new_v4(), a, b, 5, "Great".to_string())?;
+    manager.add_review(Uuid::new_v4(), b, a, 5, "Great".to_string())?;
+    assert_eq!(manager.detect_collusion()?.len(), 1);
+
+    // 另一个连通部分里形成的团伙与已有团伙互不影响
+    manager.add_review(Uuid::new_v4(), c, d, 5, "Great".to_string())?;
+    manager.add_review(Uuid::new_v4(), d, c, 5, "Great".to_string())?;
+    let rings = manager.detect_collusion()?;
+    assert_eq!(rings.len(), 2);
+    assert!(rings.iter().any(|ring| ring.contains(&a) && ring.contains(&b)));
+    assert!(rings.iter().any(|ring| ring.contains(&c) && ring.contains(&d)));
+
+    let storage = ReputationStorage::in_memory()?;
+    for agent in [a, b, c] {
+        storage.save_record(&ReputationRecord::new(agent))?;
+    }
+    for (agent, reviewer) in [(a, b), (b, a), (c, d)] {
+        storage.save_review(&Review::new(Uuid::new_v4(), agent, reviewer, 5, "Great".to_string()))?;
+    }
+    let connected = storage.list_connected_reviews(&a)?;
+    assert_eq!(connected.len(), 2);
+    assert!(connected.iter().all(|r| r.agent_id == a || r.agent_id == b));
+
+    // 与全量检测的结果一致
+    assert_eq!(manager.recalculate_all()?, 4);
+    let full: Vec<_> = manager.detect_collusion()?.into_iter().map(|ring| ring.members).collect();
+    assert_eq!(full, rings.into_iter().map(|ring| ring.members).collect::<Vec<_>>());
+
+    Ok(())
+}
@@ -38,6 +38,11 @@ pub struct AgentConfig {
     pub max_tokens: u32,
     pub temperature: f32,
     pub metadata: serde_json::Value,
+    /// Upper bound on input tokens (system prompt, tools and history) sent per
+    /// request. Older history is compacted to stay under it; `None` disables
+    /// context management.
+    #[serde(default)]
+    pub context_budget: Option<u32>,
 }
 
 impl AgentConfig {
@@ -50,6 +55,7 @@ impl AgentConfig {
             max_tokens: 8192,
             temperature: 0.7,
             metadata: serde_json::Value::Null,
+            context_budget: None,
         }
     }
 
@@ -57,6 +63,11 @@ impl AgentConfig {
         self.model = model.into();
         self
     }
+
+    pub fn with_context_budget(mut self, tokens: u32) -> Self {
+        self.context_budget = Some(tokens);
+        self
+    }
 }
 
 #[async_trait]
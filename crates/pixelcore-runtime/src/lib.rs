@@ -35,5 +35,9 @@ pub use task_scheduler::{TaskScheduler, Task, TaskPriority, TaskStatus, Schedule
 pub use workflow::{
     Workflow, WorkflowStatus, WorkflowNode, WorkflowEdge, NodeType, EdgeCondition,
     WorkflowExecutor, ExecutionContext, ExecutionStatus,
-    ErrorHandlingStrategy, RetryPolicy, WorkflowPersistence, PersistenceError
+    ErrorHandlingStrategy, RetryPolicy, WorkflowPersistence, PersistenceError,
+    TaskHandler, FnTaskHandler, EchoTaskHandler, Expression, ExpressionError, JoinPolicy, BranchFailurePolicy,
+    RunJournal, JournalEntry, NodeTransition, WorkflowDefinition, WorkflowLoader,
+    DefinitionError, WorkflowValidator, ValidationIssue, WorkflowEvent, WorkflowEventKind,
+    WorkflowEventSink, NodeTimelineEntry, NodeRunStatus
 };
@@ -0,0 +1,759 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::edge::{EdgeCondition, WorkflowEdge};
+use super::error_handling::{ErrorHandlingStrategy, RetryPolicy};
+use super::node::{BranchFailurePolicy, JoinPolicy, NodeType, WorkflowNode};
+use super::workflow::{Workflow, WorkflowStatus};
+
+/// 工作流定义错误（带源文件位置）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionError {
+    pub message: String,
+    /// 出错行号（从 1 开始）
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl DefinitionError {
+    fn new(message: impl Into<String>, line: Option<usize>) -> Self {
+        Self { message: message.into(), line, column: None }
+    }
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "line {}, column {}: {}", line, column, self.message),
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for DefinitionError {}
+
+impl From<serde_yaml::Error> for DefinitionError {
+    fn from(e: serde_yaml::Error) -> Self {
+        let location = e.location();
+        Self {
+            message: e.to_string(),
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for DefinitionError {
+    fn from(e: serde_json::Error) -> Self {
+        let line = (e.line() > 0).then(|| e.line());
+        let column = (e.column() > 0).then(|| e.column());
+        Self { message: e.to_string(), line, column }
+    }
+}
+
+/// 节点类型（定义文件中的 `type` 字段）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Start,
+    End,
+    Task,
+    Decision,
+    Loop,
+    Parallel,
+    Join,
+}
+
+/// 错误处理策略定义，节点引用使用 key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ErrorHandlingDefinition {
+    #[default]
+    Fail,
+    Ignore,
+    Retry {
+        #[serde(default)]
+        policy: RetryPolicy,
+    },
+    Fallback {
+        fallback_node: String,
+    },
+    Compensate {
+        compensation_node: String,
+    },
+}
+
+impl ErrorHandlingDefinition {
+    fn is_fail(&self) -> bool {
+        *self == Self::Fail
+    }
+}
+
+/// 节点定义
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeDefinition {
+    /// 节点 key，在工作流内唯一，边和错误处理通过它引用节点
+    pub key: String,
+    /// key 是导出时由节点名称生成的，转换回 `Workflow` 时节点不设置 key
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generated_key: bool,
+    #[serde(rename = "type")]
+    pub kind: NodeKind,
+    /// 显示名称，缺省为 key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    /// task：任务名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// task：任务参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<serde_json::Value>,
+    /// decision / loop：条件表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// loop：最大迭代次数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_iterations: Option<usize>,
+    /// parallel：分支数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branches: Option<usize>,
+    /// parallel：单分支超时（毫秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_timeout_ms: Option<u64>,
+    /// parallel：分支失败处理
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_branch_failure: Option<BranchFailurePolicy>,
+    /// join：汇聚策略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<JoinPolicy>,
+    #[serde(default, skip_serializing_if = "ErrorHandlingDefinition::is_fail")]
+    pub error_handling: ErrorHandlingDefinition,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub metadata: serde_json::Value,
+}
+
+/// 边定义；`when`、`branch`、`parallel_branch` 至多设置一个，都不设置时为无条件边
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EdgeDefinition {
+    pub from: String,
+    pub to: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_branch: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub metadata: serde_json::Value,
+}
+
+/// 声明式工作流定义（YAML / JSON）
+///
+/// ```yaml
+/// name: order-pipeline
+/// variables:
+///   threshold: 50
+/// nodes:
+///   - key: start
+///     type: start
+///   - key: fetch
+///     type: task
+///     task: http_fetch
+///     params: { url: "${source_url}" }
+///     error_handling: { type: retry, policy: { max_retries: 3 } }
+///   - key: check
+///     type: decision
+///     condition: "count > threshold"
+///   - key: end
+///     type: end
+/// edges:
+///   - { from: start, to: fetch }
+///   - { from: fetch, to: check }
+///   - { from: check, to: end, branch: true }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, serde_json::Value>,
+    /// 导出时的状态和时间戳，手写定义可以省略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<WorkflowStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    pub nodes: Vec<NodeDefinition>,
+    #[serde(default)]
+    pub edges: Vec<EdgeDefinition>,
+}
+
+impl WorkflowDefinition {
+    pub fn from_yaml(source: &str) -> Result<Self, DefinitionError> {
+        Ok(serde_yaml::from_str(source)?)
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, DefinitionError> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, DefinitionError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, DefinitionError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 转换为 `Workflow`
+    ///
+    /// `source` 为定义的原文，用于给引用错误定位行号。
+    pub fn to_workflow(&self, source: Option<&str>) -> Result<Workflow, DefinitionError> {
+        let locate = |field: &str, value: &str| source.and_then(|s| locate_line(s, field, value));
+
+        let mut workflow = Workflow::new(self.name.clone(), self.description.clone());
+        if let Some(id) = self.id {
+            workflow.id = id;
+        }
+        workflow.variables = self.variables.clone().into_iter().collect();
+
+        // 先分配 ID，以便解析前向引用
+        let mut ids: HashMap<&str, Uuid> = HashMap::new();
+        for node in &self.nodes {
+            if ids.insert(node.key.as_str(), node.id.unwrap_or_else(Uuid::new_v4)).is_some() {
+                return Err(DefinitionError::new(
+                    format!("Duplicate node key '{}'", node.key),
+                    locate("key", &node.key),
+                ));
+            }
+        }
+        let resolve = |field: &str, key: &str| {
+            ids.get(key).copied().ok_or_else(|| {
+                DefinitionError::new(format!("Unknown node key '{}' in `{}`", key, field), locate(field, key))
+            })
+        };
+
+        for def in &self.nodes {
+            let node_type = node_type_from_definition(def)
+                .map_err(|message| DefinitionError::new(message, locate("key", &def.key)))?;
+            let error_handling = match &def.error_handling {
+                ErrorHandlingDefinition::Fail => ErrorHandlingStrategy::Fail,
+                ErrorHandlingDefinition::Ignore => ErrorHandlingStrategy::Ignore,
+                ErrorHandlingDefinition::Retry { policy } => ErrorHandlingStrategy::Retry { policy: policy.clone() },
+                ErrorHandlingDefinition::Fallback { fallback_node } => ErrorHandlingStrategy::Fallback {
+                    fallback_node: resolve("fallback_node", fallback_node)?,
+                },
+                ErrorHandlingDefinition::Compensate { compensation_node } => ErrorHandlingStrategy::Compensate {
+                    compensation_node: resolve("compensation_node", compensation_node)?,
+                },
+            };
+
+            let mut node = WorkflowNode::new(def.name.clone().unwrap_or_else(|| def.key.clone()), node_type)
+                .with_error_handling(error_handling);
+            if !def.generated_key {
+                node = node.with_key(def.key.clone());
+            }
+            node.id = ids[def.key.as_str()];
+            node.metadata = def.metadata.clone();
+            workflow.add_node(node);
+        }
+
+        for def in &self.edges {
+            let from = resolve("from", &def.from)?;
+            let to = resolve("to", &def.to)?;
+            let condition = match (&def.when, def.branch, def.parallel_branch) {
+                (None, None, None) => EdgeCondition::Always,
+                (Some(expr), None, None) => EdgeCondition::Expression { expr: expr.clone() },
+                (None, Some(value), None) => EdgeCondition::Branch { value },
+                (None, None, Some(index)) => EdgeCondition::ParallelBranch { index },
+                _ => {
+                    return Err(DefinitionError::new(
+                        format!(
+                            "Edge {} -> {} may set only one of `when`, `branch` and `parallel_branch`",
+                            def.from, def.to
+                        ),
+                        locate("from", &def.from),
+                    ))
+                }
+            };
+
+            let mut edge = WorkflowEdge::new(from, to, condition);
+            if let Some(id) = def.id {
+                edge.id = id;
+            }
+            edge.metadata = def.metadata.clone();
+            workflow.add_edge(edge);
+        }
+
+        // 添加节点和边会刷新 `updated_at`，所以最后再恢复
+        if let Some(status) = &self.status {
+            workflow.status = status.clone();
+        }
+        if let Some(created_at) = self.created_at {
+            workflow.created_at = created_at;
+        }
+        if let Some(updated_at) = self.updated_at {
+            workflow.updated_at = updated_at;
+        }
+
+        Ok(workflow)
+    }
+
+    /// 从 `Workflow` 生成定义
+    ///
+    /// 节点 key 取自 `WorkflowNode::key`，缺失时由节点名称生成并标记为
+    /// `generated_key`。节点和边的 ID 都会写出，因此 `from_workflow` → `to_workflow`
+    /// 保留所有 ID，没有 key 的节点转换回来后仍然没有 key。
+    pub fn from_workflow(workflow: &Workflow) -> Self {
+        // 保持稳定的输出顺序：开始节点在前，其余按 key / 名称排序
+        let mut nodes: Vec<&WorkflowNode> = workflow.nodes.values().collect();
+        nodes.sort_by(|a, b| {
+            let rank = |n: &WorkflowNode| !matches!(n.node_type, NodeType::Start);
+            (rank(a), a.key.as_ref().unwrap_or(&a.name), a.id)
+                .cmp(&(rank(b), b.key.as_ref().unwrap_or(&b.name), b.id))
+        });
+
+        let mut used = HashSet::new();
+        let mut keys: HashMap<Uuid, String> = HashMap::new();
+        for node in &nodes {
+            let base = node.key.clone().unwrap_or_else(|| slugify(&node.name));
+            let mut key = base.clone();
+            let mut suffix = 2;
+            while !used.insert(key.clone()) {
+                key = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            keys.insert(node.id, key);
+        }
+        let key_of = |id: &Uuid| keys.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+        let nodes = nodes
+            .into_iter()
+            .map(|node| {
+                let key = key_of(&node.id);
+                let mut def = NodeDefinition {
+                    name: (node.name != key).then(|| node.name.clone()),
+                    key,
+                    generated_key: node.key.is_none(),
+                    kind: NodeKind::Start,
+                    id: Some(node.id),
+                    task: None,
+                    params: None,
+                    condition: None,
+                    max_iterations: None,
+                    branches: None,
+                    branch_timeout_ms: None,
+                    on_branch_failure: None,
+                    policy: None,
+                    error_handling: match &node.error_handling {
+                        ErrorHandlingStrategy::Fail => ErrorHandlingDefinition::Fail,
+                        ErrorHandlingStrategy::Ignore => ErrorHandlingDefinition::Ignore,
+                        ErrorHandlingStrategy::Retry { policy } => {
+                            ErrorHandlingDefinition::Retry { policy: policy.clone() }
+                        }
+                        ErrorHandlingStrategy::Fallback { fallback_node } => {
+                            ErrorHandlingDefinition::Fallback { fallback_node: key_of(fallback_node) }
+                        }
+                        ErrorHandlingStrategy::Compensate { compensation_node } => {
+                            ErrorHandlingDefinition::Compensate { compensation_node: key_of(compensation_node) }
+                        }
+                    },
+                    metadata: node.metadata.clone(),
+                };
+                match &node.node_type {
+                    NodeType::Start => def.kind = NodeKind::Start,
+                    NodeType::End => def.kind = NodeKind::End,
+                    NodeType::Task { task_name, params } => {
+                        def.kind = NodeKind::Task;
+                        def.task = Some(task_name.clone());
+                        def.params = (!params.is_null()).then(|| params.clone());
+                    }
+                    NodeType::Decision { condition } => {
+                        def.kind = NodeKind::Decision;
+                        def.condition = Some(condition.clone());
+                    }
+                    NodeType::Loop { condition, max_iterations } => {
+                        def.kind = NodeKind::Loop;
+                        def.condition = Some(condition.clone());
+                        def.max_iterations = Some(*max_iterations);
+                    }
+                    NodeType::Parallel { branches, branch_timeout_ms, on_branch_failure } => {
+                        def.kind = NodeKind::Parallel;
+                        def.branches = Some(*branches);
+                        def.branch_timeout_ms = *branch_timeout_ms;
+                        def.on_branch_failure = (*on_branch_failure != BranchFailurePolicy::default())
+                            .then_some(*on_branch_failure);
+                    }
+                    NodeType::Join { policy } => {
+                        def.kind = NodeKind::Join;
+                        def.policy = (*policy != JoinPolicy::default()).then_some(*policy);
+                    }
+                }
+                def
+            })
+            .collect();
+
+        let edges = workflow
+            .edges
+            .iter()
+            .map(|edge| {
+                let mut def = EdgeDefinition {
+                    from: key_of(&edge.from),
+                    to: key_of(&edge.to),
+                    when: None,
+                    branch: None,
+                    parallel_branch: None,
+                    id: Some(edge.id),
+                    metadata: edge.metadata.clone(),
+                };
+                match &edge.condition {
+                    EdgeCondition::Always => {}
+                    EdgeCondition::Expression { expr } => def.when = Some(expr.clone()),
+                    EdgeCondition::Branch { value } => def.branch = Some(*value),
+                    EdgeCondition::ParallelBranch { index } => def.parallel_branch = Some(*index),
+                }
+                def
+            })
+            .collect();
+
+        Self {
+            id: Some(workflow.id),
+            name: workflow.name.clone(),
+            description: workflow.description.clone(),
+            variables: workflow.variables.clone().into_iter().collect(),
+            status: Some(workflow.status.clone()),
+            created_at: Some(workflow.created_at),
+            updated_at: Some(workflow.updated_at),
+            nodes,
+            edges,
+        }
+    }
+}
+
+fn node_type_from_definition(def: &NodeDefinition) -> Result<NodeType, String> {
+    let require = |value: Option<&str>, field: &str| {
+        value
+            .map(str::to_string)
+            .ok_or_else(|| format!("Node '{}' of type {:?} requires `{}`", def.key, def.kind, field))
+    };
+
+    // 检查不属于该类型的字段
+    let present: [(&str, bool); 8] = [
+        ("task", def.task.is_some()),
+        ("params", def.params.is_some()),
+        ("condition", def.condition.is_some()),
+        ("max_iterations", def.max_iterations.is_some()),
+        ("branches", def.branches.is_some()),
+        ("branch_timeout_ms", def.branch_timeout_ms.is_some()),
+        ("on_branch_failure", def.on_branch_failure.is_some()),
+        ("policy", def.policy.is_some()),
+    ];
+    let allowed: &[&str] = match def.kind {
+        NodeKind::Start | NodeKind::End => &[],
+        NodeKind::Task => &["task", "params"],
+        NodeKind::Decision => &["condition"],
+        NodeKind::Loop => &["condition", "max_iterations"],
+        NodeKind::Parallel => &["branches", "branch_timeout_ms", "on_branch_failure"],
+        NodeKind::Join => &["policy"],
+    };
+    if let Some((field, _)) = present.iter().find(|(field, set)| *set && !allowed.contains(field)) {
+        return Err(format!("Field `{}` is not valid for node '{}' of type {:?}", field, def.key, def.kind));
+    }
+
+    Ok(match def.kind {
+        NodeKind::Start => NodeType::Start,
+        NodeKind::End => NodeType::End,
+        NodeKind::Task => NodeType::Task {
+            task_name: require(def.task.as_deref(), "task")?,
+            params: def.params.clone().unwrap_or(serde_json::Value::Null),
+        },
+        NodeKind::Decision => NodeType::Decision {
+            condition: require(def.condition.as_deref(), "condition")?,
+        },
+        NodeKind::Loop => NodeType::Loop {
+            condition: require(def.condition.as_deref(), "condition")?,
+            max_iterations: def
+                .max_iterations
+                .ok_or_else(|| format!("Node '{}' of type Loop requires `max_iterations`", def.key))?,
+        },
+        NodeKind::Parallel => NodeType::Parallel {
+            branches: def
+                .branches
+                .ok_or_else(|| format!("Node '{}' of type Parallel requires `branches`", def.key))?,
+            branch_timeout_ms: def.branch_timeout_ms,
+            on_branch_failure: def.on_branch_failure.unwrap_or_default(),
+        },
+        NodeKind::Join => NodeType::Join {
+            policy: def.policy.unwrap_or_default(),
+        },
+    })
+}
+
+fn slugify(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect::<String>()
+        .split('_')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    if slug.is_empty() { "node".to_string() } else { slug }
+}
+
+/// 在源文本中查找 `field: value`（YAML）或 `"field": "value"`（JSON）所在行
+pub(crate) fn locate_line(source: &str, field: &str, value: &str) -> Option<usize> {
+    let unquote = |s: &str| s.trim().trim_end_matches(',').trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+    source.lines().enumerate().find_map(|(index, line)| {
+        // 同一行可能有多个字段（YAML flow mapping / JSON 对象）
+        let found = line
+            .split(['{', '}', ','])
+            .map(|part| part.trim().trim_start_matches("- ").trim())
+            .any(|part| match part.split_once(':') {
+                Some((k, v)) => unquote(k) == field && unquote(v) == value,
+                None => false,
+            });
+        found.then_some(index + 1)
+    })
+}
+
+/// 工作流定义加载器
+pub struct WorkflowLoader;
+
+impl WorkflowLoader {
+    /// 从 YAML 加载工作流
+    pub fn from_yaml(source: &str) -> Result<Workflow, DefinitionError> {
+        WorkflowDefinition::from_yaml(source)?.to_workflow(Some(source))
+    }
+
+    /// 从 JSON 加载工作流
+    pub fn from_json(source: &str) -> Result<Workflow, DefinitionError> {
+        WorkflowDefinition::from_json(source)?.to_workflow(Some(source))
+    }
+
+    /// 从文件加载工作流，按扩展名选择格式（`.json` 为 JSON，其余按 YAML 解析）
+    pub async fn load(path: impl AsRef<Path>) -> Result<Workflow, DefinitionError> {
+        let path = path.as_ref();
+        let source = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| DefinitionError::new(format!("Failed to read {}: {}", path.display(), e), None))?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&source)
+        } else {
+            Self::from_yaml(&source)
+        }
+    }
+
+    pub fn to_yaml(workflow: &Workflow) -> Result<String, DefinitionError> {
+        WorkflowDefinition::from_workflow(workflow).to_yaml()
+    }
+
+    pub fn to_json(workflow: &Workflow) -> Result<String, DefinitionError> {
+        WorkflowDefinition::from_workflow(workflow).to_json()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIPELINE: &str = r#"
+name: order-pipeline
+description: Charge, ship and notify
+variables:
+  threshold: 50
+nodes:
+  - key: start
+    type: start
+  - key: charge
+    type: task
+    task: charge_card
+    params: { amount: "${amount}" }
+    error_handling:
+      type: retry
+      policy: { max_retries: 2, retry_delay_ms: 10 }
+  - key: reserve
+    type: task
+    task: reserve_stock
+    error_handling: { type: compensate, compensation_node: release }
+  - key: release
+    type: task
+    task: release_stock
+  - key: check
+    type: decision
+    condition: "amount > threshold"
+  - key: fan
+    type: parallel
+    branches: 2
+    branch_timeout_ms: 5000
+    on_branch_failure: let_finish
+  - key: email
+    type: task
+    task: send_email
+    error_handling: { type: fallback, fallback_node: sms }
+  - key: sms
+    type: task
+    task: send_sms
+  - key: join
+    type: join
+    policy: { type: n_of_m, n: 1 }
+  - key: poll
+    type: loop
+    condition: "true"
+    max_iterations: 3
+  - key: end
+    type: end
+edges:
+  - { from: start, to: charge }
+  - { from: charge, to: reserve }
+  - { from: reserve, to: check }
+  - { from: check, to: fan, branch: true }
+  - { from: check, to: end, branch: false }
+  - { from: fan, to: email, parallel_branch: 0 }
+  - { from: fan, to: poll, parallel_branch: 1 }
+  - { from: email, to: join }
+  - { from: poll, to: join, when: "exit" }
+  - { from: join, to: end }
+"#;
+
+    #[test]
+    fn test_load_yaml_covers_all_node_types() {
+        let workflow = WorkflowLoader::from_yaml(PIPELINE).unwrap();
+        assert_eq!(workflow.name, "order-pipeline");
+        assert_eq!(workflow.nodes.len(), 11);
+        assert_eq!(workflow.edges.len(), 10);
+        assert!(workflow.validate().is_ok());
+
+        let reserve = workflow.find_node_by_key("reserve").unwrap();
+        let release = workflow.find_node_by_key("release").unwrap();
+        assert_eq!(
+            reserve.error_handling,
+            ErrorHandlingStrategy::Compensate { compensation_node: release.id }
+        );
+
+        let charge = workflow.find_node_by_key("charge").unwrap();
+        match &charge.error_handling {
+            ErrorHandlingStrategy::Retry { policy } => {
+                assert_eq!(policy.max_retries, 2);
+                assert_eq!(policy.backoff_multiplier, 2.0);
+            }
+            other => panic!("unexpected strategy {:?}", other),
+        }
+
+        let join = workflow.find_node_by_key("join").unwrap();
+        assert_eq!(join.node_type, NodeType::Join { policy: JoinPolicy::NOfM { n: 1 } });
+    }
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        let definition = WorkflowDefinition::from_yaml(PIPELINE).unwrap();
+        let mut workflow = definition.to_workflow(None).unwrap();
+        workflow.status = WorkflowStatus::Paused;
+        workflow.created_at = Utc::now() - chrono::Duration::days(3);
+
+        // Workflow -> 定义 -> Workflow 保留所有 ID 和结构
+        let yaml = WorkflowLoader::to_yaml(&workflow).unwrap();
+        let reloaded = WorkflowLoader::from_yaml(&yaml).unwrap();
+        assert_eq!(reloaded.id, workflow.id);
+        assert_eq!(reloaded.variables, workflow.variables);
+        assert_eq!(reloaded.status, workflow.status);
+        assert_eq!(reloaded.created_at, workflow.created_at);
+        assert_eq!(reloaded.updated_at, workflow.updated_at);
+        for (id, node) in &workflow.nodes {
+            let other = &reloaded.nodes[id];
+            assert_eq!(other.key, node.key);
+            assert_eq!(other.name, node.name);
+            assert_eq!(other.node_type, node.node_type);
+            assert_eq!(other.error_handling, node.error_handling);
+        }
+        let edges: Vec<_> = workflow.edges.iter().map(|e| (e.id, e.from, e.to, e.condition.clone())).collect();
+        let reloaded_edges: Vec<_> = reloaded.edges.iter().map(|e| (e.id, e.from, e.to, e.condition.clone())).collect();
+        assert_eq!(edges, reloaded_edges);
+
+        // 定义 -> Workflow -> 定义 稳定
+        let again = WorkflowDefinition::from_workflow(&reloaded);
+        assert_eq!(again, WorkflowDefinition::from_workflow(&workflow));
+
+        // JSON 同样可以往返
+        let json = WorkflowLoader::to_json(&workflow).unwrap();
+        assert_eq!(WorkflowDefinition::from_json(&json).unwrap(), again);
+    }
+
+    #[test]
+    fn test_generates_keys_for_code_built_workflows() {
+        let mut workflow = Workflow::new("Built in Rust", "");
+        let start = workflow.add_node(WorkflowNode::start("Start"));
+        let a = workflow.add_node(WorkflowNode::task("Do Work", "work", serde_json::json!({})));
+        let b = workflow.add_node(WorkflowNode::task("Do Work", "work", serde_json::json!({})));
+        workflow.connect(start, a);
+        workflow.connect(a, b);
+
+        let definition = WorkflowDefinition::from_workflow(&workflow);
+        let keys: Vec<_> = definition.nodes.iter().map(|n| n.key.as_str()).collect();
+        assert_eq!(keys[0], "start");
+        assert!(keys.contains(&"do_work") && keys.contains(&"do_work_2"));
+        assert!(definition.nodes.iter().all(|n| n.generated_key));
+
+        // 往返后节点仍然没有 key，其余内容不变
+        let reloaded = WorkflowLoader::from_yaml(&WorkflowLoader::to_yaml(&workflow).unwrap()).unwrap();
+        for (id, node) in &workflow.nodes {
+            let other = &reloaded.nodes[id];
+            assert_eq!(other.key, None);
+            assert_eq!(other.name, node.name);
+            assert_eq!(other.node_type, node.node_type);
+        }
+        let edges = |w: &Workflow| w.edges.iter().map(|e| (e.id, e.from, e.to)).collect::<Vec<_>>();
+        assert_eq!(edges(&reloaded), edges(&workflow));
+        assert_eq!(WorkflowDefinition::from_workflow(&reloaded), definition);
+    }
+
+    #[test]
+    fn test_schema_errors_report_line() {
+        let source = "name: broken\nnodes:\n  - key: start\n    type: start\n  - key: go\n    type: teleport\n";
+        let err = WorkflowLoader::from_yaml(source).unwrap_err();
+        assert_eq!(err.line, Some(6));
+
+        let source = "name: broken\nnodes:\n  - key: start\n    type: start\n    colour: red\n";
+        let err = WorkflowLoader::from_yaml(source).unwrap_err();
+        assert_eq!(err.line, Some(5));
+        assert!(err.message.contains("colour"));
+    }
+
+    #[test]
+    fn test_reference_errors_report_line() {
+        let source = "name: broken\nnodes:\n  - key: start\n    type: start\n  - key: end\n    type: end\nedges:\n  - from: start\n    to: finish\n";
+        let err = WorkflowLoader::from_yaml(source).unwrap_err();
+        assert_eq!(err.line, Some(9));
+        assert!(err.message.contains("finish"));
+
+        let source = "name: broken\nnodes:\n  - key: start\n    type: start\n  - key: work\n    type: task\n";
+        let err = WorkflowLoader::from_yaml(source).unwrap_err();
+        assert_eq!(err.line, Some(5));
+        assert!(err.message.contains("`task`"));
+
+        let source = r#"{
+  "name": "broken",
+  "nodes": [
+    { "key": "start", "type": "start", "condition": "x" }
+  ]
+}"#;
+        let err = WorkflowLoader::from_json(source).unwrap_err();
+        assert_eq!(err.line, Some(4));
+    }
+}
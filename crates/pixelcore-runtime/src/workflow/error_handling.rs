@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 /// 重试策略
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RetryPolicy {
     /// 最大重试次数
     pub max_retries: usize,
@@ -54,7 +56,7 @@ impl RetryPolicy {
 }
 
 /// 错误处理策略
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ErrorHandlingStrategy {
     /// 失败时停止整个工作流
@@ -66,10 +68,29 @@ pub enum ErrorHandlingStrategy {
         policy: RetryPolicy,
     },
     /// 跳转到指定节点
+    ///
+    /// 失败节点的错误记录在 `ExecutionContext::node_errors` 和 `last_error` 变量中
     Fallback {
         /// 目标节点 ID
-        fallback_node: String,
+        fallback_node: Uuid,
     },
+    /// 补偿（Saga）：节点成功后登记补偿路径，
+    /// 若后续节点导致工作流失败，按完成顺序的逆序执行补偿路径
+    Compensate {
+        /// 补偿路径的起始节点 ID
+        compensation_node: Uuid,
+    },
+}
+
+impl ErrorHandlingStrategy {
+    /// 策略引用的节点（fallback 或补偿路径起点）
+    pub fn target_node(&self) -> Option<Uuid> {
+        match self {
+            Self::Fallback { fallback_node } => Some(*fallback_node),
+            Self::Compensate { compensation_node } => Some(*compensation_node),
+            _ => None,
+        }
+    }
 }
 
 impl Default for ErrorHandlingStrategy {
@@ -128,5 +149,10 @@ mod tests {
         if let ErrorHandlingStrategy::Retry { policy } = retry {
             assert_eq!(policy.max_retries, 5);
         }
+
+        let target = Uuid::new_v4();
+        let fallback = ErrorHandlingStrategy::Fallback { fallback_node: target };
+        assert_eq!(fallback.target_node(), Some(target));
+        assert_eq!(ErrorHandlingStrategy::Fail.target_node(), None);
     }
 }
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::event::{Event, EventBus, EventKind};
+use crate::streaming::{ResponseChunk, StreamingSender};
+
+/// 工作流事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowEventKind {
+    RunStarted,
+    RunCompleted,
+    RunFailed,
+    NodeStarted,
+    NodeCompleted,
+    NodeFailed,
+    /// 任务失败后即将重试
+    NodeRetried,
+    /// 恢复执行时跳过已完成的节点
+    NodeSkipped,
+}
+
+impl WorkflowEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RunStarted => "run_started",
+            Self::RunCompleted => "run_completed",
+            Self::RunFailed => "run_failed",
+            Self::NodeStarted => "node_started",
+            Self::NodeCompleted => "node_completed",
+            Self::NodeFailed => "node_failed",
+            Self::NodeRetried => "node_retried",
+            Self::NodeSkipped => "node_skipped",
+        }
+    }
+}
+
+/// 工作流执行事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowEvent {
+    pub kind: WorkflowEventKind,
+    pub execution_id: Uuid,
+    pub workflow_id: Uuid,
+    pub node_id: Option<Uuid>,
+    pub node_name: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    /// 当前尝试次数（任务节点，从 1 开始）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl WorkflowEvent {
+    pub fn new(kind: WorkflowEventKind, execution_id: Uuid, workflow_id: Uuid) -> Self {
+        Self {
+            kind,
+            execution_id,
+            workflow_id,
+            node_id: None,
+            node_name: None,
+            timestamp: Utc::now(),
+            attempt: None,
+            duration_ms: None,
+            output: None,
+            error: None,
+        }
+    }
+
+    pub fn with_node(mut self, node_id: Uuid, node_name: impl Into<String>) -> Self {
+        self.node_id = Some(node_id);
+        self.node_name = Some(node_name.into());
+        self
+    }
+
+    /// 事件的简短描述
+    pub fn summary(&self) -> String {
+        let subject = match &self.node_name {
+            Some(name) => format!("{} '{}'", self.kind.as_str(), name),
+            None => self.kind.as_str().to_string(),
+        };
+        match (&self.error, self.attempt) {
+            (Some(error), Some(attempt)) => format!("{} (attempt {}): {}", subject, attempt, error),
+            (Some(error), None) => format!("{}: {}", subject, error),
+            _ => subject,
+        }
+    }
+}
+
+/// 工作流事件接收方
+///
+/// 通过 `WorkflowExecutor::with_event_sink` 挂载。`emit` 在执行路径上同步调用，
+/// 实现不应阻塞；发送失败（例如没有订阅者）直接忽略。
+pub trait WorkflowEventSink: Send + Sync {
+    fn emit(&self, event: &WorkflowEvent);
+}
+
+/// 以 `EventKind::Custom("workflow.<kind>")` 发布到事件总线，`source` 为 `workflow:<workflow_id>`
+impl WorkflowEventSink for EventBus {
+    fn emit(&self, event: &WorkflowEvent) {
+        let payload = serde_json::to_value(event).unwrap_or(serde_json::Value::Null);
+        let _ = self.publish(Event::new(
+            EventKind::Custom(format!("workflow.{}", event.kind.as_str())),
+            format!("workflow:{}", event.workflow_id),
+            payload,
+        ));
+    }
+}
+
+/// 以 `ResponseChunk` 发送：失败为 `Error`，其余为 `Status`，运行结束时发送 `Done`；
+/// 完整事件放在 `metadata` 中
+impl WorkflowEventSink for StreamingSender {
+    fn emit(&self, event: &WorkflowEvent) {
+        let mut chunk = match event.kind {
+            WorkflowEventKind::NodeFailed | WorkflowEventKind::RunFailed => ResponseChunk::error(event.summary()),
+            WorkflowEventKind::RunCompleted => ResponseChunk::done(),
+            _ => ResponseChunk::status(event.summary()),
+        };
+        chunk.metadata = serde_json::to_value(event).ok();
+        let _ = self.send_chunk(chunk);
+        if event.kind == WorkflowEventKind::RunFailed {
+            let _ = self.send_done();
+        }
+    }
+}
@@ -1,15 +1,24 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex as StdMutex};
 use tokio::sync::RwLock;
-use tokio::time::{sleep, Duration};
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout, Duration};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use super::workflow::Workflow;
-use super::node::{NodeType, WorkflowNode};
+use super::node::{BranchFailurePolicy, JoinPolicy, NodeType, WorkflowNode};
 use super::edge::{EdgeCondition, WorkflowEdge};
 use super::error_handling::{ErrorHandlingStrategy, RetryPolicy};
+use super::expression::Expression;
+use super::journal::{JournalEntry, NodeTransition, RunJournal};
+use super::task_handler::TaskHandler;
+use super::template::render_params;
+use super::events::{WorkflowEvent, WorkflowEventKind, WorkflowEventSink};
+use super::timeline::{NodeRunStatus, NodeTimelineEntry};
+use crate::event::EventBus;
+use crate::streaming::StreamingSender;
 
 /// 执行状态
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -32,6 +41,21 @@ pub struct ExecutionContext {
     pub started_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
     pub error: Option<String>,
+    /// 失败节点的错误信息（用于 fallback 等错误处理）
+    #[serde(default)]
+    pub node_errors: HashMap<Uuid, String>,
+    /// 已完成且登记了补偿路径的节点（按完成顺序）
+    #[serde(default)]
+    pub compensation_stack: Vec<Uuid>,
+    /// 已执行补偿的节点
+    #[serde(default)]
+    pub compensated: Vec<Uuid>,
+    /// 任务节点的完成次数（恢复执行时据此跳过已完成的节点）
+    #[serde(default)]
+    pub completed_nodes: HashMap<Uuid, u32>,
+    /// 节点执行时间线（按开始顺序）
+    #[serde(default)]
+    pub timeline: Vec<NodeTimelineEntry>,
 }
 
 impl ExecutionContext {
@@ -46,6 +70,11 @@ impl ExecutionContext {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            node_errors: HashMap::new(),
+            compensation_stack: Vec::new(),
+            compensated: Vec::new(),
+            completed_nodes: HashMap::new(),
+            timeline: Vec::new(),
         }
     }
 
@@ -64,23 +93,101 @@ impl ExecutionContext {
     pub fn get_node_result(&self, node_id: &Uuid) -> Option<&serde_json::Value> {
         self.node_results.get(node_id)
     }
+
+    /// 记录节点错误，并写入 `last_error` 变量供后续节点引用
+    pub fn set_node_error(&mut self, node_id: Uuid, error: impl Into<String>) {
+        let error = error.into();
+        self.variables.insert(
+            "last_error".to_string(),
+            serde_json::json!({ "node_id": node_id.to_string(), "message": error }),
+        );
+        self.node_errors.insert(node_id, error);
+    }
+
+    pub fn get_node_error(&self, node_id: &Uuid) -> Option<&String> {
+        self.node_errors.get(node_id)
+    }
+
+    /// 节点的所有时间线条目
+    pub fn node_timeline(&self, node_id: &Uuid) -> Vec<&NodeTimelineEntry> {
+        self.timeline.iter().filter(|e| e.node_id == *node_id).collect()
+    }
 }
 
 /// 工作流执行器
 pub struct WorkflowExecutor {
     workflow: Arc<RwLock<Workflow>>,
     context: Arc<RwLock<ExecutionContext>>,
+    task_handlers: Arc<HashMap<String, Arc<dyn TaskHandler>>>,
+    /// 没有匹配处理器的任务使用的处理器；为空时这类任务直接失败
+    fallback_handler: Option<Arc<dyn TaskHandler>>,
+    journal: Option<Arc<dyn RunJournal>>,
+    /// 本次运行中各任务节点已进入的次数，用于与 `completed_nodes` 对比实现重放
+    replayed: Arc<StdMutex<HashMap<Uuid, u32>>>,
+    event_sinks: Arc<Vec<Arc<dyn WorkflowEventSink>>>,
 }
 
 impl WorkflowExecutor {
     pub fn new(workflow: Workflow) -> Self {
-        let context = ExecutionContext::new(workflow.id);
+        let mut context = ExecutionContext::new(workflow.id);
+        context.variables = workflow.variables.clone();
         Self {
             workflow: Arc::new(RwLock::new(workflow)),
             context: Arc::new(RwLock::new(context)),
+            task_handlers: Arc::new(HashMap::new()),
+            fallback_handler: None,
+            journal: None,
+            replayed: Arc::new(StdMutex::new(HashMap::new())),
+            event_sinks: Arc::new(Vec::new()),
         }
     }
 
+    /// 挂载运行日志，每次节点状态变化都会自动写入
+    pub fn with_journal(mut self, journal: Arc<dyn RunJournal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// 挂载事件接收方，运行和节点状态变化时发送 `WorkflowEvent`
+    pub fn with_event_sink(mut self, sink: Arc<dyn WorkflowEventSink>) -> Self {
+        Arc::make_mut(&mut self.event_sinks).push(sink);
+        self
+    }
+
+    /// 将执行事件发布到事件总线
+    pub fn with_event_bus(self, bus: EventBus) -> Self {
+        self.with_event_sink(Arc::new(bus))
+    }
+
+    /// 将执行事件作为 `ResponseChunk` 发送
+    pub fn with_streaming(self, sender: StreamingSender) -> Self {
+        self.with_event_sink(Arc::new(sender))
+    }
+
+    /// 注册任务处理器（按 `task_name` 匹配任务节点）
+    pub fn register_task_handler(&mut self, task_name: impl Into<String>, handler: Arc<dyn TaskHandler>) {
+        Arc::make_mut(&mut self.task_handlers).insert(task_name.into(), handler);
+    }
+
+    pub fn with_task_handler(mut self, task_name: impl Into<String>, handler: Arc<dyn TaskHandler>) -> Self {
+        self.register_task_handler(task_name, handler);
+        self
+    }
+
+    /// 为所有未注册的任务设置处理器（例如演示时使用 `EchoTaskHandler`）
+    ///
+    /// 默认没有兜底处理器，未注册的任务会以 `no handler registered` 失败，
+    /// 避免任务名拼写错误或漏注册被当作成功。
+    pub fn with_fallback_handler(mut self, handler: Arc<dyn TaskHandler>) -> Self {
+        self.fallback_handler = Some(handler);
+        self
+    }
+
+    /// 已注册的任务名称
+    pub fn task_handler_names(&self) -> Vec<&str> {
+        self.task_handlers.keys().map(|s| s.as_str()).collect()
+    }
+
     /// 开始执行工作流
     pub async fn execute(&self) -> Result<ExecutionContext, String> {
         // 验证工作流
@@ -95,11 +202,152 @@ impl WorkflowExecutor {
         drop(workflow);
 
         // 从开始节点执行
-        self.execute_from_node(start_id).await?;
+        self.run_from(start_id).await
+    }
+
+    /// 从运行日志恢复执行
+    ///
+    /// 从开始节点重放整个工作流：`completed_nodes` 中记录的已完成任务不会再次
+    /// 执行，而是复用已保存的结果，因此任务的副作用不会在崩溃恢复后重复发生。
+    pub async fn recover(&self) -> Result<ExecutionContext, String> {
+        {
+            let mut context = self.context.write().await;
+            context.status = ExecutionStatus::Running;
+            context.error = None;
+        }
+        self.replayed.lock().unwrap().clear();
+        self.execute().await
+    }
+
+    /// 从指定节点运行，并在结束时写入日志
+    async fn run_from(&self, node_id: Uuid) -> Result<ExecutionContext, String> {
+        if let Some(journal) = &self.journal {
+            let workflow = self.workflow.read().await;
+            let context = self.context.read().await;
+            journal.begin(&workflow, &context).await?;
+        }
+
+        // 上次运行中未结束的节点
+        for entry in self.context.write().await.timeline.iter_mut() {
+            if entry.status == NodeRunStatus::Running {
+                entry.finish(NodeRunStatus::Interrupted);
+            }
+        }
+        let started = self.event(WorkflowEventKind::RunStarted).await;
+        self.emit(started);
+
+        let result = self.execute_from_node(node_id).await;
+        if let Err(e) = &result {
+            self.fail_and_compensate(e).await;
+        }
+
+        let context = self.context.read().await.clone();
+        if let Some(journal) = &self.journal {
+            journal.finish(&context).await?;
+        }
+
+        let mut finished = match &result {
+            Ok(()) => self.event(WorkflowEventKind::RunCompleted).await,
+            Err(_) => self.event(WorkflowEventKind::RunFailed).await,
+        };
+        finished.duration_ms = Some((Utc::now() - context.started_at).num_milliseconds().max(0) as u64);
+        finished.error = context.error.clone();
+        self.emit(finished);
+
+        result.map(|()| context)
+    }
 
-        // 返回执行上下文
+    /// 写入节点状态变化
+    async fn journal_transition(
+        &self,
+        node_id: Uuid,
+        transition: NodeTransition,
+        error: Option<&str>,
+    ) -> Result<(), String> {
+        let Some(journal) = &self.journal else { return Ok(()) };
         let context = self.context.read().await;
-        Ok(context.clone())
+        let mut entry = JournalEntry::new(context.execution_id, node_id, transition);
+        if let Some(error) = error {
+            entry = entry.with_error(error);
+        }
+        journal.record(&entry, &context).await
+    }
+
+    fn emit(&self, event: WorkflowEvent) {
+        for sink in self.event_sinks.iter() {
+            sink.emit(&event);
+        }
+    }
+
+    async fn event(&self, kind: WorkflowEventKind) -> WorkflowEvent {
+        let context = self.context.read().await;
+        WorkflowEvent::new(kind, context.execution_id, context.workflow_id)
+    }
+
+    /// 记录节点开始，返回时间线条目的下标
+    async fn node_started(&self, node: &WorkflowNode) -> usize {
+        let entry = {
+            let mut context = self.context.write().await;
+            context.timeline.push(NodeTimelineEntry::new(node.id, node.name.clone()));
+            context.timeline.len() - 1
+        };
+        let event = self.event(WorkflowEventKind::NodeStarted).await.with_node(node.id, node.name.clone());
+        self.emit(event);
+        entry
+    }
+
+    /// 记录节点结束
+    async fn node_finished(&self, entry: usize, result: Result<serde_json::Value, &str>) {
+        let (kind, snapshot) = {
+            let mut context = self.context.write().await;
+            let item = &mut context.timeline[entry];
+            let kind = match result {
+                Ok(output) => {
+                    item.output = (!output.is_null()).then_some(output);
+                    item.finish(NodeRunStatus::Completed);
+                    WorkflowEventKind::NodeCompleted
+                }
+                Err(error) => {
+                    item.error = Some(error.to_string());
+                    item.finish(NodeRunStatus::Failed);
+                    WorkflowEventKind::NodeFailed
+                }
+            };
+            (kind, item.clone())
+        };
+
+        let mut event = self.event(kind).await.with_node(snapshot.node_id, snapshot.node_name);
+        event.attempt = (snapshot.attempts > 0).then_some(snapshot.attempts);
+        event.duration_ms = snapshot.duration_ms;
+        event.output = snapshot.output;
+        event.error = snapshot.error;
+        self.emit(event);
+    }
+
+    /// 记录恢复执行时跳过的节点
+    async fn node_skipped(&self, node: &WorkflowNode) {
+        {
+            let mut context = self.context.write().await;
+            let mut item = NodeTimelineEntry::new(node.id, node.name.clone());
+            item.output = context.get_node_result(&node.id).cloned();
+            item.finish(NodeRunStatus::Skipped);
+            context.timeline.push(item);
+        }
+        let event = self.event(WorkflowEventKind::NodeSkipped).await.with_node(node.id, node.name.clone());
+        self.emit(event);
+    }
+
+    /// 判断任务节点本次进入是否为重放（之前的运行已完成过这一次执行）
+    async fn is_replay(&self, node_id: Uuid) -> bool {
+        let completed = self.context.read().await
+            .completed_nodes
+            .get(&node_id)
+            .copied()
+            .unwrap_or(0);
+        let mut replayed = self.replayed.lock().unwrap();
+        let entered = replayed.entry(node_id).or_insert(0);
+        *entered += 1;
+        *entered <= completed
     }
 
     /// 从指定节点开始执行
@@ -111,28 +359,48 @@ impl WorkflowExecutor {
                 .clone();
             drop(workflow);
 
+            // 恢复时重放：已完成的任务节点复用记录的结果，不再执行
+            if matches!(node.node_type, NodeType::Task { .. }) && self.is_replay(node_id).await {
+                self.journal_transition(node_id, NodeTransition::Replayed, None).await?;
+                self.node_skipped(&node).await;
+                return self.execute_next_nodes(node_id).await;
+            }
+
+            // 分支到达汇聚节点后停止，由并行节点负责汇聚并继续执行
+            if matches!(node.node_type, NodeType::Join { .. }) {
+                return self.journal_transition(node_id, NodeTransition::Started, None).await;
+            }
+
             // 更新当前节点
             {
                 let mut context = self.context.write().await;
                 context.current_node = Some(node_id);
             }
+            self.journal_transition(node_id, NodeTransition::Started, None).await?;
+            let entry = self.node_started(&node).await;
 
             // 执行节点
             match &node.node_type {
                 NodeType::Start => {
                     // 开始节点，直接继续
+                    self.node_finished(entry, Ok(serde_json::Value::Null)).await;
                     self.execute_next_nodes(node_id).await?;
                 }
                 NodeType::End => {
                     // 结束节点，标记完成
-                    let mut context = self.context.write().await;
-                    context.status = ExecutionStatus::Completed;
-                    context.completed_at = Some(Utc::now());
+                    {
+                        let mut context = self.context.write().await;
+                        context.status = ExecutionStatus::Completed;
+                        context.completed_at = Some(Utc::now());
+                    }
+                    self.journal_transition(node_id, NodeTransition::Completed, None).await?;
+                    self.node_finished(entry, Ok(serde_json::Value::Null)).await;
                 }
                 NodeType::Task { task_name, params } => {
                     // 执行任务节点（带错误处理）
                     let result = self.execute_task_with_error_handling(
                         node_id,
+                        entry,
                         task_name,
                         params,
                         &node.error_handling
@@ -143,27 +411,35 @@ impl WorkflowExecutor {
                             // 保存结果
                             {
                                 let mut context = self.context.write().await;
-                                context.set_node_result(node_id, task_result);
+                                context.set_node_result(node_id, task_result.clone());
+                                *context.completed_nodes.entry(node_id).or_insert(0) += 1;
+                                if let ErrorHandlingStrategy::Compensate { .. } = node.error_handling {
+                                    context.compensation_stack.push(node_id);
+                                }
                             }
+                            self.journal_transition(node_id, NodeTransition::Completed, None).await?;
+                            self.node_finished(entry, Ok(task_result)).await;
 
                             // 继续执行下一个节点
                             self.execute_next_nodes(node_id).await?;
                         }
                         Err(e) => {
+                            self.context.write().await.set_node_error(node_id, e.clone());
+                            self.journal_transition(node_id, NodeTransition::Failed, Some(&e)).await?;
+                            self.node_finished(entry, Err(&e)).await;
+
                             // 根据错误处理策略决定是否继续
                             match &node.error_handling {
-                                ErrorHandlingStrategy::Fail => {
-                                    let mut context = self.context.write().await;
-                                    context.status = ExecutionStatus::Failed;
-                                    context.error = Some(e.clone());
-                                    return Err(e);
-                                }
                                 ErrorHandlingStrategy::Ignore => {
                                     // 忽略错误，继续执行
                                     self.execute_next_nodes(node_id).await?;
                                 }
+                                ErrorHandlingStrategy::Fallback { fallback_node } => {
+                                    // 跳转到 fallback 节点继续执行
+                                    self.execute_from_node(*fallback_node).await?;
+                                }
                                 _ => {
-                                    // 其他策略已在 execute_task_with_error_handling 中处理
+                                    // Fail / Compensate / 重试耗尽：终止工作流
                                     return Err(e);
                                 }
                             }
@@ -172,19 +448,27 @@ impl WorkflowExecutor {
                 }
                 NodeType::Decision { condition } => {
                     // 执行决策节点
-                    let result = self.evaluate_condition(condition).await?;
+                    let result = match self.evaluate_condition(condition).await {
+                        Ok(result) => result,
+                        Err(e) => {
+                            self.node_finished(entry, Err(&e)).await;
+                            return Err(e);
+                        }
+                    };
+                    self.node_finished(entry, Ok(serde_json::json!({ "result": result }))).await;
 
                     // 根据结果选择分支
                     self.execute_decision_branch(node_id, result).await?;
                 }
                 NodeType::Loop { condition, max_iterations } => {
                     // 执行循环节点
-                    self.execute_loop(node_id, condition, *max_iterations).await?;
+                    self.execute_loop(node_id, entry, condition, *max_iterations).await?;
                 }
-                NodeType::Parallel { branches } => {
+                NodeType::Parallel { branches, branch_timeout_ms, on_branch_failure } => {
                     // 执行并行节点
-                    self.execute_parallel(node_id, *branches).await?;
+                    self.execute_parallel(node_id, entry, *branches, *branch_timeout_ms, *on_branch_failure).await?;
                 }
+                NodeType::Join { .. } => unreachable!("join nodes are handled above"),
             }
 
             Ok(())
@@ -221,41 +505,38 @@ impl WorkflowExecutor {
     }
 
     /// 执行任务
-    async fn execute_task(&self, task_name: &str, params: &serde_json::Value) -> Result<serde_json::Value, String> {
-        // 这里是任务执行的占位符
-        // 实际实现中，这里会调用 Agent 或其他执行器
-        Ok(serde_json::json!({
-            "task": task_name,
-            "params": params,
-            "result": "success"
-        }))
+    async fn execute_task(&self, entry: usize, task_name: &str, params: &serde_json::Value) -> Result<serde_json::Value, String> {
+        let params = {
+            let mut context = self.context.write().await;
+            let params = render_params(params, &context)?;
+            let item = &mut context.timeline[entry];
+            item.attempts += 1;
+            item.input = Some(params.clone());
+            params
+        };
+
+        match self.task_handlers.get(task_name).or(self.fallback_handler.as_ref()) {
+            Some(handler) => handler.handle(task_name, params).await,
+            None => Err(format!("no handler registered for task '{}'", task_name)),
+        }
     }
 
     /// 带错误处理的任务执行
     async fn execute_task_with_error_handling(
         &self,
         node_id: Uuid,
+        entry: usize,
         task_name: &str,
         params: &serde_json::Value,
         strategy: &ErrorHandlingStrategy,
     ) -> Result<serde_json::Value, String> {
         match strategy {
             ErrorHandlingStrategy::Retry { policy } => {
-                self.execute_task_with_retry(task_name, params, policy).await
-            }
-            ErrorHandlingStrategy::Fallback { fallback_node } => {
-                match self.execute_task(task_name, params).await {
-                    Ok(result) => Ok(result),
-                    Err(_) => {
-                        // 跳转到 fallback 节点
-                        // 这里需要解析 fallback_node 字符串为 Uuid
-                        Err(format!("Fallback to node: {}", fallback_node))
-                    }
-                }
+                self.execute_task_with_retry(node_id, entry, task_name, params, policy).await
             }
             _ => {
-                // Fail 和 Ignore 策略在调用方处理
-                self.execute_task(task_name, params).await
+                // Fail、Ignore、Fallback 和 Compensate 策略在调用方处理
+                self.execute_task(entry, task_name, params).await
             }
         }
     }
@@ -263,6 +544,8 @@ impl WorkflowExecutor {
     /// 带重试的任务执行
     async fn execute_task_with_retry(
         &self,
+        node_id: Uuid,
+        entry: usize,
         task_name: &str,
         params: &serde_json::Value,
         policy: &RetryPolicy,
@@ -270,12 +553,18 @@ impl WorkflowExecutor {
         let mut last_error = String::new();
 
         for attempt in 0..=policy.max_retries {
-            match self.execute_task(task_name, params).await {
+            match self.execute_task(entry, task_name, params).await {
                 Ok(result) => return Ok(result),
                 Err(e) => {
                     last_error = e;
 
                     if attempt < policy.max_retries {
+                        let node_name = self.context.read().await.timeline[entry].node_name.clone();
+                        let mut event = self.event(WorkflowEventKind::NodeRetried).await.with_node(node_id, node_name);
+                        event.attempt = Some(attempt as u32 + 1);
+                        event.error = Some(last_error.clone());
+                        self.emit(event);
+
                         let delay = policy.calculate_delay(attempt);
                         sleep(Duration::from_millis(delay)).await;
                     }
@@ -288,9 +577,11 @@ impl WorkflowExecutor {
 
     /// 评估条件
     async fn evaluate_condition(&self, condition: &str) -> Result<bool, String> {
-        // 简单的条件评估占位符
-        // 实际实现中，这里会使用表达式解析器
-        Ok(condition.contains("true"))
+        let expression = Expression::parse(condition)
+            .map_err(|e| format!("Invalid expression '{}': {}", condition, e))?;
+        let context = self.context.read().await;
+        expression.evaluate_bool(&context)
+            .map_err(|e| format!("Failed to evaluate '{}': {}", condition, e))
     }
 
     /// 评估边条件
@@ -330,7 +621,36 @@ impl WorkflowExecutor {
     }
 
     /// 执行循环节点
-    async fn execute_loop(&self, node_id: Uuid, condition: &str, max_iterations: usize) -> Result<(), String> {
+    async fn execute_loop(&self, node_id: Uuid, entry: usize, condition: &str, max_iterations: usize) -> Result<(), String> {
+        let iterations = match self.run_loop_body(node_id, condition, max_iterations).await {
+            Ok(iterations) => iterations,
+            Err(e) => {
+                self.node_finished(entry, Err(&e)).await;
+                return Err(e);
+            }
+        };
+        self.node_finished(entry, Ok(serde_json::json!({ "iterations": iterations }))).await;
+
+        // 循环结束后，继续执行循环后的节点
+        // 查找Expression类型的出边（循环退出边）
+        let exit_nodes = {
+            let workflow = self.workflow.read().await;
+            let edges = workflow.get_outgoing_edges(&node_id);
+            edges.into_iter()
+                .filter(|e| matches!(e.condition, EdgeCondition::Expression { .. }))
+                .map(|e| e.to)
+                .collect::<Vec<_>>()
+        };
+
+        for exit_node_id in exit_nodes {
+            self.execute_from_node(exit_node_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 执行循环体直到条件不满足或达到最大迭代次数，返回迭代次数
+    async fn run_loop_body(&self, node_id: Uuid, condition: &str, max_iterations: usize) -> Result<usize, String> {
         let mut iteration = 0;
 
         loop {
@@ -372,26 +692,22 @@ impl WorkflowExecutor {
             iteration += 1;
         }
 
-        // 循环结束后，继续执行循环后的节点
-        // 查找Expression类型的出边（循环退出边）
-        let exit_nodes = {
-            let workflow = self.workflow.read().await;
-            let edges = workflow.get_outgoing_edges(&node_id);
-            edges.into_iter()
-                .filter(|e| matches!(e.condition, EdgeCondition::Expression { .. }))
-                .map(|e| e.to)
-                .collect::<Vec<_>>()
-        };
-
-        for exit_node_id in exit_nodes {
-            self.execute_from_node(exit_node_id).await?;
-        }
-
-        Ok(())
+        Ok(iteration)
     }
 
-    /// 执行并行节点
-    async fn execute_parallel(&self, node_id: Uuid, branches: usize) -> Result<(), String> {
+    /// 执行并行节点（fan-out），并在汇聚节点（fan-in）按策略合并分支结果
+    ///
+    /// 分支从并行节点的 `ParallelBranch` 边（或 `Always` 边）出发，执行到下游
+    /// 第一个 `Join` 节点为止。没有 `Join` 节点时等待所有分支成功，然后沿并行
+    /// 节点的其他出边继续。
+    async fn execute_parallel(
+        &self,
+        node_id: Uuid,
+        entry: usize,
+        branches: usize,
+        branch_timeout_ms: Option<u64>,
+        on_branch_failure: BranchFailurePolicy,
+    ) -> Result<(), String> {
         // 获取所有并行分支的节点
         let branch_nodes = {
             let workflow = self.workflow.read().await;
@@ -425,39 +741,142 @@ impl WorkflowExecutor {
             branch_nodes
         };
 
+        // 查找汇聚节点
+        let (join_id, policy) = {
+            let workflow = self.workflow.read().await;
+            match find_join_node(&workflow, &branch_nodes) {
+                Some((id, policy)) => (Some(id), policy),
+                None => (None, JoinPolicy::All),
+            }
+        };
+
+        let total = branch_nodes.len();
+        let required = policy.required(total);
+
         // 并行执行所有分支
-        let mut handles = Vec::new();
-        for branch_node_id in branch_nodes {
+        let mut set = JoinSet::new();
+        let mut task_index = HashMap::new();
+        for (index, branch_node_id) in branch_nodes.iter().copied().enumerate() {
             let executor = self.clone_for_parallel();
-            let handle = tokio::spawn(async move {
-                executor.execute_from_node(branch_node_id).await
+            let handle = set.spawn(async move {
+                let run = executor.execute_from_node(branch_node_id);
+                match branch_timeout_ms {
+                    Some(ms) => match timeout(Duration::from_millis(ms), run).await {
+                        Ok(result) => result.map_err(|e| ("failed", e)),
+                        Err(_) => Err(("timed_out", format!("Branch timed out after {}ms", ms))),
+                    },
+                    None => run.await.map_err(|e| ("failed", e)),
+                }
             });
-            handles.push(handle);
+            task_index.insert(handle.id(), index);
         }
 
-        // 等待所有分支完成
-        let mut errors = Vec::new();
-        for (i, handle) in handles.into_iter().enumerate() {
-            match handle.await {
-                Ok(Ok(())) => {
-                    // 分支成功完成
-                }
-                Ok(Err(e)) => {
-                    errors.push(format!("Branch {} failed: {}", i, e));
-                }
-                Err(e) => {
-                    errors.push(format!("Branch {} panicked: {}", i, e));
-                }
+        // 按完成顺序收集分支结果，直到汇聚策略得出结论
+        let mut outcomes: Vec<Option<Result<(), (&str, String)>>> = vec![None; total];
+        let mut succeeded = 0;
+        let mut failed = 0;
+        while let Some(joined) = set.join_next_with_id().await {
+            let (index, outcome) = match joined {
+                Ok((id, outcome)) => (task_index[&id], outcome),
+                Err(e) if e.is_cancelled() => continue,
+                Err(e) => (task_index[&e.id()], Err(("failed", format!("Branch panicked: {}", e)))),
+            };
+            if outcome.is_ok() {
+                succeeded += 1;
+            } else {
+                failed += 1;
+            }
+            outcomes[index] = Some(outcome);
+
+            let decided = succeeded >= required || total - failed < required;
+            if decided && on_branch_failure == BranchFailurePolicy::CancelOthers {
+                set.abort_all();
             }
         }
 
-        // 如果有任何分支失败，返回错误
-        if !errors.is_empty() {
-            return Err(format!("Parallel execution failed: {}", errors.join(", ")));
+        // 合并各分支结果
+        let merged = {
+            let workflow = self.workflow.read().await;
+            let context = self.context.read().await;
+            let branch_results: Vec<serde_json::Value> = branch_nodes
+                .iter()
+                .enumerate()
+                .map(|(index, start)| {
+                    let (status, error) = match &outcomes[index] {
+                        Some(Ok(())) => ("completed", None),
+                        Some(Err((status, e))) => (*status, Some(e.clone())),
+                        None => ("cancelled", None),
+                    };
+                    serde_json::json!({
+                        "index": index,
+                        "node_id": start.to_string(),
+                        "status": status,
+                        "output": branch_output(&workflow, &context, *start, join_id),
+                        "error": error,
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "branches": branch_results,
+                "succeeded": succeeded,
+                "failed": failed,
+                "required": required,
+            })
+        };
+
+        {
+            let mut context = self.context.write().await;
+            context.set_node_result(node_id, merged.clone());
+            if let Some(join_id) = join_id {
+                context.set_node_result(join_id, merged.clone());
+            }
+        }
+
+        let join_node = match join_id {
+            Some(join_id) => self.workflow.read().await.get_node(&join_id).cloned(),
+            None => None,
+        };
+        let join_entry = match &join_node {
+            Some(join_node) => Some(self.node_started(join_node).await),
+            None => None,
+        };
+
+        // 汇聚条件未满足
+        if succeeded < required {
+            let errors: Vec<String> = outcomes
+                .iter()
+                .enumerate()
+                .filter_map(|(i, o)| match o {
+                    Some(Err((_, e))) => Some(format!("Branch {} failed: {}", i, e)),
+                    _ => None,
+                })
+                .collect();
+            let error = format!(
+                "Parallel execution failed ({}/{} branches succeeded, {} required): {}",
+                succeeded, total, required, errors.join(", ")
+            );
+            self.node_finished(entry, Err(&error)).await;
+            if let Some(join_entry) = join_entry {
+                self.node_finished(join_entry, Err(&error)).await;
+            }
+            return Err(error);
+        }
+
+        self.node_finished(entry, Ok(merged.clone())).await;
+        if let Some(join_entry) = join_entry {
+            self.node_finished(join_entry, Ok(merged)).await;
         }
 
         // 并行执行完成后，继续执行后续节点
-        // 查找非ParallelBranch和非Always的出边（汇聚节点）
+        if let Some(join_id) = join_id {
+            {
+                let mut context = self.context.write().await;
+                context.current_node = Some(join_id);
+            }
+            return self.execute_next_nodes(join_id).await;
+        }
+
+        // 没有汇聚节点：查找非ParallelBranch和非Always的出边
         let next_nodes = {
             let workflow = self.workflow.read().await;
             let edges = workflow.get_outgoing_edges(&node_id);
@@ -475,11 +894,47 @@ impl WorkflowExecutor {
         Ok(())
     }
 
+    /// 标记工作流失败，并按逆序执行已登记的补偿路径
+    async fn fail_and_compensate(&self, error: &str) {
+        let pending = {
+            let mut context = self.context.write().await;
+            std::mem::take(&mut context.compensation_stack)
+        };
+
+        let mut compensation_errors = Vec::new();
+        for node_id in pending.into_iter().rev() {
+            let target = {
+                let workflow = self.workflow.read().await;
+                workflow.get_node(&node_id).and_then(|n| n.error_handling.target_node())
+            };
+            let Some(target) = target else { continue };
+
+            match self.execute_from_node(target).await {
+                Ok(()) => self.context.write().await.compensated.push(node_id),
+                Err(e) => compensation_errors.push(format!("{}: {}", node_id, e)),
+            }
+        }
+
+        let mut context = self.context.write().await;
+        context.status = ExecutionStatus::Failed;
+        context.completed_at = None;
+        context.error = Some(if compensation_errors.is_empty() {
+            error.to_string()
+        } else {
+            format!("{} (compensation failed: {})", error, compensation_errors.join(", "))
+        });
+    }
+
     /// 为并行执行克隆executor
     fn clone_for_parallel(&self) -> Self {
         Self {
             workflow: Arc::clone(&self.workflow),
             context: Arc::clone(&self.context),
+            task_handlers: Arc::clone(&self.task_handlers),
+            fallback_handler: self.fallback_handler.clone(),
+            journal: self.journal.clone(),
+            replayed: Arc::clone(&self.replayed),
+            event_sinks: Arc::clone(&self.event_sinks),
         }
     }
 
@@ -493,6 +948,11 @@ impl WorkflowExecutor {
         Self {
             workflow: Arc::new(RwLock::new(workflow)),
             context: Arc::new(RwLock::new(context)),
+            task_handlers: Arc::new(HashMap::new()),
+            fallback_handler: None,
+            journal: None,
+            replayed: Arc::new(StdMutex::new(HashMap::new())),
+            event_sinks: Arc::new(Vec::new()),
         }
     }
 
@@ -509,23 +969,72 @@ impl WorkflowExecutor {
             context.current_node
         };
 
-        if let Some(node_id) = current_node_id {
+        let resume_from = match current_node_id {
             // 从当前节点继续执行
-            self.execute_from_node(node_id).await?;
-        } else {
+            Some(node_id) => node_id,
             // 如果没有当前节点，从开始节点执行
-            let workflow = self.workflow.read().await;
-            let start_node = workflow.find_start_node()
-                .ok_or("No start node found")?;
-            let start_id = start_node.id;
-            drop(workflow);
-            self.execute_from_node(start_id).await?;
+            None => {
+                let workflow = self.workflow.read().await;
+                let start_node = workflow.find_start_node()
+                    .ok_or("No start node found")?;
+                start_node.id
+            }
+        };
+
+        {
+            let mut context = self.context.write().await;
+            context.status = ExecutionStatus::Running;
+            context.error = None;
         }
 
-        // 返回执行上下文
-        let context = self.context.read().await;
-        Ok(context.clone())
+        self.run_from(resume_from).await
+    }
+}
+
+/// 从分支起点出发查找最近的汇聚节点
+fn find_join_node(workflow: &Workflow, starts: &[Uuid]) -> Option<(Uuid, JoinPolicy)> {
+    let mut queue: VecDeque<Uuid> = starts.iter().copied().collect();
+    let mut visited = HashSet::new();
+    while let Some(id) = queue.pop_front() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let node = workflow.get_node(&id)?;
+        if let NodeType::Join { policy } = node.node_type {
+            return Some((id, policy));
+        }
+        queue.extend(workflow.get_outgoing_edges(&id).into_iter().map(|e| e.to));
     }
+    None
+}
+
+/// 分支输出：汇聚节点前最后一个节点（或分支末端节点）的结果
+fn branch_output(
+    workflow: &Workflow,
+    context: &ExecutionContext,
+    start: Uuid,
+    join_id: Option<Uuid>,
+) -> serde_json::Value {
+    let mut queue = VecDeque::from([start]);
+    let mut visited = HashSet::new();
+    let mut output = serde_json::Value::Null;
+    while let Some(id) = queue.pop_front() {
+        if Some(id) == join_id || !visited.insert(id) {
+            continue;
+        }
+        let next: Vec<Uuid> = workflow.get_outgoing_edges(&id).into_iter().map(|e| e.to).collect();
+        let terminal = next.iter().all(|n| {
+            Some(*n) == join_id
+                || workflow.get_node(n).is_some_and(|node| matches!(node.node_type, NodeType::End))
+        });
+        if terminal {
+            if let Some(result) = context.get_node_result(&id) {
+                output = result.clone();
+            }
+        }
+        queue.extend(next);
+    }
+    output
 }
 
 #[cfg(test)]
@@ -533,6 +1042,7 @@ mod tests {
     use super::*;
     use crate::workflow::workflow::Workflow;
     use crate::workflow::node::WorkflowNode;
+    use crate::workflow::task_handler::EchoTaskHandler;
 
     #[tokio::test]
     async fn test_simple_workflow_execution() {
@@ -549,7 +1059,7 @@ mod tests {
         workflow.connect(start_id, task_id);
         workflow.connect(task_id, end_id);
 
-        let executor = WorkflowExecutor::new(workflow);
+        let executor = WorkflowExecutor::new(workflow).with_fallback_handler(Arc::new(EchoTaskHandler));
         let result = executor.execute().await;
 
         assert!(result.is_ok());
@@ -557,6 +1067,553 @@ mod tests {
         assert_eq!(context.status, ExecutionStatus::Completed);
     }
 
+    #[tokio::test]
+    async fn test_task_handler_with_templated_params() {
+        use crate::workflow::task_handler::FnTaskHandler;
+
+        let mut workflow = Workflow::new("Test", "Test");
+        workflow.set_variable("name", serde_json::json!("Alice"));
+
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let greet_id = workflow.add_node(WorkflowNode::task(
+            "Greet",
+            "greet",
+            serde_json::json!({"who": "${name}"}),
+        ));
+        let shout_id = workflow.add_node(WorkflowNode::task(
+            "Shout",
+            "shout",
+            serde_json::json!({"text": format!("${{node.{}.result.greeting}}", greet_id)}),
+        ));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+
+        workflow.connect(start_id, greet_id);
+        workflow.connect(greet_id, shout_id);
+        workflow.connect(shout_id, end_id);
+
+        let executor = WorkflowExecutor::new(workflow)
+            .with_task_handler("greet", Arc::new(FnTaskHandler(|_: &str, params: serde_json::Value| {
+                let who = params["who"].as_str().unwrap_or_default().to_string();
+                Ok(serde_json::json!({"greeting": format!("hello {}", who)}))
+            })))
+            .with_task_handler("shout", Arc::new(FnTaskHandler(|_: &str, params: serde_json::Value| {
+                Ok(serde_json::json!(params["text"].as_str().unwrap_or_default().to_uppercase()))
+            })));
+
+        let context = executor.execute().await.unwrap();
+        assert_eq!(context.status, ExecutionStatus::Completed);
+        assert_eq!(context.get_node_result(&shout_id), Some(&serde_json::json!("HELLO ALICE")));
+    }
+
+    #[tokio::test]
+    async fn test_task_handler_failure() {
+        use crate::workflow::task_handler::FnTaskHandler;
+
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let task_id = workflow.add_node(WorkflowNode::task("Broken", "broken", serde_json::json!({})));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start_id, task_id);
+        workflow.connect(task_id, end_id);
+
+        let executor = WorkflowExecutor::new(workflow)
+            .with_task_handler("broken", Arc::new(FnTaskHandler(|_: &str, _| Err("boom".to_string()))));
+
+        assert_eq!(executor.execute().await.unwrap_err(), "boom");
+        assert_eq!(executor.get_context().await.status, ExecutionStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_unregistered_task_fails() {
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let task_id = workflow.add_node(WorkflowNode::task("Typo", "sned_email", serde_json::json!({})));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start_id, task_id);
+        workflow.connect(task_id, end_id);
+
+        let executor = WorkflowExecutor::new(workflow);
+        assert_eq!(executor.execute().await.unwrap_err(), "no handler registered for task 'sned_email'");
+        assert_eq!(executor.get_context().await.status, ExecutionStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_decision_branches_on_variables() {
+        let mut workflow = Workflow::new("Test", "Test");
+        workflow.set_variable("value", serde_json::json!(100));
+
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let decision_id = workflow.add_node(WorkflowNode::decision("Check", "value > 50 && value < 200"));
+        let high_id = workflow.add_node(WorkflowNode::task("High", "high", serde_json::json!({})));
+        let low_id = workflow.add_node(WorkflowNode::task("Low", "low", serde_json::json!({})));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+
+        workflow.connect(start_id, decision_id);
+        workflow.add_edge(WorkflowEdge::branch(decision_id, high_id, true));
+        workflow.add_edge(WorkflowEdge::branch(decision_id, low_id, false));
+        workflow.connect(high_id, end_id);
+        workflow.connect(low_id, end_id);
+
+        let executor = WorkflowExecutor::new(workflow).with_fallback_handler(Arc::new(EchoTaskHandler));
+        let context = executor.execute().await.unwrap();
+
+        assert!(context.get_node_result(&high_id).is_some());
+        assert!(context.get_node_result(&low_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expression_edge_uses_node_result() {
+        use crate::workflow::task_handler::FnTaskHandler;
+
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let score_id = workflow.add_node(WorkflowNode::task("Score", "score", serde_json::json!({})));
+        let pass_id = workflow.add_node(WorkflowNode::task("Pass", "pass", serde_json::json!({})));
+        let fail_id = workflow.add_node(WorkflowNode::task("Fail", "fail", serde_json::json!({})));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+
+        workflow.connect(start_id, score_id);
+        workflow.connect_when(score_id, fail_id, format!("node.{}.result.score < 0.5", score_id));
+        workflow.connect_when(score_id, pass_id, format!("node.{}.result.score >= 0.5", score_id));
+        workflow.connect(pass_id, end_id);
+        workflow.connect(fail_id, end_id);
+
+        let executor = WorkflowExecutor::new(workflow).with_fallback_handler(Arc::new(EchoTaskHandler))
+            .with_task_handler("score", Arc::new(FnTaskHandler(|_: &str, _| Ok(serde_json::json!({"score": 0.75})))));
+        let context = executor.execute().await.unwrap();
+
+        assert!(context.get_node_result(&pass_id).is_some());
+        assert!(context.get_node_result(&fail_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fallback_continues_at_fallback_node() {
+        use crate::workflow::task_handler::FnTaskHandler;
+
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        let recover_id = workflow.add_node(WorkflowNode::task(
+            "Recover",
+            "recover",
+            serde_json::json!({"reason": "${last_error.message}"}),
+        ));
+        let task_id = workflow.add_node(
+            WorkflowNode::task("Broken", "broken", serde_json::json!({}))
+                .with_error_handling(ErrorHandlingStrategy::Fallback { fallback_node: recover_id }),
+        );
+        workflow.connect(start_id, task_id);
+        workflow.connect(task_id, end_id);
+        workflow.connect(recover_id, end_id);
+
+        let executor = WorkflowExecutor::new(workflow).with_fallback_handler(Arc::new(EchoTaskHandler))
+            .with_task_handler("broken", Arc::new(FnTaskHandler(|_: &str, _| Err("disk full".to_string()))));
+        let context = executor.execute().await.unwrap();
+
+        assert_eq!(context.status, ExecutionStatus::Completed);
+        assert_eq!(context.get_node_error(&task_id).map(String::as_str), Some("disk full"));
+        assert_eq!(
+            context.get_node_result(&recover_id).unwrap()["params"]["reason"],
+            serde_json::json!("disk full")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compensation_runs_in_reverse_on_later_failure() {
+        use crate::workflow::task_handler::FnTaskHandler;
+        use std::sync::Mutex;
+
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        let undo_a = workflow.add_node(WorkflowNode::task("Undo A", "undo", serde_json::json!({"step": "a"})));
+        let undo_b = workflow.add_node(WorkflowNode::task("Undo B", "undo", serde_json::json!({"step": "b"})));
+        let a = workflow.add_node(
+            WorkflowNode::task("A", "do", serde_json::json!({}))
+                .with_error_handling(ErrorHandlingStrategy::Compensate { compensation_node: undo_a }),
+        );
+        let b = workflow.add_node(
+            WorkflowNode::task("B", "do", serde_json::json!({}))
+                .with_error_handling(ErrorHandlingStrategy::Compensate { compensation_node: undo_b }),
+        );
+        let c = workflow.add_node(WorkflowNode::task("C", "broken", serde_json::json!({})));
+        workflow.connect(start_id, a);
+        workflow.connect(a, b);
+        workflow.connect(b, c);
+        workflow.connect(c, end_id);
+
+        let undone = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&undone);
+        let executor = WorkflowExecutor::new(workflow).with_fallback_handler(Arc::new(EchoTaskHandler))
+            .with_task_handler("broken", Arc::new(FnTaskHandler(|_: &str, _| Err("boom".to_string()))))
+            .with_task_handler("undo", Arc::new(FnTaskHandler(move |_: &str, params: serde_json::Value| {
+                log.lock().unwrap().push(params["step"].as_str().unwrap().to_string());
+                Ok(serde_json::Value::Null)
+            })));
+
+        assert!(executor.execute().await.is_err());
+
+        let context = executor.get_context().await;
+        assert_eq!(context.status, ExecutionStatus::Failed);
+        assert_eq!(context.compensated, vec![b, a]);
+        assert_eq!(*undone.lock().unwrap(), vec!["b", "a"]);
+    }
+
+    /// 构建 fan-out → join → after 的工作流，每个分支执行一个任务
+    fn fan_out_workflow(
+        tasks: &[&str],
+        timeout_ms: Option<u64>,
+        on_failure: BranchFailurePolicy,
+        policy: JoinPolicy,
+    ) -> (Workflow, Uuid, Uuid) {
+        let mut workflow = Workflow::new("Fan-out", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let parallel_id = workflow.add_node(WorkflowNode::new("Fan-out", NodeType::Parallel {
+            branches: tasks.len(),
+            branch_timeout_ms: timeout_ms,
+            on_branch_failure: on_failure,
+        }));
+        let join_id = workflow.add_node(WorkflowNode::join("Join", policy));
+        let after_id = workflow.add_node(WorkflowNode::task("After", "after", serde_json::json!({})));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+
+        workflow.connect(start_id, parallel_id);
+        for (i, task) in tasks.iter().enumerate() {
+            let branch_id = workflow.add_node(WorkflowNode::task(
+                format!("Branch {}", i),
+                *task,
+                serde_json::json!({ "i": i }),
+            ));
+            workflow.add_edge(WorkflowEdge::parallel_branch(parallel_id, branch_id, i));
+            workflow.connect(branch_id, join_id);
+        }
+        workflow.connect(join_id, after_id);
+        workflow.connect(after_id, end_id);
+
+        (workflow, join_id, after_id)
+    }
+
+    fn with_branch_tasks(workflow: Workflow) -> WorkflowExecutor {
+        use crate::workflow::task_handler::FnTaskHandler;
+
+        struct SlowTask;
+
+        #[async_trait::async_trait]
+        impl TaskHandler for SlowTask {
+            async fn handle(&self, _: &str, params: serde_json::Value) -> Result<serde_json::Value, String> {
+                sleep(Duration::from_secs(5)).await;
+                Ok(params)
+            }
+        }
+
+        WorkflowExecutor::new(workflow).with_fallback_handler(Arc::new(EchoTaskHandler))
+            .with_task_handler("ok", Arc::new(FnTaskHandler(|_: &str, params: serde_json::Value| {
+                Ok(serde_json::json!({ "doubled": params["i"].as_u64().unwrap() * 2 }))
+            })))
+            .with_task_handler("fail", Arc::new(FnTaskHandler(|_: &str, _| Err("branch error".to_string()))))
+            .with_task_handler("slow", Arc::new(SlowTask))
+    }
+
+    #[tokio::test]
+    async fn test_parallel_join_all_merges_branch_results() {
+        let (workflow, join_id, after_id) = fan_out_workflow(
+            &["ok", "ok", "ok"],
+            None,
+            BranchFailurePolicy::CancelOthers,
+            JoinPolicy::All,
+        );
+        let context = with_branch_tasks(workflow).execute().await.unwrap();
+
+        assert_eq!(context.status, ExecutionStatus::Completed);
+        assert!(context.get_node_result(&after_id).is_some());
+
+        let merged = context.get_node_result(&join_id).unwrap();
+        assert_eq!(merged["succeeded"], 3);
+        assert_eq!(merged["branches"][2]["status"], "completed");
+        assert_eq!(merged["branches"][2]["output"], serde_json::json!({ "doubled": 4 }));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_join_any_cancels_slow_branches() {
+        let (workflow, join_id, after_id) = fan_out_workflow(
+            &["slow", "ok"],
+            None,
+            BranchFailurePolicy::CancelOthers,
+            JoinPolicy::Any,
+        );
+
+        let started = std::time::Instant::now();
+        let context = with_branch_tasks(workflow).execute().await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        assert!(context.get_node_result(&after_id).is_some());
+        let merged = context.get_node_result(&join_id).unwrap();
+        assert_eq!(merged["branches"][0]["status"], "cancelled");
+        assert_eq!(merged["branches"][1]["status"], "completed");
+    }
+
+    #[tokio::test]
+    async fn test_parallel_n_of_m_with_branch_timeout() {
+        let (workflow, join_id, after_id) = fan_out_workflow(
+            &["ok", "slow", "ok"],
+            Some(50),
+            BranchFailurePolicy::LetFinish,
+            JoinPolicy::NOfM { n: 2 },
+        );
+        let context = with_branch_tasks(workflow).execute().await.unwrap();
+
+        assert!(context.get_node_result(&after_id).is_some());
+        let merged = context.get_node_result(&join_id).unwrap();
+        assert_eq!(merged["succeeded"], 2);
+        assert_eq!(merged["branches"][1]["status"], "timed_out");
+    }
+
+    #[tokio::test]
+    async fn test_parallel_join_all_fails_on_branch_failure() {
+        let (workflow, _, after_id) = fan_out_workflow(
+            &["ok", "fail"],
+            None,
+            BranchFailurePolicy::LetFinish,
+            JoinPolicy::All,
+        );
+        let executor = with_branch_tasks(workflow);
+
+        let err = executor.execute().await.unwrap_err();
+        assert!(err.contains("branch error"));
+        let context = executor.get_context().await;
+        assert_eq!(context.status, ExecutionStatus::Failed);
+        assert!(context.get_node_result(&after_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recover_skips_completed_tasks() {
+        use crate::workflow::task_handler::FnTaskHandler;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct MemoryJournal {
+            entries: StdMutex<Vec<JournalEntry>>,
+            snapshot: StdMutex<Option<ExecutionContext>>,
+        }
+
+        #[async_trait::async_trait]
+        impl RunJournal for MemoryJournal {
+            async fn begin(&self, _: &Workflow, context: &ExecutionContext) -> Result<(), String> {
+                *self.snapshot.lock().unwrap() = Some(context.clone());
+                Ok(())
+            }
+            async fn record(&self, entry: &JournalEntry, context: &ExecutionContext) -> Result<(), String> {
+                self.entries.lock().unwrap().push(entry.clone());
+                *self.snapshot.lock().unwrap() = Some(context.clone());
+                Ok(())
+            }
+            async fn finish(&self, _: &ExecutionContext) -> Result<(), String> {
+                Ok(())
+            }
+        }
+
+        let mut workflow = Workflow::new("Durable", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let charge_id = workflow.add_node(WorkflowNode::task("Charge", "charge", serde_json::json!({})));
+        let ship_id = workflow.add_node(WorkflowNode::task("Ship", "ship", serde_json::json!({})));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start_id, charge_id);
+        workflow.connect(charge_id, ship_id);
+        workflow.connect(ship_id, end_id);
+
+        let charges = Arc::new(AtomicUsize::new(0));
+        let ship_up = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let build = |workflow: Workflow, context: Option<ExecutionContext>, journal: Arc<MemoryJournal>| {
+            let charges = Arc::clone(&charges);
+            let ship_up = Arc::clone(&ship_up);
+            let executor = match context {
+                Some(context) => WorkflowExecutor::from_checkpoint(workflow, context),
+                None => WorkflowExecutor::new(workflow),
+            };
+            executor
+                .with_journal(journal)
+                .with_task_handler("charge", Arc::new(FnTaskHandler(move |_: &str, _| {
+                    charges.fetch_add(1, Ordering::SeqCst);
+                    Ok(serde_json::json!("charged"))
+                })))
+                .with_task_handler("ship", Arc::new(FnTaskHandler(move |_: &str, _| {
+                    if ship_up.load(Ordering::SeqCst) {
+                        Ok(serde_json::json!("shipped"))
+                    } else {
+                        Err("carrier unavailable".to_string())
+                    }
+                })))
+        };
+
+        // 第一次运行在 Ship 处中断
+        let journal = Arc::new(MemoryJournal::default());
+        assert!(build(workflow.clone(), None, Arc::clone(&journal)).execute().await.is_err());
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+
+        // 从日志快照恢复：Charge 不会再次执行
+        ship_up.store(true, Ordering::SeqCst);
+        let snapshot = journal.snapshot.lock().unwrap().clone().unwrap();
+        let journal = Arc::new(MemoryJournal::default());
+        let context = build(workflow, Some(snapshot), Arc::clone(&journal)).recover().await.unwrap();
+
+        assert_eq!(context.status, ExecutionStatus::Completed);
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+        assert_eq!(context.get_node_result(&ship_id), Some(&serde_json::json!("shipped")));
+
+        let entries = journal.entries.lock().unwrap();
+        assert!(entries.iter().any(|e| e.node_id == charge_id && e.transition == NodeTransition::Replayed));
+        assert!(entries.iter().any(|e| e.node_id == ship_id && e.transition == NodeTransition::Completed));
+    }
+
+    #[tokio::test]
+    async fn test_events_and_timeline_record_retries() {
+        use crate::event::{EventBus, EventKind};
+        use crate::workflow::task_handler::FnTaskHandler;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let flaky_id = workflow.add_node(
+            WorkflowNode::task("Flaky", "flaky", serde_json::json!({"n": "${n}"})).with_error_handling(
+                ErrorHandlingStrategy::Retry { policy: RetryPolicy::new(2).with_delay(1) },
+            ),
+        );
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start_id, flaky_id);
+        workflow.connect(flaky_id, end_id);
+        workflow.set_variable("n", serde_json::json!(7));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let executor = WorkflowExecutor::new(workflow)
+            .with_event_bus(bus)
+            .with_task_handler("flaky", Arc::new(FnTaskHandler(move |_: &str, params: serde_json::Value| {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err("transient".to_string())
+                } else {
+                    Ok(serde_json::json!({"doubled": params["n"].as_i64().unwrap() * 2}))
+                }
+            })));
+        let context = executor.execute().await.unwrap();
+
+        let mut kinds = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            assert_eq!(event.source, format!("workflow:{}", context.workflow_id));
+            let EventKind::Custom(kind) = event.kind else { panic!("unexpected kind") };
+            kinds.push((kind, event.payload["node_name"].as_str().unwrap_or_default().to_string()));
+        }
+        let expected = [
+            ("workflow.run_started", ""),
+            ("workflow.node_started", "Start"),
+            ("workflow.node_completed", "Start"),
+            ("workflow.node_started", "Flaky"),
+            ("workflow.node_retried", "Flaky"),
+            ("workflow.node_completed", "Flaky"),
+            ("workflow.node_started", "End"),
+            ("workflow.node_completed", "End"),
+            ("workflow.run_completed", ""),
+        ];
+        let expected: Vec<_> = expected.iter().map(|(k, n)| (k.to_string(), n.to_string())).collect();
+        assert_eq!(kinds, expected);
+
+        let flaky = context.node_timeline(&flaky_id);
+        assert_eq!(flaky.len(), 1);
+        assert_eq!(flaky[0].status, NodeRunStatus::Completed);
+        assert_eq!(flaky[0].attempts, 2);
+        assert_eq!(flaky[0].input, Some(serde_json::json!({"n": 7})));
+        assert_eq!(flaky[0].output, Some(serde_json::json!({"doubled": 14})));
+        assert!(flaky[0].duration_ms.is_some());
+        assert_eq!(context.timeline.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_reports_failures() {
+        use crate::streaming::{ChunkType, StreamingResponse};
+        use crate::workflow::task_handler::FnTaskHandler;
+
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let task_id = workflow.add_node(WorkflowNode::task("Broken", "broken", serde_json::json!({})));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start_id, task_id);
+        workflow.connect(task_id, end_id);
+
+        let (sender, mut receiver) = StreamingResponse::new().split();
+        let executor = WorkflowExecutor::new(workflow)
+            .with_streaming(sender)
+            .with_task_handler("broken", Arc::new(FnTaskHandler(|_: &str, _| Err("boom".to_string()))));
+        assert!(executor.execute().await.is_err());
+        drop(executor);
+
+        let chunks = receiver.collect_all().await;
+        let types: Vec<_> = chunks.iter().map(|c| c.chunk_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                ChunkType::Status,
+                ChunkType::Status,
+                ChunkType::Status,
+                ChunkType::Status,
+                ChunkType::Error,
+                ChunkType::Error,
+                ChunkType::Done,
+            ]
+        );
+        assert_eq!(chunks[4].content, "node_failed 'Broken' (attempt 1): boom");
+        assert_eq!(chunks[4].metadata.as_ref().unwrap()["node_id"], serde_json::json!(task_id.to_string()));
+
+        let kinds = chunk_event_kinds(&chunks);
+        assert_eq!(kinds, vec!["run_started", "node_started", "node_completed", "node_started", "node_failed", "run_failed"]);
+    }
+
+    fn chunk_event_kinds(chunks: &[crate::streaming::ResponseChunk]) -> Vec<String> {
+        chunks
+            .iter()
+            .filter_map(|c| c.metadata.as_ref())
+            .map(|m| m["kind"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_timeline_covers_loops_and_joins() {
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let loop_id = workflow.add_node(WorkflowNode::new(
+            "Repeat",
+            NodeType::Loop { condition: "true".to_string(), max_iterations: 3 },
+        ));
+        let body_id = workflow.add_node(WorkflowNode::task("Body", "body", serde_json::json!({})));
+        let fan_id = workflow.add_node(WorkflowNode::new(
+            "Fan",
+            NodeType::Parallel { branches: 2, branch_timeout_ms: None, on_branch_failure: BranchFailurePolicy::default() },
+        ));
+        let a_id = workflow.add_node(WorkflowNode::task("A", "a", serde_json::json!({})));
+        let b_id = workflow.add_node(WorkflowNode::task("B", "b", serde_json::json!({})));
+        let join_id = workflow.add_node(WorkflowNode::join("Join", JoinPolicy::All));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+
+        workflow.connect(start_id, loop_id);
+        workflow.connect(loop_id, body_id);
+        workflow.connect_when(loop_id, fan_id, "true");
+        workflow.add_edge(WorkflowEdge::new(fan_id, a_id, EdgeCondition::ParallelBranch { index: 0 }));
+        workflow.add_edge(WorkflowEdge::new(fan_id, b_id, EdgeCondition::ParallelBranch { index: 1 }));
+        workflow.connect(a_id, join_id);
+        workflow.connect(b_id, join_id);
+        workflow.connect(join_id, end_id);
+
+        let context = WorkflowExecutor::new(workflow).with_fallback_handler(Arc::new(EchoTaskHandler)).execute().await.unwrap();
+
+        assert_eq!(context.node_timeline(&body_id).len(), 3);
+        let repeat = context.node_timeline(&loop_id);
+        assert_eq!(repeat[0].output, Some(serde_json::json!({"iterations": 3})));
+        let join = context.node_timeline(&join_id);
+        assert_eq!(join.len(), 1);
+        assert_eq!(join[0].output.as_ref().unwrap()["succeeded"], serde_json::json!(2));
+        assert!(context.timeline.iter().all(|e| e.status == NodeRunStatus::Completed));
+    }
+
     #[tokio::test]
     async fn test_workflow_with_variables() {
         let mut workflow = Workflow::new("Test", "Test");
@@ -0,0 +1,833 @@
+use std::cmp::Ordering;
+use std::fmt;
+use uuid::Uuid;
+
+use super::executor::ExecutionContext;
+
+/// 表达式解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionError {
+    pub message: String,
+    /// 出错位置（字符偏移）
+    pub position: usize,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+/// 路径片段
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    In,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+
+/// 表达式语法树
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(serde_json::Value),
+    /// 变量路径，如 `order.items[0].price`
+    Variable(Vec<PathSegment>),
+    /// 节点结果路径，如 `node.<id>.result.status`
+    NodeResult(Uuid, Vec<PathSegment>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+/// 条件表达式
+///
+/// 支持的语法：
+/// - 字面量：数字、`"字符串"` / `'字符串'`、`true`、`false`、`null`
+/// - 变量路径：`count`、`user.name`、`items[0]`、`node.<id>.result.field`
+/// - 比较：`==` `!=` `<` `<=` `>` `>=`
+/// - 布尔：`&&` / `and`、`||` / `or`、`!` / `not`
+/// - 算术：`+` `-` `*` `/` `%`（`+` 也可拼接字符串）
+/// - 字符串/数组：`a contains b`、`a in b`
+/// - 函数：`len(x)`、`lower(s)`、`upper(s)`、`starts_with(s, p)`、`ends_with(s, p)`、`exists(path)`
+///
+/// 不存在的路径求值为 `null`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    ast: Expr,
+}
+
+impl Expression {
+    /// 解析表达式
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let tokens = Lexer::new(source).tokenize()?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let ast = parser.parse_or()?;
+        if let Some(tok) = parser.peek() {
+            return Err(ExpressionError {
+                message: format!("Unexpected token {:?}", tok.kind),
+                position: tok.position,
+            });
+        }
+        Ok(Self { source: source.to_string(), ast })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn ast(&self) -> &Expr {
+        &self.ast
+    }
+
+    /// 在执行上下文中求值
+    pub fn evaluate(&self, context: &ExecutionContext) -> Result<serde_json::Value, String> {
+        eval(&self.ast, context)
+    }
+
+    /// 求值并转换为布尔值
+    pub fn evaluate_bool(&self, context: &ExecutionContext) -> Result<bool, String> {
+        self.evaluate(context).map(|v| is_truthy(&v))
+    }
+}
+
+/// 值的真假判定：`null`、`false`、`0`、空字符串、空数组和空对象为假
+pub fn is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64().map(|f| f != 0.0).unwrap_or(false),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(a) => !a.is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 词法分析
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Number(f64),
+    Str(String),
+    Path(Vec<PathSegment>),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+struct Lexer<'a> {
+    chars: Vec<(usize, char)>,
+    idx: usize,
+    source: &'a str,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { chars: source.char_indices().collect(), idx: 0, source }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.idx).map(|(_, c)| *c)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.idx + offset).map(|(_, c)| *c)
+    }
+
+    fn position(&self) -> usize {
+        self.chars.get(self.idx).map(|(p, _)| *p).unwrap_or(self.source.len())
+    }
+
+    fn error(&self, message: impl Into<String>) -> ExpressionError {
+        ExpressionError { message: message.into(), position: self.position() }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, ExpressionError> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek() {
+            let position = self.position();
+            let kind = match c {
+                c if c.is_whitespace() => {
+                    self.idx += 1;
+                    continue;
+                }
+                '(' => { self.idx += 1; TokenKind::LParen }
+                ')' => { self.idx += 1; TokenKind::RParen }
+                ',' => { self.idx += 1; TokenKind::Comma }
+                '"' | '\'' => TokenKind::Str(self.read_string(c)?),
+                c if c.is_ascii_digit() => TokenKind::Number(self.read_number()?),
+                c if c.is_alphabetic() || c == '_' => self.read_word()?,
+                _ => TokenKind::Op(self.read_operator()?),
+            };
+            tokens.push(Token { kind, position });
+        }
+        Ok(tokens)
+    }
+
+    fn read_string(&mut self, quote: char) -> Result<String, ExpressionError> {
+        let start = self.position();
+        self.idx += 1;
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(ExpressionError {
+                        message: "Unterminated string literal".to_string(),
+                        position: start,
+                    })
+                }
+                Some('\\') => {
+                    self.idx += 1;
+                    let escaped = self.peek().ok_or_else(|| self.error("Unterminated escape"))?;
+                    out.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        other => other,
+                    });
+                    self.idx += 1;
+                }
+                Some(c) if c == quote => {
+                    self.idx += 1;
+                    return Ok(out);
+                }
+                Some(c) => {
+                    out.push(c);
+                    self.idx += 1;
+                }
+            }
+        }
+    }
+
+    fn read_number(&mut self) -> Result<f64, ExpressionError> {
+        let start = self.idx;
+        let position = self.position();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || (c == '.' && self.peek_at(1).is_some_and(|n| n.is_ascii_digit())) {
+                self.idx += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.idx].iter().map(|(_, c)| *c).collect();
+        text.parse().map_err(|_| ExpressionError {
+            message: format!("Invalid number: {}", text),
+            position,
+        })
+    }
+
+    fn read_ident(&mut self) -> String {
+        let start = self.idx;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                self.idx += 1;
+            } else {
+                break;
+            }
+        }
+        self.chars[start..self.idx].iter().map(|(_, c)| *c).collect()
+    }
+
+    /// 读取标识符或路径（`a.b[0].c`、`node.<uuid>.result`）
+    fn read_word(&mut self) -> Result<TokenKind, ExpressionError> {
+        let first = self.read_ident();
+
+        match first.as_str() {
+            "and" => return Ok(TokenKind::Op("&&")),
+            "or" => return Ok(TokenKind::Op("||")),
+            "not" => return Ok(TokenKind::Op("!")),
+            "contains" => return Ok(TokenKind::Op("contains")),
+            "in" => return Ok(TokenKind::Op("in")),
+            _ => {}
+        }
+
+        // 函数调用
+        if self.peek() == Some('(') {
+            return Ok(TokenKind::Ident(first));
+        }
+
+        let mut segments = vec![PathSegment::Key(first)];
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.idx += 1;
+                    let segment = if segments.len() == 1 && segments[0] == PathSegment::Key("node".to_string()) {
+                        // 节点 ID 包含连字符，单独读取
+                        let start = self.idx;
+                        while self.peek().is_some_and(|c| c.is_ascii_hexdigit() || c == '-') {
+                            self.idx += 1;
+                        }
+                        self.chars[start..self.idx].iter().map(|(_, c)| *c).collect()
+                    } else {
+                        self.read_ident()
+                    };
+                    if segment.is_empty() {
+                        return Err(self.error("Expected name after '.'"));
+                    }
+                    match segment.parse::<usize>() {
+                        Ok(i) if !segment.contains('-') => segments.push(PathSegment::Index(i)),
+                        _ => segments.push(PathSegment::Key(segment)),
+                    }
+                }
+                Some('[') => {
+                    self.idx += 1;
+                    while self.peek().is_some_and(char::is_whitespace) {
+                        self.idx += 1;
+                    }
+                    match self.peek() {
+                        Some(q @ ('"' | '\'')) => segments.push(PathSegment::Key(self.read_string(q)?)),
+                        Some(c) if c.is_ascii_digit() => {
+                            let n = self.read_number()?;
+                            if n.fract() != 0.0 {
+                                return Err(self.error("Array index must be an integer"));
+                            }
+                            segments.push(PathSegment::Index(n as usize));
+                        }
+                        _ => return Err(self.error("Expected index or quoted key in '[...]'")),
+                    }
+                    while self.peek().is_some_and(char::is_whitespace) {
+                        self.idx += 1;
+                    }
+                    if self.peek() != Some(']') {
+                        return Err(self.error("Expected ']'"));
+                    }
+                    self.idx += 1;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(TokenKind::Path(segments))
+    }
+
+    fn read_operator(&mut self) -> Result<&'static str, ExpressionError> {
+        const OPERATORS: [&str; 17] = [
+            "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%", "=", "&", "|",
+        ];
+        let rest: String = self.chars[self.idx..].iter().take(2).map(|(_, c)| *c).collect();
+        for op in OPERATORS {
+            if rest.starts_with(op) {
+                if matches!(op, "=" | "&" | "|") {
+                    return Err(self.error(format!("Unknown operator '{}'", op)));
+                }
+                self.idx += op.chars().count();
+                return Ok(op);
+            }
+        }
+        Err(self.error(format!("Unexpected character '{}'", rest.chars().next().unwrap_or_default())))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 语法分析
+// ---------------------------------------------------------------------------
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn end_position(&self) -> usize {
+        self.tokens.last().map(|t| t.position + 1).unwrap_or(0)
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        if let Some(Token { kind: TokenKind::Op(op), .. }) = self.peek() {
+            if let Some(found) = ops.iter().find(|o| *o == op) {
+                self.pos += 1;
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_and()?;
+        while self.eat_op(&["||"]).is_some() {
+            let right = self.parse_and()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_not()?;
+        while self.eat_op(&["&&"]).is_some() {
+            let right = self.parse_not()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, ExpressionError> {
+        if self.eat_op(&["!"]).is_some() {
+            let inner = self.parse_not()?;
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(inner)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ExpressionError> {
+        let left = self.parse_additive()?;
+        let op = match self.eat_op(&["==", "!=", "<=", ">=", "<", ">", "contains", "in"]) {
+            Some("==") => BinaryOp::Eq,
+            Some("!=") => BinaryOp::Ne,
+            Some("<=") => BinaryOp::Le,
+            Some(">=") => BinaryOp::Ge,
+            Some("<") => BinaryOp::Lt,
+            Some(">") => BinaryOp::Gt,
+            Some("contains") => BinaryOp::Contains,
+            Some("in") => BinaryOp::In,
+            _ => return Ok(left),
+        };
+        let right = self.parse_additive()?;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_multiplicative()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let right = self.parse_multiplicative()?;
+            let op = if op == "+" { BinaryOp::Add } else { BinaryOp::Sub };
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_unary()?;
+        while let Some(op) = self.eat_op(&["*", "/", "%"]) {
+            let right = self.parse_unary()?;
+            let op = match op {
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ExpressionError> {
+        if self.eat_op(&["-"]).is_some() {
+            let inner = self.parse_unary()?;
+            return Ok(Expr::Unary(UnaryOp::Neg, Box::new(inner)));
+        }
+        if self.eat_op(&["!"]).is_some() {
+            let inner = self.parse_unary()?;
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ExpressionError> {
+        let token = self.peek().cloned().ok_or_else(|| ExpressionError {
+            message: "Unexpected end of expression".to_string(),
+            position: self.end_position(),
+        })?;
+        self.pos += 1;
+
+        match token.kind {
+            TokenKind::Number(n) => Ok(Expr::Literal(number_value(n))),
+            TokenKind::Str(s) => Ok(Expr::Literal(serde_json::Value::String(s))),
+            TokenKind::LParen => {
+                let inner = self.parse_or()?;
+                match self.peek() {
+                    Some(Token { kind: TokenKind::RParen, .. }) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(ExpressionError {
+                        message: "Expected ')'".to_string(),
+                        position: self.peek().map(|t| t.position).unwrap_or_else(|| self.end_position()),
+                    }),
+                }
+            }
+            TokenKind::Ident(name) => self.parse_call(name, token.position),
+            TokenKind::Path(segments) => path_expr(segments, token.position),
+            other => Err(ExpressionError {
+                message: format!("Unexpected token {:?}", other),
+                position: token.position,
+            }),
+        }
+    }
+
+    fn parse_call(&mut self, name: String, position: usize) -> Result<Expr, ExpressionError> {
+        let arity = match name.as_str() {
+            "len" | "lower" | "upper" | "exists" => 1,
+            "starts_with" | "ends_with" => 2,
+            _ => {
+                return Err(ExpressionError {
+                    message: format!("Unknown function '{}'", name),
+                    position,
+                })
+            }
+        };
+
+        // 跳过 '('
+        self.pos += 1;
+        let mut args = Vec::new();
+        if !matches!(self.peek(), Some(Token { kind: TokenKind::RParen, .. })) {
+            loop {
+                args.push(self.parse_or()?);
+                match self.peek() {
+                    Some(Token { kind: TokenKind::Comma, .. }) => self.pos += 1,
+                    _ => break,
+                }
+            }
+        }
+        match self.peek() {
+            Some(Token { kind: TokenKind::RParen, .. }) => self.pos += 1,
+            _ => {
+                return Err(ExpressionError {
+                    message: format!("Expected ')' after arguments to '{}'", name),
+                    position: self.peek().map(|t| t.position).unwrap_or_else(|| self.end_position()),
+                })
+            }
+        }
+
+        if args.len() != arity {
+            return Err(ExpressionError {
+                message: format!("Function '{}' expects {} argument(s), got {}", name, arity, args.len()),
+                position,
+            });
+        }
+        Ok(Expr::Call(name, args))
+    }
+}
+
+fn path_expr(segments: Vec<PathSegment>, position: usize) -> Result<Expr, ExpressionError> {
+    if let [PathSegment::Key(head)] = segments.as_slice() {
+        match head.as_str() {
+            "true" => return Ok(Expr::Literal(serde_json::Value::Bool(true))),
+            "false" => return Ok(Expr::Literal(serde_json::Value::Bool(false))),
+            "null" => return Ok(Expr::Literal(serde_json::Value::Null)),
+            _ => {}
+        }
+    }
+
+    if segments.first() == Some(&PathSegment::Key("node".to_string())) && segments.len() > 1 {
+        let id = match &segments[1] {
+            PathSegment::Key(id) => Uuid::parse_str(id).ok(),
+            PathSegment::Index(_) => None,
+        };
+        let id = id.ok_or_else(|| ExpressionError {
+            message: "Expected node id after 'node.'".to_string(),
+            position,
+        })?;
+        if segments.get(2) != Some(&PathSegment::Key("result".to_string())) {
+            return Err(ExpressionError {
+                message: "Expected 'node.<id>.result'".to_string(),
+                position,
+            });
+        }
+        return Ok(Expr::NodeResult(id, segments[3..].to_vec()));
+    }
+
+    Ok(Expr::Variable(segments))
+}
+
+// ---------------------------------------------------------------------------
+// 求值
+// ---------------------------------------------------------------------------
+
+fn number_value(n: f64) -> serde_json::Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        serde_json::json!(n as i64)
+    } else {
+        serde_json::Number::from_f64(n)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null)
+    }
+}
+
+fn lookup<'v>(root: Option<&'v serde_json::Value>, path: &[PathSegment]) -> Option<&'v serde_json::Value> {
+    path.iter().try_fold(root?, |current, segment| match (segment, current) {
+        (PathSegment::Key(k), serde_json::Value::Object(map)) => map.get(k),
+        (PathSegment::Index(i), serde_json::Value::Array(items)) => items.get(*i),
+        (PathSegment::Index(i), serde_json::Value::Object(map)) => map.get(&i.to_string()),
+        _ => None,
+    })
+}
+
+fn resolve_path(expr: &Expr, context: &ExecutionContext) -> Option<serde_json::Value> {
+    match expr {
+        Expr::Variable(segments) => {
+            let (head, rest) = segments.split_first()?;
+            let head = match head {
+                PathSegment::Key(k) => k.as_str(),
+                PathSegment::Index(_) => return None,
+            };
+            lookup(context.get_variable(head), rest).cloned()
+        }
+        Expr::NodeResult(id, rest) => lookup(context.get_node_result(id), rest).cloned(),
+        _ => None,
+    }
+}
+
+fn eval(expr: &Expr, context: &ExecutionContext) -> Result<serde_json::Value, String> {
+    use serde_json::Value;
+
+    match expr {
+        Expr::Literal(v) => Ok(v.clone()),
+        Expr::Variable(_) | Expr::NodeResult(..) => Ok(resolve_path(expr, context).unwrap_or(Value::Null)),
+        Expr::Unary(UnaryOp::Not, inner) => Ok(Value::Bool(!is_truthy(&eval(inner, context)?))),
+        Expr::Unary(UnaryOp::Neg, inner) => {
+            let v = eval(inner, context)?;
+            let n = v.as_f64().ok_or_else(|| format!("Cannot negate {}", v))?;
+            Ok(number_value(-n))
+        }
+        Expr::Binary(BinaryOp::And, l, r) => {
+            let left = eval(l, context)?;
+            if !is_truthy(&left) {
+                return Ok(Value::Bool(false));
+            }
+            Ok(Value::Bool(is_truthy(&eval(r, context)?)))
+        }
+        Expr::Binary(BinaryOp::Or, l, r) => {
+            let left = eval(l, context)?;
+            if is_truthy(&left) {
+                return Ok(Value::Bool(true));
+            }
+            Ok(Value::Bool(is_truthy(&eval(r, context)?)))
+        }
+        Expr::Binary(op, l, r) => {
+            let left = eval(l, context)?;
+            let right = eval(r, context)?;
+            binary(*op, &left, &right)
+        }
+        Expr::Call(name, args) => {
+            if name == "exists" {
+                return Ok(Value::Bool(resolve_path(&args[0], context).is_some()));
+            }
+            let values = args.iter().map(|a| eval(a, context)).collect::<Result<Vec<_>, _>>()?;
+            call(name, &values)
+        }
+    }
+}
+
+fn values_equal(left: &serde_json::Value, right: &serde_json::Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => left == right,
+    }
+}
+
+fn compare(left: &serde_json::Value, right: &serde_json::Value) -> Result<Ordering, String> {
+    use serde_json::Value;
+
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap_or(f64::NAN), b.as_f64().unwrap_or(f64::NAN));
+            a.partial_cmp(&b).ok_or_else(|| "Cannot compare NaN".to_string())
+        }
+        (Value::String(a), Value::String(b)) => Ok(a.cmp(b)),
+        _ => Err(format!("Cannot compare {} with {}", left, right)),
+    }
+}
+
+fn binary(op: BinaryOp, left: &serde_json::Value, right: &serde_json::Value) -> Result<serde_json::Value, String> {
+    use serde_json::Value;
+
+    let result = match op {
+        BinaryOp::Eq => Value::Bool(values_equal(left, right)),
+        BinaryOp::Ne => Value::Bool(!values_equal(left, right)),
+        BinaryOp::Lt => Value::Bool(compare(left, right)? == Ordering::Less),
+        BinaryOp::Le => Value::Bool(compare(left, right)? != Ordering::Greater),
+        BinaryOp::Gt => Value::Bool(compare(left, right)? == Ordering::Greater),
+        BinaryOp::Ge => Value::Bool(compare(left, right)? != Ordering::Less),
+        BinaryOp::Contains => Value::Bool(contains(left, right)?),
+        BinaryOp::In => Value::Bool(contains(right, left)?),
+        BinaryOp::Add => match (left, right) {
+            (Value::String(a), b) => Value::String(format!("{}{}", a, display(b))),
+            (a, Value::String(b)) => Value::String(format!("{}{}", display(a), b)),
+            _ => arithmetic(op, left, right)?,
+        },
+        BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => arithmetic(op, left, right)?,
+        BinaryOp::And | BinaryOp::Or => unreachable!("short-circuit operators are evaluated in eval"),
+    };
+    Ok(result)
+}
+
+fn arithmetic(op: BinaryOp, left: &serde_json::Value, right: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let (a, b) = match (left.as_f64(), right.as_f64()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Err(format!("Arithmetic requires numbers, got {} and {}", left, right)),
+    };
+    let n = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div | BinaryOp::Rem if b == 0.0 => return Err("Division by zero".to_string()),
+        BinaryOp::Div => a / b,
+        _ => a % b,
+    };
+    Ok(number_value(n))
+}
+
+fn contains(haystack: &serde_json::Value, needle: &serde_json::Value) -> Result<bool, String> {
+    use serde_json::Value;
+
+    match haystack {
+        Value::String(s) => match needle {
+            Value::String(n) => Ok(s.contains(n.as_str())),
+            other => Ok(s.contains(&display(other))),
+        },
+        Value::Array(items) => Ok(items.iter().any(|item| values_equal(item, needle))),
+        Value::Object(map) => match needle {
+            Value::String(key) => Ok(map.contains_key(key)),
+            _ => Err(format!("Object keys must be strings, got {}", needle)),
+        },
+        Value::Null => Ok(false),
+        _ => Err(format!("Cannot search in {}", haystack)),
+    }
+}
+
+fn display(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn call(name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value, String> {
+    use serde_json::Value;
+
+    let as_str = |v: &Value| -> Result<String, String> {
+        v.as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| format!("Function '{}' expects a string, got {}", name, v))
+    };
+
+    match name {
+        "len" => match &args[0] {
+            Value::String(s) => Ok(serde_json::json!(s.chars().count())),
+            Value::Array(a) => Ok(serde_json::json!(a.len())),
+            Value::Object(o) => Ok(serde_json::json!(o.len())),
+            Value::Null => Ok(serde_json::json!(0)),
+            other => Err(format!("len() not supported for {}", other)),
+        },
+        "lower" => Ok(Value::String(as_str(&args[0])?.to_lowercase())),
+        "upper" => Ok(Value::String(as_str(&args[0])?.to_uppercase())),
+        "starts_with" => Ok(Value::Bool(as_str(&args[0])?.starts_with(&as_str(&args[1])?))),
+        "ends_with" => Ok(Value::Bool(as_str(&args[0])?.ends_with(&as_str(&args[1])?))),
+        _ => Err(format!("Unknown function '{}'", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> ExecutionContext {
+        let mut context = ExecutionContext::new(Uuid::new_v4());
+        context.set_variable("value", json!(100));
+        context.set_variable("status", json!("approved"));
+        context.set_variable("order", json!({"items": [{"price": 10}, {"price": 25}], "tags": ["vip"]}));
+        context
+    }
+
+    fn eval_str(src: &str, context: &ExecutionContext) -> serde_json::Value {
+        Expression::parse(src).unwrap().evaluate(context).unwrap()
+    }
+
+    #[test]
+    fn test_comparison_and_boolean() {
+        let context = context();
+        assert_eq!(eval_str("value > 50", &context), json!(true));
+        assert_eq!(eval_str("value > 50 && status == 'approved'", &context), json!(true));
+        assert_eq!(eval_str("value < 50 or not (status != \"approved\")", &context), json!(true));
+        assert_eq!(eval_str("!(value >= 100)", &context), json!(false));
+    }
+
+    #[test]
+    fn test_arithmetic_and_paths() {
+        let context = context();
+        assert_eq!(eval_str("order.items[0].price + order.items.1.price", &context), json!(35));
+        assert_eq!(eval_str("value / 8", &context), json!(12.5));
+        assert_eq!(eval_str("-value % 7", &context), json!(-2));
+        assert_eq!(eval_str("'id-' + value", &context), json!("id-100"));
+    }
+
+    #[test]
+    fn test_string_and_collection_operators() {
+        let context = context();
+        assert_eq!(eval_str("status contains 'prov'", &context), json!(true));
+        assert_eq!(eval_str("'vip' in order.tags", &context), json!(true));
+        assert_eq!(eval_str("len(order.items) == 2", &context), json!(true));
+        assert_eq!(eval_str("starts_with(upper(status), 'APP')", &context), json!(true));
+        assert_eq!(eval_str("exists(order.missing)", &context), json!(false));
+        assert_eq!(eval_str("missing == null", &context), json!(true));
+    }
+
+    #[test]
+    fn test_node_result_access() {
+        let mut context = context();
+        let node_id = Uuid::new_v4();
+        context.set_node_result(node_id, json!({"score": 0.9}));
+
+        let expr = format!("node.{}.result.score >= 0.8", node_id);
+        assert_eq!(eval_str(&expr, &context), json!(true));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Expression::parse("value >").is_err());
+        assert!(Expression::parse("(value > 1").is_err());
+        assert!(Expression::parse("value = 1").is_err());
+        assert!(Expression::parse("'unterminated").is_err());
+        assert!(Expression::parse("unknown_fn(1)").is_err());
+        assert!(Expression::parse("node.not-a-uuid.result").is_err());
+
+        let err = Expression::parse("value > 1 1").unwrap_err();
+        assert_eq!(err.position, 10);
+    }
+
+    #[test]
+    fn test_runtime_type_error() {
+        let context = context();
+        let expr = Expression::parse("status > 1").unwrap();
+        assert!(expr.evaluate(&context).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::executor::ExecutionContext;
+use super::workflow::Workflow;
+
+/// 节点状态变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeTransition {
+    /// 节点开始执行
+    Started,
+    /// 节点执行完成
+    Completed,
+    /// 节点执行失败
+    Failed,
+    /// 节点在恢复时重放，复用已记录的结果
+    Replayed,
+}
+
+/// 执行日志条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub execution_id: Uuid,
+    pub node_id: Uuid,
+    pub transition: NodeTransition,
+    pub timestamp: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+impl JournalEntry {
+    pub fn new(execution_id: Uuid, node_id: Uuid, transition: NodeTransition) -> Self {
+        Self {
+            execution_id,
+            node_id,
+            transition,
+            timestamp: Utc::now(),
+            error: None,
+        }
+    }
+
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+}
+
+/// 工作流运行日志
+///
+/// 通过 `WorkflowExecutor::with_journal` 挂载后，执行器在运行开始、每次节点状态
+/// 变化以及运行结束时自动写入，实现崩溃后可恢复的持久化执行。
+/// 任何写入失败都会终止当前运行。
+#[async_trait]
+pub trait RunJournal: Send + Sync {
+    /// 运行开始（或恢复）时调用
+    async fn begin(&self, workflow: &Workflow, context: &ExecutionContext) -> Result<(), String>;
+
+    /// 节点状态变化时调用，`context` 为变化后的上下文快照
+    async fn record(&self, entry: &JournalEntry, context: &ExecutionContext) -> Result<(), String>;
+
+    /// 运行结束（完成或失败）时调用
+    async fn finish(&self, context: &ExecutionContext) -> Result<(), String>;
+}
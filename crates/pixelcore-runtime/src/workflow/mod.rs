@@ -3,11 +3,30 @@ pub mod edge;
 pub mod workflow;
 pub mod executor;
 pub mod error_handling;
+pub mod expression;
 pub mod persistence;
+pub mod journal;
+pub mod task_handler;
+pub mod template;
+pub mod definition;
+pub mod validator;
+pub mod events;
+pub mod timeline;
 
-pub use node::{NodeType, WorkflowNode};
+pub use node::{NodeType, WorkflowNode, JoinPolicy, BranchFailurePolicy};
 pub use edge::{EdgeCondition, WorkflowEdge};
 pub use workflow::{Workflow, WorkflowStatus};
 pub use executor::{WorkflowExecutor, ExecutionContext, ExecutionStatus};
 pub use error_handling::{ErrorHandlingStrategy, RetryPolicy};
+pub use expression::{Expression, ExpressionError};
 pub use persistence::{WorkflowPersistence, PersistenceError};
+pub use journal::{RunJournal, JournalEntry, NodeTransition};
+pub use task_handler::{TaskHandler, FnTaskHandler, EchoTaskHandler};
+pub use template::render_params;
+pub use definition::{
+    WorkflowDefinition, NodeDefinition, EdgeDefinition, NodeKind, ErrorHandlingDefinition,
+    WorkflowLoader, DefinitionError,
+};
+pub use validator::{WorkflowValidator, ValidationIssue, Severity};
+pub use events::{WorkflowEvent, WorkflowEventKind, WorkflowEventSink};
+pub use timeline::{NodeTimelineEntry, NodeRunStatus};
@@ -30,17 +30,67 @@ pub enum NodeType {
         /// 最大迭代次数
         max_iterations: usize,
     },
-    /// 并行节点
+    /// 并行节点（fan-out）
     Parallel {
         /// 并行分支数
         branches: usize,
+        /// 单个分支的超时时间（毫秒）
+        #[serde(default)]
+        branch_timeout_ms: Option<u64>,
+        /// 分支失败时的处理方式
+        #[serde(default)]
+        on_branch_failure: BranchFailurePolicy,
     },
+    /// 汇聚节点（fan-in），等待上游并行分支按策略完成
+    Join {
+        /// 完成策略
+        #[serde(default)]
+        policy: JoinPolicy,
+    },
+}
+
+/// 汇聚策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JoinPolicy {
+    /// 所有分支成功
+    #[default]
+    All,
+    /// 任一分支成功
+    Any,
+    /// 至少 n 个分支成功
+    NOfM { n: usize },
+}
+
+impl JoinPolicy {
+    /// 汇聚所需的成功分支数
+    pub fn required(&self, total: usize) -> usize {
+        match self {
+            JoinPolicy::All => total,
+            JoinPolicy::Any => 1.min(total),
+            JoinPolicy::NOfM { n } => (*n).min(total),
+        }
+    }
+}
+
+/// 分支失败（或汇聚已有结论）时如何处理其余分支
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BranchFailurePolicy {
+    /// 取消仍在运行的分支
+    #[default]
+    CancelOthers,
+    /// 等待其余分支自然结束
+    LetFinish,
 }
 
 /// 工作流节点
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowNode {
     pub id: Uuid,
+    /// 人类可读的节点标识（声明式定义中的 `key`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
     pub name: String,
     pub node_type: NodeType,
     pub error_handling: ErrorHandlingStrategy,
@@ -51,6 +101,7 @@ impl WorkflowNode {
     pub fn new(name: impl Into<String>, node_type: NodeType) -> Self {
         Self {
             id: Uuid::new_v4(),
+            key: None,
             name: name.into(),
             node_type,
             error_handling: ErrorHandlingStrategy::default(),
@@ -58,6 +109,11 @@ impl WorkflowNode {
         }
     }
 
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
     pub fn with_error_handling(mut self, strategy: ErrorHandlingStrategy) -> Self {
         self.error_handling = strategy;
         self
@@ -92,6 +148,14 @@ impl WorkflowNode {
     }
 
     pub fn parallel(name: impl Into<String>, branches: usize) -> Self {
-        Self::new(name, NodeType::Parallel { branches })
+        Self::new(name, NodeType::Parallel {
+            branches,
+            branch_timeout_ms: None,
+            on_branch_failure: BranchFailurePolicy::default(),
+        })
+    }
+
+    pub fn join(name: impl Into<String>, policy: JoinPolicy) -> Self {
+        Self::new(name, NodeType::Join { policy })
     }
 }
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+
+/// 任务处理器
+///
+/// 通过 `WorkflowExecutor::register_task_handler` 按 `NodeType::Task::task_name`
+/// 注册，执行器在运行任务节点时调用。参数中的 `${...}` 占位符已在调用前渲染。
+#[async_trait]
+pub trait TaskHandler: Send + Sync {
+    async fn handle(
+        &self,
+        task_name: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String>;
+}
+
+/// 基于闭包的任务处理器
+pub struct FnTaskHandler<F>(pub F);
+
+#[async_trait]
+impl<F> TaskHandler for FnTaskHandler<F>
+where
+    F: Fn(&str, serde_json::Value) -> Result<serde_json::Value, String> + Send + Sync,
+{
+    async fn handle(
+        &self,
+        task_name: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        (self.0)(task_name, params)
+    }
+}
+
+/// 回显参数的任务处理器，仅用于演示和测试
+///
+/// 需要显式注册（按任务名或通过 `WorkflowExecutor::with_fallback_handler`），
+/// 返回 `{"task": ..., "params": ..., "result": "success"}`。
+pub struct EchoTaskHandler;
+
+#[async_trait]
+impl TaskHandler for EchoTaskHandler {
+    async fn handle(
+        &self,
+        task_name: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        Ok(serde_json::json!({
+            "task": task_name,
+            "params": params,
+            "result": "success"
+        }))
+    }
+}
@@ -0,0 +1,142 @@
+use uuid::Uuid;
+
+use super::executor::ExecutionContext;
+
+/// 渲染任务参数中的 `${...}` 占位符
+///
+/// 支持两种引用：
+/// - `${var}` / `${var.field}`：执行上下文中的变量
+/// - `${node.<id>.result}` / `${node.<id>.result.field}`：先前节点的执行结果
+///
+/// 如果字符串恰好是一个占位符，则替换为原始 JSON 值（保留类型），
+/// 否则按字符串插值。
+pub fn render_params(
+    params: &serde_json::Value,
+    context: &ExecutionContext,
+) -> Result<serde_json::Value, String> {
+    match params {
+        serde_json::Value::String(s) => render_string(s, context),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| render_params(item, context))
+            .collect::<Result<Vec<_>, _>>()
+            .map(serde_json::Value::Array),
+        serde_json::Value::Object(map) => {
+            let mut rendered = serde_json::Map::with_capacity(map.len());
+            for (key, value) in map {
+                rendered.insert(key.clone(), render_params(value, context)?);
+            }
+            Ok(serde_json::Value::Object(rendered))
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+fn render_string(s: &str, context: &ExecutionContext) -> Result<serde_json::Value, String> {
+    // 整个字符串就是一个占位符：保留原始 JSON 类型
+    if let Some(expr) = s.strip_prefix("${").and_then(|rest| rest.strip_suffix('}')) {
+        if !expr.contains("${") && !expr.contains('}') {
+            return resolve(expr.trim(), context);
+        }
+    }
+
+    let mut output = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("Unterminated placeholder in: {}", s))?;
+        match resolve(after[..end].trim(), context)? {
+            serde_json::Value::String(v) => output.push_str(&v),
+            v => output.push_str(&v.to_string()),
+        }
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+
+    Ok(serde_json::Value::String(output))
+}
+
+fn resolve(expr: &str, context: &ExecutionContext) -> Result<serde_json::Value, String> {
+    let mut segments = expr.split('.');
+    let head = segments.next().unwrap_or_default();
+
+    let (root, path): (&serde_json::Value, Vec<&str>) = if head == "node" {
+        let id_str = segments
+            .next()
+            .ok_or_else(|| format!("Missing node id in placeholder: ${{{}}}", expr))?;
+        let node_id = Uuid::parse_str(id_str)
+            .map_err(|_| format!("Invalid node id in placeholder: ${{{}}}", expr))?;
+        if segments.next() != Some("result") {
+            return Err(format!("Expected `node.<id>.result` in placeholder: ${{{}}}", expr));
+        }
+        let result = context
+            .get_node_result(&node_id)
+            .ok_or_else(|| format!("No result for node {} in placeholder: ${{{}}}", node_id, expr))?;
+        (result, segments.collect())
+    } else {
+        let value = context
+            .get_variable(head)
+            .ok_or_else(|| format!("Unknown variable in placeholder: ${{{}}}", expr))?;
+        (value, segments.collect())
+    };
+
+    let mut current = root;
+    for segment in path {
+        current = match current {
+            serde_json::Value::Object(map) => map.get(segment),
+            serde_json::Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Path `{}` not found in placeholder: ${{{}}}", segment, expr))?;
+    }
+
+    Ok(current.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> ExecutionContext {
+        let mut context = ExecutionContext::new(Uuid::new_v4());
+        context.set_variable("user", json!({"name": "Alice", "age": 30}));
+        context.set_variable("count", json!(5));
+        context
+    }
+
+    #[test]
+    fn test_render_whole_placeholder_keeps_type() {
+        let context = context();
+        let rendered = render_params(&json!({"n": "${count}", "age": "${user.age}"}), &context).unwrap();
+        assert_eq!(rendered, json!({"n": 5, "age": 30}));
+    }
+
+    #[test]
+    fn test_render_interpolation() {
+        let context = context();
+        let rendered = render_params(&json!(["Hello ${user.name}, you have ${count} items"]), &context).unwrap();
+        assert_eq!(rendered, json!(["Hello Alice, you have 5 items"]));
+    }
+
+    #[test]
+    fn test_render_node_result() {
+        let mut context = context();
+        let node_id = Uuid::new_v4();
+        context.set_node_result(node_id, json!({"rows": [1, 2, 3]}));
+
+        let template = json!({"first": format!("${{node.{}.result.rows.0}}", node_id)});
+        let rendered = render_params(&template, &context).unwrap();
+        assert_eq!(rendered, json!({"first": 1}));
+    }
+
+    #[test]
+    fn test_render_missing_variable() {
+        let context = context();
+        assert!(render_params(&json!("${missing}"), &context).is_err());
+        assert!(render_params(&json!("${count"), &context).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 节点运行状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeRunStatus {
+    Running,
+    Completed,
+    Failed,
+    /// 恢复执行时跳过（复用之前运行的结果）
+    Skipped,
+    /// 运行在节点结束前中断（进程崩溃或暂停）
+    Interrupted,
+}
+
+/// 时间线条目：节点的一次执行
+///
+/// 同一节点在循环中多次执行时每次各有一条。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeTimelineEntry {
+    pub node_id: Uuid,
+    pub node_name: String,
+    pub status: NodeRunStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<u64>,
+    /// 任务执行次数（含重试）
+    pub attempts: u32,
+    /// 渲染后的任务参数
+    pub input: Option<serde_json::Value>,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+impl NodeTimelineEntry {
+    pub fn new(node_id: Uuid, node_name: impl Into<String>) -> Self {
+        Self {
+            node_id,
+            node_name: node_name.into(),
+            status: NodeRunStatus::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            duration_ms: None,
+            attempts: 0,
+            input: None,
+            output: None,
+            error: None,
+        }
+    }
+
+    /// 结束条目并计算耗时
+    pub fn finish(&mut self, status: NodeRunStatus) {
+        let now = Utc::now();
+        self.status = status;
+        self.finished_at = Some(now);
+        self.duration_ms = Some((now - self.started_at).num_milliseconds().max(0) as u64);
+    }
+}
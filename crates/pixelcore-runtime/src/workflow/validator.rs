@@ -0,0 +1,396 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use uuid::Uuid;
+
+use super::definition::{locate_line, DefinitionError, WorkflowDefinition};
+use super::node::{JoinPolicy, NodeType};
+use super::workflow::Workflow;
+
+/// 问题严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 验证发现的问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// 相关节点
+    pub node: Option<Uuid>,
+    /// 相关节点的 key
+    pub key: Option<String>,
+    pub message: String,
+    /// 定义文件中的行号（从 1 开始）
+    pub line: Option<usize>,
+}
+
+impl ValidationIssue {
+    fn new(severity: Severity, workflow: &Workflow, node: Option<Uuid>, message: String) -> Self {
+        Self {
+            severity,
+            node,
+            key: node.and_then(|id| workflow.get_node(&id)).and_then(|n| n.key.clone()),
+            message,
+            line: None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "{} (line {}): {}", severity, line, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// 工作流结构验证器
+///
+/// 在 `Workflow::validate` 的基础检查之外，分析图结构：
+/// - 缺少开始 / 结束节点（错误）
+/// - 从开始节点不可达的节点（错误），错误处理引用的节点视为可达
+/// - 不经过 `Loop` 节点的环（错误）
+/// - `NOfM` 汇聚策略的 `n` 为 0 或超过汇入的分支数（错误）
+/// - 没有出边的非结束节点（警告）
+pub struct WorkflowValidator;
+
+impl WorkflowValidator {
+    pub fn validate(workflow: &Workflow) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let issue = |severity, node, message| ValidationIssue::new(severity, workflow, node, message);
+
+        if let Err(e) = workflow.validate() {
+            // 基础检查中已经涵盖的开始 / 结束节点问题由下面单独报告
+            if !e.contains("start node") && !e.contains("end node") {
+                issues.push(issue(Severity::Error, None, e));
+            }
+        }
+
+        let start = workflow.find_start_node().map(|n| n.id);
+        if start.is_none() {
+            issues.push(issue(Severity::Error, None, "Workflow has no start node".to_string()));
+        }
+        if !workflow.nodes.values().any(|n| matches!(n.node_type, NodeType::End)) {
+            issues.push(issue(Severity::Error, None, "Workflow has no end node".to_string()));
+        }
+
+        let successors = Self::successors(workflow);
+
+        // 可达性
+        if let Some(start) = start {
+            let mut reachable = HashSet::from([start]);
+            let mut queue = VecDeque::from([start]);
+            while let Some(id) = queue.pop_front() {
+                for next in successors.get(&id).into_iter().flatten() {
+                    if reachable.insert(*next) {
+                        queue.push_back(*next);
+                    }
+                }
+            }
+            for node in Self::sorted_nodes(workflow) {
+                if !reachable.contains(&node) {
+                    let name = &workflow.nodes[&node].name;
+                    issues.push(issue(
+                        Severity::Error,
+                        Some(node),
+                        format!("Node '{}' is unreachable from the start node", name),
+                    ));
+                }
+            }
+        }
+
+        // 不经过 Loop 节点的环
+        for component in Self::cycles(workflow, &successors) {
+            let has_loop = component
+                .iter()
+                .any(|id| matches!(workflow.nodes[id].node_type, NodeType::Loop { .. }));
+            if !has_loop {
+                let names: Vec<_> = component.iter().map(|id| workflow.nodes[id].name.as_str()).collect();
+                issues.push(issue(
+                    Severity::Error,
+                    component.first().copied(),
+                    format!("Cycle without a loop node: {}", names.join(" -> ")),
+                ));
+            }
+        }
+
+        // 汇聚策略
+        for id in Self::sorted_nodes(workflow) {
+            let node = &workflow.nodes[&id];
+            let NodeType::Join { policy: JoinPolicy::NOfM { n } } = node.node_type else { continue };
+            let branches: HashSet<Uuid> = workflow.get_incoming_edges(&id).iter().map(|e| e.from).collect();
+            if n == 0 {
+                issues.push(issue(
+                    Severity::Error,
+                    Some(id),
+                    format!("Join node '{}' requires n >= 1 successful branches, got n = 0", node.name),
+                ));
+            } else if n > branches.len() {
+                issues.push(issue(
+                    Severity::Error,
+                    Some(id),
+                    format!(
+                        "Join node '{}' waits for {} branches but only {} lead into it",
+                        node.name,
+                        n,
+                        branches.len()
+                    ),
+                ));
+            }
+        }
+
+        // 死端
+        let handler_targets: HashSet<Uuid> =
+            workflow.nodes.values().filter_map(|n| n.error_handling.target_node()).collect();
+        for id in Self::sorted_nodes(workflow) {
+            let node = &workflow.nodes[&id];
+            if matches!(node.node_type, NodeType::End) || handler_targets.contains(&id) {
+                continue;
+            }
+            if workflow.get_outgoing_edges(&id).is_empty() {
+                issues.push(issue(
+                    Severity::Warning,
+                    Some(id),
+                    format!("Node '{}' has no outgoing edges and is not an end node", node.name),
+                ));
+            }
+        }
+
+        issues
+    }
+
+    /// 加载并验证 YAML 定义，问题附带行号
+    pub fn validate_yaml(source: &str) -> Result<Vec<ValidationIssue>, DefinitionError> {
+        let workflow = WorkflowDefinition::from_yaml(source)?.to_workflow(Some(source))?;
+        Ok(Self::with_lines(Self::validate(&workflow), source))
+    }
+
+    /// 加载并验证 JSON 定义，问题附带行号
+    pub fn validate_json(source: &str) -> Result<Vec<ValidationIssue>, DefinitionError> {
+        let workflow = WorkflowDefinition::from_json(source)?.to_workflow(Some(source))?;
+        Ok(Self::with_lines(Self::validate(&workflow), source))
+    }
+
+    fn with_lines(issues: Vec<ValidationIssue>, source: &str) -> Vec<ValidationIssue> {
+        issues
+            .into_iter()
+            .map(|mut issue| {
+                issue.line = issue.key.as_deref().and_then(|key| locate_line(source, "key", key));
+                issue
+            })
+            .collect()
+    }
+
+    fn sorted_nodes(workflow: &Workflow) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = workflow.nodes.keys().copied().collect();
+        ids.sort_by(|a, b| (&workflow.nodes[a].name, a).cmp(&(&workflow.nodes[b].name, b)));
+        ids
+    }
+
+    /// 后继节点：边的目标以及错误处理跳转的目标
+    fn successors(workflow: &Workflow) -> HashMap<Uuid, Vec<Uuid>> {
+        let mut successors: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for edge in &workflow.edges {
+            if workflow.nodes.contains_key(&edge.to) {
+                successors.entry(edge.from).or_default().push(edge.to);
+            }
+        }
+        for node in workflow.nodes.values() {
+            if let Some(target) = node.error_handling.target_node() {
+                if workflow.nodes.contains_key(&target) {
+                    successors.entry(node.id).or_default().push(target);
+                }
+            }
+        }
+        successors
+    }
+
+    /// 用 Tarjan 算法找出所有包含环的强连通分量
+    fn cycles(workflow: &Workflow, successors: &HashMap<Uuid, Vec<Uuid>>) -> Vec<Vec<Uuid>> {
+        struct State<'a> {
+            successors: &'a HashMap<Uuid, Vec<Uuid>>,
+            index: usize,
+            indices: HashMap<Uuid, usize>,
+            lowlink: HashMap<Uuid, usize>,
+            stack: Vec<Uuid>,
+            on_stack: HashSet<Uuid>,
+            components: Vec<Vec<Uuid>>,
+        }
+
+        fn connect(state: &mut State<'_>, node: Uuid) {
+            state.indices.insert(node, state.index);
+            state.lowlink.insert(node, state.index);
+            state.index += 1;
+            state.stack.push(node);
+            state.on_stack.insert(node);
+
+            let successors = state.successors.get(&node).cloned().unwrap_or_default();
+            for next in successors {
+                if !state.indices.contains_key(&next) {
+                    connect(state, next);
+                    let low = state.lowlink[&node].min(state.lowlink[&next]);
+                    state.lowlink.insert(node, low);
+                } else if state.on_stack.contains(&next) {
+                    let low = state.lowlink[&node].min(state.indices[&next]);
+                    state.lowlink.insert(node, low);
+                }
+            }
+
+            if state.lowlink[&node] == state.indices[&node] {
+                let mut component = Vec::new();
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack.remove(&member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                let self_loop = state.successors.get(&node).is_some_and(|s| s.contains(&node));
+                if component.len() > 1 || self_loop {
+                    component.reverse();
+                    state.components.push(component);
+                }
+            }
+        }
+
+        let mut state = State {
+            successors,
+            index: 0,
+            indices: HashMap::new(),
+            lowlink: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: HashSet::new(),
+            components: Vec::new(),
+        };
+        for node in Self::sorted_nodes(workflow) {
+            if !state.indices.contains_key(&node) {
+                connect(&mut state, node);
+            }
+        }
+        state.components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_workflow_has_no_issues() {
+        let source = r#"
+name: ok
+nodes:
+  - { key: start, type: start }
+  - { key: poll, type: loop, condition: "count < 3", max_iterations: 5 }
+  - { key: work, type: task, task: work }
+  - { key: end, type: end }
+edges:
+  - { from: start, to: poll }
+  - { from: poll, to: work, when: "count < 3" }
+  - { from: work, to: poll }
+  - { from: poll, to: end, when: "count >= 3" }
+"#;
+        assert_eq!(WorkflowValidator::validate_yaml(source).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_reports_structural_problems_with_lines() {
+        let source = r#"name: broken
+nodes:
+  - key: start
+    type: start
+  - key: a
+    type: task
+    task: a
+  - key: b
+    type: task
+    task: b
+  - key: orphan
+    type: task
+    task: orphan
+edges:
+  - { from: start, to: a }
+  - { from: a, to: b }
+  - { from: b, to: a }
+"#;
+        let issues = WorkflowValidator::validate_yaml(source).unwrap();
+        let errors: Vec<_> = issues.iter().filter(|i| i.is_error()).collect();
+
+        assert!(errors.iter().any(|i| i.message.contains("no end node")));
+        let orphan = errors.iter().find(|i| i.message.contains("unreachable")).unwrap();
+        assert_eq!(orphan.key.as_deref(), Some("orphan"));
+        assert_eq!(orphan.line, Some(11));
+        let cycle = errors.iter().find(|i| i.message.contains("Cycle")).unwrap();
+        assert_eq!(cycle.key.as_deref(), Some("a"));
+        assert_eq!(cycle.line, Some(5));
+
+        let warning = issues.iter().find(|i| i.severity == Severity::Warning).unwrap();
+        assert_eq!(warning.key.as_deref(), Some("orphan"));
+    }
+
+    #[test]
+    fn test_error_handling_targets_are_reachable() {
+        let source = r#"
+name: fallback
+nodes:
+  - { key: start, type: start }
+  - key: primary
+    type: task
+    task: primary
+    error_handling: { type: fallback, fallback_node: backup }
+  - { key: backup, type: task, task: backup }
+  - { key: end, type: end }
+edges:
+  - { from: start, to: primary }
+  - { from: primary, to: end }
+  - { from: backup, to: end }
+"#;
+        assert!(WorkflowValidator::validate_yaml(source).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_impossible_join_policies() {
+        let source = |n: usize| {
+            format!(
+                r#"
+name: fan
+nodes:
+  - {{ key: start, type: start }}
+  - {{ key: fan, type: parallel, branches: 2 }}
+  - {{ key: a, type: task, task: a }}
+  - {{ key: b, type: task, task: b }}
+  - {{ key: join, type: join, policy: {{ type: n_of_m, n: {n} }} }}
+  - {{ key: end, type: end }}
+edges:
+  - {{ from: start, to: fan }}
+  - {{ from: fan, to: a, parallel_branch: 0 }}
+  - {{ from: fan, to: b, parallel_branch: 1 }}
+  - {{ from: a, to: join }}
+  - {{ from: b, to: join }}
+  - {{ from: join, to: end }}
+"#
+            )
+        };
+        assert!(WorkflowValidator::validate_yaml(&source(2)).unwrap().is_empty());
+
+        for (n, expected) in [(0, "n = 0"), (3, "waits for 3 branches but only 2")] {
+            let issues = WorkflowValidator::validate_yaml(&source(n)).unwrap();
+            assert_eq!(issues.len(), 1, "{:?}", issues);
+            assert!(issues[0].is_error() && issues[0].message.contains(expected), "{}", issues[0]);
+            assert_eq!(issues[0].key.as_deref(), Some("join"));
+        }
+    }
+}
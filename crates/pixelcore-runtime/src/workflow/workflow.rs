@@ -3,8 +3,9 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 
-use super::node::WorkflowNode;
-use super::edge::WorkflowEdge;
+use super::node::{NodeType, WorkflowNode};
+use super::edge::{EdgeCondition, WorkflowEdge};
+use super::expression::Expression;
 
 /// 工作流状态
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -74,6 +75,11 @@ impl Workflow {
         self.nodes.get(id)
     }
 
+    /// 按 key 查找节点
+    pub fn find_node_by_key(&self, key: &str) -> Option<&WorkflowNode> {
+        self.nodes.values().find(|n| n.key.as_deref() == Some(key))
+    }
+
     /// 获取节点的出边
     pub fn get_outgoing_edges(&self, node_id: &Uuid) -> Vec<&WorkflowEdge> {
         self.edges.iter().filter(|e| &e.from == node_id).collect()
@@ -86,7 +92,7 @@ impl Workflow {
 
     /// 查找开始节点
     pub fn find_start_node(&self) -> Option<&WorkflowNode> {
-        self.nodes.values().find(|n| matches!(n.node_type, super::node::NodeType::Start))
+        self.nodes.values().find(|n| matches!(n.node_type, NodeType::Start))
     }
 
     /// 设置变量
@@ -108,7 +114,7 @@ impl Workflow {
         }
 
         // 检查是否有结束节点
-        let has_end = self.nodes.values().any(|n| matches!(n.node_type, super::node::NodeType::End));
+        let has_end = self.nodes.values().any(|n| matches!(n.node_type, NodeType::End));
         if !has_end {
             return Err("Workflow must have at least one end node".to_string());
         }
@@ -121,6 +127,32 @@ impl Workflow {
             if !self.nodes.contains_key(&edge.to) {
                 return Err(format!("Edge references non-existent to node: {}", edge.to));
             }
+            if let EdgeCondition::Expression { expr } = &edge.condition {
+                Expression::parse(expr).map_err(|e| {
+                    format!("Invalid expression on edge {} -> {}: '{}': {}", edge.from, edge.to, expr, e)
+                })?;
+            }
+        }
+
+        // 检查错误处理策略引用的节点
+        for node in self.nodes.values() {
+            if let Some(target) = node.error_handling.target_node() {
+                if !self.nodes.contains_key(&target) {
+                    return Err(format!(
+                        "Node '{}' error handling references non-existent node: {}",
+                        node.name, target
+                    ));
+                }
+            }
+        }
+
+        // 检查条件表达式能否解析
+        for node in self.nodes.values() {
+            if let NodeType::Decision { condition } | NodeType::Loop { condition, .. } = &node.node_type {
+                Expression::parse(condition).map_err(|e| {
+                    format!("Invalid expression in node '{}': '{}': {}", node.name, condition, e)
+                })?;
+            }
         }
 
         Ok(())
@@ -180,6 +212,47 @@ mod tests {
         assert!(workflow.validate().is_ok());
     }
 
+    #[test]
+    fn test_validate_rejects_bad_expressions() {
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let decision_id = workflow.add_node(WorkflowNode::decision("Check", "value >"));
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start_id, decision_id);
+        workflow.connect(decision_id, end_id);
+
+        let err = workflow.validate().unwrap_err();
+        assert!(err.contains("Check"));
+
+        workflow.nodes.get_mut(&decision_id).unwrap().node_type = NodeType::Decision {
+            condition: "value > 1".to_string(),
+        };
+        assert!(workflow.validate().is_ok());
+
+        workflow.connect_when(start_id, end_id, "a ==");
+        assert!(workflow.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_error_handling_targets() {
+        use crate::workflow::error_handling::ErrorHandlingStrategy;
+
+        let mut workflow = Workflow::new("Test", "Test");
+        let start_id = workflow.add_node(WorkflowNode::start("Start"));
+        let task = WorkflowNode::task("Task", "task", serde_json::json!({}))
+            .with_error_handling(ErrorHandlingStrategy::Fallback { fallback_node: Uuid::new_v4() });
+        let task_id = workflow.add_node(task);
+        let end_id = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start_id, task_id);
+        workflow.connect(task_id, end_id);
+
+        assert!(workflow.validate().is_err());
+
+        workflow.nodes.get_mut(&task_id).unwrap().error_handling =
+            ErrorHandlingStrategy::Fallback { fallback_node: end_id };
+        assert!(workflow.validate().is_ok());
+    }
+
     #[test]
     fn test_workflow_variables() {
         let mut workflow = Workflow::new("Test", "Test");
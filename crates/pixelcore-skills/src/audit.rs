@@ -0,0 +1,91 @@
+//! Audit trail for skill permission decisions
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use pixelcore_runtime::agent::AgentId;
+use pixelcore_runtime::event::{Event, EventBus, EventKind};
+
+use crate::permissions::PermissionCheck;
+
+/// Outcome of a permission check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionDecision {
+    Allowed,
+    Denied,
+}
+
+/// One permission decision made by `SkillRegistry` before running a skill
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionAuditEvent {
+    pub agent_id: Option<AgentId>,
+    pub skill: String,
+    pub decision: PermissionDecision,
+    /// Everything the call required
+    pub checks: Vec<PermissionCheck>,
+    /// The check that was refused, if any
+    pub denied: Option<PermissionCheck>,
+    /// Why the call was denied when no single check applies (e.g. unparsable input)
+    pub reason: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PermissionAuditEvent {
+    pub fn is_allowed(&self) -> bool {
+        self.decision == PermissionDecision::Allowed
+    }
+}
+
+/// Receives permission audit events
+///
+/// Recording happens on the execution path, so implementations should not block.
+pub trait PermissionAuditSink: Send + Sync {
+    fn record(&self, event: &PermissionAuditEvent);
+}
+
+/// Publishes `EventKind::Custom("skill.permission_allowed" | "skill.permission_denied")`
+/// with `source` set to `skill:<name>`
+impl PermissionAuditSink for EventBus {
+    fn record(&self, event: &PermissionAuditEvent) {
+        let kind = match event.decision {
+            PermissionDecision::Allowed => "skill.permission_allowed",
+            PermissionDecision::Denied => "skill.permission_denied",
+        };
+        let payload = serde_json::to_value(event).unwrap_or(serde_json::Value::Null);
+        let _ = self.publish(Event::new(
+            EventKind::Custom(kind.to_string()),
+            format!("skill:{}", event.skill),
+            payload,
+        ));
+    }
+}
+
+/// In-memory audit log, mostly useful for inspection and tests
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    events: Mutex<Vec<PermissionAuditEvent>>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All recorded events, oldest first
+    pub fn events(&self) -> Vec<PermissionAuditEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Recorded denials, oldest first
+    pub fn denials(&self) -> Vec<PermissionAuditEvent> {
+        self.events.lock().unwrap().iter().filter(|e| !e.is_allowed()).cloned().collect()
+    }
+}
+
+impl PermissionAuditSink for AuditLog {
+    fn record(&self, event: &PermissionAuditEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
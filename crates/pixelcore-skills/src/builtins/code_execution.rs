@@ -1,18 +1,50 @@
 //! Code execution skills with sandboxing
 //!
 //! This module provides skills for executing code in various languages
-//! with proper sandboxing and resource limits.
+//! with proper sandboxing and resource limits. Every run goes through a
+//! `Sandbox`; see `crate::sandbox` for what it isolates.
 
 use crate::skill::{Skill, SkillInput, SkillOutput};
 use crate::error::SkillError;
+use crate::permissions::{PermissionCheck, PermissionManager};
+use crate::registry::SkillRegistry;
+use crate::sandbox::{Sandbox, SandboxConfig, SandboxOutput};
 use async_trait::async_trait;
 use serde_json::{json, Value};
-use std::process::Stdio;
-use tokio::process::Command;
+use std::sync::Arc;
 use std::time::Duration;
 
-/// Python code execution skill with sandboxing
-pub struct PythonExecuteSkill;
+macro_rules! sandboxed_skill {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Default)]
+        pub struct $name {
+            sandbox: Sandbox,
+        }
+
+        impl $name {
+            /// Run with the default `SandboxConfig`, which requires namespace isolation
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// Run with the limits granted by `permissions`
+            pub fn from_permissions(permissions: &PermissionManager) -> Self {
+                Self::with_sandbox(SandboxConfig::from_permissions(permissions))
+            }
+
+            /// Run with custom limits
+            pub fn with_sandbox(config: SandboxConfig) -> Self {
+                Self { sandbox: Sandbox::new(config) }
+            }
+        }
+    };
+}
+
+sandboxed_skill!(
+    /// Python code execution skill with sandboxing
+    PythonExecuteSkill
+);
 
 #[async_trait]
 impl Skill for PythonExecuteSkill {
@@ -42,6 +74,10 @@ impl Skill for PythonExecuteSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::process("python3", None), compute_budget(input, &self.sandbox)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let code = input.args.get("code")
             .and_then(|v| v.as_str())
@@ -53,20 +89,16 @@ impl Skill for PythonExecuteSkill {
             .min(30); // Max 30 seconds
 
         // Execute Python code with timeout
-        let result = execute_python_code(code, timeout_seconds).await?;
-
-        Ok(SkillOutput::ok(json!({
-            "stdout": result.stdout,
-            "stderr": result.stderr,
-            "exit_code": result.exit_code,
-            "success": result.exit_code == 0,
-            "timed_out": result.timed_out,
-        })))
+        let result = self.sandbox.run("python3", &["-c", code], Duration::from_secs(timeout_seconds)).await?;
+
+        Ok(execution_output(result))
     }
 }
 
-/// JavaScript code execution skill
-pub struct JavaScriptExecuteSkill;
+sandboxed_skill!(
+    /// JavaScript code execution skill
+    JavaScriptExecuteSkill
+);
 
 #[async_trait]
 impl Skill for JavaScriptExecuteSkill {
@@ -96,6 +128,10 @@ impl Skill for JavaScriptExecuteSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::process("node", None), compute_budget(input, &self.sandbox)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let code = input.args.get("code")
             .and_then(|v| v.as_str())
@@ -107,20 +143,16 @@ impl Skill for JavaScriptExecuteSkill {
             .min(30);
 
         // Execute JavaScript code with timeout
-        let result = execute_javascript_code(code, timeout_seconds).await?;
-
-        Ok(SkillOutput::ok(json!({
-            "stdout": result.stdout,
-            "stderr": result.stderr,
-            "exit_code": result.exit_code,
-            "success": result.exit_code == 0,
-            "timed_out": result.timed_out,
-        })))
+        let result = self.sandbox.run("node", &["-e", code], Duration::from_secs(timeout_seconds)).await?;
+
+        Ok(execution_output(result))
     }
 }
 
-/// Shell command execution skill (restricted)
-pub struct ShellExecuteSkill;
+sandboxed_skill!(
+    /// Shell command execution skill (restricted)
+    ShellExecuteSkill
+);
 
 #[async_trait]
 impl Skill for ShellExecuteSkill {
@@ -150,6 +182,12 @@ impl Skill for ShellExecuteSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        let mut checks = PermissionCheck::shell_command(input.str_arg("command")?);
+        checks.push(compute_budget(input, &self.sandbox));
+        Ok(checks)
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let command = input.args.get("command")
             .and_then(|v| v.as_str())
@@ -168,147 +206,32 @@ impl Skill for ShellExecuteSkill {
         }
 
         // Execute shell command with timeout
-        let result = execute_shell_command(command, timeout_seconds).await?;
-
-        Ok(SkillOutput::ok(json!({
-            "stdout": result.stdout,
-            "stderr": result.stderr,
-            "exit_code": result.exit_code,
-            "success": result.exit_code == 0,
-            "timed_out": result.timed_out,
-        })))
-    }
-}
+        let result = self.sandbox.run("sh", &["-c", command], Duration::from_secs(timeout_seconds)).await?;
 
-/// Execution result
-struct ExecutionResult {
-    stdout: String,
-    stderr: String,
-    exit_code: i32,
-    timed_out: bool,
-}
-
-/// Execute Python code with timeout
-async fn execute_python_code(code: &str, timeout_seconds: u64) -> Result<ExecutionResult, SkillError> {
-    let mut child = Command::new("python3")
-        .arg("-c")
-        .arg(code)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| SkillError::Execution(format!("Failed to spawn python3: {}", e)))?;
-
-    // Wait with timeout
-    let timeout = Duration::from_secs(timeout_seconds);
-    let result = tokio::time::timeout(timeout, async {
-        child.wait_with_output().await
-    }).await;
-
-    match result {
-        Ok(Ok(output)) => {
-            Ok(ExecutionResult {
-                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                exit_code: output.status.code().unwrap_or(-1),
-                timed_out: false,
-            })
-        }
-        Ok(Err(e)) => {
-            Err(SkillError::Execution(format!("Failed to execute python3: {}", e)))
-        }
-        Err(_) => {
-            // Timeout - process will be cleaned up by OS
-            Ok(ExecutionResult {
-                stdout: String::new(),
-                stderr: format!("Execution timed out after {} seconds", timeout_seconds),
-                exit_code: -1,
-                timed_out: true,
-            })
-        }
+        Ok(execution_output(result))
     }
 }
 
-/// Execute JavaScript code with timeout
-async fn execute_javascript_code(code: &str, timeout_seconds: u64) -> Result<ExecutionResult, SkillError> {
-    let mut child = Command::new("node")
-        .arg("-e")
-        .arg(code)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| SkillError::Execution(format!("Failed to spawn node: {}", e)))?;
-
-    // Wait with timeout
-    let timeout = Duration::from_secs(timeout_seconds);
-    let result = tokio::time::timeout(timeout, async {
-        child.wait_with_output().await
-    }).await;
-
-    match result {
-        Ok(Ok(output)) => {
-            Ok(ExecutionResult {
-                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                exit_code: output.status.code().unwrap_or(-1),
-                timed_out: false,
-            })
-        }
-        Ok(Err(e)) => {
-            Err(SkillError::Execution(format!("Failed to execute node: {}", e)))
-        }
-        Err(_) => {
-            // Timeout - process will be cleaned up by OS
-            Ok(ExecutionResult {
-                stdout: String::new(),
-                stderr: format!("Execution timed out after {} seconds", timeout_seconds),
-                exit_code: -1,
-                timed_out: true,
-            })
-        }
+/// Compute budget implied by the requested timeout and the sandbox memory limit
+fn compute_budget(input: &SkillInput, sandbox: &Sandbox) -> PermissionCheck {
+    let timeout_seconds = input.args.get("timeout_seconds")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(5)
+        .min(30);
+    PermissionCheck::Compute {
+        estimated_time_ms: timeout_seconds * 1000,
+        estimated_memory_mb: sandbox.config().memory_mb,
     }
 }
 
-/// Execute shell command with timeout
-async fn execute_shell_command(command: &str, timeout_seconds: u64) -> Result<ExecutionResult, SkillError> {
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| SkillError::Execution(format!("Failed to spawn sh: {}", e)))?;
-
-    // Wait with timeout
-    let timeout = Duration::from_secs(timeout_seconds);
-    let result = tokio::time::timeout(timeout, async {
-        child.wait_with_output().await
-    }).await;
-
-    match result {
-        Ok(Ok(output)) => {
-            Ok(ExecutionResult {
-                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                exit_code: output.status.code().unwrap_or(-1),
-                timed_out: false,
-            })
-        }
-        Ok(Err(e)) => {
-            Err(SkillError::Execution(format!("Failed to execute sh: {}", e)))
-        }
-        Err(_) => {
-            // Timeout - process will be cleaned up by OS
-            Ok(ExecutionResult {
-                stdout: String::new(),
-                stderr: format!("Execution timed out after {} seconds", timeout_seconds),
-                exit_code: -1,
-                timed_out: true,
-            })
-        }
-    }
+fn execution_output(result: SandboxOutput) -> SkillOutput {
+    SkillOutput::ok(json!({
+        "stdout": result.stdout,
+        "stderr": result.stderr,
+        "exit_code": result.exit_code,
+        "success": result.exit_code == 0,
+        "timed_out": result.timed_out,
+    }))
 }
 
 /// Check if a command contains dangerous operations
@@ -342,22 +265,37 @@ fn is_dangerous_command(command: &str) -> bool {
     false
 }
 
-/// Create code execution skills
-pub fn create_code_execution_skills() -> Vec<Box<dyn Skill>> {
+/// Create code execution skills whose sandbox limits come from `permissions`
+pub fn create_code_execution_skills(permissions: &PermissionManager) -> Vec<Arc<dyn Skill>> {
     vec![
-        Box::new(PythonExecuteSkill),
-        Box::new(JavaScriptExecuteSkill),
-        Box::new(ShellExecuteSkill),
+        Arc::new(PythonExecuteSkill::from_permissions(permissions)),
+        Arc::new(JavaScriptExecuteSkill::from_permissions(permissions)),
+        Arc::new(ShellExecuteSkill::from_permissions(permissions)),
     ]
 }
 
+/// Register the code execution skills, sized by the registry's current
+/// permissions. Register again after changing them to pick up new limits.
+pub fn register_code_execution_skills(registry: &mut SkillRegistry) {
+    for skill in create_code_execution_skills(registry.permissions()) {
+        registry.register(skill);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::permissions::Permission;
+    use crate::sandbox::Isolation;
+
+    /// Tests that are not about isolation itself also run where user namespaces are unavailable.
+    fn sandbox() -> SandboxConfig {
+        SandboxConfig::default().with_isolation(Isolation::BestEffort)
+    }
 
     #[tokio::test]
     async fn test_python_execute_basic() {
-        let skill = PythonExecuteSkill;
+        let skill = PythonExecuteSkill::with_sandbox(sandbox());
         let input = SkillInput {
             name: "python_execute".to_string(),
             args: json!({
@@ -374,7 +312,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_javascript_execute_basic() {
-        let skill = JavaScriptExecuteSkill;
+        let skill = JavaScriptExecuteSkill::with_sandbox(sandbox());
         let input = SkillInput {
             name: "javascript_execute".to_string(),
             args: json!({
@@ -391,7 +329,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_shell_execute_basic() {
-        let skill = ShellExecuteSkill;
+        let skill = ShellExecuteSkill::with_sandbox(sandbox());
         let input = SkillInput {
             name: "shell_execute".to_string(),
             args: json!({
@@ -408,7 +346,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_shell_execute_dangerous_command() {
-        let skill = ShellExecuteSkill;
+        let skill = ShellExecuteSkill::with_sandbox(sandbox());
         let input = SkillInput {
             name: "shell_execute".to_string(),
             args: json!({
@@ -420,6 +358,33 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_python_has_no_network() {
+        let skill = PythonExecuteSkill::new();
+        let input = SkillInput {
+            name: "python_execute".to_string(),
+            args: json!({
+                "code": "import socket\ntry:\n    socket.create_connection(('1.1.1.1', 80), timeout=2)\n    print('connected')\nexcept OSError:\n    print('blocked')"
+            }),
+        };
+
+        let result = skill.execute(input).await.unwrap();
+        assert_eq!(result.result["stdout"].as_str().unwrap().trim(), "blocked");
+    }
+
+    #[test]
+    fn test_skills_are_sized_by_permissions() {
+        let mut permissions = PermissionManager::new();
+        permissions.grant(Permission::Compute { max_time_ms: 2_000, max_memory_mb: 64 });
+        let skill = PythonExecuteSkill::from_permissions(&permissions);
+        assert_eq!(skill.sandbox.config().timeout, Duration::from_secs(2));
+        assert_eq!(skill.sandbox.config().memory_mb, 64);
+
+        let mut registry = SkillRegistry::new().with_permissions(permissions);
+        register_code_execution_skills(&mut registry);
+        assert!(registry.contains("python_execute") && registry.contains("shell_execute"));
+    }
+
     #[test]
     fn test_is_dangerous_command() {
         assert!(is_dangerous_command("rm -rf /"));
@@ -2,55 +2,176 @@ use async_trait::async_trait;
 use std::sync::Arc;
 use crate::skill::{Skill, SkillInput, SkillOutput};
 use crate::error::SkillError;
-use pixelcore_runtime::agent::AgentId;
+use crate::permissions::PermissionCheck;
 use pixelcore_runtime::message::Message;
 use pixelcore_swarm::coordinator::Coordinator;
 
+/// Sends a message to another agent in the swarm, chosen by name or capability.
+///
+/// The input schema lists the swarm's agents and capability tags, so the
+/// model sees who it can delegate to without knowing any agent IDs.
+///
+/// Each call needs a `Permission::Delegate` for the named agent or, when
+/// delegating by capability, for the capability tag.
 pub struct DelegateSkill {
     pub coordinator: Arc<Coordinator>,
 }
 
+impl DelegateSkill {
+    pub fn new(coordinator: Arc<Coordinator>) -> Self {
+        Self { coordinator }
+    }
+}
+
 #[async_trait]
 impl Skill for DelegateSkill {
     fn name(&self) -> &str { "delegate" }
 
     fn description(&self) -> &str {
-        "Delegate a task to another agent in the swarm and return its response."
+        "Delegate a task to another agent in the swarm and return its response. \
+         Name the agent, or give a capability and the best available agent is picked."
     }
 
     fn input_schema(&self) -> serde_json::Value {
+        let agents = self.coordinator.describe_agents();
+        let agent_description = if agents.is_empty() {
+            "Name of the target agent.".to_string()
+        } else {
+            format!("Name of the target agent. Available agents:\n{agents}")
+        };
+        let mut capability = serde_json::json!({
+            "type": "string",
+            "description": "A capability to delegate by, used when 'agent' is not given."
+        });
+        let capabilities: Vec<String> = self.coordinator.capabilities().into_iter().collect();
+        if !capabilities.is_empty() {
+            capability["enum"] = serde_json::json!(capabilities);
+        }
+
         serde_json::json!({
             "type": "object",
             "properties": {
-                "agent_id": {
+                "agent": {
                     "type": "string",
-                    "description": "The ID of the target agent."
+                    "description": agent_description
                 },
+                "capability": capability,
                 "message": {
                     "type": "string",
                     "description": "The message to send to the target agent."
                 }
             },
-            "required": ["agent_id", "message"]
+            "required": ["message"]
         })
     }
 
-    async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
-        let agent_id = input.args.get("agent_id")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| SkillError::InvalidInput("missing 'agent_id'".to_string()))?;
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        match target_agent(input).or_else(|| arg(input, "capability")) {
+            Some(target) => Ok(vec![PermissionCheck::Delegate { target: target.to_string() }]),
+            None => Err(SkillError::InvalidInput("give either 'agent' or 'capability'".to_string())),
+        }
+    }
 
-        let content = input.args.get("message")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| SkillError::InvalidInput("missing 'message'".to_string()))?;
+    async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
+        let content = input.str_arg("message")?;
 
-        let id: AgentId = agent_id.parse()
-            .map_err(|_| SkillError::InvalidInput(format!("invalid agent_id UUID: {agent_id}")))?;
-        let msg = Message::user(content);
+        let id = match (target_agent(&input), arg(&input, "capability")) {
+            (Some(agent), _) => self.coordinator.resolve(agent).await,
+            (None, Some(capability)) => self.coordinator.select(capability).await.map(|c| c.id),
+            (None, None) => {
+                return Err(SkillError::InvalidInput("give either 'agent' or 'capability'".to_string()))
+            }
+        }
+        .map_err(|e| SkillError::InvalidInput(e.to_string()))?;
 
-        let reply = self.coordinator.route(&id, msg).await
+        let reply = self.coordinator.route(&id, Message::user(content)).await
             .map_err(|e| SkillError::Execution(e.to_string()))?;
+        let name = self.coordinator.swarm().profile(&id).map(|p| p.name).unwrap_or_default();
+
+        Ok(SkillOutput::ok(serde_json::json!({
+            "agent": name,
+            "agent_id": id.to_string(),
+            "response": reply.content,
+        })))
+    }
+}
+
+/// Non-blank string argument `key`
+fn arg<'a>(input: &'a SkillInput, key: &str) -> Option<&'a str> {
+    input.args.get(key).and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty())
+}
+
+/// The named target agent; `agent_id` is the pre-capability field name, still
+/// accepted from older callers
+fn target_agent(input: &SkillInput) -> Option<&str> {
+    arg(input, "agent").or_else(|| arg(input, "agent_id"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixelcore_runtime::agent::{Agent, AgentConfig, AgentId, AgentState};
+    use pixelcore_runtime::error::RuntimeError;
+    use pixelcore_runtime::event::EventBus;
+    use pixelcore_swarm::{AgentProfile, Swarm};
+    use crate::permissions::{Permission, PermissionManager};
+    use serde_json::json;
+
+    struct ReverseAgent {
+        config: AgentConfig,
+        state: AgentState,
+    }
+
+    #[async_trait]
+    impl Agent for ReverseAgent {
+        fn id(&self) -> AgentId { self.config.id }
+        fn name(&self) -> &str { &self.config.name }
+        fn state(&self) -> &AgentState { &self.state }
+        fn config(&self) -> &AgentConfig { &self.config }
+        async fn start(&mut self) -> Result<(), RuntimeError> { Ok(()) }
+        async fn stop(&mut self) -> Result<(), RuntimeError> { Ok(()) }
+        async fn process(&mut self, message: Message) -> Result<Message, RuntimeError> {
+            Ok(Message::assistant(message.content.chars().rev().collect::<String>()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delegate_by_capability() {
+        let swarm = Swarm::new();
+        let config = AgentConfig::new("Reverser", "");
+        let id = config.id;
+        let profile = AgentProfile::new("Reverser").with_description("Reverses text").with_capabilities(["reverse"]);
+        swarm
+            .add_with_profile(Arc::new(tokio::sync::Mutex::new(ReverseAgent { config, state: AgentState::Running })), profile)
+            .await;
+        let skill = DelegateSkill::new(Arc::new(Coordinator::new(swarm, EventBus::new())));
+
+        let schema = skill.input_schema();
+        assert_eq!(schema["properties"]["capability"]["enum"], json!(["reverse"]));
+        assert!(schema["properties"]["agent"]["description"].as_str().unwrap().contains("- Reverser [reverse]: Reverses text"));
+
+        let input = SkillInput { name: "delegate".to_string(), args: json!({ "capability": "reverse", "message": "abc" }) };
+        let output = skill.execute(input).await.unwrap();
+        assert_eq!(output.result, json!({ "agent": "Reverser", "agent_id": id.to_string(), "response": "cba" }));
+
+        let input = SkillInput { name: "delegate".to_string(), args: json!({ "agent": "nobody", "message": "abc" }) };
+        assert!(matches!(skill.execute(input).await, Err(SkillError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_delegate_requires_permission_for_target() {
+        let skill = DelegateSkill::new(Arc::new(Coordinator::new(Swarm::new(), EventBus::new())));
+        let check = |args| skill.required_permissions(&SkillInput { name: "delegate".to_string(), args });
+
+        let by_name = check(json!({ "agent": "Reverser", "message": "abc" })).unwrap();
+        assert_eq!(by_name, vec![PermissionCheck::Delegate { target: "Reverser".to_string() }]);
+        let by_capability = check(json!({ "capability": "reverse", "message": "abc" })).unwrap();
+        assert_eq!(by_capability, vec![PermissionCheck::Delegate { target: "reverse".to_string() }]);
+        assert!(matches!(check(json!({ "message": "abc" })), Err(SkillError::InvalidInput(_))));
 
-        Ok(SkillOutput::ok(serde_json::json!({ "response": reply.content })))
+        let mut permissions = PermissionManager::new();
+        permissions.grant(Permission::Delegate { target: "reverser".to_string() });
+        assert!(permissions.check_all(&by_name).is_ok());
+        assert!(permissions.check_all(&by_capability).is_err());
     }
 }
@@ -7,6 +7,7 @@ use serde_json::json;
 use std::sync::Arc;
 
 use crate::{Skill, SkillInput, SkillOutput, SkillError};
+use crate::permissions::{FileOperation, PermissionCheck};
 
 /// Excel read skill - reads Excel files and returns data as JSON
 pub struct ExcelReadSkill;
@@ -38,6 +39,10 @@ impl Skill for ExcelReadSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::file(input.str_arg("file_path")?, FileOperation::Read)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let file_path = input.args.get("file_path")
             .and_then(|v| v.as_str())
@@ -95,6 +100,10 @@ impl Skill for ExcelWriteSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::file(input.str_arg("file_path")?, FileOperation::Write)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let file_path = input.args.get("file_path")
             .and_then(|v| v.as_str())
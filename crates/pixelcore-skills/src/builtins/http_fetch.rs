@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use crate::skill::{Skill, SkillInput, SkillOutput};
 use crate::error::SkillError;
+use crate::permissions::PermissionCheck;
 
 pub struct HttpFetchSkill;
 
@@ -17,6 +18,10 @@ impl Skill for HttpFetchSkill {
             "required": ["url"]
         })
     }
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::network_url(input.str_arg("url")?)?])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let url = input.args.get("url")
             .and_then(|v| v.as_str())
@@ -1,15 +1,18 @@
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use anyhow::{Context, Result};
-use pixelcore_claw::LocalMcpClient;
+use pixelcore_claw::{LocalMcpClient, McpNotification};
+use tokio::task::JoinHandle;
 use crate::skill::Skill;
 use super::mcp_skill::McpSkill;
 
 /// MCP Skill 提供者
 ///
-/// 自动从 MCP 服务器发现工具并创建对应的 Skills
+/// 自动从 MCP 服务器发现工具并创建对应的 Skills。服务器发送
+/// `notifications/tools/list_changed` 时会重新拉取工具列表。
 pub struct McpSkillProvider {
     client: Arc<LocalMcpClient>,
-    skills: Vec<Arc<dyn Skill>>,
+    skills: Arc<RwLock<Vec<Arc<dyn Skill>>>>,
+    watcher: JoinHandle<()>,
 }
 
 impl McpSkillProvider {
@@ -42,30 +45,69 @@ impl McpSkillProvider {
             .await
             .context("Failed to start MCP client")?;
 
+        Self::from_client(client).await
+    }
+
+    /// 使用已初始化的 MCP 客户端创建提供者
+    pub async fn from_client(client: LocalMcpClient) -> Result<Self> {
         let client = Arc::new(client);
 
-        // 获取所有工具
+        // 先订阅再拉取，避免错过两者之间的变更通知
+        let mut notifications = client.notifications();
+        let skills = Arc::new(RwLock::new(Self::load_skills(&client).await?));
+
+        let watcher = tokio::spawn({
+            let client = Arc::clone(&client);
+            let skills = Arc::clone(&skills);
+            async move {
+                while let Some(notification) = notifications.recv().await {
+                    if !matches!(notification, McpNotification::ToolsListChanged) {
+                        continue;
+                    }
+                    match Self::load_skills(&client).await {
+                        Ok(loaded) => *skills.write().unwrap() = loaded,
+                        Err(e) => tracing::warn!("Failed to refresh MCP tools: {}", e),
+                    }
+                }
+            }
+        });
+
+        Ok(Self { client, skills, watcher })
+    }
+
+    /// 为服务器的每个工具创建 Skill
+    async fn load_skills(client: &Arc<LocalMcpClient>) -> Result<Vec<Arc<dyn Skill>>> {
         let tools = client.list_tools()
             .await
             .context("Failed to list MCP tools")?;
 
         tracing::info!("Discovered {} MCP tools", tools.len());
 
-        // 为每个工具创建 Skill
-        let skills: Vec<Arc<dyn Skill>> = tools
+        Ok(tools
             .into_iter()
             .map(|tool| {
-                let skill = McpSkill::new(Arc::clone(&client), tool);
+                let skill = McpSkill::new(Arc::clone(client), tool);
                 Arc::new(skill) as Arc<dyn Skill>
             })
-            .collect();
+            .collect())
+    }
+
+    /// 重新拉取工具列表，返回工具数量
+    pub async fn refresh(&self) -> Result<usize> {
+        let loaded = Self::load_skills(&self.client).await?;
+        let count = loaded.len();
+        *self.skills.write().unwrap() = loaded;
+        Ok(count)
+    }
 
-        Ok(Self { client, skills })
+    /// 获取当前所有 Skills
+    pub fn skills(&self) -> Vec<Arc<dyn Skill>> {
+        self.skills.read().unwrap().clone()
     }
 
-    /// 获取所有 Skills
-    pub fn skills(&self) -> &[Arc<dyn Skill>] {
-        &self.skills
+    /// 按名称查找 Skill
+    pub fn skill(&self, name: &str) -> Option<Arc<dyn Skill>> {
+        self.skills.read().unwrap().iter().find(|s| s.name() == name).cloned()
     }
 
     /// 获取 MCP 客户端
@@ -80,12 +122,83 @@ impl McpSkillProvider {
 
     /// 关闭 MCP 服务器
     pub async fn shutdown(&self) -> Result<()> {
+        self.watcher.abort();
         self.client.shutdown().await
     }
 }
 
 impl Drop for McpSkillProvider {
     fn drop(&mut self) {
+        self.watcher.abort();
         tracing::debug!("McpSkillProvider dropped");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixelcore_claw::StdioTransport;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    /// 最小的 MCP 服务器：每次 `tools/list` 多返回一个工具，
+    /// 收到 `tools/call` 后发送 `notifications/tools/list_changed`
+    async fn serve(io: tokio::io::DuplexStream) {
+        let (read, mut write) = tokio::io::split(io);
+        let mut lines = BufReader::new(read).lines();
+        let listed = AtomicUsize::new(0);
+        while let Ok(Some(line)) = lines.next_line().await {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let Some(id) = request.get("id").cloned() else { continue };
+            let (result, notify) = match request["method"].as_str().unwrap() {
+                "initialize" => (json!({
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": {"tools": {"listChanged": true}},
+                    "serverInfo": {"name": "mock", "version": "1"}
+                }), false),
+                "tools/list" => {
+                    let n = listed.fetch_add(1, Ordering::SeqCst) + 1;
+                    let tools: Vec<Value> = (0..n)
+                        .map(|i| json!({"name": format!("tool_{}", i), "inputSchema": {"type": "object"}}))
+                        .collect();
+                    (json!({"tools": tools}), false)
+                }
+                _ => (json!({"content": [{"type": "text", "text": "{\"ok\": true}"}]}), true),
+            };
+            let mut out = json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string();
+            if notify {
+                out.push('\n');
+                out.push_str(&json!({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"}).to_string());
+            }
+            out.push('\n');
+            write.write_all(out.as_bytes()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_on_list_changed() {
+        let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+        tokio::spawn(serve(server_io));
+        let (read, write) = tokio::io::split(client_io);
+        let client = LocalMcpClient::connect(StdioTransport::from_io(read, write)).await.unwrap();
+        let provider = McpSkillProvider::from_client(client).await.unwrap();
+        assert_eq!(provider.skills().len(), 1);
+
+        let skill = provider.skill("tool_0").unwrap();
+        let output = skill
+            .execute(crate::skill::SkillInput { name: "tool_0".to_string(), args: json!({}) })
+            .await
+            .unwrap();
+        assert_eq!(output.result, json!({"ok": true}));
+
+        for _ in 0..100 {
+            if provider.skills().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(provider.skill("tool_1").is_some());
+    }
+}
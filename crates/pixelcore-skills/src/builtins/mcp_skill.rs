@@ -18,16 +18,18 @@ impl McpSkill {
         Self { client, tool }
     }
 
-    /// 从工具内容提取文本
+    /// 从工具内容提取文本（包括嵌入的文本资源）
     fn extract_text(content: &[ToolContent]) -> String {
         content.iter()
-            .filter_map(|c| match c {
-                ToolContent::Text { text } => Some(text.as_str()),
-                _ => None,
-            })
+            .filter_map(ToolContent::as_text)
             .collect::<Vec<_>>()
             .join("\n")
     }
+
+    /// 是否包含无法用文本表达的内容（图片、音频、二进制资源）
+    fn has_binary_content(content: &[ToolContent]) -> bool {
+        content.iter().any(|c| c.as_text().is_none())
+    }
 }
 
 #[async_trait]
@@ -57,6 +59,11 @@ impl Skill for McpSkill {
             return Ok(SkillOutput::err(error_msg));
         }
 
+        // 工具返回了结构化结果时直接使用
+        if let Some(structured) = result.structured_content {
+            return Ok(SkillOutput::ok(structured));
+        }
+
         // 提取结果文本
         let text = Self::extract_text(&result.content);
 
@@ -64,6 +71,36 @@ impl Skill for McpSkill {
         let result_value = serde_json::from_str(&text)
             .unwrap_or_else(|_| serde_json::json!(text));
 
+        // 图片等内容原样附带返回，由调用方决定如何呈现
+        if Self::has_binary_content(&result.content) {
+            return Ok(SkillOutput::ok(serde_json::json!({
+                "text": result_value,
+                "content": result.content,
+            })));
+        }
+
         Ok(SkillOutput::ok(result_value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixelcore_claw::ResourceContents;
+
+    #[test]
+    fn test_extract_content() {
+        let content = vec![
+            ToolContent::text("a"),
+            ToolContent::resource(ResourceContents {
+                uri: "file:///b.txt".to_string(),
+                mime_type: None,
+                text: Some("b".to_string()),
+                blob: None,
+            }),
+        ];
+        assert_eq!(McpSkill::extract_text(&content), "a\nb");
+        assert!(!McpSkill::has_binary_content(&content));
+        assert!(McpSkill::has_binary_content(&[ToolContent::image("aGk=", "image/png")]));
+    }
+}
@@ -26,5 +26,5 @@ pub use redis::{RedisGetSkill, RedisSetSkill, RedisDeleteSkill, RedisExistsSkill
 pub use excel::{ExcelReadSkill, ExcelWriteSkill, create_excel_skills};
 pub use pdf::{PdfExtractSkill, create_pdf_skills};
 pub use postgres::{PostgresQuerySkill, PostgresExecuteSkill, create_postgres_skills};
-pub use code_execution::{PythonExecuteSkill, JavaScriptExecuteSkill, ShellExecuteSkill, create_code_execution_skills};
+pub use code_execution::{PythonExecuteSkill, JavaScriptExecuteSkill, ShellExecuteSkill, create_code_execution_skills, register_code_execution_skills};
 
@@ -6,6 +6,7 @@ use serde_json::json;
 use std::sync::Arc;
 
 use crate::{Skill, SkillInput, SkillOutput, SkillError};
+use crate::permissions::{FileOperation, PermissionCheck};
 
 /// PDF extract skill - extracts text content from PDF files
 pub struct PdfExtractSkill;
@@ -33,6 +34,10 @@ impl Skill for PdfExtractSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::file(input.str_arg("file_path")?, FileOperation::Read)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let file_path = input.args.get("file_path")
             .and_then(|v| v.as_str())
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use tokio_postgres::{NoTls, Row};
 
 use crate::{Skill, SkillInput, SkillOutput, SkillError};
+use crate::permissions::PermissionCheck;
 
 /// PostgreSQL query skill - executes SELECT queries
 pub struct PostgresQuerySkill;
@@ -37,6 +38,10 @@ impl Skill for PostgresQuerySkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![postgres_target(input.str_arg("connection_string")?)?])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let connection_string = input.args.get("connection_string")
             .and_then(|v| v.as_str())
@@ -91,6 +96,10 @@ impl Skill for PostgresExecuteSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![postgres_target(input.str_arg("connection_string")?)?])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let connection_string = input.args.get("connection_string")
             .and_then(|v| v.as_str())
@@ -115,6 +124,30 @@ impl Skill for PostgresExecuteSkill {
     }
 }
 
+/// Network permission for the server named by a connection string, in either
+/// URL (`postgres://user@host:5432/db`) or key/value (`host=... port=...`) form
+fn postgres_target(connection_string: &str) -> Result<PermissionCheck, SkillError> {
+    if connection_string.starts_with("postgres://") || connection_string.starts_with("postgresql://") {
+        return Ok(PermissionCheck::network_url(connection_string)?.or_default_port(5432));
+    }
+
+    let mut host = None;
+    let mut port = 5432;
+    for pair in connection_string.split_whitespace() {
+        match pair.split_once('=') {
+            Some(("host", value)) => host = Some(value.to_string()),
+            Some(("port", value)) => {
+                port = value.parse().map_err(|_| SkillError::InvalidInput(format!("invalid port '{}'", value)))?;
+            }
+            _ => {}
+        }
+    }
+    Ok(PermissionCheck::Network {
+        host: host.unwrap_or_else(|| "localhost".to_string()),
+        port,
+    })
+}
+
 /// Execute a SELECT query and return results as JSON
 async fn postgres_query(connection_string: &str, query: &str) -> Result<Vec<serde_json::Value>, String> {
     let (client, connection) = tokio_postgres::connect(connection_string, NoTls).await
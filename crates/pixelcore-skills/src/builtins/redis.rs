@@ -6,6 +6,7 @@ use serde_json::json;
 use std::sync::Arc;
 
 use crate::{Skill, SkillInput, SkillOutput, SkillError};
+use crate::permissions::PermissionCheck;
 
 /// Redis get skill - retrieves a value by key
 pub struct RedisGetSkill;
@@ -37,6 +38,10 @@ impl Skill for RedisGetSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::network_url(input.str_arg("url")?)?.or_default_port(6379)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let url = input.args.get("url")
             .and_then(|v| v.as_str())
@@ -99,6 +104,10 @@ impl Skill for RedisSetSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::network_url(input.str_arg("url")?)?.or_default_port(6379)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let url = input.args.get("url")
             .and_then(|v| v.as_str())
@@ -161,6 +170,10 @@ impl Skill for RedisDeleteSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::network_url(input.str_arg("url")?)?.or_default_port(6379)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let url = input.args.get("url")
             .and_then(|v| v.as_str())
@@ -215,6 +228,10 @@ impl Skill for RedisExistsSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::network_url(input.str_arg("url")?)?.or_default_port(6379)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let url = input.args.get("url")
             .and_then(|v| v.as_str())
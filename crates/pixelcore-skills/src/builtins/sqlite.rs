@@ -6,6 +6,7 @@ use serde_json::json;
 use std::sync::Arc;
 
 use crate::{Skill, SkillInput, SkillOutput, SkillError};
+use crate::permissions::{FileOperation, PermissionCheck};
 
 /// SQLite query skill - executes SELECT queries
 pub struct SqliteQuerySkill;
@@ -44,6 +45,10 @@ impl Skill for SqliteQuerySkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        sqlite_file(input.str_arg("db_path")?, FileOperation::Read)
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let db_path = input.args.get("db_path")
             .and_then(|v| v.as_str())
@@ -110,6 +115,10 @@ impl Skill for SqliteExecuteSkill {
         })
     }
 
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        sqlite_file(input.str_arg("db_path")?, FileOperation::Write)
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let db_path = input.args.get("db_path")
             .and_then(|v| v.as_str())
@@ -150,6 +159,14 @@ fn value_to_string(value: &serde_json::Value) -> String {
     }
 }
 
+/// File permission for a database path; in-memory databases need none
+fn sqlite_file(db_path: &str, operation: FileOperation) -> Result<Vec<PermissionCheck>, SkillError> {
+    if db_path == ":memory:" {
+        return Ok(Vec::new());
+    }
+    Ok(vec![PermissionCheck::file(db_path, operation)])
+}
+
 /// Execute a SELECT query and return results as JSON
 fn execute_query(db_path: &str, query: &str, params: &[String]) -> Result<Vec<serde_json::Value>, String> {
     let conn = Connection::open(db_path)
@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use std::sync::Arc;
 use crate::skill::{Skill, SkillInput, SkillOutput};
 use crate::error::SkillError;
+use crate::permissions::{PermissionCheck, StorageOperation};
 use pixelcore_storage::Storage;
 
 pub struct StorageGetSkill {
@@ -25,6 +26,10 @@ impl Skill for StorageGetSkill {
             "required": ["key"]
         })
     }
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::storage_key(input.str_arg("key")?, StorageOperation::Read)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let key = input.args.get("key")
             .and_then(|v| v.as_str())
@@ -50,6 +55,10 @@ impl Skill for StorageSetSkill {
             "required": ["key", "value"]
         })
     }
+    fn required_permissions(&self, input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(vec![PermissionCheck::storage_key(input.str_arg("key")?, StorageOperation::Write)])
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
         let key = input.args.get("key")
             .and_then(|v| v.as_str())
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::permissions::PermissionCheck;
+
 #[derive(Debug, Error)]
 pub enum SkillError {
     #[error("Skill not found: {0}")]
@@ -10,4 +12,7 @@ pub enum SkillError {
 
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    #[error("Permission denied for skill '{skill}': {permission}")]
+    PermissionDenied { skill: String, permission: PermissionCheck },
 }
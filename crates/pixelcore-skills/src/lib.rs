@@ -3,11 +3,18 @@ pub mod registry;
 pub mod error;
 pub mod builtins;
 pub mod permissions;
+pub mod audit;
+pub mod sandbox;
+pub mod task_handlers;
+pub mod mcp_server;
 
 pub use skill::{Skill, SkillInput, SkillOutput};
 pub use registry::SkillRegistry;
 pub use error::SkillError;
 pub use permissions::{Permission, PermissionManager, PermissionCheck, FileOperation, StorageOperation};
+pub use sandbox::{Isolation, Sandbox, SandboxConfig, SandboxOutput};
+pub use audit::{AuditLog, PermissionAuditEvent, PermissionAuditSink, PermissionDecision};
+pub use task_handlers::{SkillTaskHandler, AgentTaskHandler};
 pub use builtins::{
     EchoSkill,
     StorageGetSkill,
@@ -47,4 +54,5 @@ pub use builtins::{
     JavaScriptExecuteSkill,
     ShellExecuteSkill,
     create_code_execution_skills,
+    register_code_execution_skills,
 };
@@ -0,0 +1,119 @@
+//! Serve a `SkillRegistry` through `pixelcore_claw::McpServer`
+
+use async_trait::async_trait;
+use pixelcore_claw::{CallToolResult, JsonRpcError, McpTool, McpToolProvider, ToolContent};
+use serde_json::Value;
+
+use crate::error::SkillError;
+use crate::registry::SkillRegistry;
+use crate::skill::{SkillInput, SkillOutput};
+
+/// Skills become MCP tools. Calls go through `SkillRegistry::execute`, so the
+/// registry's permissions and audit sinks apply to remote callers too.
+#[async_trait]
+impl McpToolProvider for SkillRegistry {
+    fn list_tools(&self) -> Vec<McpTool> {
+        let mut tools: Vec<McpTool> = self
+            .as_tools()
+            .into_iter()
+            .map(|tool| McpTool {
+                name: tool.name,
+                description: tool.description,
+                input_schema: tool.input_schema,
+            })
+            .collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, JsonRpcError> {
+        let input = SkillInput { name: name.to_string(), args: arguments };
+        match self.execute(input).await {
+            Ok(output) => Ok(tool_result(output)),
+            Err(SkillError::NotFound(name)) => {
+                Err(JsonRpcError::new(JsonRpcError::INVALID_PARAMS, format!("Unknown tool: {}", name)))
+            }
+            // Execution failures are reported to the model, not as protocol errors.
+            Err(e) => Ok(error_result(e.to_string())),
+        }
+    }
+}
+
+/// Map a skill's output onto an MCP tool result.
+///
+/// String results are sent as-is, anything else as JSON text. Object results
+/// are also sent as `structuredContent`.
+pub fn tool_result(output: SkillOutput) -> CallToolResult {
+    if !output.success {
+        let message = output.error.unwrap_or_else(|| render(&output.result));
+        return error_result(message);
+    }
+    CallToolResult {
+        content: vec![ToolContent::text(render(&output.result))],
+        structured_content: output.result.is_object().then_some(output.result),
+        is_error: None,
+    }
+}
+
+fn error_result(message: String) -> CallToolResult {
+    CallToolResult {
+        content: vec![ToolContent::text(message)],
+        structured_content: None,
+        is_error: Some(true),
+    }
+}
+
+fn render(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtins::{EchoSkill, JsonParseSkill, SqliteQuerySkill};
+    use crate::permissions::PermissionManager;
+    use pixelcore_claw::McpServer;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_registry_as_mcp_server() {
+        let mut registry = SkillRegistry::new().with_permissions(PermissionManager::new());
+        registry.register(Arc::new(EchoSkill));
+        registry.register(Arc::new(JsonParseSkill));
+        registry.register(Arc::new(SqliteQuerySkill));
+        let server = McpServer::new("pixelcore", "0.1.0", Arc::new(registry));
+
+        let reply = server.handle(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"})).await.unwrap();
+        let names: Vec<&str> = reply["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["echo", "json_parse", "sqlite_query"]);
+        assert!(reply["result"]["tools"][0]["inputSchema"].is_object());
+
+        let call = |name: &str, arguments: Value| {
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": name, "arguments": arguments}})
+        };
+
+        let reply = server.handle(call("json_parse", json!({"json_string": "{\"a\": 1}"}))).await.unwrap();
+        assert_eq!(reply["result"]["structuredContent"], json!({"a": 1}));
+        assert!(reply["result"].get("isError").is_none());
+
+        // Denied by the registry's permissions: a tool error, not a protocol error.
+        let reply = server
+            .handle(call("sqlite_query", json!({"db_path": "/tmp/x.db", "query": "SELECT 1"})))
+            .await
+            .unwrap();
+        assert_eq!(reply["result"]["isError"], true);
+        assert!(reply["result"]["content"][0]["text"].as_str().unwrap().contains("Permission denied"));
+
+        let reply = server.handle(call("nope", json!({}))).await.unwrap();
+        assert_eq!(reply["error"]["code"], JsonRpcError::INVALID_PARAMS);
+    }
+}
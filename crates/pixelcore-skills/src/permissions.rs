@@ -1,7 +1,10 @@
 //! Permission management system for Skills
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::SkillError;
 
 /// Permission types that Skills can require
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -49,6 +52,12 @@ pub enum Permission {
         /// Allowed arguments pattern (e.g., "*.py" for Python scripts)
         args_pattern: Option<String>,
     },
+
+    /// Delegation permission (sending work to another agent)
+    Delegate {
+        /// Agent name or capability tag that may be delegated to ("*" for any)
+        target: String,
+    },
 }
 
 impl Permission {
@@ -60,8 +69,8 @@ impl Permission {
                 Permission::FileSystem { path: allowed_path, read, write },
                 PermissionCheck::FileSystem { path: requested_path, operation }
             ) => {
-                // Check if requested path is within allowed path
-                if !requested_path.starts_with(allowed_path) {
+                // Check if requested path is within allowed path (after resolving `..`)
+                if !normalize_path(requested_path).starts_with(normalize_path(allowed_path)) {
                     return false;
                 }
 
@@ -87,9 +96,9 @@ impl Permission {
                     return true;
                 }
 
-                if allowed_host.starts_with("*.") {
-                    let domain = &allowed_host[2..];
-                    requested_host.ends_with(domain)
+                if let Some(domain) = allowed_host.strip_prefix("*.") {
+                    requested_host == domain
+                        || requested_host.ends_with(&allowed_host[1..])
                 } else {
                     allowed_host == requested_host
                 }
@@ -151,13 +160,19 @@ impl Permission {
                 true
             }
 
+            // Delegation permissions (names and capabilities resolve case-insensitively)
+            (
+                Permission::Delegate { target: allowed },
+                PermissionCheck::Delegate { target: requested }
+            ) => allowed == "*" || allowed.to_lowercase() == requested.to_lowercase(),
+
             _ => false,
         }
     }
 }
 
 /// Permission check request
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PermissionCheck {
     FileSystem {
         path: PathBuf,
@@ -179,15 +194,121 @@ pub enum PermissionCheck {
         command: String,
         args: Option<Vec<String>>,
     },
+    Delegate {
+        target: String,
+    },
+}
+
+impl PermissionCheck {
+    /// File access check; the path is made absolute and `..` segments are resolved
+    pub fn file(path: impl AsRef<Path>, operation: FileOperation) -> Self {
+        PermissionCheck::FileSystem { path: normalize_path(path.as_ref()), operation }
+    }
+
+    /// Network check for the host and port of `url`
+    pub fn network_url(url: &str) -> Result<Self, SkillError> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| SkillError::InvalidInput(format!("invalid url '{}': {}", url, e)))?;
+        let host = parsed.host_str()
+            .ok_or_else(|| SkillError::InvalidInput(format!("url '{}' has no host", url)))?;
+        Ok(PermissionCheck::Network {
+            host: host.to_string(),
+            port: parsed.port_or_known_default().unwrap_or(0),
+        })
+    }
+
+    /// Use `port` for network checks whose URL named neither a port nor a scheme default
+    pub fn or_default_port(mut self, default: u16) -> Self {
+        if let PermissionCheck::Network { port, .. } = &mut self {
+            if *port == 0 {
+                *port = default;
+            }
+        }
+        self
+    }
+
+    /// Storage check for `key`; the namespace is the part before the first `:`
+    /// (`"agent:42:history"` is in `agent`), or `default` for keys without one
+    pub fn storage_key(key: &str, operation: StorageOperation) -> Self {
+        let namespace = match key.split_once(':') {
+            Some((namespace, _)) if !namespace.is_empty() => namespace,
+            _ => "default",
+        };
+        PermissionCheck::Storage { namespace: namespace.to_string(), operation }
+    }
+
+    /// Process check for running `command` with `args`
+    pub fn process(command: impl Into<String>, args: Option<Vec<String>>) -> Self {
+        PermissionCheck::Process { command: command.into(), args }
+    }
+
+    /// One process check per program invoked by a shell command line.
+    ///
+    /// The line is split on `;`, `&`, `|`, newlines, backticks and `$(`, so
+    /// `ls && rm -rf /` needs permission for both `ls` and `rm`.
+    pub fn shell_command(command: &str) -> Vec<Self> {
+        command
+            .replace("$(", ";")
+            .split([';', '&', '|', '\n', '`', '(', ')'])
+            .filter_map(|segment| {
+                let mut words = segment
+                    .split_whitespace()
+                    .skip_while(|word| word.contains('=') && !word.starts_with('='));
+                let program = words.next()?;
+                let args: Vec<String> = words.map(str::to_string).collect();
+                Some(PermissionCheck::process(program, Some(args)))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for PermissionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PermissionCheck::FileSystem { path, operation } => {
+                write!(f, "file {:?} {}", operation, path.display())
+            }
+            PermissionCheck::Network { host, port } => write!(f, "network {}:{}", host, port),
+            PermissionCheck::Compute { estimated_time_ms, estimated_memory_mb } => {
+                write!(f, "compute {}ms/{}MB", estimated_time_ms, estimated_memory_mb)
+            }
+            PermissionCheck::Storage { namespace, operation } => {
+                write!(f, "storage {:?} {}", operation, namespace)
+            }
+            PermissionCheck::Process { command, .. } => write!(f, "process {}", command),
+            PermissionCheck::Delegate { target } => write!(f, "delegate {}", target),
+        }
+    }
+}
+
+/// Make `path` absolute and resolve `.`/`..` lexically (without touching the filesystem)
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileOperation {
     Read,
     Write,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StorageOperation {
     Read,
     Write,
@@ -238,6 +359,19 @@ impl PermissionManager {
         self.permissions.iter().any(|perm| perm.allows(operation))
     }
 
+    /// Check every operation, returning the first one that is not allowed
+    pub fn check_all<'a>(&self, operations: &'a [PermissionCheck]) -> Result<(), &'a PermissionCheck> {
+        match operations.iter().find(|op| !self.check(op)) {
+            Some(denied) => Err(denied),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether this manager allows everything
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow_all
+    }
+
     /// Get all granted permissions
     pub fn permissions(&self) -> &[Permission] {
         &self.permissions
@@ -331,6 +465,49 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn test_path_traversal_is_resolved() {
+        let perm = Permission::FileSystem {
+            path: PathBuf::from("/tmp/data"),
+            read: true,
+            write: true,
+        };
+
+        assert!(!perm.allows(&PermissionCheck::file("/tmp/data/../../etc/passwd", FileOperation::Read)));
+        assert!(!perm.allows(&PermissionCheck::FileSystem {
+            path: PathBuf::from("/tmp/data/../secret"),
+            operation: FileOperation::Read,
+        }));
+        assert!(perm.allows(&PermissionCheck::file("/tmp/data/./db.sqlite", FileOperation::Write)));
+    }
+
+    #[test]
+    fn test_wildcard_host_requires_subdomain_boundary() {
+        let perm = Permission::Network { host: "*.example.com".to_string(), port: 0 };
+        let check = |url: &str| perm.allows(&PermissionCheck::network_url(url).unwrap());
+
+        assert!(check("https://api.example.com/v1"));
+        assert!(check("http://example.com:8080"));
+        assert!(!check("https://evilexample.com"));
+        assert!(PermissionCheck::network_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_shell_command_checks_every_program() {
+        let checks = PermissionCheck::shell_command("FOO=1 ls -la /tmp && cat x | grep y; echo $(whoami)");
+        let programs: Vec<_> = checks.iter().map(|c| match c {
+            PermissionCheck::Process { command, .. } => command.as_str(),
+            _ => unreachable!(),
+        }).collect();
+        assert_eq!(programs, vec!["ls", "cat", "grep", "echo", "whoami"]);
+
+        let mut manager = PermissionManager::new();
+        manager.grant(Permission::Process { command: "ls".to_string(), args_pattern: None });
+        manager.grant(Permission::Process { command: "cat".to_string(), args_pattern: None });
+        let denied = manager.check_all(&checks).unwrap_err();
+        assert_eq!(denied, &PermissionCheck::process("grep", Some(vec!["y".to_string()])));
+    }
+
     #[test]
     fn test_allow_all() {
         let manager = PermissionManager::allow_all();
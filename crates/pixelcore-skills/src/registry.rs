@@ -1,23 +1,81 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use crate::skill::Skill;
+use chrono::Utc;
+use tracing::{debug, warn};
+use crate::skill::{Skill, SkillInput, SkillOutput};
 use crate::error::SkillError;
+use crate::permissions::{PermissionCheck, PermissionManager};
+use crate::audit::{PermissionAuditEvent, PermissionAuditSink, PermissionDecision};
 use pixelcore_claw::types::Tool;
+use pixelcore_runtime::agent::AgentId;
 
+/// The skills available to one agent, together with the permissions it holds.
+///
+/// A new registry grants nothing: skills that declare required permissions are
+/// denied until `with_permissions` grants them. Skills can only be run through
+/// `execute`, which checks every call and records it on the attached audit
+/// sinks, whether or not it is allowed.
 pub struct SkillRegistry {
     skills: HashMap<String, Arc<dyn Skill>>,
+    permissions: PermissionManager,
+    audit_sinks: Vec<Arc<dyn PermissionAuditSink>>,
+    agent_id: Option<AgentId>,
 }
 
 impl SkillRegistry {
     pub fn new() -> Self {
-        Self { skills: HashMap::new() }
+        Self {
+            skills: HashMap::new(),
+            permissions: PermissionManager::new(),
+            audit_sinks: Vec::new(),
+            agent_id: None,
+        }
+    }
+
+    /// Only allow what `permissions` grants.
+    pub fn with_permissions(mut self, permissions: PermissionManager) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Record every permission decision on `sink`.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn PermissionAuditSink>) -> Self {
+        self.audit_sinks.push(sink);
+        self
+    }
+
+    /// Agent the registry belongs to, included in audit events.
+    pub fn with_agent(mut self, agent_id: AgentId) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    pub fn set_permissions(&mut self, permissions: PermissionManager) {
+        self.permissions = permissions;
+    }
+
+    pub fn add_audit_sink(&mut self, sink: Arc<dyn PermissionAuditSink>) {
+        self.audit_sinks.push(sink);
+    }
+
+    pub fn set_agent(&mut self, agent_id: AgentId) {
+        self.agent_id = Some(agent_id);
+    }
+
+    pub fn permissions(&self) -> &PermissionManager {
+        &self.permissions
     }
 
     pub fn register(&mut self, skill: Arc<dyn Skill>) {
         self.skills.insert(skill.name().to_string(), skill);
     }
 
-    pub fn get(&self, name: &str) -> Result<Arc<dyn Skill>, SkillError> {
+    pub fn contains(&self, name: &str) -> bool {
+        self.skills.contains_key(name)
+    }
+
+    /// Not public: calling a skill directly would skip the permission check.
+    fn get(&self, name: &str) -> Result<Arc<dyn Skill>, SkillError> {
         self.skills
             .get(name)
             .cloned()
@@ -28,6 +86,72 @@ impl SkillRegistry {
         self.skills.keys().map(|s| s.as_str()).collect()
     }
 
+    /// Check the permissions `input.name` needs for this call, then run it.
+    ///
+    /// Returns `SkillError::PermissionDenied` without running the skill when a
+    /// required permission is not granted.
+    pub async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError> {
+        let skill = self.get(&input.name)?;
+        self.authorize(skill.as_ref(), &input)?;
+        skill.execute(input).await
+    }
+
+    /// Check a call against the permission manager and record the decision.
+    pub fn authorize(&self, skill: &dyn Skill, input: &SkillInput) -> Result<(), SkillError> {
+        let checks = match skill.required_permissions(input) {
+            Ok(checks) => checks,
+            Err(e) => {
+                self.audit(skill.name(), Vec::new(), None, Some(e.to_string()));
+                return Err(e);
+            }
+        };
+
+        match self.permissions.check_all(&checks) {
+            Ok(()) => {
+                self.audit(skill.name(), checks, None, None);
+                Ok(())
+            }
+            Err(denied) => {
+                let denied = denied.clone();
+                self.audit(skill.name(), checks, Some(denied.clone()), None);
+                Err(SkillError::PermissionDenied { skill: skill.name().to_string(), permission: denied })
+            }
+        }
+    }
+
+    fn audit(&self, skill: &str, checks: Vec<PermissionCheck>, denied: Option<PermissionCheck>, reason: Option<String>) {
+        let decision = if denied.is_none() && reason.is_none() {
+            PermissionDecision::Allowed
+        } else {
+            PermissionDecision::Denied
+        };
+        match decision {
+            PermissionDecision::Allowed => debug!(skill, checks = checks.len(), "skill permitted"),
+            PermissionDecision::Denied => warn!(
+                skill,
+                denied = denied.as_ref().map(|d| d.to_string()),
+                reason = reason.as_deref(),
+                "skill denied"
+            ),
+        }
+        if self.audit_sinks.is_empty() {
+            return;
+        }
+
+        let event = PermissionAuditEvent {
+            agent_id: self.agent_id,
+            skill: skill.to_string(),
+            decision,
+            checks,
+            denied,
+            reason,
+            timestamp: Utc::now(),
+        };
+        for sink in &self.audit_sinks {
+            sink.record(&event);
+        }
+    }
+
     /// Convert all registered skills to `Tool` descriptors for LLM requests.
     pub fn as_tools(&self) -> Vec<Tool> {
         self.skills.values().map(|s| Tool {
@@ -43,3 +167,82 @@ impl Default for SkillRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLog;
+    use crate::builtins::{EchoSkill, HttpFetchSkill, SqliteQuerySkill};
+    use crate::permissions::{FileOperation, Permission};
+    use std::path::PathBuf;
+
+    fn input(name: &str, args: serde_json::Value) -> SkillInput {
+        SkillInput { name: name.to_string(), args }
+    }
+
+    #[tokio::test]
+    async fn test_denied_call_does_not_run_and_is_audited() {
+        let log = Arc::new(AuditLog::new());
+        let mut permissions = PermissionManager::new();
+        permissions.grant(Permission::FileSystem { path: PathBuf::from("/tmp/allowed"), read: true, write: false });
+
+        let agent_id = uuid::Uuid::new_v4();
+        let mut registry = SkillRegistry::new()
+            .with_permissions(permissions)
+            .with_audit_sink(log.clone())
+            .with_agent(agent_id);
+        registry.register(Arc::new(EchoSkill));
+        registry.register(Arc::new(HttpFetchSkill));
+        registry.register(Arc::new(SqliteQuerySkill));
+
+        // Skills without requirements still run.
+        let out = registry.execute(input("echo", serde_json::json!({"message": "hi"}))).await.unwrap();
+        assert!(out.success);
+
+        let err = registry
+            .execute(input("http_fetch", serde_json::json!({"url": "http://127.0.0.1:9/"})))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SkillError::PermissionDenied { ref skill, .. } if skill == "http_fetch"));
+
+        let err = registry
+            .execute(input("sqlite_query", serde_json::json!({"db_path": "/tmp/allowed/../x.db", "query": "SELECT 1"})))
+            .await
+            .unwrap_err();
+        let SkillError::PermissionDenied { permission, .. } = err else { panic!("expected denial") };
+        assert_eq!(permission, PermissionCheck::file("/tmp/x.db", FileOperation::Read));
+
+        let events = log.events();
+        assert_eq!(events.len(), 3);
+        assert!(events[0].is_allowed());
+        assert_eq!(events[0].agent_id, Some(agent_id));
+        assert_eq!(log.denials().len(), 2);
+        assert_eq!(log.denials()[0].skill, "http_fetch");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_input_is_denied() {
+        let log = Arc::new(AuditLog::new());
+        let mut registry = SkillRegistry::new().with_audit_sink(log.clone());
+        registry.register(Arc::new(HttpFetchSkill));
+
+        let err = registry.execute(input("http_fetch", serde_json::json!({"url": "::bad"}))).await.unwrap_err();
+        assert!(matches!(err, SkillError::InvalidInput(_)));
+        assert_eq!(log.denials().len(), 1);
+        assert!(log.denials()[0].reason.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_new_registry_denies_by_default() {
+        let mut registry = SkillRegistry::new();
+        registry.register(Arc::new(EchoSkill));
+        registry.register(Arc::new(HttpFetchSkill));
+
+        assert!(registry.execute(input("echo", serde_json::json!({"message": "hi"}))).await.is_ok());
+        let err = registry
+            .execute(input("http_fetch", serde_json::json!({"url": "http://127.0.0.1:9/"})))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SkillError::PermissionDenied { .. }));
+    }
+}
@@ -0,0 +1,920 @@
+//! Sandboxed process runner for the code execution skills
+//!
+//! Each run gets a fresh temporary working directory, a cleared environment,
+//! its own process group and resource limits. On Linux the child is further
+//! isolated before `exec`:
+//!
+//! - new user, mount, network, IPC and UTS namespaces (no network, only a
+//!   down loopback interface);
+//! - a private filesystem view: every mount is made read-only, `/tmp`,
+//!   `/var/tmp` and `/dev/shm` are replaced by empty tmpfs mounts and only the
+//!   working directory is writable;
+//! - when started as root, the child first drops to `nobody`;
+//! - a seccomp filter that refuses mount, namespace, tracing, kernel-module
+//!   and similar syscalls.
+//!
+//! The whole process group is killed when the run finishes or times out, so
+//! background processes cannot outlive it.
+//!
+//! Namespaces need unprivileged user namespaces, which some kernels and
+//! container runtimes disable. The default `Isolation::Required` refuses to run
+//! there; `Isolation::BestEffort` runs with limits only and logs a warning.
+//! `Sandbox::isolation_available` reports which case applies.
+
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Once, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tracing::warn;
+
+use crate::error::SkillError;
+use crate::permissions::{Permission, PermissionManager};
+
+/// How strictly namespace isolation is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Isolation {
+    /// Refuse to run if namespaces cannot be set up (the default)
+    Required,
+    /// Use namespaces when the kernel allows it, otherwise warn and run with
+    /// limits only
+    BestEffort,
+    /// Resource limits, seccomp and process-group cleanup only
+    Disabled,
+}
+
+/// Limits applied to a sandboxed run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    /// Wall-clock limit; the process group is killed when it is reached
+    pub timeout: Duration,
+    /// CPU time limit (`RLIMIT_CPU`)
+    pub cpu_time: Duration,
+    /// Data segment limit in MB (`RLIMIT_DATA`), 0 for unlimited
+    pub memory_mb: u64,
+    /// Largest file the child may write, in MB (`RLIMIT_FSIZE`)
+    pub max_file_size_mb: u64,
+    /// Process limit (`RLIMIT_NPROC`)
+    pub max_processes: u64,
+    /// Bytes kept from each of stdout and stderr
+    pub max_output_bytes: usize,
+    /// Keep the host network instead of an isolated network namespace
+    pub network: bool,
+    pub isolation: Isolation,
+    /// Install the seccomp filter (x86_64 and aarch64 only; runs fail elsewhere)
+    pub seccomp: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            cpu_time: Duration::from_secs(10),
+            memory_mb: 512,
+            max_file_size_mb: 16,
+            max_processes: 64,
+            max_output_bytes: 1024 * 1024,
+            network: false,
+            isolation: Isolation::Required,
+            seccomp: true,
+        }
+    }
+}
+
+impl SandboxConfig {
+    /// Limits derived from the `Permission::Compute` grants in `permissions`.
+    ///
+    /// The most generous grant sets the timeout, CPU time and memory limit;
+    /// without one (or for an unrestricted manager) the defaults apply.
+    pub fn from_permissions(permissions: &PermissionManager) -> Self {
+        let mut config = Self::default();
+        if permissions.is_unrestricted() {
+            return config;
+        }
+
+        // 0 means unlimited, so it wins over any finite grant.
+        let widest = |a: u64, b: u64| if a == 0 || b == 0 { 0 } else { a.max(b) };
+        let granted = permissions.permissions().iter().filter_map(|p| match p {
+            Permission::Compute { max_time_ms, max_memory_mb } => Some((*max_time_ms, *max_memory_mb)),
+            _ => None,
+        }).reduce(|(t1, m1), (t2, m2)| (widest(t1, t2), widest(m1, m2)));
+
+        if let Some((time_ms, memory_mb)) = granted {
+            if time_ms > 0 {
+                config.timeout = Duration::from_millis(time_ms);
+                config.cpu_time = Duration::from_millis(time_ms);
+            }
+            config.memory_mb = memory_mb;
+        }
+        config
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_memory_mb(mut self, memory_mb: u64) -> Self {
+        self.memory_mb = memory_mb;
+        self
+    }
+
+    pub fn with_isolation(mut self, isolation: Isolation) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    pub fn with_network(mut self, network: bool) -> Self {
+        self.network = network;
+        self
+    }
+}
+
+/// Result of a sandboxed run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Exit code, or -1 when the process was killed by a signal
+    pub exit_code: i32,
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+}
+
+/// Runs programs under a `SandboxConfig`
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    config: SandboxConfig,
+}
+
+impl Sandbox {
+    pub fn new(config: SandboxConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &SandboxConfig {
+        &self.config
+    }
+
+    /// Whether namespace isolation can be set up on this host.
+    ///
+    /// Probed once by applying the isolation steps in a forked child.
+    pub fn isolation_available() -> bool {
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(probe_isolation)
+    }
+
+    /// Isolation actually applied to a run
+    fn isolation(&self) -> Result<Isolation, SkillError> {
+        match self.config.isolation {
+            Isolation::Disabled => Ok(Isolation::Disabled),
+            _ if Self::isolation_available() => Ok(self.config.isolation),
+            Isolation::Required => Err(SkillError::Execution(
+                "sandbox isolation is unavailable on this host (unprivileged user namespaces may be disabled); \
+                 set `Isolation::BestEffort` to run with resource limits only"
+                    .to_string(),
+            )),
+            Isolation::BestEffort => {
+                static WARNED: Once = Once::new();
+                WARNED.call_once(|| {
+                    warn!("sandbox isolation is unavailable on this host; running code with resource limits only")
+                });
+                Ok(Isolation::Disabled)
+            }
+        }
+    }
+
+    /// Run `program` with `args` in a fresh working directory.
+    ///
+    /// `timeout` is capped by the configured timeout.
+    pub async fn run(&self, program: &str, args: &[&str], timeout: Duration) -> Result<SandboxOutput, SkillError> {
+        let isolation = self.isolation()?;
+        let workdir = tempfile::Builder::new()
+            .prefix("pixelcore-sandbox-")
+            .tempdir()
+            .map_err(|e| SkillError::Execution(format!("failed to create sandbox directory: {}", e)))?;
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .current_dir(workdir.path())
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_else(|| "/usr/local/bin:/usr/bin:/bin".into()))
+            .env("HOME", workdir.path())
+            .env("TMPDIR", workdir.path())
+            .env("LANG", "C.UTF-8")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true);
+        if isolation != Isolation::Disabled {
+            // Inside the namespace the host temp directory is hidden; only the
+            // working directory (the current directory) stays reachable.
+            command.env("HOME", ".").env("TMPDIR", "/tmp");
+        }
+        self.confine(&mut command, workdir.path(), isolation)?;
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| SkillError::Execution(format!("failed to start {} in sandbox: {}", program, e)))?;
+        let pid = child.id();
+        let limit = self.config.max_output_bytes;
+        let stdout = tokio::spawn(read_capped(child.stdout.take(), limit));
+        let stderr = tokio::spawn(read_capped(child.stderr.take(), limit));
+
+        let timeout = timeout.min(self.config.timeout);
+        let status = tokio::time::timeout(timeout, child.wait()).await;
+        // Kill the whole group either way so background processes die with the run.
+        if let Some(pid) = pid {
+            kill_process_group(pid);
+        }
+        let (status, timed_out) = match status {
+            Ok(status) => (status.map_err(|e| SkillError::Execution(e.to_string()))?, false),
+            Err(_) => (child.wait().await.map_err(|e| SkillError::Execution(e.to_string()))?, true),
+        };
+
+        let stdout = stdout.await.unwrap_or_default();
+        let mut stderr = stderr.await.unwrap_or_default();
+        if timed_out {
+            if !stderr.is_empty() && !stderr.ends_with('\n') {
+                stderr.push('\n');
+            }
+            stderr.push_str(&format!("Execution timed out after {} seconds", timeout.as_secs_f64()));
+        }
+
+        Ok(SandboxOutput {
+            stdout,
+            stderr,
+            exit_code: status.code().unwrap_or(-1),
+            signal: exit_signal(&status),
+            timed_out,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn confine(&self, command: &mut Command, workdir: &Path, isolation: Isolation) -> Result<(), SkillError> {
+        let config = SandboxConfig { isolation, ..self.config.clone() };
+        let setup = linux::ChildSetup::new(&config, workdir)?;
+        // SAFETY: the closure only performs raw syscalls on data prepared
+        // above; it does not allocate or take locks.
+        unsafe {
+            command.pre_exec(move || setup.apply());
+        }
+        Ok(())
+    }
+
+    #[cfg(all(unix, not(target_os = "linux")))]
+    fn confine(&self, command: &mut Command, _workdir: &Path, _isolation: Isolation) -> Result<(), SkillError> {
+        let limits = rlimits(&self.config);
+        // SAFETY: setrlimit is async-signal-safe.
+        unsafe {
+            command.pre_exec(move || {
+                for (resource, value) in limits {
+                    let limit = libc::rlimit { rlim_cur: value, rlim_max: value };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn probe_isolation() -> bool {
+    linux::probe()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe_isolation() -> bool {
+    false
+}
+
+/// Read at most `limit` bytes, then drain the rest so the writer never blocks
+async fn read_capped<R: AsyncRead + Unpin>(reader: Option<R>, limit: usize) -> String {
+    let Some(mut reader) = reader else { return String::new() };
+    let mut kept = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let mut truncated = false;
+    loop {
+        match reader.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let room = limit.saturating_sub(kept.len());
+                kept.extend_from_slice(&chunk[..n.min(room)]);
+                truncated |= n > room;
+            }
+        }
+    }
+    let mut text = String::from_utf8_lossy(&kept).into_owned();
+    if truncated {
+        text.push_str("\n[output truncated]");
+    }
+    text
+}
+
+fn kill_process_group(pid: u32) {
+    // SAFETY: plain syscall; ESRCH (group already gone) is ignored.
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+/// `(resource, limit)` pairs applied to the child
+fn rlimits(config: &SandboxConfig) -> Vec<(RlimitResource, libc::rlim_t)> {
+    let mb = |value: u64| value.saturating_mul(1024 * 1024) as libc::rlim_t;
+    let mut limits = vec![
+        (libc::RLIMIT_CPU, config.cpu_time.as_secs().max(1) as libc::rlim_t),
+        (libc::RLIMIT_FSIZE, mb(config.max_file_size_mb)),
+        (libc::RLIMIT_NPROC, config.max_processes as libc::rlim_t),
+        (libc::RLIMIT_NOFILE, 256),
+        (libc::RLIMIT_CORE, 0),
+    ];
+    if config.memory_mb > 0 {
+        limits.push((libc::RLIMIT_DATA, mb(config.memory_mb)));
+    }
+    limits
+}
+
+#[cfg(target_os = "linux")]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(not(target_os = "linux"))]
+type RlimitResource = libc::c_int;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use super::{rlimits, Isolation, RlimitResource, SandboxConfig};
+    use crate::error::SkillError;
+
+    const NOBODY: libc::uid_t = 65534;
+
+    /// Everything the child needs, prepared before `fork` so that the
+    /// `pre_exec` hook does not allocate.
+    pub(super) struct ChildSetup {
+        isolation: Isolation,
+        network: bool,
+        drop_to_nobody: bool,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        workdir: CString,
+        tmpfs_options: CString,
+        hidden_dirs: Vec<CString>,
+        /// Mount points to remount one by one where `mount_setattr` is missing
+        mount_points: Vec<CString>,
+        limits: Vec<(RlimitResource, libc::rlim_t)>,
+        filter: Option<Vec<libc::sock_filter>>,
+    }
+
+    impl ChildSetup {
+        pub(super) fn new(config: &SandboxConfig, workdir: &Path) -> Result<Self, SkillError> {
+            let cstring = |bytes: &[u8]| {
+                CString::new(bytes).map_err(|_| SkillError::Execution("path contains a NUL byte".to_string()))
+            };
+
+            // SAFETY: trivial getters.
+            let (euid, uid, gid) = unsafe { (libc::geteuid(), libc::getuid(), libc::getgid()) };
+            let drop_to_nobody = euid == 0 && config.isolation != Isolation::Disabled;
+            let (uid, gid) = if drop_to_nobody {
+                // The child drops to nobody before exec, so it must own its working directory.
+                let path = cstring(workdir.as_os_str().as_bytes())?;
+                // SAFETY: path is a valid C string.
+                if unsafe { libc::chown(path.as_ptr(), NOBODY, NOBODY) } != 0 {
+                    return Err(SkillError::Execution(format!(
+                        "failed to prepare sandbox directory: {}",
+                        io::Error::last_os_error()
+                    )));
+                }
+                (NOBODY, NOBODY)
+            } else {
+                (uid, gid)
+            };
+
+            let hidden_dirs = ["/tmp", "/var/tmp", "/dev/shm"]
+                .iter()
+                .filter(|dir| Path::new(dir).is_dir())
+                .map(|dir| cstring(dir.as_bytes()))
+                .collect::<Result<_, _>>()?;
+            let mount_points = mount_points()
+                .into_iter()
+                .filter(|path| path.as_bytes() != workdir.as_os_str().as_bytes())
+                .collect();
+            let filter = if config.seccomp {
+                Some(seccomp_filter().ok_or_else(|| {
+                    SkillError::Execution(format!(
+                        "the sandbox seccomp filter is not available on {}; set `SandboxConfig::seccomp` to false",
+                        std::env::consts::ARCH
+                    ))
+                })?)
+            } else {
+                None
+            };
+
+            Ok(Self {
+                isolation: config.isolation,
+                network: config.network,
+                drop_to_nobody,
+                uid_map: format!("{uid} {uid} 1\n").into_bytes(),
+                gid_map: format!("{gid} {gid} 1\n").into_bytes(),
+                workdir: cstring(workdir.as_os_str().as_bytes())?,
+                tmpfs_options: cstring(format!("size={}m,mode=1777", config.max_file_size_mb.max(1)).as_bytes())?,
+                hidden_dirs,
+                mount_points,
+                limits: rlimits(config),
+                filter,
+            })
+        }
+
+        /// Runs in the forked child between `fork` and `exec`.
+        pub(super) fn apply(&self) -> io::Result<()> {
+            if self.isolation != Isolation::Disabled {
+                match self.isolate() {
+                    Ok(()) => {}
+                    Err(e) if self.isolation == Isolation::Required => return Err(e),
+                    Err(_) => {}
+                }
+            }
+            for &(resource, value) in &self.limits {
+                let limit = libc::rlimit { rlim_cur: value, rlim_max: value };
+                // SAFETY: plain syscall on a stack value.
+                check(unsafe { libc::setrlimit(resource, &limit) })?;
+            }
+            if let Some(filter) = &self.filter {
+                install_seccomp(filter)?;
+            }
+            Ok(())
+        }
+
+        fn isolate(&self) -> io::Result<()> {
+            // SAFETY: raw syscalls on C strings prepared in `new`.
+            unsafe {
+                if self.drop_to_nobody {
+                    check(libc::setgroups(0, std::ptr::null()))?;
+                    check(libc::setgid(NOBODY))?;
+                    check(libc::setuid(NOBODY))?;
+                    // Changing uid clears the dumpable flag, which would leave
+                    // /proc/self owned by root and the id maps unwritable.
+                    check(libc::prctl(libc::PR_SET_DUMPABLE, 1, 0, 0, 0))?;
+                }
+
+                let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWIPC | libc::CLONE_NEWUTS;
+                if !self.network {
+                    flags |= libc::CLONE_NEWNET;
+                }
+                check(libc::unshare(flags))?;
+                write_file(c"/proc/self/setgroups", b"deny")?;
+                write_file(c"/proc/self/uid_map", &self.uid_map)?;
+                write_file(c"/proc/self/gid_map", &self.gid_map)?;
+
+                let none = std::ptr::null();
+                check(libc::mount(none, c"/".as_ptr(), none, libc::MS_REC | libc::MS_PRIVATE, none as _))?;
+                // The working directory becomes its own writable mount, entered
+                // before the rest of the filesystem is locked down.
+                check(libc::mount(self.workdir.as_ptr(), self.workdir.as_ptr(), none, libc::MS_BIND, none as _))?;
+                check(libc::chdir(self.workdir.as_ptr()))?;
+                self.remount_readonly()?;
+                for dir in &self.hidden_dirs {
+                    check(libc::mount(
+                        c"tmpfs".as_ptr(),
+                        dir.as_ptr(),
+                        c"tmpfs".as_ptr(),
+                        libc::MS_NOSUID | libc::MS_NODEV,
+                        self.tmpfs_options.as_ptr() as *const libc::c_void,
+                    ))?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Make every mount except the working directory read-only
+        ///
+        /// `mount_setattr` (Linux 5.12) covers all submounts in one call; older
+        /// kernels remount each mount point listed before the fork, keeping the
+        /// flags a user namespace is not allowed to drop.
+        unsafe fn remount_readonly(&self) -> io::Result<()> {
+            let attr = |attr_set, attr_clr| libc::mount_attr { attr_set, attr_clr, propagation: 0, userns_fd: 0 };
+            let size = std::mem::size_of::<libc::mount_attr>();
+            let readonly = attr(libc::MOUNT_ATTR_RDONLY, 0);
+            let result = libc::syscall(
+                libc::SYS_mount_setattr,
+                libc::AT_FDCWD,
+                c"/".as_ptr(),
+                libc::AT_RECURSIVE,
+                &readonly as *const libc::mount_attr,
+                size,
+            );
+            if result == 0 {
+                let writable = attr(0, libc::MOUNT_ATTR_RDONLY);
+                return check(libc::syscall(
+                    libc::SYS_mount_setattr,
+                    libc::AT_FDCWD,
+                    self.workdir.as_ptr(),
+                    0,
+                    &writable as *const libc::mount_attr,
+                    size,
+                ) as libc::c_int);
+            }
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::ENOSYS) {
+                return Err(error);
+            }
+
+            let none = std::ptr::null();
+            for mount_point in &self.mount_points {
+                let mut stat: libc::statvfs = std::mem::zeroed();
+                if libc::statvfs(mount_point.as_ptr(), &mut stat) != 0 {
+                    match io::Error::last_os_error().raw_os_error() {
+                        // Unreachable for the sandboxed user, so not writable either.
+                        Some(libc::EACCES) | Some(libc::ENOENT) => continue,
+                        _ => return Err(io::Error::last_os_error()),
+                    }
+                }
+                let kept = [
+                    (libc::ST_NOSUID, libc::MS_NOSUID),
+                    (libc::ST_NODEV, libc::MS_NODEV),
+                    (libc::ST_NOEXEC, libc::MS_NOEXEC),
+                    (libc::ST_NOATIME, libc::MS_NOATIME),
+                    (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+                    (libc::ST_RELATIME, libc::MS_RELATIME),
+                ]
+                .iter()
+                .filter(|(st, _)| stat.f_flag & st != 0)
+                .fold(0, |flags, (_, ms)| flags | ms);
+                check(libc::mount(
+                    none,
+                    mount_point.as_ptr(),
+                    none,
+                    libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY | kept,
+                    none as _,
+                ))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Mount points of the current mount namespace, parents first
+    fn mount_points() -> Vec<CString> {
+        let Ok(mountinfo) = std::fs::read_to_string("/proc/self/mountinfo") else {
+            return vec![c"/".to_owned()];
+        };
+        mountinfo
+            .lines()
+            .filter_map(|line| line.split(' ').nth(4))
+            .filter_map(|path| CString::new(unescape_mount_path(path)).ok())
+            .collect()
+    }
+
+    /// Decode the `\ooo` octal escapes mountinfo uses for spaces and the like
+    pub(super) fn unescape_mount_path(path: &str) -> Vec<u8> {
+        let bytes = path.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+                std::str::from_utf8(digits).ok().and_then(|digits| u8::from_str_radix(digits, 8).ok())
+            });
+            match octal {
+                Some(byte) if bytes[i] == b'\\' => {
+                    decoded.push(byte);
+                    i += 4;
+                }
+                _ => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        decoded
+    }
+
+    /// Run the isolation steps in a forked child and report whether they succeed
+    pub(super) fn probe() -> bool {
+        let Ok(workdir) = tempfile::Builder::new().prefix("pixelcore-sandbox-probe-").tempdir() else {
+            return false;
+        };
+        let config = SandboxConfig { isolation: Isolation::Required, ..SandboxConfig::default() };
+        let Ok(setup) = ChildSetup::new(&config, workdir.path()) else {
+            return false;
+        };
+        // SAFETY: the child only performs the same raw syscalls as the
+        // `pre_exec` hook and then exits without unwinding.
+        unsafe {
+            let pid = libc::fork();
+            if pid < 0 {
+                return false;
+            }
+            if pid == 0 {
+                libc::_exit(if setup.isolate().is_ok() { 0 } else { 1 });
+            }
+            let mut status = 0;
+            if libc::waitpid(pid, &mut status, 0) != pid {
+                return false;
+            }
+            libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+        }
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    unsafe fn write_file(path: &std::ffi::CStr, data: &[u8]) -> io::Result<()> {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        check(fd)?;
+        let written = libc::write(fd, data.as_ptr() as *const libc::c_void, data.len());
+        libc::close(fd);
+        if written != data.len() as isize {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // ── seccomp ──────────────────────────────────────────────────────────────
+
+    // BPF_LD | BPF_W | BPF_ABS
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x05 | 0x10;
+    const BPF_JMP_JGE_K: u16 = 0x05 | 0x30;
+    const BPF_JMP_JSET_K: u16 = 0x05 | 0x40;
+    const BPF_RET_K: u16 = 0x06;
+
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    const OFFSET_NR: u32 = 0;
+    const OFFSET_ARCH: u32 = 4;
+    const OFFSET_ARG0: u32 = 16;
+
+    /// `AUDIT_ARCH_*` value the filter checks; `None` where no filter is built
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_003E);
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xC000_00B7);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const AUDIT_ARCH: Option<u32> = None;
+
+    const NAMESPACE_FLAGS: u32 = (libc::CLONE_NEWNS
+        | libc::CLONE_NEWUSER
+        | libc::CLONE_NEWNET
+        | libc::CLONE_NEWPID
+        | libc::CLONE_NEWIPC
+        | libc::CLONE_NEWUTS
+        | libc::CLONE_NEWCGROUP) as u32;
+
+    /// Syscalls refused with `EPERM`
+    fn denied_syscalls() -> Vec<libc::c_long> {
+        let mut denied = vec![
+            libc::SYS_ptrace,
+            libc::SYS_process_vm_readv,
+            libc::SYS_process_vm_writev,
+            libc::SYS_mount,
+            libc::SYS_mount_setattr,
+            libc::SYS_move_mount,
+            libc::SYS_open_tree,
+            libc::SYS_fsopen,
+            libc::SYS_fsmount,
+            libc::SYS_fsconfig,
+            libc::SYS_fspick,
+            libc::SYS_umount2,
+            libc::SYS_pivot_root,
+            libc::SYS_chroot,
+            libc::SYS_unshare,
+            libc::SYS_setns,
+            libc::SYS_setsid,
+            libc::SYS_setpgid,
+            libc::SYS_reboot,
+            libc::SYS_kexec_load,
+            libc::SYS_init_module,
+            libc::SYS_finit_module,
+            libc::SYS_delete_module,
+            libc::SYS_swapon,
+            libc::SYS_swapoff,
+            libc::SYS_acct,
+            libc::SYS_quotactl,
+            libc::SYS_syslog,
+            libc::SYS_settimeofday,
+            libc::SYS_clock_settime,
+            libc::SYS_sethostname,
+            libc::SYS_setdomainname,
+            libc::SYS_keyctl,
+            libc::SYS_add_key,
+            libc::SYS_request_key,
+            libc::SYS_bpf,
+            libc::SYS_perf_event_open,
+            libc::SYS_userfaultfd,
+            libc::SYS_open_by_handle_at,
+            libc::SYS_name_to_handle_at,
+            libc::SYS_fanotify_init,
+            libc::SYS_kcmp,
+        ];
+        #[cfg(target_arch = "x86_64")]
+        denied.extend([libc::SYS_iopl, libc::SYS_ioperm]);
+        denied
+    }
+
+    fn stmt(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code, jt: 0, jf: 0, k }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    /// Build the BPF program: kill on a foreign architecture, refuse the
+    /// denied syscalls and namespace-creating `clone`, force `clone3` back to
+    /// `clone` (its flags cannot be inspected), allow everything else.
+    ///
+    /// Returns `None` on architectures the filter has not been written for.
+    pub(super) fn seccomp_filter() -> Option<Vec<libc::sock_filter>> {
+        let arch = AUDIT_ARCH?;
+        let eperm = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+        let mut program = vec![
+            stmt(BPF_LD_W_ABS, OFFSET_ARCH),
+            jump(BPF_JMP_JEQ_K, arch, 1, 0),
+            stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD_W_ABS, OFFSET_NR),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        program.extend([jump(BPF_JMP_JGE_K, 0x4000_0000, 0, 1), stmt(BPF_RET_K, eperm)]);
+        for nr in denied_syscalls() {
+            program.extend([jump(BPF_JMP_JEQ_K, nr as u32, 0, 1), stmt(BPF_RET_K, eperm)]);
+        }
+        program.extend([
+            jump(BPF_JMP_JEQ_K, libc::SYS_clone3 as u32, 0, 1),
+            stmt(BPF_RET_K, SECCOMP_RET_ERRNO | libc::ENOSYS as u32),
+            jump(BPF_JMP_JEQ_K, libc::SYS_clone as u32, 0, 3),
+            stmt(BPF_LD_W_ABS, OFFSET_ARG0),
+            jump(BPF_JMP_JSET_K, NAMESPACE_FLAGS, 0, 1),
+            stmt(BPF_RET_K, eperm),
+            stmt(BPF_RET_K, SECCOMP_RET_ALLOW),
+        ]);
+        Some(program)
+    }
+
+    fn install_seccomp(filter: &[libc::sock_filter]) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_ptr() as *mut libc::sock_filter,
+        };
+        // SAFETY: `program` points at a valid filter for the duration of the call.
+        unsafe {
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+            check(libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox() -> Sandbox {
+        Sandbox::new(
+            SandboxConfig::default()
+                .with_timeout(Duration::from_secs(10))
+                .with_isolation(Isolation::BestEffort),
+        )
+    }
+
+    async fn sh(sandbox: &Sandbox, script: &str, timeout: Duration) -> SandboxOutput {
+        sandbox.run("sh", &["-c", script], timeout).await.unwrap()
+    }
+
+    #[test]
+    fn test_config_from_compute_permissions() {
+        let mut permissions = PermissionManager::new();
+        permissions.grant(Permission::Compute { max_time_ms: 2_000, max_memory_mb: 128 });
+        permissions.grant(Permission::Compute { max_time_ms: 5_000, max_memory_mb: 64 });
+        let config = SandboxConfig::from_permissions(&permissions);
+        assert_eq!(config.timeout, Duration::from_secs(5));
+        assert_eq!(config.memory_mb, 128);
+
+        let config = SandboxConfig::from_permissions(&PermissionManager::allow_all());
+        assert_eq!(config, SandboxConfig::default());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_timeout_kills_whole_process_group() {
+        let sandbox = sandbox();
+        let out = sh(&sandbox, "sleep 30 & echo $!; sleep 30", Duration::from_millis(500)).await;
+        assert!(out.timed_out);
+        let background: u32 = out.stdout.trim().parse().unwrap();
+        // The backgrounded sleep must be dead (gone, or a zombie awaiting its reaper).
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let state = std::fs::read_to_string(format!("/proc/{}/stat", background)).unwrap_or_default();
+        assert!(state.is_empty() || state.contains(") Z "), "still running: {}", state);
+    }
+
+    #[tokio::test]
+    async fn test_environment_and_limits() {
+        std::env::set_var("PIXELCORE_SANDBOX_SECRET", "leak");
+        let sandbox = sandbox();
+        let out = sh(&sandbox, "echo \"[$PIXELCORE_SANDBOX_SECRET]\"; head -c 20000000 /dev/zero > big; echo $?", Duration::from_secs(5)).await;
+        let lines: Vec<_> = out.stdout.lines().collect();
+        assert_eq!(lines[0], "[]");
+        assert_ne!(lines[1], "0", "writing past RLIMIT_FSIZE should fail");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_linux_isolation() {
+        if !Sandbox::isolation_available() {
+            eprintln!("skipping: namespace isolation is unavailable here");
+            return;
+        }
+        let marker = std::env::temp_dir().join(format!("pixelcore-sandbox-marker-{}", std::process::id()));
+        std::fs::write(&marker, "host").unwrap();
+
+        let sandbox = Sandbox::new(SandboxConfig::default().with_timeout(Duration::from_secs(10)));
+        let script = format!(
+            "cat {} 2>/dev/null || echo hidden; touch /etc/pixelcore 2>/dev/null || echo readonly; \
+             echo ok > local && cat local; unshare -U true 2>/dev/null || echo no-unshare; \
+             tail -n +3 /proc/net/dev | cut -d: -f1 | tr -d ' '",
+            marker.display()
+        );
+        let out = sh(&sandbox, &script, Duration::from_secs(5)).await;
+        std::fs::remove_file(&marker).unwrap();
+
+        let lines: Vec<_> = out.stdout.lines().collect();
+        // Only the loopback device exists in the new network namespace.
+        assert_eq!(lines, ["hidden", "readonly", "ok", "no-unshare", "lo"], "stderr: {}", out.stderr);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_submounts_are_read_only() {
+        if !Sandbox::isolation_available() {
+            eprintln!("skipping: namespace isolation is unavailable here");
+            return;
+        }
+        let sandbox = Sandbox::new(SandboxConfig::default().with_timeout(Duration::from_secs(10)));
+        let script = "pwd; for mount in $(cut -d' ' -f5 /proc/self/mountinfo); do \
+                      if touch \"$mount/.pixelcore-probe\" 2>/dev/null; then \
+                      rm -f \"$mount/.pixelcore-probe\"; echo \"$mount\"; fi; done";
+        let out = sh(&sandbox, script, Duration::from_secs(5)).await;
+
+        let mut lines = out.stdout.lines();
+        let workdir = lines.next().unwrap().to_string();
+        let writable: Vec<_> = lines
+            .filter(|mount| *mount != workdir && !["/tmp", "/var/tmp", "/dev/shm"].contains(mount))
+            .collect();
+        assert!(writable.is_empty(), "writable mounts: {:?}, stderr: {}", writable, out.stderr);
+
+        // Permissions alone may already stop the writes above; the mount flags must say read-only too.
+        let out = sh(&sandbox, "pwd; cut -d' ' -f5,6 /proc/self/mountinfo", Duration::from_secs(5)).await;
+        let mut lines = out.stdout.lines();
+        let workdir = lines.next().unwrap().to_string();
+        // A mount stacked on the same path hides the one below; only the last is reachable.
+        let visible: std::collections::BTreeMap<_, _> = lines.filter_map(|line| line.split_once(' ')).collect();
+        let read_write: Vec<_> = visible
+            .into_iter()
+            .filter(|(mount, options)| {
+                !options.split(',').any(|option| option == "ro")
+                    && *mount != workdir
+                    && !["/tmp", "/var/tmp", "/dev/shm"].contains(mount)
+            })
+            .collect();
+        assert!(read_write.is_empty(), "read-write mounts: {:?}", read_write);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_unescape_mount_path() {
+        assert_eq!(linux::unescape_mount_path(r"/mnt/my\040disk"), b"/mnt/my disk");
+        assert_eq!(linux::unescape_mount_path(r"/a\b"), b"/a\\b");
+    }
+
+    #[tokio::test]
+    async fn test_required_isolation_fails_clearly_when_unavailable() {
+        let sandbox = Sandbox::new(SandboxConfig::default());
+        let result = sandbox.run("true", &[], Duration::from_secs(5)).await;
+        if Sandbox::isolation_available() {
+            assert!(result.is_ok());
+        } else {
+            assert!(result.unwrap_err().to_string().contains("BestEffort"));
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use crate::error::SkillError;
+use crate::permissions::PermissionCheck;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillInput {
@@ -8,6 +9,16 @@ pub struct SkillInput {
     pub args: serde_json::Value,
 }
 
+impl SkillInput {
+    /// A required string argument
+    pub fn str_arg(&self, key: &str) -> Result<&str, SkillError> {
+        self.args
+            .get(key)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| SkillError::InvalidInput(format!("missing '{}'", key)))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillOutput {
     pub success: bool,
@@ -30,5 +41,15 @@ pub trait Skill: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
     fn input_schema(&self) -> serde_json::Value;
+
+    /// Permissions needed to run this call with `input`.
+    ///
+    /// `SkillRegistry::execute` checks them against its `PermissionManager`
+    /// before `execute` runs. Skills that touch no external resource keep the
+    /// default of requiring nothing.
+    fn required_permissions(&self, _input: &SkillInput) -> Result<Vec<PermissionCheck>, SkillError> {
+        Ok(Vec::new())
+    }
+
     async fn execute(&self, input: SkillInput) -> Result<SkillOutput, SkillError>;
 }
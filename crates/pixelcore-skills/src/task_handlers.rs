@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use crate::registry::SkillRegistry;
+use crate::skill::SkillInput;
+use pixelcore_runtime::agent::AgentId;
+use pixelcore_runtime::message::Message;
+use pixelcore_runtime::workflow::TaskHandler;
+use pixelcore_swarm::coordinator::Coordinator;
+
+/// Runs a workflow task node by executing a skill from a `SkillRegistry`.
+///
+/// By default the skill is looked up by the task name; use `with_skill` to bind
+/// a task name to a differently named skill. Rendered task params are passed as
+/// the skill's `args`.
+pub struct SkillTaskHandler {
+    registry: Arc<SkillRegistry>,
+    skill_name: Option<String>,
+}
+
+impl SkillTaskHandler {
+    pub fn new(registry: Arc<SkillRegistry>) -> Self {
+        Self { registry, skill_name: None }
+    }
+
+    pub fn with_skill(mut self, skill_name: impl Into<String>) -> Self {
+        self.skill_name = Some(skill_name.into());
+        self
+    }
+}
+
+#[async_trait]
+impl TaskHandler for SkillTaskHandler {
+    async fn handle(
+        &self,
+        task_name: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let name = self.skill_name.as_deref().unwrap_or(task_name);
+
+        let output = self
+            .registry
+            .execute(SkillInput { name: name.to_string(), args: params })
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if output.success {
+            Ok(output.result)
+        } else {
+            Err(output.error.unwrap_or_else(|| format!("skill '{name}' failed")))
+        }
+    }
+}
+
+/// Runs a workflow task node by sending a message to an agent through a `Coordinator`.
+///
+/// The message is taken from the `message` param when present, otherwise the
+/// whole params object is sent as JSON. The result is `{"response": <reply>}`,
+/// matching `DelegateSkill`.
+pub struct AgentTaskHandler {
+    coordinator: Arc<Coordinator>,
+    agent_id: AgentId,
+}
+
+impl AgentTaskHandler {
+    pub fn new(coordinator: Arc<Coordinator>, agent_id: AgentId) -> Self {
+        Self { coordinator, agent_id }
+    }
+}
+
+#[async_trait]
+impl TaskHandler for AgentTaskHandler {
+    async fn handle(
+        &self,
+        _task_name: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let content = match params.get("message") {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => params.to_string(),
+        };
+
+        let reply = self.coordinator
+            .route(&self.agent_id, Message::user(content))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(serde_json::json!({ "response": reply.content }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtins::EchoSkill;
+    use pixelcore_runtime::workflow::{ExecutionStatus, Workflow, WorkflowExecutor, WorkflowNode};
+
+    #[tokio::test]
+    async fn test_skill_task_handler_in_workflow() {
+        let mut registry = SkillRegistry::new();
+        registry.register(Arc::new(EchoSkill));
+        let registry = Arc::new(registry);
+
+        let mut workflow = Workflow::new("Test", "Test");
+        workflow.set_variable("greeting", serde_json::json!("hi"));
+        let start = workflow.add_node(WorkflowNode::start("Start"));
+        let task = workflow.add_node(WorkflowNode::task(
+            "Echo",
+            "say",
+            serde_json::json!({ "message": "${greeting} there" }),
+        ));
+        let end = workflow.add_node(WorkflowNode::end("End"));
+        workflow.connect(start, task);
+        workflow.connect(task, end);
+
+        let executor = WorkflowExecutor::new(workflow).with_task_handler(
+            "say",
+            Arc::new(SkillTaskHandler::new(registry).with_skill("echo")),
+        );
+        let context = executor.execute().await.unwrap();
+
+        assert_eq!(context.status, ExecutionStatus::Completed);
+        assert_eq!(
+            context.get_node_result(&task),
+            Some(&serde_json::json!({ "message": "hi there" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_skill_task_handler_missing_skill() {
+        let handler = SkillTaskHandler::new(Arc::new(SkillRegistry::new()));
+        let err = handler.handle("nope", serde_json::json!({})).await.unwrap_err();
+        assert!(err.contains("nope"));
+    }
+}
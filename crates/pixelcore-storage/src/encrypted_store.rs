@@ -1,4 +1,4 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use crate::error::StorageError;
@@ -89,6 +89,64 @@ impl EncryptedStore {
         Ok(keys)
     }
 
+    /// 按前缀列出键值对（按键排序）
+    pub fn scan_prefix(&self, prefix: &str) -> Result<Vec<(StorageKey, StorageValue)>, StorageError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT key, value FROM kv_store WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key",
+        )?;
+        let rows = stmt.query_map(params![prefix], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (key, value) = row?;
+            entries.push((key, serde_json::from_str(&value)?));
+        }
+        Ok(entries)
+    }
+
+    /// 原子比较并交换：当前值等于 `expected` 时写入 `new`（`None` 表示不存在/删除）
+    pub fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&StorageValue>,
+        new: Option<&StorageValue>,
+    ) -> Result<bool, StorageError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let current: Option<String> = tx
+            .query_row("SELECT value FROM kv_store WHERE key = ?", params![key], |row| row.get(0))
+            .optional()?;
+        let current: Option<StorageValue> = current.map(|v| serde_json::from_str(&v)).transpose()?;
+        if current.as_ref() != expected {
+            return Ok(false);
+        }
+
+        match new {
+            Some(value) => {
+                let value_str = serde_json::to_string(value)?;
+                let now = chrono::Utc::now().timestamp();
+                tx.execute(
+                    "INSERT INTO kv_store (key, value, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?3)
+                     ON CONFLICT(key) DO UPDATE SET
+                        value = excluded.value,
+                        updated_at = excluded.updated_at",
+                    params![key, value_str, now],
+                )?;
+            }
+            None => {
+                tx.execute("DELETE FROM kv_store WHERE key = ?", params![key])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(true)
+    }
+
     pub fn clear(&self) -> Result<(), StorageError> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM kv_store", [])?;
@@ -16,4 +16,7 @@ pub enum StorageError {
 
     #[error("SQLite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
+
+    #[error("Lease lost: {0} is owned by another instance")]
+    LeaseLost(String),
 }
@@ -1,7 +1,9 @@
 pub mod store;
 pub mod error;
 pub mod encrypted_store;
+pub mod workflow_runs;
 
 pub use store::{Storage, StorageKey, StorageValue};
 pub use error::StorageError;
 pub use encrypted_store::EncryptedStore;
+pub use workflow_runs::{WorkflowRunStore, RunRecord};
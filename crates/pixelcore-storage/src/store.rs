@@ -111,6 +111,76 @@ impl Storage {
             Backend::Encrypted(store) => store.keys(),
         }
     }
+
+    /// 按前缀列出键值对（按键排序）
+    pub fn scan_prefix(&self, prefix: &str) -> Result<Vec<(StorageKey, StorageValue)>, StorageError> {
+        match self.inner.as_ref() {
+            Backend::Memory(map) => {
+                let mut entries: Vec<_> = map
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .filter(|(k, _)| k.starts_with(prefix))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                Ok(entries)
+            }
+            Backend::Sled(db) => {
+                let mut entries = Vec::new();
+                for item in db.scan_prefix(prefix) {
+                    let (k, v) = item?;
+                    if let Ok(key) = String::from_utf8(k.to_vec()) {
+                        entries.push((key, serde_json::from_slice(&v)?));
+                    }
+                }
+                Ok(entries)
+            }
+            Backend::Encrypted(store) => store.scan_prefix(prefix),
+        }
+    }
+
+    /// 原子比较并交换
+    ///
+    /// 仅当 `key` 的当前值等于 `expected` 时写入 `new`，返回是否写入成功。
+    /// `None` 表示键不存在（`expected`）或删除该键（`new`）。
+    pub fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&StorageValue>,
+        new: Option<StorageValue>,
+    ) -> Result<bool, StorageError> {
+        match self.inner.as_ref() {
+            Backend::Memory(map) => {
+                let mut map = map.write().unwrap();
+                if map.get(key) != expected {
+                    return Ok(false);
+                }
+                match new {
+                    Some(value) => map.insert(key.to_string(), value),
+                    None => map.remove(key),
+                };
+                Ok(true)
+            }
+            Backend::Sled(db) => {
+                let expected = expected.map(serde_json::to_vec).transpose()?;
+                let new = new.as_ref().map(serde_json::to_vec).transpose()?;
+                Ok(db.compare_and_swap(key, expected, new)?.is_ok())
+            }
+            Backend::Encrypted(store) => store.compare_and_swap(key, expected, new.as_ref()),
+        }
+    }
+
+    /// 将缓冲的写入刷到磁盘（sled 后台定期刷盘，需要持久性保证时调用）
+    pub fn flush(&self) -> Result<(), StorageError> {
+        match self.inner.as_ref() {
+            Backend::Sled(db) => {
+                db.flush()?;
+                Ok(())
+            }
+            Backend::Memory(_) | Backend::Encrypted(_) => Ok(()),
+        }
+    }
 }
 
 impl Default for Storage {
@@ -0,0 +1,350 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use pixelcore_runtime::workflow::{
+    ExecutionContext, ExecutionStatus, JournalEntry, RunJournal, Workflow, WorkflowExecutor,
+};
+
+use crate::error::StorageError;
+use crate::store::Storage;
+
+const RUN_PREFIX: &str = "workflow_run:";
+const JOURNAL_PREFIX: &str = "workflow_run_journal:";
+
+/// 持久化的工作流运行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub execution_id: Uuid,
+    pub workflow: Workflow,
+    pub context: ExecutionContext,
+    /// 当前负责执行该运行的进程
+    pub owner: Uuid,
+    /// 所有者的租约到期时间，执行期间由所有者定期续约
+    #[serde(default)]
+    pub lease_expires_at: DateTime<Utc>,
+    /// 已写入的日志条目数
+    pub journal_len: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RunRecord {
+    /// 运行是否尚未结束
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self.context.status, ExecutionStatus::Running | ExecutionStatus::Paused)
+    }
+
+    /// 租约是否已过期（所有者已停止续约，通常是进程崩溃）
+    pub fn lease_expired(&self, now: DateTime<Utc>) -> bool {
+        self.lease_expires_at <= now
+    }
+}
+
+/// 基于 `Storage` 的工作流运行存储
+///
+/// 作为 `RunJournal` 挂载到 `WorkflowExecutor` 后，每次节点状态变化都会写入
+/// 上下文快照和一条日志。执行期间后台任务按租约时长的三分之一续约；进程崩溃后
+/// 租约过期，其他实例用 `recover_incomplete` 接管并恢复未完成的运行。
+///
+/// 运行记录的每次写入都是对 `owner` 的比较并交换：运行被其他实例接管后，原实例
+/// 的写入会以 `StorageError::LeaseLost` 失败，从而终止原实例上的执行。
+pub struct WorkflowRunStore {
+    storage: Arc<Storage>,
+    owner: Uuid,
+    lease_ttl: Duration,
+    sequences: Mutex<HashMap<Uuid, u64>>,
+    heartbeats: Mutex<HashMap<Uuid, JoinHandle<()>>>,
+}
+
+impl WorkflowRunStore {
+    pub fn new(storage: Storage) -> Self {
+        Self {
+            storage: Arc::new(storage),
+            owner: Uuid::new_v4(),
+            lease_ttl: Duration::from_secs(30),
+            sequences: Mutex::new(HashMap::new()),
+            heartbeats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 设置租约时长（默认 30 秒）
+    pub fn with_lease_ttl(mut self, ttl: Duration) -> Self {
+        self.lease_ttl = ttl;
+        self
+    }
+
+    /// 当前 store 实例的所有者 ID
+    pub fn owner(&self) -> Uuid {
+        self.owner
+    }
+
+    fn run_key(execution_id: &Uuid) -> String {
+        format!("{RUN_PREFIX}{execution_id}")
+    }
+
+    fn journal_prefix(execution_id: &Uuid) -> String {
+        format!("{JOURNAL_PREFIX}{execution_id}:")
+    }
+
+    pub fn get_run(&self, execution_id: &Uuid) -> Result<RunRecord, StorageError> {
+        let value = self.storage.get(&Self::run_key(execution_id))?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn list_runs(&self) -> Result<Vec<RunRecord>, StorageError> {
+        self.storage
+            .scan_prefix(RUN_PREFIX)?
+            .into_iter()
+            .map(|(_, value)| serde_json::from_value(value).map_err(StorageError::from))
+            .collect()
+    }
+
+    /// 列出租约已过期的未完成运行（通常是崩溃前的进程留下的）
+    pub fn incomplete_runs(&self) -> Result<Vec<RunRecord>, StorageError> {
+        let now = Utc::now();
+        Ok(self
+            .list_runs()?
+            .into_iter()
+            .filter(|r| r.is_incomplete() && r.owner != self.owner && r.lease_expired(now))
+            .collect())
+    }
+
+    /// 运行的日志条目（按写入顺序）
+    ///
+    /// 只返回运行记录中 `journal_len` 计入的条目；失去所有权的实例可能留下未计入的条目。
+    pub fn journal(&self, execution_id: &Uuid) -> Result<Vec<JournalEntry>, StorageError> {
+        let journal_len = match self.get_run(execution_id) {
+            Ok(record) => record.journal_len,
+            Err(StorageError::NotFound(_)) => 0,
+            Err(e) => return Err(e),
+        };
+        self.storage
+            .scan_prefix(&Self::journal_prefix(execution_id))?
+            .into_iter()
+            .take(journal_len as usize)
+            .map(|(_, value)| serde_json::from_value(value).map_err(StorageError::from))
+            .collect()
+    }
+
+    /// 认领 `incomplete_runs` 列出的运行
+    ///
+    /// 只有当运行的所有者仍是列出时看到的那个实例、且租约仍已过期时才能认领
+    /// 成功，因此多个实例同时恢复时每个运行只会被其中一个认领；已被其他实例认领
+    /// 或原所有者恢复续约时返回 `None`。
+    pub fn claim(&self, run: &RunRecord) -> Result<Option<RunRecord>, StorageError> {
+        let key = Self::run_key(&run.execution_id);
+        let current = self.storage.get(&key)?;
+        let mut record: RunRecord = serde_json::from_value(current.clone())?;
+        let now = Utc::now();
+        if record.owner == self.owner {
+            return Ok(Some(record));
+        }
+        if record.owner != run.owner || !record.is_incomplete() || !record.lease_expired(now) {
+            return Ok(None);
+        }
+
+        record.owner = self.owner;
+        record.lease_expires_at = now + self.lease_ttl;
+        record.updated_at = now;
+        let claimed = self
+            .storage
+            .compare_and_swap(&key, Some(&current), Some(serde_json::to_value(&record)?))?;
+        if !claimed {
+            return Ok(None);
+        }
+        self.storage.flush()?;
+        Ok(Some(record))
+    }
+
+    /// 续约，运行已被其他实例接管时返回 `StorageError::LeaseLost`
+    pub fn renew_lease(&self, execution_id: &Uuid) -> Result<(), StorageError> {
+        Self::update_owned(&self.storage, self.owner, execution_id, |record| {
+            record.lease_expires_at = Utc::now() + self.lease_ttl;
+        })?;
+        Ok(())
+    }
+
+    /// 删除运行记录及其日志
+    pub fn delete_run(&self, execution_id: &Uuid) -> Result<bool, StorageError> {
+        for (key, _) in self.storage.scan_prefix(&Self::journal_prefix(execution_id))? {
+            self.storage.delete(&key)?;
+        }
+        self.stop_heartbeat(execution_id);
+        self.sequences.lock().unwrap().remove(execution_id);
+        self.storage.delete(&Self::run_key(execution_id))
+    }
+
+    /// 认领并恢复所有未完成的运行
+    ///
+    /// `configure` 用于给恢复的执行器注册任务处理器。已完成的任务节点不会再次执行。
+    pub async fn recover_incomplete<F>(
+        self: &Arc<Self>,
+        configure: F,
+    ) -> Result<Vec<(Uuid, Result<ExecutionContext, String>)>, StorageError>
+    where
+        F: Fn(WorkflowExecutor) -> WorkflowExecutor,
+    {
+        let mut recovered = Vec::new();
+        for run in self.incomplete_runs()? {
+            let Some(record) = self.claim(&run)? else { continue };
+
+            let executor = configure(WorkflowExecutor::from_checkpoint(record.workflow, record.context))
+                .with_journal(Arc::clone(self) as Arc<dyn RunJournal>);
+            recovered.push((record.execution_id, executor.recover().await));
+        }
+        Ok(recovered)
+    }
+
+    /// 以比较并交换的方式修改自己持有的运行记录，并顺带续约
+    ///
+    /// 记录的所有者不是 `owner` 时返回 `StorageError::LeaseLost`；并发写入导致
+    /// 交换失败时重新读取后重试。
+    fn update_owned(
+        storage: &Storage,
+        owner: Uuid,
+        execution_id: &Uuid,
+        mut update: impl FnMut(&mut RunRecord),
+    ) -> Result<RunRecord, StorageError> {
+        let key = Self::run_key(execution_id);
+        loop {
+            let current = storage.get(&key)?;
+            let mut record: RunRecord = serde_json::from_value(current.clone())?;
+            if record.owner != owner {
+                return Err(StorageError::LeaseLost(execution_id.to_string()));
+            }
+            update(&mut record);
+            record.updated_at = Utc::now();
+            if storage.compare_and_swap(&key, Some(&current), Some(serde_json::to_value(&record)?))? {
+                storage.flush()?;
+                return Ok(record);
+            }
+        }
+    }
+
+    /// 开始（或恢复）运行：新运行直接创建记录，已有记录必须由自己持有
+    fn start_run(&self, workflow: &Workflow, context: &ExecutionContext) -> Result<RunRecord, StorageError> {
+        let key = Self::run_key(&context.execution_id);
+        loop {
+            if self.storage.contains(&key)? {
+                return Self::update_owned(&self.storage, self.owner, &context.execution_id, |record| {
+                    record.workflow = workflow.clone();
+                    record.context = context.clone();
+                    record.lease_expires_at = Utc::now() + self.lease_ttl;
+                });
+            }
+
+            let now = Utc::now();
+            let record = RunRecord {
+                execution_id: context.execution_id,
+                workflow: workflow.clone(),
+                context: context.clone(),
+                owner: self.owner,
+                lease_expires_at: now + self.lease_ttl,
+                journal_len: 0,
+                updated_at: now,
+            };
+            if self.storage.compare_and_swap(&key, None, Some(serde_json::to_value(&record)?))? {
+                self.storage.flush()?;
+                return Ok(record);
+            }
+        }
+    }
+
+    /// 在后台定期续约，直到运行结束或租约被其他实例接管
+    fn start_heartbeat(&self, execution_id: Uuid) {
+        // 只持有弱引用，store 释放后不会让底层存储继续保持打开
+        let storage = Arc::downgrade(&self.storage);
+        let (owner, ttl) = (self.owner, self.lease_ttl);
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(ttl / 3).await;
+                let Some(storage) = storage.upgrade() else { break };
+                let renewed = Self::update_owned(&storage, owner, &execution_id, |record| {
+                    record.lease_expires_at = Utc::now() + ttl;
+                });
+                if let Err(e) = renewed {
+                    tracing::warn!("Stopped renewing lease for workflow run {}: {}", execution_id, e);
+                    break;
+                }
+            }
+        });
+        if let Some(previous) = self.heartbeats.lock().unwrap().insert(execution_id, handle) {
+            previous.abort();
+        }
+    }
+
+    fn stop_heartbeat(&self, execution_id: &Uuid) {
+        if let Some(handle) = self.heartbeats.lock().unwrap().remove(execution_id) {
+            handle.abort();
+        }
+    }
+
+    fn sequence(&self, execution_id: &Uuid, fallback: u64) -> u64 {
+        *self.sequences.lock().unwrap().entry(*execution_id).or_insert(fallback)
+    }
+}
+
+impl Drop for WorkflowRunStore {
+    fn drop(&mut self) {
+        for (_, handle) in self.heartbeats.lock().unwrap().drain() {
+            handle.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl RunJournal for WorkflowRunStore {
+    async fn begin(&self, workflow: &Workflow, context: &ExecutionContext) -> Result<(), String> {
+        let record = self.start_run(workflow, context).map_err(|e| e.to_string())?;
+        self.sequences.lock().unwrap().insert(context.execution_id, record.journal_len);
+        self.start_heartbeat(context.execution_id);
+        Ok(())
+    }
+
+    async fn record(&self, entry: &JournalEntry, context: &ExecutionContext) -> Result<(), String> {
+        // 持有序号锁完成整次写入：并行分支的写入按序号顺序落盘，写入失败也不会留下空号
+        let mut sequences = self.sequences.lock().unwrap();
+        let seq = *sequences.entry(entry.execution_id).or_insert(0);
+
+        // 先确认仍持有运行，失去所有权后不再写日志
+        let record = self.get_run(&entry.execution_id).map_err(|e| e.to_string())?;
+        if record.owner != self.owner {
+            return Err(StorageError::LeaseLost(entry.execution_id.to_string()).to_string());
+        }
+
+        // 先写日志再更新记录，崩溃时 `journal_len` 不会计入不存在的条目
+        let key = format!("{}{:012}", Self::journal_prefix(&entry.execution_id), seq);
+        let value = serde_json::to_value(entry).map_err(|e| e.to_string())?;
+        self.storage.set(key, value).map_err(|e| e.to_string())?;
+
+        Self::update_owned(&self.storage, self.owner, &entry.execution_id, |record| {
+            if seq >= record.journal_len {
+                record.context = context.clone();
+            }
+            record.journal_len = record.journal_len.max(seq + 1);
+            record.lease_expires_at = Utc::now() + self.lease_ttl;
+        })
+        .map_err(|e| e.to_string())?;
+
+        sequences.insert(entry.execution_id, seq + 1);
+        Ok(())
+    }
+
+    async fn finish(&self, context: &ExecutionContext) -> Result<(), String> {
+        self.stop_heartbeat(&context.execution_id);
+        let journal_len = self.sequence(&context.execution_id, 0);
+        let result = Self::update_owned(&self.storage, self.owner, &context.execution_id, |record| {
+            record.context = context.clone();
+            record.journal_len = record.journal_len.max(journal_len);
+            // 结束时释放租约，暂停的运行可以立即被其他实例接管
+            record.lease_expires_at = Utc::now();
+        });
+        self.sequences.lock().unwrap().remove(&context.execution_id);
+        result.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,215 @@
+use pixelcore_runtime::workflow::{
+    ExecutionStatus, FnTaskHandler, JournalEntry, NodeTransition, RunJournal, Workflow, WorkflowExecutor,
+    WorkflowNode,
+};
+use pixelcore_storage::{Storage, StorageError, WorkflowRunStore};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn order_workflow() -> Workflow {
+    let mut workflow = Workflow::new("order", "charge then ship");
+    let start = workflow.add_node(WorkflowNode::start("Start"));
+    let charge = workflow.add_node(WorkflowNode::task("Charge", "charge", json!({})));
+    let ship = workflow.add_node(WorkflowNode::task("Ship", "ship", json!({})));
+    let end = workflow.add_node(WorkflowNode::end("End"));
+    workflow.connect(start, charge);
+    workflow.connect(charge, ship);
+    workflow.connect(ship, end);
+    workflow
+}
+
+#[test]
+fn test_compare_and_swap() {
+    let storage = Storage::new();
+
+    assert!(storage.compare_and_swap("k", None, Some(json!(1))).unwrap());
+    assert!(!storage.compare_and_swap("k", None, Some(json!(2))).unwrap());
+    assert!(storage.compare_and_swap("k", Some(&json!(1)), Some(json!(2))).unwrap());
+    assert_eq!(storage.get("k").unwrap(), json!(2));
+    assert!(storage.compare_and_swap("k", Some(&json!(2)), None).unwrap());
+    assert!(!storage.contains("k").unwrap());
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::open(temp_dir.path().join("cas.db")).unwrap();
+    assert!(storage.compare_and_swap("k", None, Some(json!({"a": 1}))).unwrap());
+    assert!(!storage.compare_and_swap("k", Some(&json!({"a": 2})), Some(json!(3))).unwrap());
+    assert!(storage.compare_and_swap("k", Some(&json!({"a": 1})), Some(json!(3))).unwrap());
+}
+
+#[test]
+fn test_scan_prefix() {
+    let storage = Storage::new();
+    storage.set("run:2", json!(2)).unwrap();
+    storage.set("run:1", json!(1)).unwrap();
+    storage.set("other", json!(0)).unwrap();
+
+    let entries = storage.scan_prefix("run:").unwrap();
+    assert_eq!(entries, vec![("run:1".to_string(), json!(1)), ("run:2".to_string(), json!(2))]);
+}
+
+#[tokio::test]
+async fn test_crash_recovery_does_not_repeat_completed_tasks() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("runs.db");
+
+    let charges = Arc::new(AtomicUsize::new(0));
+    let carrier_up = Arc::new(AtomicBool::new(false));
+    let configure = |executor: WorkflowExecutor| {
+        let charges = Arc::clone(&charges);
+        let carrier_up = Arc::clone(&carrier_up);
+        executor
+            .with_task_handler("charge", Arc::new(FnTaskHandler(move |_: &str, _| {
+                charges.fetch_add(1, Ordering::SeqCst);
+                Ok(json!("charged"))
+            })))
+            .with_task_handler("ship", Arc::new(FnTaskHandler(move |_: &str, _| {
+                if carrier_up.load(Ordering::SeqCst) {
+                    Ok(json!("shipped"))
+                } else {
+                    Err("carrier unavailable".to_string())
+                }
+            })))
+    };
+
+    // 第一个进程：Charge 完成后在 Ship 处“崩溃”（运行停在 Running 状态）
+    let execution_id = {
+        let store = Arc::new(WorkflowRunStore::new(Storage::open(&db_path).unwrap()));
+        let executor = configure(WorkflowExecutor::new(order_workflow()))
+            .with_journal(Arc::clone(&store) as Arc<dyn RunJournal>);
+        assert!(executor.execute().await.is_err());
+
+        let mut context = executor.get_context().await;
+        let run = store.get_run(&context.execution_id).unwrap();
+        assert_eq!(run.context.status, ExecutionStatus::Failed);
+
+        // 模拟进程在写入失败状态前崩溃
+        context.status = ExecutionStatus::Running;
+        store.finish(&context).await.unwrap();
+        context.execution_id
+    };
+    assert_eq!(charges.load(Ordering::SeqCst), 1);
+
+    // 第二个进程：列出未完成的运行并恢复
+    carrier_up.store(true, Ordering::SeqCst);
+    let storage = Storage::open(&db_path).unwrap();
+    let store = Arc::new(WorkflowRunStore::new(storage.clone()));
+    let incomplete = store.incomplete_runs().unwrap();
+    assert_eq!(incomplete.len(), 1);
+    assert_eq!(incomplete[0].execution_id, execution_id);
+
+    let recovered = store.recover_incomplete(configure).await.unwrap();
+    assert_eq!(recovered.len(), 1);
+    let context = recovered[0].1.as_ref().unwrap();
+    assert_eq!(context.status, ExecutionStatus::Completed);
+    assert_eq!(charges.load(Ordering::SeqCst), 1);
+
+    // 已恢复的运行不会再次出现，也不会被其他实例重复认领
+    assert!(store.incomplete_runs().unwrap().is_empty());
+    let other = WorkflowRunStore::new(storage);
+    assert!(other.incomplete_runs().unwrap().is_empty());
+    assert!(other.claim(&incomplete[0]).unwrap().is_none());
+
+    let journal = store.journal(&execution_id).unwrap();
+    assert!(journal.iter().any(|e| e.transition == NodeTransition::Replayed));
+    assert_eq!(
+        journal.iter().filter(|e| e.transition == NodeTransition::Failed).count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_claim_is_exclusive() {
+    let storage = Storage::new();
+    let origin = WorkflowRunStore::new(storage.clone()).with_lease_ttl(Duration::from_millis(60));
+    let workflow = order_workflow();
+    let context = pixelcore_runtime::workflow::ExecutionContext::new(workflow.id);
+    origin.begin(&workflow, &context).await.unwrap();
+
+    // 所有者仍在续约时，运行不会被其他实例列出
+    let a = WorkflowRunStore::new(storage.clone());
+    let b = WorkflowRunStore::new(storage.clone());
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(a.incomplete_runs().unwrap().is_empty());
+
+    // 所有者崩溃后租约过期；两个实例同时看到同一个运行，只有一个能认领
+    drop(origin);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let seen_by_a = a.incomplete_runs().unwrap();
+    let seen_by_b = b.incomplete_runs().unwrap();
+    assert_eq!(seen_by_a.len(), 1);
+    assert_eq!(seen_by_b.len(), 1);
+
+    assert!(a.claim(&seen_by_a[0]).unwrap().is_some());
+    assert!(b.claim(&seen_by_b[0]).unwrap().is_none());
+    assert_eq!(a.get_run(&context.execution_id).unwrap().owner, a.owner());
+    assert!(a.incomplete_runs().unwrap().is_empty());
+    assert!(b.incomplete_runs().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_writes_fail_after_lease_is_taken_over() {
+    let storage = Storage::new();
+    let origin = WorkflowRunStore::new(storage.clone());
+    let workflow = order_workflow();
+    let context = pixelcore_runtime::workflow::ExecutionContext::new(workflow.id);
+    origin.begin(&workflow, &context).await.unwrap();
+    let node_id = workflow.nodes.keys().next().copied().unwrap();
+    let entry = JournalEntry::new(context.execution_id, node_id, NodeTransition::Started);
+    origin.record(&entry, &context).await.unwrap();
+
+    // 模拟所有者长时间停顿、租约过期后被其他实例接管
+    let key = format!("workflow_run:{}", context.execution_id);
+    let mut record = storage.get(&key).unwrap();
+    record["lease_expires_at"] = json!(chrono::Utc::now() - chrono::Duration::seconds(1));
+    storage.set(key, record).unwrap();
+
+    let other = WorkflowRunStore::new(storage.clone());
+    let runs = other.incomplete_runs().unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(other.claim(&runs[0]).unwrap().is_some());
+
+    // 原所有者的日志、上下文和续约写入都会失败
+    let err = origin.record(&entry, &context).await.unwrap_err();
+    assert!(err.contains("Lease lost"), "{err}");
+    assert!(origin.finish(&context).await.is_err());
+    assert!(matches!(origin.renew_lease(&context.execution_id), Err(StorageError::LeaseLost(_))));
+    assert_eq!(origin.journal(&context.execution_id).unwrap().len(), 1);
+    assert_eq!(other.get_run(&context.execution_id).unwrap().owner, other.owner());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_records_keep_latest_context() {
+    let store = Arc::new(WorkflowRunStore::new(Storage::new()));
+    let workflow = order_workflow();
+    let context = pixelcore_runtime::workflow::ExecutionContext::new(workflow.id);
+    store.begin(&workflow, &context).await.unwrap();
+    let node_id = workflow.nodes.keys().next().copied().unwrap();
+
+    // 模拟并行分支同时写入日志，每次写入都带上当时的上下文快照
+    let tasks: Vec<_> = (0..32)
+        .map(|i| {
+            let store = Arc::clone(&store);
+            let mut context = context.clone();
+            tokio::spawn(async move {
+                context.variables.insert("step".to_string(), json!(i));
+                let mut entry = JournalEntry::new(context.execution_id, node_id, NodeTransition::Started);
+                entry.error = Some(i.to_string());
+                store.record(&entry, &context).await.unwrap();
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let record = store.get_run(&context.execution_id).unwrap();
+    let journal = store.journal(&context.execution_id).unwrap();
+    assert_eq!(record.journal_len, 32);
+    assert_eq!(journal.len(), 32);
+    // 记录中的上下文来自序号最大的那次写入
+    let latest = journal.last().unwrap().error.clone().unwrap();
+    assert_eq!(record.context.variables["step"], json!(latest.parse::<u64>().unwrap()));
+}
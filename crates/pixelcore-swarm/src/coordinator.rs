@@ -1,22 +1,95 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
 use crate::swarm::Swarm;
 use crate::error::SwarmError;
+use pixelcore_heartbeat::{AgentLoad, FlowMonitor};
+use pixelcore_marketplace::SmartMatcher;
+use pixelcore_registry::{AgentRegistry, Capability};
 use pixelcore_runtime::agent::AgentId;
 use pixelcore_runtime::event::{Event, EventBus, EventKind};
 use pixelcore_runtime::message::Message;
+use serde::Serialize;
 
 pub struct Coordinator {
     swarm: Swarm,
     event_bus: EventBus,
+    flow: Option<Arc<FlowMonitor>>,
+    registry: Option<Arc<AgentRegistry>>,
+    matcher: SmartMatcher,
+}
+
+/// An agent able to handle a capability, as ranked by `Coordinator::candidates`
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub id: AgentId,
+    pub name: String,
+    /// Marketplace match score (0.0 - 1.0).
+    pub match_score: f64,
+    /// Live load, if a `FlowMonitor` is attached and tracks the agent.
+    pub load: Option<AgentLoad>,
+    /// `match_score` minus the load penalty; candidates are sorted by this.
+    pub score: f64,
+}
+
+/// Publishes `TaskFailed` if a routed call is dropped before it finishes, so
+/// a timed-out call does not stay in flight in the `FlowMonitor`.
+struct TaskGuard<'a> {
+    event_bus: &'a EventBus,
+    id: AgentId,
+    done: bool,
+}
+
+impl TaskGuard<'_> {
+    fn start(event_bus: &EventBus, id: AgentId) -> TaskGuard<'_> {
+        publish_task(event_bus, EventKind::TaskStarted, &id);
+        TaskGuard { event_bus, id, done: false }
+    }
+
+    fn finish(mut self, ok: bool) {
+        self.done = true;
+        let kind = if ok { EventKind::TaskCompleted } else { EventKind::TaskFailed };
+        publish_task(self.event_bus, kind, &self.id);
+    }
+}
+
+impl Drop for TaskGuard<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            publish_task(self.event_bus, EventKind::TaskFailed, &self.id);
+        }
+    }
+}
+
+fn publish_task(event_bus: &EventBus, kind: EventKind, id: &AgentId) {
+    let _ = event_bus.publish(Event::new(kind, "coordinator", serde_json::json!({ "agent_id": id.to_string() })));
 }
 
 impl Coordinator {
     pub fn new(swarm: Swarm, event_bus: EventBus) -> Self {
-        Self { swarm, event_bus }
+        Self { swarm, event_bus, flow: None, registry: None, matcher: SmartMatcher::new() }
+    }
+
+    /// Use live load from `monitor` when selecting agents. The monitor should
+    /// listen on this coordinator's event bus, which carries the task events
+    /// published by `route`.
+    pub fn with_flow_monitor(mut self, monitor: Arc<FlowMonitor>) -> Self {
+        self.flow = Some(monitor);
+        self
+    }
+
+    /// Rank agents with their registry listings (reputation, transaction
+    /// history, extra capabilities) where one exists under the agent's ID.
+    pub fn with_registry(mut self, registry: Arc<AgentRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
     }
 
     /// Route a message to a specific agent, publish result event.
     pub async fn route(&self, id: &AgentId, message: Message) -> Result<Message, SwarmError> {
-        let reply = self.swarm.route(id, message).await?;
+        let guard = TaskGuard::start(&self.event_bus, *id);
+        let result = self.swarm.route(id, message).await;
+        guard.finish(result.is_ok());
+        let reply = result?;
         let _ = self.event_bus.publish(Event::new(
             EventKind::MessageSent,
             "coordinator",
@@ -36,6 +109,100 @@ impl Coordinator {
         Ok(results)
     }
 
+    /// Agents offering `capability`, best first.
+    ///
+    /// Each agent is scored with marketplace matching against its registry
+    /// listing (or its swarm profile), then penalised for load: up to 0.5 for
+    /// tasks in flight and up to 0.2 for a high error rate.
+    pub async fn candidates(&self, capability: &str) -> Vec<Candidate> {
+        let required = [capability.to_string()];
+        let mut candidates = Vec::new();
+        for (id, profile) in self.swarm.profiles() {
+            if self.swarm.is_quarantined(&id) {
+                continue;
+            }
+            let mut listing = self
+                .registry
+                .as_ref()
+                .and_then(|registry| registry.get(&id).ok().flatten())
+                .unwrap_or_else(|| profile.to_listing(id));
+            for tag in &profile.capabilities {
+                if !listing.capabilities.iter().any(|c| c.skill_name.eq_ignore_ascii_case(tag)) {
+                    listing.capabilities.push(Capability {
+                        skill_name: tag.clone(),
+                        description: String::new(),
+                        input_schema: serde_json::Value::Null,
+                        output_schema: serde_json::Value::Null,
+                    });
+                }
+            }
+            if !listing.capabilities.iter().any(|c| c.skill_name.eq_ignore_ascii_case(capability)) {
+                continue;
+            }
+
+            let match_score = self.matcher.calculate_match_score(&listing, &required, &[]).score;
+            let load = match &self.flow {
+                Some(flow) => flow.load(&id).await,
+                None => None,
+            };
+            let penalty = load
+                .map(|l| l.in_flight.min(4) as f64 / 4.0 * 0.5 + l.error_rate * 0.2)
+                .unwrap_or(0.0);
+            candidates.push(Candidate { id, name: profile.name, match_score, load, score: match_score - penalty });
+        }
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates
+    }
+
+    /// The best agent for `capability`.
+    pub async fn select(&self, capability: &str) -> Result<Candidate, SwarmError> {
+        self.candidates(capability).await.into_iter().next().ok_or_else(|| SwarmError::NoCapableAgent {
+            capability: capability.to_string(),
+            available: self.capabilities().into_iter().collect::<Vec<_>>().join(", "),
+        })
+    }
+
+    /// Resolve an agent ID, an agent name (case-insensitive) or a capability to an agent.
+    pub async fn resolve(&self, target: &str) -> Result<AgentId, SwarmError> {
+        let target = target.trim();
+        if let Ok(id) = target.parse::<AgentId>() {
+            return Ok(id);
+        }
+        if let Some((id, _)) = self.swarm.profiles().into_iter().find(|(_, p)| p.name.eq_ignore_ascii_case(target)) {
+            return Ok(id);
+        }
+        self.select(target).await.map(|c| c.id)
+    }
+
+    /// All capability tags offered in the swarm, lowercased and sorted.
+    pub fn capabilities(&self) -> BTreeSet<String> {
+        self.swarm
+            .profiles()
+            .into_iter()
+            .flat_map(|(_, p)| p.capabilities)
+            .map(|c| c.to_lowercase())
+            .collect()
+    }
+
+    /// One line per agent (`name [capabilities]: description`) for prompts and tool descriptions.
+    pub fn describe_agents(&self) -> String {
+        self.swarm
+            .profiles()
+            .into_iter()
+            .map(|(_, p)| {
+                let mut line = format!("- {}", p.name);
+                if !p.capabilities.is_empty() {
+                    line.push_str(&format!(" [{}]", p.capabilities.join(", ")));
+                }
+                if !p.description.is_empty() {
+                    line.push_str(&format!(": {}", p.description));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     pub fn swarm(&self) -> &Swarm {
         &self.swarm
     }
@@ -44,3 +211,87 @@ impl Coordinator {
         &self.event_bus
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::AgentProfile;
+    use async_trait::async_trait;
+    use pixelcore_heartbeat::FlowStateMachineConfig;
+    use pixelcore_runtime::agent::{Agent, AgentConfig, AgentState};
+    use pixelcore_runtime::error::RuntimeError;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    struct NamedAgent {
+        config: AgentConfig,
+        state: AgentState,
+    }
+
+    #[async_trait]
+    impl Agent for NamedAgent {
+        fn id(&self) -> AgentId { self.config.id }
+        fn name(&self) -> &str { &self.config.name }
+        fn state(&self) -> &AgentState { &self.state }
+        fn config(&self) -> &AgentConfig { &self.config }
+        async fn start(&mut self) -> Result<(), RuntimeError> { Ok(()) }
+        async fn stop(&mut self) -> Result<(), RuntimeError> { Ok(()) }
+        async fn process(&mut self, _message: Message) -> Result<Message, RuntimeError> {
+            Ok(Message::assistant(self.config.name.clone()))
+        }
+    }
+
+    async fn add(swarm: &Swarm, name: &str, capabilities: &[&str]) -> AgentId {
+        let mut config = AgentConfig::new(name, "");
+        config.metadata = serde_json::json!({ "description": format!("{name} agent"), "capabilities": capabilities });
+        let id = config.id;
+        swarm.add(Arc::new(Mutex::new(NamedAgent { config, state: AgentState::Running }))).await;
+        id
+    }
+
+    #[tokio::test]
+    async fn test_select_by_capability_and_name() {
+        let swarm = Swarm::new();
+        let sql = add(&swarm, "Analyst", &["sql", "charts"]).await;
+        let writer = add(&swarm, "Writer", &["Translation"]).await;
+        let coordinator = Coordinator::new(swarm, EventBus::new());
+
+        assert_eq!(coordinator.select("SQL").await.unwrap().id, sql);
+        assert_eq!(coordinator.resolve("writer").await.unwrap(), writer);
+        assert_eq!(coordinator.resolve("translation").await.unwrap(), writer);
+        assert_eq!(coordinator.resolve(&sql.to_string()).await.unwrap(), sql);
+
+        let err = coordinator.select("poetry").await.unwrap_err();
+        assert!(matches!(&err, SwarmError::NoCapableAgent { available, .. } if available == "charts, sql, translation"));
+        assert!(coordinator.describe_agents().contains("- Analyst [sql, charts]: Analyst agent"));
+
+        coordinator.swarm().set_profile(&writer, AgentProfile::new("Writer").with_capabilities(["poetry"]));
+        assert_eq!(coordinator.select("poetry").await.unwrap().id, writer);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_select_prefers_less_loaded_agent() {
+        let swarm = Swarm::new();
+        let busy = add(&swarm, "Busy", &["sql"]).await;
+        let idle = add(&swarm, "Idle", &["sql"]).await;
+        let event_bus = EventBus::new();
+        let monitor = Arc::new(FlowMonitor::new(event_bus.clone(), FlowStateMachineConfig::default()));
+        monitor.register_agent(busy).await;
+        monitor.register_agent(idle).await;
+        monitor.run().await;
+        let coordinator = Coordinator::new(swarm, event_bus.clone()).with_flow_monitor(monitor.clone());
+
+        // Routed calls finish, so they leave nothing in flight.
+        coordinator.route(&idle, Message::user("hi")).await.unwrap();
+        for _ in 0..3 {
+            publish_task(&event_bus, EventKind::TaskStarted, &busy);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let candidates = coordinator.candidates("sql").await;
+        assert_eq!(candidates[0].id, idle);
+        assert_eq!(candidates[0].load.unwrap().in_flight, 0);
+        assert_eq!(candidates[1].load.unwrap().in_flight, 3);
+        assert!(candidates[1].score < candidates[1].match_score);
+    }
+}
@@ -5,6 +5,12 @@ pub enum SwarmError {
     #[error("Agent not found: {0}")]
     AgentNotFound(String),
 
+    #[error("Agent is quarantined: {0}")]
+    AgentQuarantined(String),
+
+    #[error("No agent offers capability '{capability}' (available: {available})")]
+    NoCapableAgent { capability: String, available: String },
+
     #[error("Runtime error: {0}")]
     Runtime(#[from] pixelcore_runtime::error::RuntimeError),
 
@@ -1,7 +1,13 @@
 pub mod swarm;
 pub mod coordinator;
 pub mod error;
+pub mod patterns;
+pub mod profile;
+pub mod supervisor;
 
 pub use swarm::Swarm;
-pub use coordinator::Coordinator;
+pub use coordinator::{Candidate, Coordinator};
 pub use error::SwarmError;
+pub use profile::AgentProfile;
+pub use supervisor::{AgentFactory, ChildSpec, Recovery, RecoveryAction, RestartStrategy, Supervisor, SupervisorConfig};
+pub use patterns::{AgentFailure, AgentReply, FailureReason, PatternOptions, PatternResult};
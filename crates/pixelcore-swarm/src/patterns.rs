@@ -0,0 +1,584 @@
+//! Multi-agent orchestration patterns on `Coordinator`.
+//!
+//! Every agent call runs under a timeout from `PatternOptions`. A failed or
+//! timed-out agent doesn't abort the pattern unless its reply is required
+//! (a pipeline stage, the supervisor, the judge, the reducer); either way it
+//! is listed in `PatternResult::failures`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use pixelcore_runtime::agent::AgentId;
+use pixelcore_runtime::event::{Event, EventKind};
+use pixelcore_runtime::message::Message;
+
+use crate::coordinator::Coordinator;
+
+/// Timeouts for the agent calls a pattern makes
+#[derive(Debug, Clone)]
+pub struct PatternOptions {
+    /// Per call, including time spent waiting for a busy agent.
+    pub timeout: Duration,
+    /// Overrides `timeout` for particular agents.
+    pub agent_timeouts: HashMap<AgentId, Duration>,
+}
+
+impl PatternOptions {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_agent_timeout(mut self, id: AgentId, timeout: Duration) -> Self {
+        self.agent_timeouts.insert(id, timeout);
+        self
+    }
+
+    fn timeout_for(&self, id: &AgentId) -> Duration {
+        self.agent_timeouts.get(id).copied().unwrap_or(self.timeout)
+    }
+}
+
+impl Default for PatternOptions {
+    fn default() -> Self {
+        Self { timeout: Duration::from_secs(120), agent_timeouts: HashMap::new() }
+    }
+}
+
+/// Why an agent call produced no reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FailureReason {
+    Timeout { after_ms: u64 },
+    Error { message: String },
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureReason::Timeout { after_ms } => write!(f, "timed out after {after_ms}ms"),
+            FailureReason::Error { message } => f.write_str(message),
+        }
+    }
+}
+
+/// One agent call that failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentFailure {
+    pub agent: AgentId,
+    /// Which step of the pattern, e.g. `stage 2`, `worker`, `judge`.
+    pub stage: String,
+    pub reason: FailureReason,
+}
+
+/// One agent call that succeeded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentReply {
+    pub agent: AgentId,
+    pub stage: String,
+    pub message: Message,
+}
+
+/// Outcome of an orchestration pattern
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatternResult {
+    /// The pattern's answer; `None` if a required call failed or, for
+    /// `vote`, no answer won a majority.
+    pub output: Option<Message>,
+    /// Every successful call, in the order the pattern made them.
+    pub replies: Vec<AgentReply>,
+    pub failures: Vec<AgentFailure>,
+}
+
+impl PatternResult {
+    /// Produced an output and no agent failed.
+    pub fn is_complete(&self) -> bool {
+        self.output.is_some() && self.failures.is_empty()
+    }
+
+    /// Agents with at least one failed call, in order of first failure.
+    pub fn failed_agents(&self) -> Vec<AgentId> {
+        let mut ids: Vec<AgentId> = Vec::new();
+        for failure in &self.failures {
+            if !ids.contains(&failure.agent) {
+                ids.push(failure.agent);
+            }
+        }
+        ids
+    }
+
+    fn record(&mut self, agent: AgentId, stage: String, result: Result<Message, FailureReason>) -> Option<Message> {
+        match result {
+            Ok(message) => {
+                self.replies.push(AgentReply { agent, stage, message: message.clone() });
+                Some(message)
+            }
+            Err(reason) => {
+                warn!(agent = %agent, %stage, %reason, "agent call failed");
+                self.failures.push(AgentFailure { agent, stage, reason });
+                None
+            }
+        }
+    }
+}
+
+impl Coordinator {
+    /// Call one agent under its timeout.
+    async fn call(&self, id: &AgentId, message: Message, options: &PatternOptions) -> Result<Message, FailureReason> {
+        let timeout = options.timeout_for(id);
+        match tokio::time::timeout(timeout, self.route(id, message)).await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(e)) => Err(FailureReason::Error { message: e.to_string() }),
+            Err(_) => Err(FailureReason::Timeout { after_ms: timeout.as_millis() as u64 }),
+        }
+    }
+
+    /// Call several agents concurrently; results are in the order of `calls`.
+    async fn call_all(
+        &self,
+        calls: Vec<(AgentId, Message)>,
+        options: &PatternOptions,
+    ) -> Vec<(AgentId, Result<Message, FailureReason>)> {
+        join_all(calls.into_iter().map(|(id, message)| async move {
+            let result = self.call(&id, message, options).await;
+            (id, result)
+        }))
+        .await
+    }
+
+    fn publish_pattern(&self, pattern: &str, result: &PatternResult) {
+        let _ = self.event_bus().publish(Event::new(
+            EventKind::Custom("pattern_complete".to_string()),
+            "coordinator",
+            serde_json::json!({
+                "pattern": pattern,
+                "complete": result.is_complete(),
+                "replies": result.replies.len(),
+                "failures": result.failures,
+            }),
+        ));
+    }
+
+    /// Send `message` to every agent and report failures instead of dropping them.
+    pub async fn gather(&self, message: Message, options: &PatternOptions) -> PatternResult {
+        let ids = self.swarm().ids().await;
+        let calls = ids.into_iter().map(|id| (id, message.clone())).collect();
+        let mut result = PatternResult::default();
+        for (id, reply) in self.call_all(calls, options).await {
+            result.record(id, "gather".to_string(), reply);
+        }
+        self.publish_pattern("gather", &result);
+        result
+    }
+
+    /// Sequential pipeline: each agent's reply is the next agent's input. Stops
+    /// at the first failure; the output is the last stage's reply.
+    pub async fn pipeline(&self, stages: &[AgentId], input: Message, options: &PatternOptions) -> PatternResult {
+        let mut result = PatternResult::default();
+        let mut current = input;
+        for (i, id) in stages.iter().enumerate() {
+            let reply = self.call(id, current.clone(), options).await;
+            match result.record(*id, format!("stage {}", i + 1), reply) {
+                Some(reply) => current = Message::user(reply.content),
+                None => {
+                    self.publish_pattern("pipeline", &result);
+                    return result;
+                }
+            }
+        }
+        if !stages.is_empty() {
+            result.output = result.replies.last().map(|r| r.message.clone());
+        }
+        self.publish_pattern("pipeline", &result);
+        result
+    }
+
+    /// Supervisor / workers: the supervisor splits `task` into subtasks, the
+    /// workers run them concurrently (round-robin), and the supervisor
+    /// combines the results. Failed subtasks are reported to the supervisor.
+    pub async fn supervise(
+        &self,
+        supervisor: &AgentId,
+        workers: &[AgentId],
+        task: Message,
+        options: &PatternOptions,
+    ) -> PatternResult {
+        let mut result = PatternResult::default();
+        if workers.is_empty() {
+            result.failures.push(AgentFailure {
+                agent: *supervisor,
+                stage: "plan".to_string(),
+                reason: FailureReason::Error { message: "no workers".to_string() },
+            });
+            self.publish_pattern("supervise", &result);
+            return result;
+        }
+
+        let plan = Message::user(format!(
+            "Split the following task into at most {} independent subtasks that can be done in parallel. \
+             Reply with a JSON array of strings only.\n\nTask:\n{}",
+            workers.len() * 2,
+            task.content
+        ));
+        let reply = self.call(supervisor, plan, options).await;
+        let Some(plan) = result.record(*supervisor, "plan".to_string(), reply) else {
+            self.publish_pattern("supervise", &result);
+            return result;
+        };
+        let subtasks = parse_subtasks(&plan.content);
+
+        let calls = subtasks
+            .iter()
+            .enumerate()
+            .map(|(i, subtask)| {
+                let message = Message::user(format!("{subtask}\n\n(Part of a larger task: {})", task.content));
+                (workers[i % workers.len()], message)
+            })
+            .collect();
+        let mut report = String::new();
+        for ((id, reply), subtask) in self.call_all(calls, options).await.into_iter().zip(&subtasks) {
+            let outcome = match result.record(id, "worker".to_string(), reply) {
+                Some(reply) => reply.content,
+                None => format!("FAILED: {}", result.failures.last().map(|f| f.reason.to_string()).unwrap_or_default()),
+            };
+            report.push_str(&format!("## Subtask: {subtask}\n{outcome}\n\n"));
+        }
+
+        let combine = Message::user(format!(
+            "Combine the results of the subtasks into a complete answer to the original task. \
+             Point out anything left undone by failed subtasks.\n\nTask:\n{}\n\n{}",
+            task.content, report
+        ));
+        let reply = self.call(supervisor, combine, options).await;
+        result.output = result.record(*supervisor, "combine".to_string(), reply);
+        self.publish_pattern("supervise", &result);
+        result
+    }
+
+    /// Debate: every debater answers, then for `rounds` critique rounds sees
+    /// the other positions and revises; the judge picks or synthesizes a
+    /// final answer from the last positions.
+    pub async fn debate(
+        &self,
+        debaters: &[AgentId],
+        judge: &AgentId,
+        question: Message,
+        rounds: usize,
+        options: &PatternOptions,
+    ) -> PatternResult {
+        let mut result = PatternResult::default();
+        let calls = debaters.iter().map(|id| (*id, question.clone())).collect();
+        let mut positions: Vec<(AgentId, String)> = Vec::new();
+        for (id, reply) in self.call_all(calls, options).await {
+            if let Some(reply) = result.record(id, "opening".to_string(), reply) {
+                positions.push((id, reply.content));
+            }
+        }
+
+        for round in 1..=rounds {
+            if positions.len() < 2 {
+                break;
+            }
+            let calls = positions
+                .iter()
+                .map(|(id, own)| {
+                    let others: String = positions
+                        .iter()
+                        .filter(|(other, _)| other != id)
+                        .map(|(_, text)| format!("- {text}\n"))
+                        .collect();
+                    let message = Message::user(format!(
+                        "Question:\n{}\n\nYour answer:\n{own}\n\nOther answers:\n{others}\n\
+                         Critique the other answers, then give your revised answer.",
+                        question.content
+                    ));
+                    (*id, message)
+                })
+                .collect();
+            let mut revised = Vec::new();
+            for ((id, reply), (_, previous)) in self.call_all(calls, options).await.into_iter().zip(&positions) {
+                // An agent that fails a round keeps its previous position.
+                let text = result
+                    .record(id, format!("round {round}"), reply)
+                    .map(|m| m.content)
+                    .unwrap_or_else(|| previous.clone());
+                revised.push((id, text));
+            }
+            positions = revised;
+        }
+
+        if positions.is_empty() {
+            self.publish_pattern("debate", &result);
+            return result;
+        }
+        let answers: String = positions
+            .iter()
+            .enumerate()
+            .map(|(i, (_, text))| format!("## Answer {}\n{text}\n\n", i + 1))
+            .collect();
+        let verdict = Message::user(format!(
+            "You are the judge. Decide which answer to the question is best, or combine them, \
+             and give the final answer with a short justification.\n\nQuestion:\n{}\n\n{answers}",
+            question.content
+        ));
+        let reply = self.call(judge, verdict, options).await;
+        result.output = result.record(*judge, "judge".to_string(), reply);
+        self.publish_pattern("debate", &result);
+        result
+    }
+
+    /// Majority vote: every voter answers; answers are compared after trimming
+    /// and lowercasing. The output is the winning reply if more than half of
+    /// the votes cast agree, with the tally in its `metadata`.
+    pub async fn vote(&self, voters: &[AgentId], question: Message, options: &PatternOptions) -> PatternResult {
+        let mut result = PatternResult::default();
+        let calls = voters.iter().map(|id| (*id, question.clone())).collect();
+        let mut tally: Vec<(String, usize)> = Vec::new();
+        for (id, reply) in self.call_all(calls, options).await {
+            if let Some(reply) = result.record(id, "vote".to_string(), reply) {
+                let answer = normalize_vote(&reply.content);
+                match tally.iter_mut().find(|(a, _)| *a == answer) {
+                    Some((_, count)) => *count += 1,
+                    None => tally.push((answer, 1)),
+                }
+            }
+        }
+
+        let cast: usize = tally.iter().map(|(_, n)| n).sum();
+        if let Some((winner, count)) = tally.iter().max_by_key(|(_, n)| *n) {
+            if count * 2 > cast {
+                let mut output = result
+                    .replies
+                    .iter()
+                    .find(|r| normalize_vote(&r.message.content) == *winner)
+                    .map(|r| r.message.clone())
+                    .expect("winner has a reply");
+                output.metadata = serde_json::json!({
+                    "votes": tally.iter().map(|(a, n)| serde_json::json!({ "answer": a, "count": n })).collect::<Vec<_>>(),
+                    "cast": cast,
+                    "winner_votes": count,
+                });
+                result.output = Some(output);
+            }
+        }
+        self.publish_pattern("vote", &result);
+        result
+    }
+
+    /// Map-reduce: `inputs` are spread round-robin over `mappers` and run
+    /// concurrently; the reducer receives the mapped results in input order.
+    /// Failed inputs are named to the reducer.
+    pub async fn map_reduce(
+        &self,
+        mappers: &[AgentId],
+        inputs: Vec<Message>,
+        reducer: &AgentId,
+        instruction: &str,
+        options: &PatternOptions,
+    ) -> PatternResult {
+        let mut result = PatternResult::default();
+        if mappers.is_empty() {
+            result.failures.push(AgentFailure {
+                agent: *reducer,
+                stage: "map".to_string(),
+                reason: FailureReason::Error { message: "no mappers".to_string() },
+            });
+            self.publish_pattern("map_reduce", &result);
+            return result;
+        }
+
+        let calls = inputs.into_iter().enumerate().map(|(i, input)| (mappers[i % mappers.len()], input)).collect();
+        let mut mapped = String::new();
+        for (i, (id, reply)) in self.call_all(calls, options).await.into_iter().enumerate() {
+            let text = match result.record(id, format!("map {}", i + 1), reply) {
+                Some(reply) => reply.content,
+                None => "(failed, no result)".to_string(),
+            };
+            mapped.push_str(&format!("## Result {}\n{text}\n\n", i + 1));
+        }
+
+        let reduce = Message::user(format!("{instruction}\n\n{mapped}"));
+        let reply = self.call(reducer, reduce, options).await;
+        result.output = result.record(*reducer, "reduce".to_string(), reply);
+        self.publish_pattern("map_reduce", &result);
+        result
+    }
+}
+
+/// Subtasks from a supervisor's plan: a JSON array of strings (possibly in a
+/// code fence), or else one per non-empty line with list markers removed.
+fn parse_subtasks(plan: &str) -> Vec<String> {
+    let trimmed = plan.trim();
+    let json = match (trimmed.find('['), trimmed.rfind(']')) {
+        (Some(start), Some(end)) if start < end => &trimmed[start..=end],
+        _ => trimmed,
+    };
+    if let Ok(items) = serde_json::from_str::<Vec<String>>(json) {
+        let items: Vec<String> = items.into_iter().filter(|s| !s.trim().is_empty()).collect();
+        if !items.is_empty() {
+            return items;
+        }
+    }
+    let lines: Vec<String> = trimmed
+        .lines()
+        .map(|l| l.trim().trim_start_matches(['-', '*', '•']).trim())
+        .map(|l| l.trim_start_matches(|c: char| c.is_ascii_digit()).trim_start_matches(['.', ')']).trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("```"))
+        .map(str::to_string)
+        .collect();
+    if lines.is_empty() { vec![plan.to_string()] } else { lines }
+}
+
+fn normalize_vote(answer: &str) -> String {
+    answer.trim().trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm::Swarm;
+    use async_trait::async_trait;
+    use pixelcore_runtime::agent::{Agent, AgentConfig, AgentState};
+    use pixelcore_runtime::error::RuntimeError;
+    use pixelcore_runtime::event::EventBus;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    /// Replies via `respond`, after `delay`.
+    struct ScriptAgent {
+        config: AgentConfig,
+        state: AgentState,
+        delay: Duration,
+        respond: fn(&str) -> Result<String, String>,
+    }
+
+    #[async_trait]
+    impl Agent for ScriptAgent {
+        fn id(&self) -> AgentId { self.config.id }
+        fn name(&self) -> &str { &self.config.name }
+        fn state(&self) -> &AgentState { &self.state }
+        fn config(&self) -> &AgentConfig { &self.config }
+        async fn start(&mut self) -> Result<(), RuntimeError> { Ok(()) }
+        async fn stop(&mut self) -> Result<(), RuntimeError> { Ok(()) }
+        async fn process(&mut self, message: Message) -> Result<Message, RuntimeError> {
+            tokio::time::sleep(self.delay).await;
+            (self.respond)(&message.content)
+                .map(Message::assistant)
+                .map_err(|e| RuntimeError::Other(anyhow::anyhow!(e)))
+        }
+    }
+
+    async fn add(swarm: &Swarm, delay_ms: u64, respond: fn(&str) -> Result<String, String>) -> AgentId {
+        let config = AgentConfig::new("agent", "");
+        let id = config.id;
+        let agent = ScriptAgent { config, state: AgentState::Running, delay: Duration::from_millis(delay_ms), respond };
+        swarm.add(Arc::new(Mutex::new(agent))).await;
+        id
+    }
+
+    fn options() -> PatternOptions {
+        PatternOptions::default().with_timeout(Duration::from_millis(200))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pipeline_stops_at_failed_stage() {
+        let swarm = Swarm::new();
+        let upper = add(&swarm, 0, |m| Ok(m.to_uppercase())).await;
+        let exclaim = add(&swarm, 0, |m| Ok(format!("{m}!"))).await;
+        let broken = add(&swarm, 0, |_| Err("broken".to_string())).await;
+        let coordinator = Coordinator::new(swarm, EventBus::new());
+
+        let result = coordinator.pipeline(&[upper, exclaim], Message::user("hi"), &options()).await;
+        assert!(result.is_complete());
+        assert_eq!(result.output.unwrap().content, "HI!");
+
+        let result = coordinator.pipeline(&[upper, broken, exclaim], Message::user("hi"), &options()).await;
+        assert!(result.output.is_none());
+        assert_eq!(result.replies.len(), 1);
+        assert_eq!(result.failures[0].agent, broken);
+        assert_eq!(result.failures[0].stage, "stage 2");
+        assert!(matches!(&result.failures[0].reason, FailureReason::Error { message } if message.contains("broken")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_vote_reports_timeouts_and_majority() {
+        let swarm = Swarm::new();
+        let a = add(&swarm, 0, |_| Ok("Paris".to_string())).await;
+        let b = add(&swarm, 0, |_| Ok("paris.".to_string())).await;
+        let c = add(&swarm, 0, |_| Ok("Lyon".to_string())).await;
+        let slow = add(&swarm, 1000, |_| Ok("Paris".to_string())).await;
+        let coordinator = Coordinator::new(swarm, EventBus::new());
+
+        let options = options().with_agent_timeout(a, Duration::from_secs(1));
+        let result = coordinator.vote(&[a, b, c, slow], Message::user("Capital of France?"), &options).await;
+        let output = result.output.clone().unwrap();
+        assert_eq!(output.content, "Paris");
+        assert_eq!(output.metadata["winner_votes"], 2);
+        assert_eq!(result.failed_agents(), vec![slow]);
+        assert_eq!(result.failures[0].reason, FailureReason::Timeout { after_ms: 200 });
+
+        let result = coordinator.vote(&[a, c], Message::user("Capital of France?"), &options).await;
+        assert!(result.output.is_none(), "a tie is not a majority");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_supervise_debate_and_map_reduce() {
+        let swarm = Swarm::new();
+        let supervisor = add(&swarm, 0, |m| {
+            Ok(if m.starts_with("Split") { r#"["first part", "second part"]"#.to_string() } else { "combined".to_string() })
+        })
+        .await;
+        let worker = add(&swarm, 0, |m| Ok(format!("done: {}", m.lines().next().unwrap()))).await;
+        let failing = add(&swarm, 0, |_| Err("no capacity".to_string())).await;
+        let judge = add(&swarm, 0, |m| Ok(format!("verdict over {} answers", m.matches("## Answer").count()))).await;
+        let coordinator = Coordinator::new(swarm, EventBus::new());
+
+        let result = coordinator.supervise(&supervisor, &[worker, failing], Message::user("big task"), &options()).await;
+        assert_eq!(result.output.as_ref().unwrap().content, "combined");
+        assert_eq!(result.failed_agents(), vec![failing]);
+        let first = &result.replies.iter().find(|r| r.stage == "worker").unwrap().message;
+        assert_eq!(first.content, "done: first part");
+
+        let result = coordinator.debate(&[worker, failing, supervisor], &judge, Message::user("q"), 1, &options()).await;
+        assert_eq!(result.output.unwrap().content, "verdict over 2 answers");
+        assert!(result.failures.iter().all(|f| f.agent == failing && f.stage == "opening"));
+
+        let inputs = vec![Message::user("a"), Message::user("b"), Message::user("c")];
+        let result = coordinator.map_reduce(&[worker, failing], inputs, &judge, "Merge", &options()).await;
+        assert!(result.output.is_some());
+        assert_eq!(result.failures.len(), 1);
+        assert_eq!(result.failures[0].stage, "map 2");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_empty_supervise_and_map_reduce_publish_completion() {
+        let swarm = Swarm::new();
+        let lead = add(&swarm, 0, |_| Ok("unused".to_string())).await;
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let coordinator = Coordinator::new(swarm, event_bus);
+
+        let result = coordinator.supervise(&lead, &[], Message::user("task"), &options()).await;
+        assert!(!result.is_complete());
+        let result = coordinator.map_reduce(&[], vec![Message::user("a")], &lead, "Merge", &options()).await;
+        assert!(!result.is_complete());
+
+        for pattern in ["supervise", "map_reduce"] {
+            let event = events.try_recv().unwrap();
+            assert_eq!(event.kind, EventKind::Custom("pattern_complete".to_string()));
+            assert_eq!(event.payload["pattern"], pattern);
+            assert_eq!(event.payload["complete"], false);
+        }
+    }
+
+    #[test]
+    fn test_parse_subtasks() {
+        assert_eq!(parse_subtasks("```json\n[\"a\", \"b\"]\n```"), ["a", "b"]);
+        assert_eq!(parse_subtasks("1. first\n2) second\n- third"), ["first", "second", "third"]);
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use pixelcore_registry::{AgentListing, AgentStatus, Capability, PricingModel, ServiceLevel};
+use pixelcore_runtime::agent::{AgentConfig, AgentId};
+
+/// What an agent in a swarm offers, used to pick agents by capability
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentProfile {
+    pub name: String,
+    pub description: String,
+    /// Capability tags such as `sql` or `translation`.
+    pub capabilities: Vec<String>,
+}
+
+impl AgentProfile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..Self::default() }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.capabilities = capabilities.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Name from the config; description and capabilities from the
+    /// `description` and `capabilities` keys of `AgentConfig::metadata`.
+    pub fn from_config(config: &AgentConfig) -> Self {
+        let capabilities = config
+            .metadata
+            .get("capabilities")
+            .and_then(|v| v.as_array())
+            .map(|tags| tags.iter().filter_map(|t| t.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        let description = config.metadata.get("description").and_then(|v| v.as_str()).unwrap_or_default();
+        Self { name: config.name.clone(), description: description.to_string(), capabilities }
+    }
+
+    /// Case-insensitive.
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c.eq_ignore_ascii_case(capability))
+    }
+
+    /// A local, published listing so that marketplace matching can rank swarm agents.
+    pub(crate) fn to_listing(&self, id: AgentId) -> AgentListing {
+        let capabilities = self
+            .capabilities
+            .iter()
+            .map(|tag| Capability {
+                skill_name: tag.clone(),
+                description: String::new(),
+                input_schema: serde_json::Value::Null,
+                output_schema: serde_json::Value::Null,
+            })
+            .collect();
+        let mut listing = AgentListing::new(
+            self.name.clone(),
+            self.description.clone(),
+            "0.0.0".to_string(),
+            AgentId::nil(),
+            capabilities,
+            PricingModel::Free,
+            ServiceLevel { response_time_ms: 0, availability_percent: 100.0, max_concurrent_requests: 1 },
+        );
+        listing.id = id;
+        listing.status = AgentStatus::Published;
+        listing
+    }
+}
@@ -0,0 +1,410 @@
+//! Recovery of swarm agents that stop responding.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::warn;
+
+use pixelcore_heartbeat::{Liveness, LivenessMonitor};
+use pixelcore_runtime::agent::{Agent, AgentConfig, AgentId};
+use pixelcore_runtime::event::{Event, EventBus, EventKind};
+
+use crate::error::SwarmError;
+use crate::swarm::Swarm;
+
+/// Builds a fresh instance of an agent from its config (same ID).
+pub type AgentFactory = Arc<dyn Fn(&AgentConfig) -> Arc<Mutex<dyn Agent>> + Send + Sync>;
+
+/// Which agents are recovered when one dies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartStrategy {
+    /// Only the failed agent.
+    #[default]
+    OneForOne,
+    /// Every supervised agent, for agents that depend on each other's state.
+    OneForAll,
+}
+
+/// What the supervisor does with a dead agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    /// `stop` then `start` the same instance. An agent hung in `process`
+    /// holds its lock, so it is replaced if a factory is set and
+    /// quarantined otherwise.
+    Restart,
+    /// Swap in a new instance from the factory.
+    Replace,
+    /// Stop routing to the agent (see `Swarm::quarantine`).
+    Quarantine,
+}
+
+/// How one agent is supervised
+#[derive(Clone)]
+pub struct ChildSpec {
+    pub id: AgentId,
+    pub action: RecoveryAction,
+    factory: Option<AgentFactory>,
+}
+
+impl ChildSpec {
+    pub fn restart(id: AgentId) -> Self {
+        Self { id, action: RecoveryAction::Restart, factory: None }
+    }
+
+    pub fn replace(id: AgentId, factory: impl Fn(&AgentConfig) -> Arc<Mutex<dyn Agent>> + Send + Sync + 'static) -> Self {
+        Self { id, action: RecoveryAction::Replace, factory: Some(Arc::new(factory)) }
+    }
+
+    pub fn quarantine(id: AgentId) -> Self {
+        Self { id, action: RecoveryAction::Quarantine, factory: None }
+    }
+
+    /// Factory used when a restart cannot get hold of a hung agent.
+    pub fn with_factory(mut self, factory: impl Fn(&AgentConfig) -> Arc<Mutex<dyn Agent>> + Send + Sync + 'static) -> Self {
+        self.factory = Some(Arc::new(factory));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    pub strategy: RestartStrategy,
+    /// Recoveries of one agent allowed within `window`; past that it is quarantined.
+    pub max_restarts: usize,
+    pub window: Duration,
+    /// How long a restart waits for the agent's lock before treating it as hung.
+    pub stop_timeout: Duration,
+}
+
+impl SupervisorConfig {
+    pub fn with_strategy(mut self, strategy: RestartStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_max_restarts(mut self, max_restarts: usize, window: Duration) -> Self {
+        self.max_restarts = max_restarts;
+        self.window = window;
+        self
+    }
+
+    pub fn with_stop_timeout(mut self, timeout: Duration) -> Self {
+        self.stop_timeout = timeout;
+        self
+    }
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            strategy: RestartStrategy::default(),
+            max_restarts: 3,
+            window: Duration::from_secs(60),
+            stop_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Outcome of recovering one agent, also published as a
+/// `Custom("supervisor_action")` event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recovery {
+    pub agent_id: AgentId,
+    /// The action actually taken, which may differ from the spec's (see `RecoveryAction::Restart`).
+    pub action: RecoveryAction,
+    pub reason: String,
+    pub error: Option<String>,
+}
+
+struct Child {
+    spec: ChildSpec,
+    config: AgentConfig,
+    recoveries: VecDeque<Instant>,
+}
+
+/// Watches `liveness_changed` events and recovers agents that go `Dead`.
+pub struct Supervisor {
+    swarm: Swarm,
+    event_bus: EventBus,
+    liveness: Arc<LivenessMonitor>,
+    config: SupervisorConfig,
+    children: Mutex<HashMap<AgentId, Child>>,
+}
+
+impl Supervisor {
+    /// `liveness` should publish to `event_bus`.
+    pub fn new(swarm: Swarm, event_bus: EventBus, liveness: Arc<LivenessMonitor>, config: SupervisorConfig) -> Self {
+        Self { swarm, event_bus, liveness, config, children: Mutex::new(HashMap::new()) }
+    }
+
+    /// Supervise an agent already in the swarm and register it with the liveness monitor.
+    pub async fn supervise(&self, spec: ChildSpec) -> Result<(), SwarmError> {
+        let config = self.swarm.get(&spec.id).await?.lock().await.config().clone();
+        self.liveness.register_agent(spec.id).await;
+        self.children
+            .lock()
+            .await
+            .insert(spec.id, Child { spec, config, recoveries: VecDeque::new() });
+        Ok(())
+    }
+
+    pub async fn unsupervise(&self, id: &AgentId) -> bool {
+        self.liveness.unregister_agent(id).await;
+        self.children.lock().await.remove(id).is_some()
+    }
+
+    /// Start reacting to liveness events.
+    pub fn run(self: &Arc<Self>) {
+        let mut receiver = self.event_bus.subscribe();
+        let supervisor = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        if let Some(id) = dead_agent(&event) {
+                            supervisor.recover(&id, "liveness: dead").await;
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Supervisor lagged, skipped {} events", n);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Recover `failed` (and, under `OneForAll`, every other supervised
+    /// agent). Does nothing for agents that are not supervised.
+    pub async fn recover(&self, failed: &AgentId, reason: &str) -> Vec<Recovery> {
+        let mut children = self.children.lock().await;
+        if !children.contains_key(failed) {
+            return Vec::new();
+        }
+        let mut targets = vec![(*failed, reason.to_string())];
+        if self.config.strategy == RestartStrategy::OneForAll {
+            let mut others: Vec<AgentId> = children.keys().filter(|id| *id != failed).copied().collect();
+            others.sort();
+            targets.extend(others.into_iter().map(|id| (id, format!("one-for-all: {failed} failed"))));
+        }
+
+        let mut recoveries = Vec::new();
+        for (id, reason) in targets {
+            let child = children.get_mut(&id).expect("target is supervised");
+            let recovery = self.recover_child(child, reason).await;
+            let _ = self.event_bus.publish(Event::new(
+                EventKind::Custom("supervisor_action".to_string()),
+                "supervisor",
+                serde_json::to_value(&recovery).unwrap_or_default(),
+            ));
+            recoveries.push(recovery);
+        }
+        recoveries
+    }
+
+    async fn recover_child(&self, child: &mut Child, mut reason: String) -> Recovery {
+        let id = child.spec.id;
+        let now = Instant::now();
+        while child.recoveries.front().is_some_and(|t| now.duration_since(*t) > self.config.window) {
+            child.recoveries.pop_front();
+        }
+        child.recoveries.push_back(now);
+
+        let mut action = child.spec.action;
+        if child.recoveries.len() > self.config.max_restarts {
+            action = RecoveryAction::Quarantine;
+            reason = format!("{reason}; more than {} recoveries within {:?}", self.config.max_restarts, self.config.window);
+        }
+
+        let mut result = match action {
+            RecoveryAction::Restart => self.restart(child).await,
+            RecoveryAction::Replace => self.replace(child).await,
+            RecoveryAction::Quarantine => Ok(RecoveryAction::Quarantine),
+        };
+        if let Err(e) = &result {
+            warn!(agent = %id, error = %e, "supervisor: recovery failed, quarantining");
+        }
+        if result.is_err() || matches!(result, Ok(RecoveryAction::Quarantine)) {
+            self.swarm.quarantine(&id);
+            result = result.map(|_| RecoveryAction::Quarantine);
+        }
+        if matches!(result, Ok(RecoveryAction::Restart | RecoveryAction::Replace)) {
+            self.liveness.reset(&id).await;
+        }
+
+        match result {
+            Ok(action) => Recovery { agent_id: id, action, reason, error: None },
+            Err(e) => Recovery { agent_id: id, action: RecoveryAction::Quarantine, reason, error: Some(e.to_string()) },
+        }
+    }
+
+    async fn restart(&self, child: &Child) -> Result<RecoveryAction, SwarmError> {
+        let agent = self.swarm.get(&child.spec.id).await?;
+        let Ok(mut agent) = tokio::time::timeout(self.config.stop_timeout, agent.lock()).await else {
+            return match child.spec.factory {
+                Some(_) => self.replace(child).await,
+                None => Ok(RecoveryAction::Quarantine),
+            };
+        };
+        if let Err(e) = agent.stop().await {
+            warn!(agent = %child.spec.id, error = %e, "supervisor: stop failed during restart");
+        }
+        agent.start().await?;
+        Ok(RecoveryAction::Restart)
+    }
+
+    async fn replace(&self, child: &Child) -> Result<RecoveryAction, SwarmError> {
+        let factory = child
+            .spec
+            .factory
+            .as_ref()
+            .ok_or_else(|| SwarmError::Other(format!("no factory to replace agent {}", child.spec.id)))?;
+        let agent = factory(&child.config);
+        agent.lock().await.start().await?;
+        if !self.swarm.replace(&child.spec.id, agent).await {
+            return Err(SwarmError::AgentNotFound(child.spec.id.to_string()));
+        }
+        Ok(RecoveryAction::Replace)
+    }
+}
+
+fn dead_agent(event: &Event) -> Option<AgentId> {
+    if event.kind != EventKind::Custom("liveness_changed".to_string()) {
+        return None;
+    }
+    let state: Liveness = serde_json::from_value(event.payload.get("new_state")?.clone()).ok()?;
+    if state != Liveness::Dead {
+        return None;
+    }
+    event.payload.get("agent_id")?.as_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator::Coordinator;
+    use async_trait::async_trait;
+    use pixelcore_heartbeat::LivenessConfig;
+    use pixelcore_runtime::agent::AgentState;
+    use pixelcore_runtime::error::RuntimeError;
+    use pixelcore_runtime::message::Message;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct TestAgent {
+        config: AgentConfig,
+        state: AgentState,
+        hang: bool,
+        starts: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Agent for TestAgent {
+        fn id(&self) -> AgentId { self.config.id }
+        fn name(&self) -> &str { &self.config.name }
+        fn state(&self) -> &AgentState { &self.state }
+        fn config(&self) -> &AgentConfig { &self.config }
+        async fn start(&mut self) -> Result<(), RuntimeError> {
+            self.starts.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+        async fn stop(&mut self) -> Result<(), RuntimeError> { Ok(()) }
+        async fn process(&mut self, _message: Message) -> Result<Message, RuntimeError> {
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            Ok(Message::assistant("fresh"))
+        }
+    }
+
+    fn agent(config: AgentConfig, hang: bool, starts: &Arc<AtomicUsize>) -> Arc<Mutex<dyn Agent>> {
+        Arc::new(Mutex::new(TestAgent { config, state: AgentState::Running, hang, starts: starts.clone() }))
+    }
+
+    fn liveness(event_bus: &EventBus) -> Arc<LivenessMonitor> {
+        let config = LivenessConfig {
+            beat_interval: Duration::from_millis(20),
+            hang_timeout: Some(Duration::from_millis(60)),
+            ..LivenessConfig::default()
+        };
+        Arc::new(LivenessMonitor::new(event_bus.clone(), config))
+    }
+
+    #[tokio::test]
+    async fn test_hung_agent_is_replaced() {
+        let event_bus = EventBus::new();
+        let swarm = Swarm::new();
+        let starts = Arc::new(AtomicUsize::new(0));
+        let config = AgentConfig::new("worker", "");
+        let id = config.id;
+        swarm.add(agent(config, true, &starts)).await;
+
+        let liveness = liveness(&event_bus);
+        liveness.run().await;
+        let supervisor = Arc::new(Supervisor::new(
+            swarm.clone(),
+            event_bus.clone(),
+            liveness.clone(),
+            SupervisorConfig::default().with_stop_timeout(Duration::from_millis(20)),
+        ));
+        let s = starts.clone();
+        supervisor
+            .supervise(ChildSpec::restart(id).with_factory(move |config| agent(config.clone(), false, &s)))
+            .await
+            .unwrap();
+        supervisor.run();
+        let mut events = event_bus.subscribe();
+
+        let coordinator = Arc::new(Coordinator::new(swarm, event_bus.clone()));
+        let c = coordinator.clone();
+        let hung = tokio::spawn(async move { c.route(&id, Message::user("hi")).await });
+
+        let recovery = loop {
+            let event = tokio::time::timeout(Duration::from_secs(2), events.recv()).await.unwrap().unwrap();
+            if event.kind == EventKind::Custom("supervisor_action".to_string()) {
+                break serde_json::from_value::<Recovery>(event.payload).unwrap();
+            }
+        };
+        assert_eq!(recovery.agent_id, id);
+        assert_eq!(recovery.action, RecoveryAction::Replace);
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        assert_eq!(coordinator.route(&id, Message::user("hi")).await.unwrap().content, "fresh");
+        assert_eq!(liveness.state(&id).await, Some(Liveness::Alive));
+        hung.abort();
+    }
+
+    #[tokio::test]
+    async fn test_one_for_all_and_restart_limit() {
+        let event_bus = EventBus::new();
+        let swarm = Swarm::new();
+        let starts = Arc::new(AtomicUsize::new(0));
+        let (a, b) = (AgentConfig::new("a", ""), AgentConfig::new("b", ""));
+        let (a_id, b_id) = (a.id, b.id);
+        swarm.add(agent(a, false, &starts)).await;
+        swarm.add(agent(b, false, &starts)).await;
+
+        let config = SupervisorConfig::default()
+            .with_strategy(RestartStrategy::OneForAll)
+            .with_max_restarts(1, Duration::from_secs(60));
+        let supervisor = Supervisor::new(swarm.clone(), event_bus.clone(), liveness(&event_bus), config);
+        supervisor.supervise(ChildSpec::restart(a_id)).await.unwrap();
+        supervisor.supervise(ChildSpec::restart(b_id)).await.unwrap();
+
+        let recoveries = supervisor.recover(&a_id, "test").await;
+        assert_eq!(recoveries.len(), 2);
+        assert!(recoveries.iter().all(|r| r.action == RecoveryAction::Restart));
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+
+        let recoveries = supervisor.recover(&a_id, "test").await;
+        assert_eq!(recoveries[0].action, RecoveryAction::Quarantine);
+        assert!(swarm.is_quarantined(&a_id));
+        assert!(matches!(swarm.route(&a_id, Message::user("hi")).await, Err(SwarmError::AgentQuarantined(_))));
+        // b's one-for-all restarts count towards its own limit too
+        assert_eq!(recoveries[1].action, RecoveryAction::Quarantine);
+        assert!(swarm.is_quarantined(&b_id));
+    }
+}
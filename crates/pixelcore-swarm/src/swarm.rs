@@ -1,30 +1,100 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::warn;
 use pixelcore_runtime::agent::{Agent, AgentId};
 use pixelcore_runtime::message::Message;
 use crate::error::SwarmError;
+use crate::profile::AgentProfile;
 
+/// Cloning gives another handle to the same agents.
+#[derive(Clone)]
 pub struct Swarm {
     agents: Arc<RwLock<HashMap<AgentId, Arc<tokio::sync::Mutex<dyn Agent>>>>>,
+    /// Kept outside the agent locks so that tool descriptions can be built synchronously.
+    profiles: Arc<std::sync::RwLock<HashMap<AgentId, AgentProfile>>>,
+    /// Agents taken out of routing, see `quarantine`.
+    quarantined: Arc<std::sync::RwLock<HashSet<AgentId>>>,
 }
 
 impl Swarm {
     pub fn new() -> Self {
         Self {
             agents: Arc::new(RwLock::new(HashMap::new())),
+            profiles: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            quarantined: Arc::new(std::sync::RwLock::new(HashSet::new())),
         }
     }
 
+    /// Add an agent, profiled from its config (see `AgentProfile::from_config`).
     pub async fn add(&self, agent: Arc<tokio::sync::Mutex<dyn Agent>>) {
+        let profile = AgentProfile::from_config(agent.lock().await.config());
+        self.add_with_profile(agent, profile).await;
+    }
+
+    pub async fn add_with_profile(&self, agent: Arc<tokio::sync::Mutex<dyn Agent>>, profile: AgentProfile) {
         let id = agent.lock().await.id();
         self.agents.write().await.insert(id, agent);
+        self.profiles.write().unwrap().insert(id, profile);
     }
 
     pub async fn remove(&self, id: &AgentId) -> bool {
+        self.profiles.write().unwrap().remove(id);
+        self.quarantined.write().unwrap().remove(id);
         self.agents.write().await.remove(id).is_some()
     }
 
+    /// Swap in a new instance for the agent under `id`, keeping its profile.
+    /// Calls still running on the old instance finish on it. Returns false if
+    /// the agent is not in the swarm.
+    pub async fn replace(&self, id: &AgentId, agent: Arc<tokio::sync::Mutex<dyn Agent>>) -> bool {
+        match self.agents.write().await.get_mut(id) {
+            Some(existing) => {
+                *existing = agent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop routing to an agent without removing it; `route` fails with
+    /// `SwarmError::AgentQuarantined` and `broadcast` skips it.
+    pub fn quarantine(&self, id: &AgentId) {
+        self.quarantined.write().unwrap().insert(*id);
+    }
+
+    /// Undo `quarantine`. Returns false if the agent was not quarantined.
+    pub fn release(&self, id: &AgentId) -> bool {
+        self.quarantined.write().unwrap().remove(id)
+    }
+
+    pub fn is_quarantined(&self, id: &AgentId) -> bool {
+        self.quarantined.read().unwrap().contains(id)
+    }
+
+    /// Replace an agent's profile. Returns false if the agent is not in the swarm.
+    pub fn set_profile(&self, id: &AgentId, profile: AgentProfile) -> bool {
+        match self.profiles.write().unwrap().get_mut(id) {
+            Some(existing) => {
+                *existing = profile;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn profile(&self, id: &AgentId) -> Option<AgentProfile> {
+        self.profiles.read().unwrap().get(id).cloned()
+    }
+
+    /// All profiles, sorted by name.
+    pub fn profiles(&self) -> Vec<(AgentId, AgentProfile)> {
+        let mut profiles: Vec<(AgentId, AgentProfile)> =
+            self.profiles.read().unwrap().iter().map(|(id, p)| (*id, p.clone())).collect();
+        profiles.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        profiles
+    }
+
     pub async fn get(&self, id: &AgentId) -> Result<Arc<tokio::sync::Mutex<dyn Agent>>, SwarmError> {
         self.agents
             .read()
@@ -44,20 +114,27 @@ impl Swarm {
 
     /// Send a message to a specific agent and return its reply.
     pub async fn route(&self, id: &AgentId, message: Message) -> Result<Message, SwarmError> {
+        if self.is_quarantined(id) {
+            return Err(SwarmError::AgentQuarantined(id.to_string()));
+        }
         let agent = self.get(id).await?;
         let reply = agent.lock().await.process(message).await?;
         Ok(reply)
     }
 
     /// Broadcast a message to all agents concurrently; returns (id, reply) pairs, skipping errors.
+    ///
+    /// Errors are only logged; `Coordinator::gather` reports them.
     pub async fn broadcast(&self, message: Message) -> Vec<(AgentId, Message)> {
-        let ids = self.ids().await;
+        let ids: Vec<AgentId> = self.ids().await.into_iter().filter(|id| !self.is_quarantined(id)).collect();
         let handles: Vec<_> = ids.into_iter().map(|id| {
             let agents = Arc::clone(&self.agents);
             let msg = message.clone();
             tokio::spawn(async move {
                 let agent = agents.read().await.get(&id).cloned()?;
-                let reply = agent.lock().await.process(msg).await.ok()?;
+                let reply = agent.lock().await.process(msg).await
+                    .map_err(|e| warn!(agent = %id, error = %e, "broadcast: agent failed"))
+                    .ok()?;
                 Some((id, reply))
             })
         }).collect();
@@ -14,7 +14,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Demo 1: Python Code Execution");
     println!("─────────────────────────────");
 
-    let python_skill = PythonExecuteSkill;
+    let python_skill = PythonExecuteSkill::new();
 
     // Example 1: Simple calculation
     println!("\n1. Simple calculation:");
@@ -61,7 +61,7 @@ print(json.dumps(data, indent=2))
     println!("\n\nDemo 2: JavaScript Code Execution");
     println!("─────────────────────────────");
 
-    let js_skill = JavaScriptExecuteSkill;
+    let js_skill = JavaScriptExecuteSkill::new();
 
     // Example 1: Array operations
     println!("\n1. Array operations:");
@@ -119,7 +119,7 @@ main();
     println!("\n\nDemo 3: Shell Command Execution");
     println!("─────────────────────────────");
 
-    let shell_skill = ShellExecuteSkill;
+    let shell_skill = ShellExecuteSkill::new();
 
     // Example 1: List files
     println!("\n1. List files:");
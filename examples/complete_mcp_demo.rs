@@ -122,9 +122,7 @@ async fn test_filesystem_tools(provider: &McpSkillProvider) -> Result<()> {
     println!("测试文件系统工具:");
 
     // 写入文件
-    let write_skill = provider.skills().iter()
-        .find(|s| s.name() == "write_file")
-        .unwrap();
+    let write_skill = provider.skill("write_file").unwrap();
 
     let input = SkillInput {
         name: "write_file".to_string(),
@@ -138,9 +136,7 @@ async fn test_filesystem_tools(provider: &McpSkillProvider) -> Result<()> {
     println!("  ✅ write_file: {}", if result.success { "成功" } else { "失败" });
 
     // 读取文件
-    let read_skill = provider.skills().iter()
-        .find(|s| s.name() == "read_file")
-        .unwrap();
+    let read_skill = provider.skill("read_file").unwrap();
 
     let input = SkillInput {
         name: "read_file".to_string(),
@@ -163,9 +159,7 @@ async fn test_time_tools(provider: &McpSkillProvider) -> Result<()> {
     println!("\n测试时间工具:");
 
     // 获取当前时间
-    let time_skill = provider.skills().iter()
-        .find(|s| s.name() == "get_current_time")
-        .unwrap();
+    let time_skill = provider.skill("get_current_time").unwrap();
 
     let input = SkillInput {
         name: "get_current_time".to_string(),
@@ -176,9 +170,7 @@ async fn test_time_tools(provider: &McpSkillProvider) -> Result<()> {
     println!("  ✅ get_current_time: {}", result.result);
 
     // 格式化时间
-    let format_skill = provider.skills().iter()
-        .find(|s| s.name() == "format_time")
-        .unwrap();
+    let format_skill = provider.skill("format_time").unwrap();
 
     let input = SkillInput {
         name: "format_time".to_string(),
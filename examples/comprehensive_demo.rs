@@ -7,11 +7,10 @@
 
 use pixelcore_runtime::workflow::{Workflow, WorkflowNode};
 use pixelcore_skills::{
-    SkillRegistry, SkillInput,
-    builtins::code_execution::{PythonExecuteSkill, JavaScriptExecuteSkill, ShellExecuteSkill},
+    Permission, PermissionManager, SkillRegistry, SkillInput,
+    builtins::code_execution::register_code_execution_skills,
 };
 use serde_json::json;
-use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -63,10 +62,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Demo 2: Executing Code with Skills");
     println!("───────────────────────────────────");
 
-    let mut registry = SkillRegistry::new();
-    registry.register(Arc::new(PythonExecuteSkill));
-    registry.register(Arc::new(JavaScriptExecuteSkill));
-    registry.register(Arc::new(ShellExecuteSkill));
+    // Registries deny everything by default; grant the interpreters and
+    // commands this demo runs.
+    let mut permissions = PermissionManager::new();
+    for command in ["python3", "node", "echo", "uname", "date"] {
+        permissions.grant(Permission::Process { command: command.to_string(), args_pattern: None });
+    }
+    permissions.grant(Permission::Compute { max_time_ms: 30_000, max_memory_mb: 0 });
+
+    let mut registry = SkillRegistry::new().with_permissions(permissions);
+    register_code_execution_skills(&mut registry);
 
     println!("✓ Registered {} skills\n", registry.list().len());
 
@@ -79,16 +84,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }),
     };
 
-    match registry.get("python_execute") {
-        Ok(skill) => {
-            match skill.execute(python_input).await {
-                Ok(output) => {
-                    println!("✓ Python output:\n{}", output.result);
-                }
-                Err(e) => println!("✗ Python error: {}", e),
-            }
+    match registry.execute(python_input).await {
+        Ok(output) => {
+            println!("✓ Python output:\n{}", output.result);
         }
-        Err(e) => println!("✗ Failed to get skill: {}", e),
+        Err(e) => println!("✗ Python error: {}", e),
     }
 
     // Execute JavaScript code
@@ -100,16 +100,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }),
     };
 
-    match registry.get("javascript_execute") {
-        Ok(skill) => {
-            match skill.execute(js_input).await {
-                Ok(output) => {
-                    println!("✓ JavaScript output:\n{}", output.result);
-                }
-                Err(e) => println!("✗ JavaScript error: {}", e),
-            }
+    match registry.execute(js_input).await {
+        Ok(output) => {
+            println!("✓ JavaScript output:\n{}", output.result);
         }
-        Err(e) => println!("✗ Failed to get skill: {}", e),
+        Err(e) => println!("✗ JavaScript error: {}", e),
     }
 
     // Execute Shell command
@@ -121,16 +116,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }),
     };
 
-    match registry.get("shell_execute") {
-        Ok(skill) => {
-            match skill.execute(shell_input).await {
-                Ok(output) => {
-                    println!("✓ Shell output:\n{}", output.result);
-                }
-                Err(e) => println!("✗ Shell error: {}", e),
-            }
+    match registry.execute(shell_input).await {
+        Ok(output) => {
+            println!("✓ Shell output:\n{}", output.result);
         }
-        Err(e) => println!("✗ Failed to get skill: {}", e),
+        Err(e) => println!("✗ Shell error: {}", e),
     }
 
     println!("\n=== Demo Complete ===");
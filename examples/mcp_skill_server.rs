@@ -0,0 +1,74 @@
+//! 把 PixelCore 的数据类技能作为 MCP 服务器提供给其他 MCP 客户端
+//!
+//! ```bash
+//! # stdio 模式（供 MCP 主机以子进程方式启动）
+//! cargo run --example mcp_skill_server -- /path/to/data
+//!
+//! # Streamable HTTP 模式，端点为 http://127.0.0.1:8808/mcp
+//! cargo run --example mcp_skill_server -- /path/to/data --http 127.0.0.1:8808
+//! ```
+//!
+//! 文件类技能只能访问指定的数据目录（默认为当前目录）。
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use pixelcore_claw::McpServer;
+use pixelcore_skills::{
+    create_data_skills, create_excel_skills, create_pdf_skills, create_sqlite_skills, Permission,
+    PermissionManager, SkillRegistry,
+};
+use pixelcore_storage::Storage;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // stdout 用于协议消息，日志只能写到 stderr
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env()
+                .add_directive(tracing::Level::INFO.into()),
+        )
+        .init();
+
+    let mut args = std::env::args().skip(1);
+    let mut root = std::env::current_dir()?;
+    let mut http_addr = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--http" => http_addr = args.next(),
+            path => root = PathBuf::from(path),
+        }
+    }
+    let root = root.canonicalize()?;
+
+    let mut permissions = PermissionManager::new();
+    permissions.grant(Permission::FileSystem { path: root.clone(), read: true, write: true });
+
+    let mut registry = SkillRegistry::new().with_permissions(permissions);
+    for skill in create_sqlite_skills()
+        .into_iter()
+        .chain(create_excel_skills())
+        .chain(create_pdf_skills())
+        .chain(create_data_skills())
+    {
+        registry.register(skill);
+    }
+
+    // 把运行信息作为资源提供
+    let storage = Storage::new();
+    storage.set("server:root", serde_json::json!(root.display().to_string()))?;
+    let mut skills = registry.list();
+    skills.sort();
+    storage.set("server:skills", serde_json::json!(skills))?;
+
+    let server = McpServer::new("pixelcore-skills", env!("CARGO_PKG_VERSION"), Arc::new(registry))
+        .with_resources(Arc::new(storage))
+        .with_instructions(format!("File paths must be inside {}", root.display()));
+
+    match http_addr {
+        Some(addr) => server.serve_http(addr).await,
+        None => server.serve_stdio().await,
+    }
+}
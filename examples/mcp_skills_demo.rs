@@ -30,7 +30,7 @@ async fn main() -> Result<()> {
 
     let skills = mcp_provider.skills();
     println!("Loaded {} MCP skills:", skills.len());
-    for skill in skills {
+    for skill in &skills {
         println!("  - {}: {}", skill.name(), skill.description());
     }
 
@@ -50,7 +50,7 @@ async fn main() -> Result<()> {
     let mut agent = ClaudeAgent::with_client(config, client);
 
     // 注册所有 MCP Skills
-    for skill in skills {
+    for skill in &skills {
         agent.register_skill(Arc::clone(skill));
     }
 
@@ -24,7 +24,7 @@ async fn main() -> Result<()> {
     println!("Loaded {} MCP skills:\n", skills.len());
 
     // 2. 显示每个 Skill 的信息
-    for skill in skills {
+    for skill in &skills {
         println!("Skill: {}", skill.name());
         println!("  Description: {}", skill.description());
         println!("  Schema: {}", serde_json::to_string_pretty(&skill.input_schema())?);
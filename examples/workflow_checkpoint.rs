@@ -1,5 +1,6 @@
+use std::sync::Arc;
 use pixelcore_runtime::workflow::{
-    Workflow, WorkflowNode, WorkflowExecutor, WorkflowPersistence, NodeType,
+    Workflow, WorkflowNode, WorkflowExecutor, WorkflowPersistence, NodeType, EchoTaskHandler,
 };
 
 #[tokio::main]
@@ -31,7 +32,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 场景1：正常执行并保存检查点
     println!("--- Scenario 1: Execute and Save Checkpoint ---");
-    let executor = WorkflowExecutor::new(workflow.clone());
+    let executor = WorkflowExecutor::new(workflow.clone())
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
 
     // 执行工作流
     println!("Executing workflow...");
@@ -74,7 +76,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let resumed_executor = WorkflowExecutor::from_checkpoint(
         restored_workflow.clone(),
         restored_context.clone()
-    );
+    )
+    .with_fallback_handler(Arc::new(EchoTaskHandler));
 
     println!("\nResuming execution from checkpoint...");
     let resume_result = resumed_executor.resume().await;
@@ -110,7 +113,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     workflow2.connect(task1_id, task2_id);
     workflow2.connect(task2_id, end2_id);
 
-    let executor2 = WorkflowExecutor::new(workflow2.clone());
+    let executor2 = WorkflowExecutor::new(workflow2.clone())
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
 
     // 执行一部分
     println!("Starting execution...");
@@ -141,7 +145,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let recovered_executor = WorkflowExecutor::from_checkpoint(
             recovered_workflow,
             recovered_context
-        );
+        )
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
 
         let final_result = recovered_executor.resume().await;
 
@@ -2,8 +2,9 @@
 // 演示如何使用 Workflow 和 WorkflowExecutor
 // 运行: cargo run --example workflow_demo
 
+use std::sync::Arc;
 use pixelcore_runtime::{
-    Workflow, WorkflowNode, WorkflowExecutor, ExecutionStatus
+    Workflow, WorkflowNode, WorkflowExecutor, ExecutionStatus, EchoTaskHandler
 };
 
 #[tokio::main]
@@ -70,7 +71,8 @@ async fn simple_workflow() -> Result<(), Box<dyn std::error::Error>> {
     println!("   ✅ 工作流验证通过");
 
     // 执行工作流
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let context = executor.execute().await?;
 
     println!("   ✅ 工作流执行完成");
@@ -133,7 +135,8 @@ async fn decision_workflow() -> Result<(), Box<dyn std::error::Error>> {
     workflow.validate()?;
     println!("   ✅ 工作流验证通过");
 
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let context = executor.execute().await?;
 
     println!("   ✅ 工作流执行完成");
@@ -202,7 +205,8 @@ async fn multi_task_workflow() -> Result<(), Box<dyn std::error::Error>> {
     println!("   节点数: {}", workflow.nodes.len());
     println!("   边数: {}", workflow.edges.len());
 
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let context = executor.execute().await?;
 
     println!("   ✅ 工作流执行完成");
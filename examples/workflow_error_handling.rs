@@ -2,8 +2,9 @@
 // 演示如何使用错误处理策略
 // 运行: cargo run --example workflow_error_handling
 
+use std::sync::Arc;
 use pixelcore_runtime::{
-    Workflow, WorkflowNode, WorkflowExecutor,
+    Workflow, WorkflowNode, WorkflowExecutor, EchoTaskHandler,
     ErrorHandlingStrategy, RetryPolicy
 };
 
@@ -61,7 +62,8 @@ async fn fail_strategy_demo() -> Result<(), Box<dyn std::error::Error>> {
     println!("   策略: 任务失败时停止整个工作流");
 
     workflow.validate()?;
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let context = executor.execute().await?;
 
     println!("   ✅ 执行完成");
@@ -101,7 +103,8 @@ async fn ignore_strategy_demo() -> Result<(), Box<dyn std::error::Error>> {
     println!("   策略: 任务2 失败时忽略错误，继续执行任务3");
 
     workflow.validate()?;
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let context = executor.execute().await?;
 
     println!("   ✅ 执行完成");
@@ -138,7 +141,8 @@ async fn retry_strategy_demo() -> Result<(), Box<dyn std::error::Error>> {
     println!("   策略: 任务失败时最多重试 3 次，每次延迟 500ms");
 
     workflow.validate()?;
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let context = executor.execute().await?;
 
     println!("   ✅ 执行完成");
@@ -180,7 +184,8 @@ async fn exponential_backoff_demo() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     workflow.validate()?;
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let context = executor.execute().await?;
 
     println!("   ✅ 执行完成");
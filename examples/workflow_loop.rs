@@ -1,5 +1,6 @@
+use std::sync::Arc;
 use pixelcore_runtime::workflow::{
-    Workflow, WorkflowNode, WorkflowExecutor, NodeType,
+    Workflow, WorkflowNode, WorkflowExecutor, NodeType, EchoTaskHandler,
 };
 
 #[tokio::main]
@@ -45,7 +46,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 执行工作流
     println!("--- Executing Workflow ---");
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let result = executor.execute().await;
 
     match result {
@@ -1,5 +1,7 @@
+use std::sync::Arc;
 use pixelcore_runtime::workflow::{
-    Workflow, WorkflowNode, WorkflowExecutor, NodeType, WorkflowEdge, EdgeCondition,
+    Workflow, WorkflowNode, WorkflowExecutor, NodeType, WorkflowEdge, EchoTaskHandler,
+    JoinPolicy, BranchFailurePolicy,
 };
 
 #[tokio::main]
@@ -12,11 +14,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 创建节点
     let start = WorkflowNode::start("Start");
 
-    // 并行节点：同时执行3个分支
+    // 并行节点：同时执行3个分支，每个分支最多 5 秒，失败时取消其余分支
     let parallel_node = WorkflowNode::new(
         "Parallel Tasks",
         NodeType::Parallel {
             branches: 3,
+            branch_timeout_ms: Some(5_000),
+            on_branch_failure: BranchFailurePolicy::CancelOthers,
         }
     );
 
@@ -39,6 +43,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         serde_json::json!({"id": 3, "message": "Processing branch 3"})
     );
 
+    // 汇聚节点：等待所有分支完成并合并结果
+    let join = WorkflowNode::join("Join", JoinPolicy::All);
+
     let end = WorkflowNode::end("End");
 
     let start_id = workflow.add_node(start);
@@ -46,6 +53,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let task1_id = workflow.add_node(task1);
     let task2_id = workflow.add_node(task2);
     let task3_id = workflow.add_node(task3);
+    let join_id = workflow.add_node(join);
     let end_id = workflow.add_node(end);
 
     // 连接节点
@@ -56,8 +64,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     workflow.add_edge(WorkflowEdge::parallel_branch(parallel_id, task2_id, 1));
     workflow.add_edge(WorkflowEdge::parallel_branch(parallel_id, task3_id, 2));
 
-    // 汇聚节点：并行执行完成后继续
-    workflow.connect_when(parallel_id, end_id, "all_branches_complete");
+    // 汇聚节点：各分支结束后在 Join 汇合，再继续执行
+    workflow.connect(task1_id, join_id);
+    workflow.connect(task2_id, join_id);
+    workflow.connect(task3_id, join_id);
+    workflow.connect(join_id, end_id);
 
     println!("Created workflow: {}", workflow.name);
     println!("Description: {}", workflow.description);
@@ -67,7 +78,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("--- Executing Workflow ---");
     println!("Starting parallel execution of 3 tasks...\n");
 
-    let executor = WorkflowExecutor::new(workflow);
+    let executor = WorkflowExecutor::new(workflow)
+        .with_fallback_handler(Arc::new(EchoTaskHandler));
     let result = executor.execute().await;
 
     match result {
@@ -81,6 +93,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("  Node {}: {:?}", node_id, result);
             }
 
+            if let Some(merged) = context.get_node_result(&join_id) {
+                println!("\nMerged branch results: {}", merged);
+            }
+
             println!("\nAll {} parallel branches completed successfully!", 3);
         }
         Err(e) => {